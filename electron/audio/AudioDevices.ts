@@ -9,13 +9,18 @@ try {
     log.error('[AudioDevices] Failed to load native module:', e);
 }
 
-const { getInputDevices, getOutputDevices } = NativeModule || {};
+const { getInputDevices, getOutputDevices, listAudioApplications } = NativeModule || {};
 
 export interface AudioDevice {
     id: string;
     name: string;
 }
 
+export interface AudioApplication {
+    pid: number;
+    name: string;
+}
+
 export class AudioDevices {
     public static getInputDevices(): AudioDevice[] {
         if (!getInputDevices) {
@@ -42,5 +47,20 @@ export class AudioDevices {
             return [];
         }
     }
-}
 
+    /**
+     * Applications currently playing audio (pass their pids to SystemAudioCapture's appFilter)
+     */
+    public static listAudioApplications(): AudioApplication[] {
+        if (!listAudioApplications) {
+            log.warn('[AudioDevices] Native functionality not available');
+            return [];
+        }
+        try {
+            return listAudioApplications();
+        } catch (e) {
+            log.error('[AudioDevices] Failed to list audio applications:', e);
+            return [];
+        }
+    }
+}
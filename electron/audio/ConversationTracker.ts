@@ -0,0 +1,111 @@
+import { EventEmitter } from 'events';
+
+let NativeModule: any = null;
+
+try {
+    NativeModule = require('natively-audio');
+} catch (e) {
+    console.error('[ConversationTracker] Failed to load native module:', e);
+}
+
+const { ConversationTracker: RustConversationTracker } = NativeModule || {};
+
+export type Party = 'user' | 'remote';
+
+export interface ConversationOptions {
+    /** Speech shorter than this is ignored (default 200) */
+    minSpeechMs?: number;
+    /** Silence that ends a party's speech (default 300) */
+    releaseMs?: number;
+    /** Shortest simultaneous speech reported as an overlap (default 300) */
+    minOverlapMs?: number;
+    /** Shortest silence within a party's speech counted as a pause (default 500) */
+    minPauseMs?: number;
+    /** Period of the 'sessionMetrics' event (default 30000, 0 = never) */
+    metricsIntervalMs?: number;
+}
+
+export interface PartyMetrics {
+    talkTimeMs: number;
+    /** Times the party took the floor */
+    turns: number;
+    /** Longest stretch holding the floor */
+    longestMonologueMs: number;
+    syllables: number;
+    /** Syllables per second of talk time */
+    syllableRate: number;
+}
+
+export interface SessionMetrics {
+    durationMs: number;
+    user: PartyMetrics;
+    remote: PartyMetrics;
+    /** User share of the total talk time (0..1) */
+    userTalkRatio: number;
+    pauses: number;
+    pauseTotalMs: number;
+    longestPauseMs: number;
+    /** User turns following the remote party */
+    responses: number;
+    /** From the remote party's last speech to the user's answer */
+    meanResponseLatencyMs?: number;
+    medianResponseLatencyMs?: number;
+    overlaps: number;
+    overlapTotalMs: number;
+    interruptions: number;
+}
+
+/**
+ * Overlap, interruption and turn events between the microphone (user) and
+ * system audio (remote). Attach both captures with trackConversation().
+ *
+ * Events (timestamps are capture times, ms since the Unix epoch):
+ * - 'overlapStart' { timeMs, by }
+ * - 'overlapEnd' { timeMs, durationMs, remaining }
+ * - 'userInterrupted' { timeMs }
+ * - 'turnTaken' { party, timeMs, gapMs }
+ * - 'sessionMetrics' { timeMs, metrics } (every metricsIntervalMs)
+ */
+export class ConversationTracker extends EventEmitter {
+    private tracker: any = null;
+
+    constructor(options?: ConversationOptions) {
+        super();
+        if (!RustConversationTracker) {
+            console.error('[ConversationTracker] Rust class implementation not found.');
+            return;
+        }
+        this.tracker = new RustConversationTracker(options ?? null);
+        this.tracker.onEvent((event: any) => {
+            const { type, ...payload } = event;
+            this.emit(type, payload);
+        });
+    }
+
+    /** Native tracker handed to the captures (null if the module is missing) */
+    public get native(): any {
+        return this.tracker;
+    }
+
+    /** Party that spoke alone last */
+    public getFloor(): Party | null {
+        return this.tracker?.getFloor() ?? null;
+    }
+
+    /**
+     * Both sides are talking at once: a good moment to hold off generating an answer
+     */
+    public isOverlapping(): boolean {
+        return this.tracker?.isOverlapping() ?? false;
+    }
+
+    /** Talk time, monologues, pauses, response latency and speaking rate so far */
+    public getSessionMetrics(): SessionMetrics | null {
+        return this.tracker?.getSessionMetrics() ?? null;
+    }
+
+    /** Start a new session (e.g. the next interview) */
+    public resetSessionMetrics(): void {
+        this.tracker?.resetSessionMetrics();
+    }
+}
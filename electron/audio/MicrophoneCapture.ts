@@ -1,5 +1,7 @@
 import { log } from '@utils/logger';
 import { EventEmitter } from 'events';
+import type { ConversationTracker } from './ConversationTracker';
+import type { AudioClip, RingBufferOptions, Transcript, TranscriptionOptions, Utterance, UtteranceOptions } from './types';
 import { app } from 'electron';
 import path from 'path';
 
@@ -14,21 +16,48 @@ try {
 
 const { MicrophoneCapture: RustMicCapture } = NativeModule || {};
 
+export type { AudioClip, RingBufferOptions, Transcript, TranscriptionOptions, Utterance, UtteranceOptions } from './types';
+
+export interface KeywordSpottingOptions {
+    keywords: {
+        keyword: string;
+        /** Recordings of the phrase (16kHz mono 16-bit PCM, 200ms..3s of sound) */
+        templates: Buffer[];
+    }[];
+    /** Largest match distance (default 0.5; lower is stricter) */
+    maxDistance?: number;
+    /** Silence matched phrases before frames are delivered (adds latency) */
+    suppress?: boolean;
+}
+
+export interface KeywordMatch {
+    keyword: string;
+    seqStart: number;
+    seqEnd: number;
+    startTimeMs: number | null;
+    endTimeMs: number | null;
+    distance: number;
+}
+
 export class MicrophoneCapture extends EventEmitter {
     private monitor: any = null;
     private isRecording: boolean = false;
     private deviceId: string | null = null;
+    private ringOptions: RingBufferOptions | null = null;
+    private conversation: ConversationTracker | null = null;
+    private keywords: KeywordSpottingOptions | null = null;
 
-    constructor(deviceId?: string | null) {
+    constructor(deviceId?: string | null, ringOptions?: RingBufferOptions | null) {
         super();
         this.deviceId = deviceId || null;
+        this.ringOptions = ringOptions || null;
         if (!RustMicCapture) {
             log.error('[MicrophoneCapture] Rust class implementation not found.');
         } else {
             log.info(`[MicrophoneCapture] Initialized wrapper. Device ID: ${this.deviceId || 'default'}`);
             try {
                 log.info('[MicrophoneCapture] Creating native monitor (Eager Init)...');
-                this.monitor = new RustMicCapture(this.deviceId);
+                this.monitor = new RustMicCapture(this.deviceId, this.ringOptions);
             } catch (e) {
                 log.error('[MicrophoneCapture] Failed to create native monitor:', e);
                 // We don't throw here to allow app to start, but start() will fail
@@ -53,27 +82,22 @@ export class MicrophoneCapture extends EventEmitter {
         }
 
         // Monitor should be ready from constructor
-        // Monitor should be ready from constructor
-        if (!this.monitor) {
-            log.info('[MicrophoneCapture] Monitor not initialized. Re-initializing...');
-            try {
-                this.monitor = new RustMicCapture(this.deviceId);
-            } catch (e) {
-                this.emit('error', e);
-                return;
-            }
-        }
+        if (!this.ensureMonitor()) return;
 
         try {
             log.info('[MicrophoneCapture] Starting native capture...');
 
-            this.monitor.start((chunk: Uint8Array) => {
+            // Native side delivers pooled Buffers: emit as-is, no extra copy
+            this.attachEvents();
+
+            this.monitor.start((chunk: Buffer & { seq?: number; captureTimeMs?: number | null }) => {
                 if (chunk && chunk.length > 0) {
                     // Debug: log occasionally
                     if (Math.random() < 0.05) {
                         log.info(`[MicrophoneCapture] Emitting chunk: ${chunk.length} bytes to JS`);
                     }
-                    this.emit('data', Buffer.from(chunk));
+                    // Capture time of the first sample (ms since the Unix epoch) for alignment / latency
+                    this.emit('data', chunk, { seq: chunk.seq, captureTimeMs: chunk.captureTimeMs ?? null });
                 }
             });
 
@@ -85,6 +109,154 @@ export class MicrophoneCapture extends EventEmitter {
         }
     }
 
+    /**
+     * Pull-based alternative to the 'data' event:
+     *   for await (const frame of capture.frames()) { ... }
+     * Frames wait in a bounded native queue until read; the loop ends on stop().
+     */
+    public async *frames(maxFrames: number = 10): AsyncGenerator<Buffer> {
+        if (this.isRecording) {
+            throw new Error('[MicrophoneCapture] frames() cannot be combined with start()');
+        }
+        if (!this.ensureMonitor()) return;
+
+        log.info('[MicrophoneCapture] Starting native capture (pull mode)...');
+        this.attachEvents();
+        this.monitor.startPull();
+        this.isRecording = true;
+        this.emit('start');
+
+        while (true) {
+            const batch: Buffer[] = await this.monitor.read(maxFrames);
+            if (batch.length === 0) return;
+            for (const frame of batch) {
+                yield frame;
+            }
+        }
+    }
+
+    /**
+     * Utterance mode: instead of 'data' frames, emits 'utterance' with one
+     * complete speech segment at a time (pre-roll and trailing padding included),
+     * ready to upload to a REST STT API
+     */
+    public startUtterances(options?: UtteranceOptions): void {
+        if (this.isRecording) return;
+        if (!this.ensureMonitor()) return;
+
+        try {
+            log.info('[MicrophoneCapture] Starting native capture (utterance mode)...');
+            this.attachEvents();
+            this.monitor.startUtterances((utterance: Utterance) => {
+                this.emit('utterance', utterance);
+            }, options ?? null);
+            this.isRecording = true;
+            this.emit('start');
+        } catch (error) {
+            log.error('[MicrophoneCapture] Failed to start:', error);
+            this.emit('error', error);
+        }
+    }
+
+    /**
+     * On-device transcription: instead of 'data' frames, emits 'transcript'
+     * with partial and final results (same shape as the cloud STT providers).
+     * Requires a native build with the offline-stt feature.
+     */
+    public startTranscription(options: TranscriptionOptions): void {
+        if (this.isRecording) return;
+        if (!NativeModule?.isOfflineSttAvailable?.()) {
+            this.emit('error', new Error('Offline STT is not available in this build'));
+            return;
+        }
+        if (!this.ensureMonitor()) return;
+
+        try {
+            log.info('[MicrophoneCapture] Starting native capture (offline transcription)...');
+            this.attachEvents();
+            this.monitor.startTranscription((transcript: Transcript) => {
+                this.emit('transcript', transcript);
+            }, options);
+            this.isRecording = true;
+            this.emit('start');
+        } catch (error) {
+            log.error('[MicrophoneCapture] Failed to start:', error);
+            this.emit('error', error);
+        }
+    }
+
+    /**
+     * Create the native monitor unless it exists (emits 'error' on failure)
+     */
+    private ensureMonitor(): boolean {
+        if (this.monitor) return true;
+        log.info('[MicrophoneCapture] Monitor not initialized. Re-initializing...');
+        try {
+            this.monitor = new RustMicCapture(this.deviceId, this.ringOptions);
+            return true;
+        } catch (e) {
+            log.error('[MicrophoneCapture] Failed to create native monitor:', e);
+            this.emit('error', e);
+            return false;
+        }
+    }
+
+    /**
+     * Report this capture's speech ("user" party) to `tracker`; null detaches.
+     * Applies to the running capture and to later starts.
+     */
+    public trackConversation(tracker: ConversationTracker | null): void {
+        this.conversation = tracker;
+        if (this.monitor) {
+            this.applyConversation();
+        }
+    }
+
+    private applyConversation(): void {
+        if (this.conversation?.native) {
+            this.monitor.trackConversation(this.conversation.native);
+        } else {
+            this.monitor.untrackConversation();
+        }
+    }
+
+    /**
+     * Spot enrolled phrases, emitted as 'keyword' (KeywordMatch); null stops.
+     * Throws if a template is unusable (too short / silent).
+     */
+    public setKeywordSpotting(options: KeywordSpottingOptions | null): void {
+        this.monitor?.setKeywordSpotting(options);
+        this.keywords = options;
+    }
+
+    private attachEvents(): void {
+        if (this.conversation) {
+            this.applyConversation();
+        }
+        if (this.keywords) {
+            try {
+                this.monitor.setKeywordSpotting(this.keywords);
+            } catch (e) {
+                log.error('[MicrophoneCapture] Error setting keyword spotting:', e);
+            }
+        }
+        // Backpressure notifications: { type: 'lagging', lagging, queued, dropped }
+        // Device format changes: { type: 'formatChanged', sampleRate, channels, outputChannels }
+        // Content classification: { type: 'contentClass', class, speech, music, noise }
+        // Spotted phrases: { type: 'keyword', keyword, seqStart, seqEnd, startTimeMs, endTimeMs, distance }
+        this.monitor.onEvent((event: any) => {
+            if (event?.type === 'lagging') {
+                this.emit('lagging', event);
+            } else if (event?.type === 'formatChanged') {
+                this.emit('formatChanged', event);
+            } else if (event?.type === 'contentClass') {
+                this.emit('contentClass', event);
+            } else if (event?.type === 'keyword') {
+                this.emit('keyword', event as KeywordMatch);
+            }
+        });
+    }
+
     /**
      * Stop capturing
      */
@@ -105,6 +277,88 @@ export class MicrophoneCapture extends EventEmitter {
         this.emit('stop');
     }
 
+    /**
+     * Pause emitting audio without tearing down the device stream.
+     * Resume is instant (no device re-open).
+     */
+    public pause(): void {
+        if (!this.isRecording) return;
+        try {
+            this.monitor?.pause();
+            this.emit('pause');
+        } catch (e) {
+            log.error('[MicrophoneCapture] Error pausing:', e);
+        }
+    }
+
+    public resume(): void {
+        if (!this.isRecording) return;
+        try {
+            this.monitor?.resume();
+            this.emit('resume');
+        } catch (e) {
+            log.error('[MicrophoneCapture] Error resuming:', e);
+        }
+    }
+
+    /**
+     * Mute: frames keep flowing (zero-filled) so STT timing is preserved
+     */
+    public setMuted(muted: boolean): void {
+        try {
+            this.monitor?.setMuted(muted);
+        } catch (e) {
+            log.error('[MicrophoneCapture] Error setting mute:', e);
+        }
+    }
+
+    /**
+     * Frame delivery counters (dropped / coalesced frames while JS was lagging)
+     * and device audio lost to ring buffer overflows
+     */
+    public getStats(): {
+        delivered: number;
+        dropped: number;
+        coalesced: number;
+        queued: number;
+        lagging: boolean;
+        ringOverflows: number;
+        ringDroppedMs: number;
+    } | null {
+        try {
+            return this.monitor?.getStats() ?? null;
+        } catch (e) {
+            log.error('[MicrophoneCapture] Error reading stats:', e);
+            return null;
+        }
+    }
+
+    /**
+     * The last `seconds` of processed audio (kept natively, default 30s;
+     * see the `historySeconds` capture option), e.g. to re-transcribe a mis-heard question
+     */
+    public getRecent(seconds: number, format: 'wav' | 'pcm' = 'wav'): AudioClip | null {
+        try {
+            return this.monitor?.getRecent(seconds, format) ?? null;
+        } catch (e) {
+            log.error('[MicrophoneCapture] Error reading history:', e);
+            return null;
+        }
+    }
+
+    /**
+     * Processed audio of frames `seqStart..=seqEnd` (the `seq` of 'data' chunks),
+     * e.g. to re-send audio lost during an STT reconnect
+     */
+    public getRange(seqStart: number, seqEnd: number, format: 'wav' | 'pcm' = 'wav'): AudioClip | null {
+        try {
+            return this.monitor?.getRange(seqStart, seqEnd, format) ?? null;
+        } catch (e) {
+            log.error('[MicrophoneCapture] Error reading history:', e);
+            return null;
+        }
+    }
+
     public destroy(): void {
         this.stop();
         this.monitor = null;
@@ -214,6 +214,34 @@ export class RestSTT extends EventEmitter {
         this.totalBufferedBytes += audioData.length;
     }
 
+    /**
+     * Transcribe one complete utterance (WAV) from a capture's utterance mode
+     * Segmentation, silence gating and the WAV header are done natively, so
+     * this bypasses the timer-based buffer entirely.
+     */
+    public async transcribeUtterance(utterance: { data: Buffer; format: string }): Promise<void> {
+        if (!this.isActive) return;
+        if (utterance.format !== 'wav') {
+            this.emit('error', new Error(`[RestSTT] Expected a WAV utterance, got ${utterance.format}`));
+            return;
+        }
+
+        try {
+            const transcript = await this.uploadAudio(utterance.data);
+            if (transcript && transcript.trim().length > 0) {
+                log.info(`[RestSTT] Transcript: "${transcript.substring(0, 60)}..."`);
+                this.emit('transcript', {
+                    text: transcript.trim(),
+                    isFinal: true,
+                    confidence: 1.0,
+                });
+            }
+        } catch (err) {
+            log.error(`[RestSTT] Upload error:`, err);
+            this.emit('error', err instanceof Error ? err : new Error(String(err)));
+        }
+    }
+
     /**
      * Concatenate buffered chunks, add WAV header, and upload to REST API
      */
@@ -1,4 +1,6 @@
 import { EventEmitter } from 'events';
+import type { ConversationTracker } from './ConversationTracker';
+import type { AudioClip, RingBufferOptions, Transcript, TranscriptionOptions, Utterance, UtteranceOptions } from './types';
 import { app } from 'electron';
 import path from 'path';
 
@@ -12,16 +14,73 @@ try {
 
 const { SystemAudioCapture: RustAudioCapture } = NativeModule || {};
 
+export type { AudioClip, RingBufferOptions, Transcript, TranscriptionOptions, Utterance, UtteranceOptions } from './types';
+
+export interface DiarizationOptions {
+    /** Voices tracked at most (default 8) */
+    maxSpeakers?: number;
+    /** Largest voice distance still counted as the same speaker (default 0.5) */
+    speakerDistanceThreshold?: number;
+}
+
+/** Stretch of speech attributed to one voice ('speaker' event) */
+export interface SpeakerSegment {
+    /** Stable within one capture run: 'spk_0', 'spk_1', ... */
+    speaker: string;
+    /** First and last 20ms frame (inclusive) */
+    seqStart: number;
+    seqEnd: number;
+    startTimeMs: number | null;
+    endTimeMs: number | null;
+    newSpeaker: boolean;
+}
+
+/** Utterance that ended with rising intonation ('likelyQuestion' event) */
+export interface LikelyQuestion {
+    /** Last pitched 20ms frame of the utterance */
+    seq: number;
+    /** Capture time of the utterance end (ms since the Unix epoch) */
+    timeMs: number | null;
+    /** 0..1 */
+    confidence: number;
+    /** Final pitch rise over the rest of the utterance */
+    riseSemitones: number;
+}
+
+/** Speaker segments kept for speakerAt() (~2 minutes of continuous speech) */
+const MAX_SPEAKER_SEGMENTS = 80;
+
 export class SystemAudioCapture extends EventEmitter {
     private monitor: any = null;
     private isRecording: boolean = false;
     private deviceId: string | null = null;
+    private appFilter: { includePids?: number[]; excludePids?: number[] } | null = null;
+    private channelOptions: { channels?: number; channelMode?: 'downmix' | 'keep' } | null = null;
+    private ringOptions: RingBufferOptions | null = null;
     private detectedSampleRate: number = 16000;
     private chunkCount: number = 0;
+    private diarization: DiarizationOptions | null = null;
+    private speakerSegments: SpeakerSegment[] = [];
+    private conversation: ConversationTracker | null = null;
 
-    constructor(deviceId?: string | null) {
+    /**
+     * @param appFilter capture only `includePids`, or everything except `excludePids`
+     *                  (see AudioDevices.listAudioApplications)
+     * @param channelOptions record `channels` (default 1) and either downmix them to mono
+     *                       or keep them interleaved in each chunk (`channelMode: 'keep'`)
+     * @param ringOptions device ring buffer size (ms) and what happens when it fills up
+     */
+    constructor(
+        deviceId?: string | null,
+        appFilter?: { includePids?: number[]; excludePids?: number[] } | null,
+        channelOptions?: { channels?: number; channelMode?: 'downmix' | 'keep' } | null,
+        ringOptions?: RingBufferOptions | null
+    ) {
         super();
         this.deviceId = deviceId || null;
+        this.appFilter = appFilter || null;
+        this.channelOptions = channelOptions || null;
+        this.ringOptions = ringOptions || null;
         if (!RustAudioCapture) {
             console.error('[SystemAudioCapture] Rust class implementation not found.');
         } else {
@@ -37,6 +96,16 @@ export class SystemAudioCapture extends EventEmitter {
         return 16000;
     }
 
+    /**
+     * Interleaved channels per chunk (1 unless channelMode is 'keep')
+     */
+    public getChannels(): number {
+        if (this.monitor) {
+            return this.monitor.getChannels();
+        }
+        return this.channelOptions?.channelMode === 'keep' ? (this.channelOptions.channels || 1) : 1;
+    }
+
     /**
      * Start capturing audio
      */
@@ -48,33 +117,25 @@ export class SystemAudioCapture extends EventEmitter {
             return;
         }
 
-        // LAZY INIT: Create monitor here when meeting starts (not in constructor)
-        // This prevents the 1-second audio mute + quality drop at app launch
-        if (!this.monitor) {
-            console.log('[SystemAudioCapture] Creating native monitor (lazy init)...');
-            try {
-                this.monitor = new RustAudioCapture(this.deviceId);
-            } catch (e) {
-                console.error('[SystemAudioCapture] Failed to create native monitor:', e);
-                this.emit('error', e);
-                return;
-            }
-        }
+        if (!this.ensureMonitor()) return;
 
         try {
             console.log('[SystemAudioCapture] Starting native capture...');
 
-            this.monitor.start((chunk: Uint8Array) => {
-                // The native module sends raw PCM bytes (Uint8Array)
+            this.attachEvents();
+
+            this.monitor.start((chunk: Buffer & { seq?: number; captureTimeMs?: number | null }) => {
+                // The native module sends raw PCM bytes in pooled Buffers: no extra copy
                 if (chunk && chunk.length > 0) {
-                    const buffer = Buffer.from(chunk);
+                    const buffer = chunk;
                     this.chunkCount++;
                     // Log first chunk and then every 100th
                     if (this.chunkCount === 1 || this.chunkCount % 100 === 0) {
                         const prefix = buffer.slice(0, 10).toString('hex');
                         console.log(`[SystemAudioCapture] Chunk #${this.chunkCount}: ${buffer.length}b, Data(hex): ${prefix}...`);
                     }
-                    this.emit('data', buffer);
+                    // Capture time of the first sample (ms since the Unix epoch) for alignment / latency
+                    this.emit('data', buffer, { seq: chunk.seq, captureTimeMs: chunk.captureTimeMs ?? null });
                 } else {
                     // Log empty/null chunks occasionally to detect silent capture
                     if (this.chunkCount === 0) {
@@ -93,6 +154,191 @@ export class SystemAudioCapture extends EventEmitter {
         }
     }
 
+    /**
+     * LAZY INIT: create the monitor when the meeting starts (not in the constructor);
+     * this prevents the 1-second audio mute + quality drop at app launch.
+     * Emits 'error' on failure.
+     */
+    private ensureMonitor(): boolean {
+        if (this.monitor) return true;
+        console.log('[SystemAudioCapture] Creating native monitor (lazy init)...');
+        try {
+            this.monitor = new RustAudioCapture(this.deviceId, this.appFilter, this.channelOptions, this.ringOptions);
+            return true;
+        } catch (e) {
+            console.error('[SystemAudioCapture] Failed to create native monitor:', e);
+            this.emit('error', e);
+            return false;
+        }
+    }
+
+    /**
+     * Pull-based alternative to the 'data' event:
+     *   for await (const frame of capture.frames()) { ... }
+     * Frames wait in a bounded native queue until read; the loop ends on stop().
+     */
+    public async *frames(maxFrames: number = 10): AsyncGenerator<Buffer> {
+        if (this.isRecording) {
+            throw new Error('[SystemAudioCapture] frames() cannot be combined with start()');
+        }
+        if (!this.ensureMonitor()) return;
+
+        console.log('[SystemAudioCapture] Starting native capture (pull mode)...');
+        this.attachEvents();
+        this.monitor.startPull();
+        this.isRecording = true;
+        this.emit('start');
+
+        while (true) {
+            const batch: Buffer[] = await this.monitor.read(maxFrames);
+            if (batch.length === 0) return;
+            for (const frame of batch) {
+                yield frame;
+            }
+        }
+    }
+
+    /**
+     * Utterance mode: instead of 'data' frames, emits 'utterance' with one
+     * complete speech segment at a time (pre-roll and trailing padding included),
+     * ready to upload to a REST STT API
+     */
+    public startUtterances(options?: UtteranceOptions): void {
+        if (this.isRecording) return;
+        if (!this.ensureMonitor()) return;
+
+        try {
+            console.log('[SystemAudioCapture] Starting native capture (utterance mode)...');
+            this.attachEvents();
+            this.monitor.startUtterances((utterance: Utterance) => {
+                this.emit('utterance', utterance);
+            }, options ?? null);
+            this.isRecording = true;
+            this.emit('start');
+        } catch (error) {
+            console.error('[SystemAudioCapture] Failed to start:', error);
+            this.emit('error', error);
+        }
+    }
+
+    /**
+     * On-device transcription: instead of 'data' frames, emits 'transcript'
+     * with partial and final results (same shape as the cloud STT providers).
+     * Requires a native build with the offline-stt feature.
+     */
+    public startTranscription(options: TranscriptionOptions): void {
+        if (this.isRecording) return;
+        if (!NativeModule?.isOfflineSttAvailable?.()) {
+            this.emit('error', new Error('Offline STT is not available in this build'));
+            return;
+        }
+        if (!this.ensureMonitor()) return;
+
+        try {
+            console.log('[SystemAudioCapture] Starting native capture (offline transcription)...');
+            this.attachEvents();
+            this.monitor.startTranscription((transcript: Transcript) => {
+                this.emit('transcript', transcript);
+            }, options);
+            this.isRecording = true;
+            this.emit('start');
+        } catch (error) {
+            console.error('[SystemAudioCapture] Failed to start:', error);
+            this.emit('error', error);
+        }
+    }
+
+    /**
+     * Label speech with stable speaker IDs (panel interviews): emits 'speaker'
+     * with a SpeakerSegment per labelled stretch. null turns it off.
+     * Applies to the running capture and to later starts.
+     */
+    public setDiarization(options: DiarizationOptions | null): void {
+        this.diarization = options;
+        if (this.monitor) {
+            this.applyDiarization();
+        }
+    }
+
+    /**
+     * Speaker talking at `timeMs` (capture time, ms since the Unix epoch),
+     * e.g. a transcript's start time; null if unknown
+     */
+    public speakerAt(timeMs: number): string | null {
+        for (let i = this.speakerSegments.length - 1; i >= 0; i--) {
+            const segment = this.speakerSegments[i];
+            if (segment.startTimeMs === null || segment.endTimeMs === null) continue;
+            if (timeMs >= segment.startTimeMs && timeMs < segment.endTimeMs) {
+                return segment.speaker;
+            }
+        }
+        return null;
+    }
+
+    private applyDiarization(): void {
+        try {
+            this.monitor.configure(this.diarization
+                ? { speakerDiarization: true, ...this.diarization }
+                : { speakerDiarization: false });
+        } catch (e) {
+            console.error('[SystemAudioCapture] Failed to configure diarization:', e);
+            this.emit('error', e);
+        }
+    }
+
+    /**
+     * Report this capture's speech ("remote" party) to `tracker`; null detaches.
+     * Applies to the running capture and to later starts.
+     */
+    public trackConversation(tracker: ConversationTracker | null): void {
+        this.conversation = tracker;
+        if (this.monitor) {
+            this.applyConversation();
+        }
+    }
+
+    private applyConversation(): void {
+        if (this.conversation?.native) {
+            this.monitor.trackConversation(this.conversation.native);
+        } else {
+            this.monitor.untrackConversation();
+        }
+    }
+
+    private attachEvents(): void {
+        if (this.conversation) {
+            this.applyConversation();
+        }
+        this.speakerSegments = [];
+        if (this.diarization) {
+            this.applyDiarization();
+        }
+        // Backpressure notifications: { type: 'lagging', lagging, queued, dropped }
+        // Device format changes: { type: 'formatChanged', sampleRate, channels, outputChannels }
+        // Content classification: { type: 'contentClass', class, speech, music, noise }
+        // Diarization: { type: 'speaker', speaker, seqStart, seqEnd, startTimeMs, endTimeMs, newSpeaker }
+        // Rising intonation at an utterance end: { type: 'likelyQuestion', seq, timeMs, confidence, riseSemitones }
+        this.monitor.onEvent((event: any) => {
+            if (event?.type === 'lagging') {
+                this.emit('lagging', event);
+            } else if (event?.type === 'formatChanged') {
+                this.emit('formatChanged', event);
+            } else if (event?.type === 'contentClass') {
+                this.emit('contentClass', event);
+            } else if (event?.type === 'speaker') {
+                const { type, ...segment } = event;
+                this.speakerSegments.push(segment);
+                if (this.speakerSegments.length > MAX_SPEAKER_SEGMENTS) {
+                    this.speakerSegments.shift();
+                }
+                this.emit('speaker', segment);
+            } else if (event?.type === 'likelyQuestion') {
+                const { type, ...cue } = event;
+                this.emit('likelyQuestion', cue);
+            }
+        });
+    }
+
     /**
      * Stop capturing
      */
@@ -106,9 +352,95 @@ export class SystemAudioCapture extends EventEmitter {
             console.error('[SystemAudioCapture] Error stopping:', e);
         }
 
-        // Destroy monitor
-        this.monitor = null;
+        // Keep the monitor: the native object supports start() again after stop()
         this.isRecording = false;
         this.emit('stop');
     }
+
+    public destroy(): void {
+        this.stop();
+        this.monitor = null;
+    }
+
+    /**
+     * Pause emitting audio without tearing down the OS stream.
+     * Avoids the tap / ScreenCaptureKit setup cost (and audio mute) of stop() + start().
+     */
+    public pause(): void {
+        if (!this.isRecording) return;
+        try {
+            this.monitor?.pause();
+            this.emit('pause');
+        } catch (e) {
+            console.error('[SystemAudioCapture] Error pausing:', e);
+        }
+    }
+
+    public resume(): void {
+        if (!this.isRecording) return;
+        try {
+            this.monitor?.resume();
+            this.emit('resume');
+        } catch (e) {
+            console.error('[SystemAudioCapture] Error resuming:', e);
+        }
+    }
+
+    /**
+     * Mute: frames keep flowing (zero-filled) so STT timing is preserved
+     */
+    public setMuted(muted: boolean): void {
+        try {
+            this.monitor?.setMuted(muted);
+        } catch (e) {
+            console.error('[SystemAudioCapture] Error setting mute:', e);
+        }
+    }
+
+    /**
+     * Frame delivery counters (dropped / coalesced frames while JS was lagging)
+     * and device audio lost to ring buffer overflows
+     */
+    public getStats(): {
+        delivered: number;
+        dropped: number;
+        coalesced: number;
+        queued: number;
+        lagging: boolean;
+        ringOverflows: number;
+        ringDroppedMs: number;
+    } | null {
+        try {
+            return this.monitor?.getStats() ?? null;
+        } catch (e) {
+            console.error('[SystemAudioCapture] Error reading stats:', e);
+            return null;
+        }
+    }
+
+    /**
+     * The last `seconds` of processed audio (kept natively, default 30s;
+     * see the `historySeconds` capture option), e.g. to re-transcribe a mis-heard question
+     */
+    public getRecent(seconds: number, format: 'wav' | 'pcm' = 'wav'): AudioClip | null {
+        try {
+            return this.monitor?.getRecent(seconds, format) ?? null;
+        } catch (e) {
+            console.error('[SystemAudioCapture] Error reading history:', e);
+            return null;
+        }
+    }
+
+    /**
+     * Processed audio of frames `seqStart..=seqEnd` (the `seq` of 'data' chunks),
+     * e.g. to re-send audio lost during an STT reconnect
+     */
+    public getRange(seqStart: number, seqEnd: number, format: 'wav' | 'pcm' = 'wav'): AudioClip | null {
+        try {
+            return this.monitor?.getRange(seqStart, seqEnd, format) ?? null;
+        } catch (e) {
+            console.error('[SystemAudioCapture] Error reading history:', e);
+            return null;
+        }
+    }
 }
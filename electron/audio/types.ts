@@ -0,0 +1,72 @@
+// Types shared by MicrophoneCapture and SystemAudioCapture
+
+export interface RingBufferOptions {
+    /** Capacity in ms of device audio (default 680) */
+    ringBufferMs?: number;
+    ringOverflow?: 'drop-newest' | 'overwrite-oldest';
+}
+
+export interface UtteranceOptions {
+    /** Audio kept before speech starts (default 300) */
+    preRollMs?: number;
+    /** Silence kept after speech ends (default 300, at most the speech hangover) */
+    trailingPaddingMs?: number;
+    /** Utterances with less speech are dropped (default 250) */
+    minDurationMs?: number;
+    /** Longer utterances are split at their quietest point (default 30000) */
+    maxDurationMs?: number;
+    format?: 'wav' | 'pcm';
+}
+
+export interface Utterance {
+    /** WAV file (default) or raw 16-bit PCM */
+    data: Buffer;
+    format: 'wav' | 'pcm';
+    sampleRate: number;
+    channels: number;
+    seq: number;
+    /** Capture time of the first sample (ms since the Unix epoch), null if unknown */
+    startTimeMs: number | null;
+    endTimeMs: number | null;
+    durationMs: number;
+    /** Cut at maxDurationMs rather than ended by a pause */
+    forcedSplit: boolean;
+}
+
+export interface TranscriptionOptions {
+    /** Path to a ggml Whisper model file (e.g. ggml-base.en.bin) */
+    modelPath: string;
+    /** Language code or 'auto' (default 'en') */
+    language?: string;
+    /** CPU threads for inference (default: up to 4) */
+    threads?: number;
+    /** New audio between partial transcripts (default 1000) */
+    partialIntervalMs?: number;
+    /** Longest utterance decoded at once (default 30000) */
+    maxUtteranceMs?: number;
+}
+
+export interface Transcript {
+    text: string;
+    isFinal: boolean;
+    confidence: number;
+    /** Sequence number of the first frame of the utterance */
+    seq: number;
+    startTimeMs: number | null;
+    durationMs: number;
+    segments: { text: string; startMs: number; endMs: number }[];
+}
+
+export interface AudioClip {
+    /** WAV file (default) or raw 16-bit PCM */
+    data: Buffer;
+    format: 'wav' | 'pcm';
+    sampleRate: number;
+    channels: number;
+    /** Sequence number of the first 20ms frame */
+    seq: number;
+    frames: number;
+    startTimeMs: number | null;
+    endTimeMs: number | null;
+    durationMs: number;
+}
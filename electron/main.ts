@@ -21,7 +21,7 @@ import { CalendarManager } from "./services/CalendarManager"
 import { ProcessingHelper } from "./ProcessingHelper"
 
 import { IntelligenceManager } from "./IntelligenceManager"
-import { SystemAudioCapture } from "./audio/SystemAudioCapture"
+import { SystemAudioCapture, LikelyQuestion } from "./audio/SystemAudioCapture"
 import { MicrophoneCapture } from "./audio/MicrophoneCapture"
 import { GoogleSTT } from "./audio/GoogleSTT"
 import { RestSTT } from "./audio/RestSTT"
@@ -456,6 +456,7 @@ export class AppState {
       this.systemAudioCapture.on('error', (err: Error) => {
         log.error('[Main] SystemAudioCapture Error:', err);
       });
+      this.systemAudioCapture.on('likelyQuestion', (cue: LikelyQuestion) => this.handleLikelyQuestion(cue));
 
       this.microphoneCapture.on('data', (chunk: Buffer) => {
         if (this.isMeetingActive) this.googleSTT_User?.write(chunk);
@@ -544,6 +545,20 @@ export class AppState {
     return instance;
   }
 
+  /**
+   * The interviewer's utterance ended with rising intonation: suggest an
+   * answer without waiting for the transcript to show a question mark
+   */
+  private handleLikelyQuestion(cue: LikelyQuestion): void {
+    if (!this.isMeetingActive) return;
+    log.info(`[Main] Likely question (confidence ${cue.confidence.toFixed(2)}, rise ${cue.riseSemitones.toFixed(1)} st)`);
+    this.intelligenceManager.handleSuggestionTrigger({
+      context: '',
+      lastQuestion: '',
+      confidence: cue.confidence
+    }).catch(err => log.error('[Main] Question trigger failed:', err));
+  }
+
   private setupSTTEventHandlers(sttInstance: any, speaker: 'user' | 'interviewer') {
     sttInstance.on('transcript', (segment: { text: string, isFinal: boolean, confidence: number }) => {
       if (!this.isMeetingActive) return;
@@ -633,11 +648,13 @@ export class AppState {
       this.systemAudioCapture.on('error', (err: Error) => {
         log.error('[Main] SystemAudioCapture Error:', err);
       });
+      this.systemAudioCapture.on('likelyQuestion', (cue: LikelyQuestion) => this.handleLikelyQuestion(cue));
     } catch (err) {
       log.warn('[Main] Failed to reconfigure SystemAudioCapture, trying default.', err);
       this.systemAudioCapture = new SystemAudioCapture(); // Fallback
       // re-wire
       this.systemAudioCapture.on('data', (chunk) => this.googleSTT?.write(chunk));
+      this.systemAudioCapture.on('likelyQuestion', (cue: LikelyQuestion) => this.handleLikelyQuestion(cue));
     }
 
     // 2. Microphone
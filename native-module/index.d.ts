@@ -3,21 +3,411 @@
 
 /* auto-generated by NAPI-RS */
 
+/**
+ * Options accepted by `configure()` on both capture classes
+ * Every field is optional; omitted fields keep their current value.
+ */
+export interface CaptureOptions {
+  /** Suppressor speech threshold (RMS, i16 scale) */
+  speechThresholdRms?: number
+  speechHangoverMs?: number
+  silenceKeepaliveIntervalMs?: number
+  /** Keepalive content: "zeros" (default) or "comfort-noise" */
+  silenceFill?: string
+  /** Send a fill frame for every suppressed 20ms slot (default false) */
+  contiguousSilence?: boolean
+  agcEnabled?: boolean
+  agcTargetDbfs?: number
+  agcMaxGainDb?: number
+  agcAttackMs?: number
+  agcReleaseMs?: number
+  /** Speech / music / noise classification (default on for system audio) */
+  contentClassification?: boolean
+  /** Treat frames classified as music / noise as non-speech (default true) */
+  contentGate?: boolean
+  /** Attenuate background music under speech, adds 20ms latency (default false) */
+  musicAttenuation?: boolean
+  /** Label speech with stable speaker IDs, raised as `speaker` events (default false) */
+  speakerDiarization?: boolean
+  /** Voices tracked at most; further voices join the closest one (default 8) */
+  maxSpeakers?: number
+  /**
+   * Largest voice distance still counted as the same speaker (default 0.5;
+   * lower splits more readily)
+   */
+  speakerDistanceThreshold?: number
+  /**
+   * Flag utterances ending with rising intonation, raised as `likelyQuestion`
+   * events (default on for system audio)
+   */
+  questionDetection?: boolean
+  /** Lowest confidence reported as a likely question (0..1, default 0.5) */
+  questionMinConfidence?: number
+  /** "suppress" (default) or "passthrough" */
+  vadMode?: string
+  muted?: boolean
+  /** Number of 20ms frames per callback (default 1) */
+  framesPerCallback?: number
+  /** Frames (or batches) allowed to wait for a stalled JS thread (default 100) */
+  maxQueuedFrames?: number
+  /** "drop-oldest", "drop-newest" or "coalesce-silence" (default) */
+  overflowPolicy?: string
+  /** Seconds of processed audio kept for getRecent() / getRange() (default 30, 0 disables) */
+  historySeconds?: number
+  /** "pcm16" (default) or "mulaw" (half the memory) */
+  historyEncoding?: string
+}
+/** Frame delivery counters returned by `getStats()` */
+export interface CaptureStats {
+  /** Frames handed to the JS callback */
+  delivered: number
+  /** Frames dropped because JS was not keeping up */
+  dropped: number
+  /** Silence / keepalive frames merged away under backpressure */
+  coalesced: number
+  /** Frames currently waiting for JS */
+  queued: number
+  lagging: boolean
+  /** Device pushes that did not fit into the ring buffer */
+  ringOverflows: number
+  /** Device audio lost to ring buffer overflows (ms) */
+  ringDroppedMs: number
+}
+/** Frame Buffer passed to start() callbacks and returned by read() */
+export interface AudioFrame extends Buffer {
+  /** Index of the (first) 20ms frame since start; suppressed frames use up numbers too */
+  seq: number
+  /** Capture time of the first sample (ms since the Unix epoch), null if unknown */
+  captureTimeMs: number | null
+}
+/** Utterance mode (`startUtterances()`): whole speech segments instead of frames */
+export interface UtteranceOptions {
+  /** Audio kept before speech starts (default 300) */
+  preRollMs?: number
+  /** Silence kept after speech ends (default 300, at most speechHangoverMs) */
+  trailingPaddingMs?: number
+  /** Utterances with less speech are dropped (default 250) */
+  minDurationMs?: number
+  /** Longer utterances are split at their quietest point (default 30000) */
+  maxDurationMs?: number
+  /** "wav" (default) or "pcm" */
+  format?: string
+}
+/** Complete utterance passed to startUtterances() callbacks */
+export interface Utterance {
+  /** WAV file or raw 16-bit PCM (see `format`) */
+  data: Buffer
+  format: string
+  sampleRate: number
+  channels: number
+  /** Sequence number of the first 20ms frame */
+  seq: number
+  /** Capture time of the first sample (ms since the Unix epoch), null if unknown */
+  startTimeMs: number | null
+  /** Capture time just after the last sample */
+  endTimeMs: number | null
+  durationMs: number
+  /** Cut at maxDurationMs rather than ended by a pause */
+  forcedSplit: boolean
+}
+/** Processed audio returned by getRecent() / getRange() */
+export interface AudioClip {
+  /** WAV file or raw 16-bit PCM (see `format`) */
+  data: Buffer
+  format: string
+  sampleRate: number
+  channels: number
+  /** Sequence number of the first 20ms frame */
+  seq: number
+  /** Number of 20ms frames */
+  frames: number
+  /** Capture time of the first sample (ms since the Unix epoch), null if unknown */
+  startTimeMs: number | null
+  /** Capture time just after the last sample */
+  endTimeMs: number | null
+  durationMs: number
+}
+/** On-device transcription (`startTranscription()`, `offline-stt` builds only) */
+export interface TranscriptionOptions {
+  /** Path to a ggml Whisper model file (e.g. ggml-base.en.bin) */
+  modelPath: string
+  /** Language code ("en", "de", ...) or "auto" (default "en") */
+  language?: string
+  /** CPU threads for inference (default: up to 4) */
+  threads?: number
+  /** New audio between partial transcripts (default 1000) */
+  partialIntervalMs?: number
+  /** Longest utterance decoded at once (default 30000) */
+  maxUtteranceMs?: number
+}
+export interface TranscriptSegment {
+  text: string
+  /** Offset from the start of the utterance */
+  startMs: number
+  endMs: number
+}
+/** Partial or final transcript passed to startTranscription() callbacks */
+export interface Transcript {
+  /** Whole utterance so far */
+  text: string
+  /** Last transcript of this utterance; partials may still change */
+  isFinal: boolean
+  /** Always 1 (kept for parity with the cloud providers) */
+  confidence: number
+  /** Sequence number of the first 20ms frame of the utterance */
+  seq: number
+  /** Capture time of the first sample (ms since the Unix epoch), null if unknown */
+  startTimeMs: number | null
+  durationMs: number
+  segments: Array<TranscriptSegment>
+}
+/** Ring buffer between the device callback and the DSP thread (fixed at construction) */
+export interface RingBufferOptions {
+  /** Capacity in ms of device audio (default 680) */
+  ringBufferMs?: number
+  /** "drop-newest" (default) or "overwrite-oldest" */
+  ringOverflow?: string
+}
+/**
+ * Restrict system audio capture to some applications (by process id)
+ * Set at most one of the two lists.
+ */
+export interface AppFilter {
+  /** Capture only these processes */
+  includePids?: Array<number>
+  /** Capture everything except these processes */
+  excludePids?: Array<number>
+}
+/** Multichannel system audio capture (fixed at construction) */
+export interface ChannelOptions {
+  /** Channels to record (default 1; macOS supports 1 or 2) */
+  channels?: number
+  /**
+   * "downmix" (default): mono frames averaged from all channels
+   * "keep": interleaved frames with every channel (see getChannels())
+   */
+  channelMode?: string
+}
 export interface AudioDeviceInfo {
   id: string
   name: string
 }
 export declare function getInputDevices(): Array<AudioDeviceInfo>
 export declare function getOutputDevices(): Array<AudioDeviceInfo>
+export interface AudioApplicationInfo {
+  pid: number
+  name: string
+}
+/** Applications currently producing audio (for SystemAudioCapture's AppFilter) */
+export declare function listAudioApplications(): Array<AudioApplicationInfo>
+/** True if this build can transcribe on-device (`startTranscription()`) */
+export declare function isOfflineSttAvailable(): boolean
 export declare class SystemAudioCapture {
-  constructor(deviceId?: string | undefined | null)
+  constructor(deviceId?: string | undefined | null, filter?: AppFilter | undefined | null, channels?: ChannelOptions | undefined | null, ring?: RingBufferOptions | undefined | null)
   getSampleRate(): number
+  /**
+   * Interleaved channels per frame (1 unless channelMode is "keep";
+   * exact once started, as the backend may not support every count)
+   */
+  getChannels(): number
+  /** "idle", "running" or "stopped" */
+  getState(): string
+  /** Update DSP / delivery parameters without restarting capture */
+  configure(opts: CaptureOptions): void
+  /** Stop emitting frames but keep the OS stream alive (instant resume) */
+  pause(): void
+  resume(): void
+  isPaused(): boolean
+  /** Zero-fill frames while keeping the stream (and keepalives) running */
+  setMuted(muted: boolean): void
+  /** Receive capture events (`{ type: "lagging" | "formatChanged" | "contentClass" | "speaker" | "likelyQuestion" | "keyword", ... }`) */
+  onEvent(callback: (...args: any[]) => any): void
+  /** Frame delivery counters (dropped / coalesced frames, queue depth) */
+  getStats(): CaptureStats
+  /**
+   * The last `seconds` of processed audio (null if none is kept)
+   * `format`: "wav" (default) or "pcm"
+   */
+  getRecent(seconds: number, format?: string | undefined | null): AudioClip | null
+  /** Processed audio of frames `seqStart..=seqEnd` still in the history */
+  getRange(seqStart: number, seqEnd: number, format?: string | undefined | null): AudioClip | null
+  /** Start capturing (no-op if already running, restart allowed after stop) */
   start(callback: (...args: any[]) => any): void
+  /** Start capturing without a callback; fetch frames with read() */
+  startPull(): void
+  /**
+   * Start capturing in utterance mode: `callback` receives whole
+   * utterances (WAV or PCM) instead of frames
+   */
+  startUtterances(callback: (utterance: Utterance) => void, options?: UtteranceOptions | undefined | null): void
+  /**
+   * Start capturing and transcribe on-device: `callback` receives partial
+   * and final transcripts instead of frames (`offline-stt` builds only)
+   */
+  startTranscription(callback: (transcript: Transcript) => void, options: TranscriptionOptions): void
+  /**
+   * Wait for up to `maxFrames` frames (default 10)
+   * Resolves with [] once capture is stopped and the queue is drained.
+   * Throws unless the last start was startPull().
+   */
+  read(maxFrames?: number | undefined | null): Promise<Array<AudioFrame>>
+  /** Report speech to `tracker` (this capture is the "remote" party) */
+  trackConversation(tracker: ConversationTracker): void
+  untrackConversation(): void
+  /** Stop capturing (no-op if not running) */
   stop(): void
 }
+/** A phrase to spot and its enrollment recordings */
+export interface KeywordDefinition {
+  /** Reported as `keyword` on matches */
+  keyword: string
+  /**
+   * Recordings of the user saying the phrase (16kHz mono 16-bit PCM,
+   * 200ms..3s of sound; leading / trailing silence is trimmed)
+   */
+  templates: Array<Buffer>
+}
+/** Keyword spotting on the microphone (`setKeywordSpotting()`) */
+export interface KeywordSpottingOptions {
+  keywords: Array<KeywordDefinition>
+  /** Largest match distance (default 0.5; lower is stricter) */
+  maxDistance?: number
+  /**
+   * Replace matched phrases with silence before frames are delivered
+   * (adds up to 2x the longest template of latency, default false)
+   */
+  suppress?: boolean
+}
 export declare class MicrophoneCapture {
-  constructor(deviceId?: string | undefined | null)
+  constructor(deviceId?: string | undefined | null, ring?: RingBufferOptions | undefined | null)
   getSampleRate(): number
+  /** "idle", "running" or "stopped" */
+  getState(): string
+  /** Update DSP / delivery parameters without restarting capture */
+  configure(opts: CaptureOptions): void
+  /** Stop emitting frames but keep the OS stream alive (instant resume) */
+  pause(): void
+  resume(): void
+  isPaused(): boolean
+  /** Zero-fill frames while keeping the stream (and keepalives) running */
+  setMuted(muted: boolean): void
+  /** Spot enrolled phrases (raised as `keyword` events), or stop with null */
+  setKeywordSpotting(options?: KeywordSpottingOptions | undefined | null): void
+  /** Receive capture events (`{ type: "lagging" | "formatChanged" | "contentClass" | "speaker" | "likelyQuestion" | "keyword", ... }`) */
+  onEvent(callback: (...args: any[]) => any): void
+  /** Frame delivery counters (dropped / coalesced frames, queue depth) */
+  getStats(): CaptureStats
+  /**
+   * The last `seconds` of processed audio (null if none is kept)
+   * `format`: "wav" (default) or "pcm"
+   */
+  getRecent(seconds: number, format?: string | undefined | null): AudioClip | null
+  /** Processed audio of frames `seqStart..=seqEnd` still in the history */
+  getRange(seqStart: number, seqEnd: number, format?: string | undefined | null): AudioClip | null
+  /** Start capturing (no-op if already running, restart allowed after stop) */
   start(callback: (...args: any[]) => any): void
+  /** Start capturing without a callback; fetch frames with read() */
+  startPull(): void
+  /**
+   * Start capturing in utterance mode: `callback` receives whole
+   * utterances (WAV or PCM) instead of frames
+   */
+  startUtterances(callback: (utterance: Utterance) => void, options?: UtteranceOptions | undefined | null): void
+  /**
+   * Start capturing and transcribe on-device: `callback` receives partial
+   * and final transcripts instead of frames (`offline-stt` builds only)
+   */
+  startTranscription(callback: (transcript: Transcript) => void, options: TranscriptionOptions): void
+  /**
+   * Wait for up to `maxFrames` frames (default 10)
+   * Resolves with [] once capture is stopped and the queue is drained.
+   * Throws unless the last start was startPull().
+   */
+  read(maxFrames?: number | undefined | null): Promise<Array<AudioFrame>>
+  /** Report speech to `tracker` (this capture is the "user" party) */
+  trackConversation(tracker: ConversationTracker): void
+  untrackConversation(): void
+  /** Stop capturing (no-op if not running) */
   stop(): void
 }
+/** Conversation tracking thresholds (`new ConversationTracker()`) */
+export interface ConversationOptions {
+  /** Speech shorter than this is ignored (default 200) */
+  minSpeechMs?: number
+  /** Silence that ends a party's speech (default 300) */
+  releaseMs?: number
+  /** Shortest simultaneous speech reported as an overlap (default 300) */
+  minOverlapMs?: number
+  /** Shortest silence within a party's speech counted as a pause (default 500) */
+  minPauseMs?: number
+  /** Period of the "sessionMetrics" event (default 30000, 0 = never) */
+  metricsIntervalMs?: number
+}
+/** One party's share of a session (see SessionMetrics) */
+export interface PartyMetrics {
+  talkTimeMs: number
+  /** Times the party took the floor */
+  turns: number
+  /** Longest stretch holding the floor */
+  longestMonologueMs: number
+  syllables: number
+  /** Syllables per second of talk time */
+  syllableRate: number
+}
+/** Conversation analytics since the tracker was created / reset */
+export interface SessionMetrics {
+  durationMs: number
+  user: PartyMetrics
+  remote: PartyMetrics
+  /** User share of the total talk time (0..1) */
+  userTalkRatio: number
+  pauses: number
+  pauseTotalMs: number
+  longestPauseMs: number
+  /** User turns following the remote party */
+  responses: number
+  /** From the remote party's last speech to the user's answer */
+  meanResponseLatencyMs?: number
+  medianResponseLatencyMs?: number
+  overlaps: number
+  overlapTotalMs: number
+  interruptions: number
+}
+/** Event passed to ConversationTracker.onEvent() callbacks */
+export interface ConversationEvent {
+  type: "overlapStart" | "overlapEnd" | "userInterrupted" | "turnTaken" | "sessionMetrics"
+  /** Capture time (ms since the Unix epoch): start of the overlap / turn, end of the overlap, time of the report */
+  timeMs: number
+  /** overlapStart: party that started talking over the other */
+  by?: "user" | "remote"
+  /** overlapEnd */
+  durationMs?: number
+  /** overlapEnd: party still talking, null if neither */
+  remaining?: "user" | "remote" | null
+  /** turnTaken: party that has the floor now */
+  party?: "user" | "remote"
+  /** turnTaken: since the previous turn ended (negative if they overlapped), null for the first turn */
+  gapMs?: number | null
+  /** sessionMetrics */
+  metrics?: SessionMetrics
+}
+/**
+ * Overlap / interruption / turn detection and session metrics between a
+ * MicrophoneCapture (the user) and a SystemAudioCapture (the remote side)
+ */
+export declare class ConversationTracker {
+  constructor(options?: ConversationOptions | undefined | null)
+  /**
+   * Receive `{ type: "overlapStart" | "overlapEnd" | "userInterrupted" | "turnTaken", timeMs, ... }`
+   * and `{ type: "sessionMetrics", timeMs, metrics }`
+   */
+  onEvent(callback: (event: ConversationEvent) => void): void
+  /** Party that spoke alone last: "user", "remote" or null */
+  getFloor(): string | null
+  /** Both parties are talking right now */
+  isOverlapping(): boolean
+  /** Talk time, monologues, pauses, response latency and speaking rate so far */
+  getSessionMetrics(): SessionMetrics
+  /** Start a new session (e.g. the next interview) */
+  resetSessionMetrics(): void
+}
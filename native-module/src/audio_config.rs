@@ -28,12 +28,19 @@ pub const VAD_PREROLL_CHUNKS: usize = 3;
 /// VAD hangover duration in milliseconds
 pub const VAD_HANGOVER_MS: u128 = 500;
 
-/// DSP thread poll interval in milliseconds
-/// Lower = less latency, higher CPU
-/// 1ms is optimal for real-time audio
-pub const DSP_POLL_MS: u64 = 1;
-
-/// Ring buffer size in samples
-/// 128KB worth of f32 samples = 32768 samples
-/// At 48kHz = ~680ms buffer (plenty of headroom)
-pub const RING_BUFFER_SAMPLES: usize = 32768;
+/// Upper bound on how long the DSP thread sleeps without a producer wakeup
+/// The thread is normally woken by the producer once per 20ms frame;
+/// this only bounds latency if a wakeup is missed, and how long stop() waits.
+pub const DSP_WAKE_TIMEOUT_MS: u64 = 50;
+
+/// Default ring buffer duration per capture (ms of audio, any rate / channel count)
+/// ~680ms of headroom for the DSP thread; overridable per capture (RingConfig)
+pub const RING_BUFFER_MS: u32 = 680;
+
+/// Capacity of the JS -> DSP thread command queue
+/// Commands are drained every frame, so this only needs to absorb bursts
+pub const DSP_COMMAND_QUEUE_SIZE: usize = 64;
+
+/// Idle frame buffers kept for reuse per capture
+/// ~1s of frames in flight to JS before the pool has to allocate
+pub const FRAME_POOL_SIZE: usize = 64;
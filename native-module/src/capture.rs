@@ -0,0 +1,612 @@
+// Capture Lifecycle - shared by MicrophoneCapture and SystemAudioCapture
+//
+// STATE MACHINE:
+//   Idle --start--> Running --stop--> Stopped --start--> Running ...
+//
+// - start() while Running and stop() while not Running are no-ops
+// - The DSP thread owns the ring buffer consumer while Running and hands it
+//   back when it exits, so the same source can be restarted
+// - The delivery queue is opened on start (dropping what the last run left
+//   unread) and closed on stop, whichever way frames reach JS
+// - napi-free: the JS classes in lib.rs wrap a CaptureCore, tests drive it
+//   with a fake source
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use anyhow::Result;
+
+use crate::dsp::{
+    command_queue, run_capture_loop, CaptureLoop, ChannelMode, CommandProducer, DspCommand, DspPipeline, DspSettings,
+    SpeechObserver, UtteranceHandler,
+};
+use crate::delivery::{DeliveryConfig, DeliveryQueue};
+use crate::events::EventHandler;
+use crate::frame_pool::PooledFrame;
+use crate::history::{AudioHistory, HistoryConfig};
+use crate::microphone::MicrophoneStream;
+use crate::sample_ring::{DataNotify, RingStats, SampleConsumer};
+use crate::segmenter::{SegmenterConfig, Utterance, UtteranceSegmenter};
+use crate::speaker::{self, SpeakerOptions};
+
+/// A device backend that feeds a capture's ring buffer
+pub trait CaptureSource {
+    /// Open / resume the device stream
+    fn start(&mut self) -> Result<()>;
+
+    /// Pause / release the device stream
+    fn stop(&mut self);
+
+    /// Native sample rate of the samples in the ring buffer
+    /// Only valid after a successful start()
+    fn sample_rate(&self) -> u32;
+
+    /// Interleaved channels of the samples in the ring buffer
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    /// Hand the ring buffer consumer to the DSP thread
+    fn take_consumer(&mut self) -> Option<SampleConsumer>;
+
+    /// Give the consumer back after the DSP thread has exited
+    fn return_consumer(&mut self, consumer: SampleConsumer);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureState {
+    Idle,
+    Running,
+    Stopped,
+}
+
+/// Ring buffer overflow counters over the capture's lifetime
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RingTotals {
+    pub overflows: u64,
+    pub dropped_ms: f64,
+}
+
+/// Lifecycle + DSP thread management for one capture
+pub struct CaptureCore<S: CaptureSource> {
+    tag: &'static str,
+    source: S,
+    state: CaptureState,
+    stop_signal: Arc<AtomicBool>,
+    capture_thread: Option<thread::JoinHandle<SampleConsumer>>,
+    /// Wakes the DSP thread immediately on stop()
+    wake: Option<Arc<DataNotify>>,
+    settings: DspSettings,
+    commands: Option<CommandProducer>,
+    events: Option<EventHandler>,
+    speech_observer: Option<SpeechObserver>,
+    /// Counters of the current ring (system audio gets a new ring per start)
+    ring: Option<Arc<RingStats>>,
+    /// Counters of earlier rings
+    ring_totals: RingTotals,
+    /// Recent processed audio, shared with the DSP thread
+    history: Arc<Mutex<AudioHistory>>,
+    /// Frames waiting for JS (push and pull delivery)
+    delivery: Arc<DeliveryQueue>,
+}
+
+impl<S: CaptureSource> CaptureCore<S> {
+    pub fn new(tag: &'static str, source: S, settings: DspSettings) -> Self {
+        Self {
+            tag,
+            source,
+            state: CaptureState::Idle,
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            capture_thread: None,
+            wake: None,
+            settings,
+            commands: None,
+            events: None,
+            speech_observer: None,
+            ring: None,
+            ring_totals: RingTotals::default(),
+            history: Arc::new(Mutex::new(AudioHistory::new(HistoryConfig::default()))),
+            delivery: Arc::new(DeliveryQueue::new(DeliveryConfig::default())),
+        }
+    }
+
+    pub fn tag(&self) -> &'static str {
+        self.tag
+    }
+
+    pub fn state(&self) -> CaptureState {
+        self.state
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state == CaptureState::Running
+    }
+
+    pub fn settings(&self) -> &DspSettings {
+        &self.settings
+    }
+
+    /// Receive events raised on the DSP thread (from the next start() on)
+    pub fn set_event_handler(&mut self, handler: EventHandler) {
+        self.events = Some(handler);
+    }
+
+    /// Report every processed frame's speech state (from the next start() on)
+    pub fn set_speech_observer(&mut self, observer: SpeechObserver) {
+        self.speech_observer = Some(observer);
+    }
+
+    /// Channels of the emitted frames (1 unless channels are kept)
+    pub fn output_channels(&self) -> u16 {
+        match self.settings.channel_mode {
+            ChannelMode::Downmix => 1,
+            ChannelMode::Keep => self.source.channels().max(1),
+        }
+    }
+
+    /// Ring buffer overflows since the capture was created
+    pub fn ring_totals(&self) -> RingTotals {
+        let mut totals = self.ring_totals;
+        if let Some(ring) = &self.ring {
+            totals.overflows += ring.overflows();
+            totals.dropped_ms += ring.dropped_ms();
+        }
+        totals
+    }
+
+    /// Processed audio of the current (or last) run
+    pub fn history(&self) -> &Arc<Mutex<AudioHistory>> {
+        &self.history
+    }
+
+    /// Frames waiting for JS (see delivery.rs)
+    pub fn delivery(&self) -> &Arc<DeliveryQueue> {
+        &self.delivery
+    }
+
+    fn track_ring(&mut self, stats: Arc<RingStats>) {
+        if let Some(previous) = self.ring.take() {
+            if !Arc::ptr_eq(&previous, &stats) {
+                self.ring_totals.overflows += previous.overflows();
+                self.ring_totals.dropped_ms += previous.dropped_ms();
+            }
+        }
+        self.ring = Some(stats);
+    }
+
+    /// Start the source and spawn the DSP thread
+    /// Idempotent: returns Ok(false) if already running.
+    pub fn start<F>(&mut self, emit: F) -> Result<bool>
+    where
+        F: FnMut(PooledFrame) + Send + 'static,
+    {
+        self.start_with(emit, None)
+    }
+
+    /// Start in utterance mode: `handler` receives whole utterances
+    /// (see segmenter.rs) instead of frames
+    pub fn start_utterances<U>(&mut self, config: SegmenterConfig, handler: U) -> Result<bool>
+    where
+        U: FnMut(Utterance) + Send + 'static,
+    {
+        self.start_with(|_frame| {}, Some((config, Box::new(handler))))
+    }
+
+    fn start_with<F>(&mut self, emit: F, segmenter: Option<(SegmenterConfig, UtteranceHandler)>) -> Result<bool>
+    where
+        F: FnMut(PooledFrame) + Send + 'static,
+    {
+        if self.is_running() {
+            println!("[{}] start() ignored: already running", self.tag);
+            return Ok(false);
+        }
+
+        self.source.start()?;
+        let consumer = match self.source.take_consumer() {
+            Some(consumer) => consumer,
+            None => {
+                self.source.stop();
+                return Err(anyhow::anyhow!("Failed to get consumer"));
+            }
+        };
+        let input_sample_rate = self.source.sample_rate() as f64;
+        let input_channels = self.source.channels().max(1) as usize;
+        self.wake = Some(consumer.notifier());
+        self.track_ring(consumer.stats());
+
+        self.stop_signal.store(false, Ordering::SeqCst);
+        let stop_signal = self.stop_signal.clone();
+        let pipeline = DspPipeline::new(&self.settings);
+        let (producer, commands) = command_queue();
+        self.commands = Some(producer);
+        let mut capture_loop = CaptureLoop::new(input_sample_rate, input_channels, pipeline, commands);
+        if let Some(events) = &self.events {
+            capture_loop.set_event_handler(events.clone());
+        }
+        if let Some(observer) = &self.speech_observer {
+            capture_loop.set_speech_observer(observer.clone());
+        }
+        // Sequence numbers start over with every run
+        if let Ok(mut history) = self.history.lock() {
+            history.clear();
+        }
+        capture_loop.set_history(self.history.clone());
+        self.delivery.open();
+        if let Some((config, handler)) = segmenter {
+            let segmenter = UtteranceSegmenter::new(config, capture_loop.output_channels());
+            capture_loop.set_segmenter(segmenter, handler);
+        }
+
+        let tag = self.tag;
+        self.capture_thread = Some(thread::spawn(move || {
+            run_capture_loop(tag, consumer, capture_loop, stop_signal, emit)
+        }));
+
+        self.state = CaptureState::Running;
+        Ok(true)
+    }
+
+    /// Stop the DSP thread and the source
+    /// Idempotent: does nothing unless running.
+    pub fn stop(&mut self) {
+        if !self.is_running() {
+            return;
+        }
+
+        self.stop_signal.store(true, Ordering::SeqCst);
+        if let Some(wake) = self.wake.take() {
+            wake.notify();
+        }
+        if let Some(handle) = self.capture_thread.take() {
+            match handle.join() {
+                Ok(consumer) => self.source.return_consumer(consumer),
+                Err(_) => eprintln!("[{}] DSP thread panicked, consumer lost", self.tag),
+            }
+        }
+        self.commands = None;
+        self.source.stop();
+        // Ends the pull stream: a pending read() resolves with []
+        self.delivery.close();
+        self.state = CaptureState::Stopped;
+    }
+
+    /// Record commands in the settings and forward them to the DSP thread (if running)
+    /// Takes effect at the next frame boundary. All or nothing: when the
+    /// queue can't take the whole batch, neither the settings nor the DSP
+    /// thread see any of it.
+    pub fn send(&mut self, commands: Vec<DspCommand>) -> Result<()> {
+        use ringbuf::traits::{Observer, Producer};
+
+        if let Some(producer) = self.commands.as_ref() {
+            if producer.vacant_len() < commands.len() {
+                return Err(anyhow::anyhow!("DSP command queue full"));
+            }
+        }
+        for command in commands {
+            self.settings.apply(&command);
+            if let Some(producer) = self.commands.as_mut() {
+                producer
+                    .try_push(command)
+                    .map_err(|_| anyhow::anyhow!("DSP command queue full"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: CaptureSource> Drop for CaptureCore<S> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// ============================================================================
+// SOURCES
+// ============================================================================
+
+impl CaptureSource for MicrophoneStream {
+    fn start(&mut self) -> Result<()> {
+        self.play()
+    }
+
+    fn stop(&mut self) {
+        let _ = self.pause();
+    }
+
+    fn sample_rate(&self) -> u32 {
+        MicrophoneStream::sample_rate(self)
+    }
+
+    fn take_consumer(&mut self) -> Option<SampleConsumer> {
+        MicrophoneStream::take_consumer(self)
+    }
+
+    fn return_consumer(&mut self, consumer: SampleConsumer) {
+        MicrophoneStream::return_consumer(self, consumer)
+    }
+}
+
+/// System audio source with lazy init
+///
+/// The OS stream is only created on start() (creating it early causes a
+/// 1-second audio mute on macOS) and released on stop().
+pub struct SystemAudioSource {
+    device_id: Option<String>,
+    options: SpeakerOptions,
+    stream: Option<speaker::SpeakerStream>,
+}
+
+impl SystemAudioSource {
+    pub fn new(device_id: Option<String>, options: SpeakerOptions) -> Self {
+        Self {
+            device_id,
+            options,
+            stream: None,
+        }
+    }
+}
+
+impl CaptureSource for SystemAudioSource {
+    fn start(&mut self) -> Result<()> {
+        if self.stream.is_some() {
+            return Ok(());
+        }
+
+        println!("[SystemAudioCapture] Creating system audio stream...");
+        let input = match speaker::SpeakerInput::with_options(self.device_id.clone(), self.options.clone()) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("[SystemAudioCapture] Failed: {}. Trying default...", e);
+                speaker::SpeakerInput::with_options(None, self.options.clone())?
+            }
+        };
+        self.stream = Some(input.stream()?);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        // Dropping the stream releases the tap / SCK stream
+        self.stream = None;
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.stream.as_ref().map(|s| s.sample_rate()).unwrap_or(0)
+    }
+
+    /// Requested channels until the stream is open, then what the backend delivers
+    fn channels(&self) -> u16 {
+        self.stream.as_ref().map(|s| s.channels()).unwrap_or(self.options.channels)
+    }
+
+    fn take_consumer(&mut self) -> Option<SampleConsumer> {
+        self.stream.as_mut().and_then(|s| s.take_consumer())
+    }
+
+    fn return_consumer(&mut self, _consumer: SampleConsumer) {
+        // The consumer belongs to the stream's ring buffer, which is
+        // released on stop(); the next start() creates a fresh one.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{mpsc, Mutex};
+    use std::time::Duration;
+
+    use crate::audio_config::FRAME_SAMPLES;
+    use crate::dsp::VadMode;
+    use crate::sample_ring::{sample_ring, SampleProducer};
+
+    /// Fake device: test pushes samples through the shared producer
+    struct FakeSource {
+        consumer: Option<SampleConsumer>,
+        producer: Arc<Mutex<SampleProducer>>,
+        running: Arc<AtomicBool>,
+        starts: usize,
+    }
+
+    impl FakeSource {
+        fn new() -> Self {
+            let (producer, consumer) = sample_ring(16_000, 16_000);
+            Self {
+                consumer: Some(consumer),
+                producer: Arc::new(Mutex::new(producer)),
+                running: Arc::new(AtomicBool::new(false)),
+                starts: 0,
+            }
+        }
+    }
+
+    impl CaptureSource for FakeSource {
+        fn start(&mut self) -> Result<()> {
+            self.starts += 1;
+            self.running.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn stop(&mut self) {
+            self.running.store(false, Ordering::SeqCst);
+        }
+
+        fn sample_rate(&self) -> u32 {
+            16_000
+        }
+
+        fn take_consumer(&mut self) -> Option<SampleConsumer> {
+            self.consumer.take()
+        }
+
+        fn return_consumer(&mut self, consumer: SampleConsumer) {
+            self.consumer = Some(consumer);
+        }
+    }
+
+    fn passthrough_settings() -> DspSettings {
+        let mut settings = DspSettings::for_microphone();
+        settings.vad_mode = VadMode::Passthrough;
+        settings
+    }
+
+    fn start_with_channel(core: &mut CaptureCore<FakeSource>) -> mpsc::Receiver<Vec<i16>> {
+        let (tx, rx) = mpsc::channel();
+        core.start(move |frame| {
+            let _ = tx.send(frame.samples().to_vec());
+        })
+        .expect("start should succeed");
+        rx
+    }
+
+    fn push_frame(producer: &Arc<Mutex<SampleProducer>>) {
+        let samples = vec![0.25f32; FRAME_SAMPLES];
+        producer.lock().unwrap().push_slice(&samples);
+    }
+
+    fn push_silence(producer: &Arc<Mutex<SampleProducer>>) {
+        producer.lock().unwrap().push_slice(&[0.0f32; FRAME_SAMPLES]);
+    }
+
+    #[test]
+    fn test_restart_after_stop() {
+        let source = FakeSource::new();
+        let producer = source.producer.clone();
+        let mut core = CaptureCore::new("Test", source, passthrough_settings());
+        assert_eq!(core.state(), CaptureState::Idle);
+
+        let rx = start_with_channel(&mut core);
+        assert_eq!(core.state(), CaptureState::Running);
+        push_frame(&producer);
+        assert!(rx.recv_timeout(Duration::from_secs(2)).is_ok());
+
+        core.stop();
+        assert_eq!(core.state(), CaptureState::Stopped);
+        assert!(core.source.consumer.is_some(), "consumer must be returned on stop");
+
+        // Second start reuses the returned consumer
+        let rx = start_with_channel(&mut core);
+        assert_eq!(core.state(), CaptureState::Running);
+        push_frame(&producer);
+        assert!(rx.recv_timeout(Duration::from_secs(2)).is_ok());
+        core.stop();
+        assert_eq!(core.source.starts, 2);
+    }
+
+    #[test]
+    fn test_double_start_and_stop_are_idempotent() {
+        let mut core = CaptureCore::new("Test", FakeSource::new(), passthrough_settings());
+
+        // Stop before start: no-op
+        core.stop();
+        assert_eq!(core.state(), CaptureState::Idle);
+
+        let _rx = start_with_channel(&mut core);
+        assert!(!core.start(|_| {}).unwrap());
+        assert_eq!(core.source.starts, 1);
+
+        core.stop();
+        core.stop();
+        assert_eq!(core.state(), CaptureState::Stopped);
+        assert!(!core.source.running.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_settings_survive_restart() {
+        let mut core = CaptureCore::new("Test", FakeSource::new(), passthrough_settings());
+        core.send(vec![DspCommand::Muted(true)]).unwrap();
+        let _rx = start_with_channel(&mut core);
+        core.stop();
+        assert!(core.settings().muted);
+    }
+
+    #[test]
+    fn test_rejected_batch_leaves_settings_untouched() {
+        use crate::audio_config::DSP_COMMAND_QUEUE_SIZE;
+        let mut core = CaptureCore::new("Test", FakeSource::new(), passthrough_settings());
+        // No audio flows, so the DSP thread never drains the queue
+        let _rx = start_with_channel(&mut core);
+        core.send(vec![DspCommand::FramesPerCallback(2); DSP_COMMAND_QUEUE_SIZE - 2]).unwrap();
+        assert_eq!(core.settings().frames_per_callback, 2);
+
+        let batch = vec![DspCommand::FramesPerCallback(3), DspCommand::FramesPerCallback(4), DspCommand::Muted(true)];
+        assert!(core.send(batch).is_err());
+        assert_eq!(core.settings().frames_per_callback, 2);
+        assert!(!core.settings().muted);
+
+        // A batch that fits still goes through
+        core.send(vec![DspCommand::Muted(true)]).unwrap();
+        assert!(core.settings().muted);
+        core.stop();
+    }
+
+    #[test]
+    fn test_utterance_mode_delivers_whole_segments() {
+        let source = FakeSource::new();
+        let producer = source.producer.clone();
+        let mut core = CaptureCore::new("Test", source, DspSettings::for_microphone());
+        let (tx, rx) = mpsc::channel();
+        core.start_utterances(SegmenterConfig::default(), move |utterance| {
+            let _ = tx.send(utterance);
+        })
+        .expect("start should succeed");
+
+        // 400ms of speech, then silence past the 200ms hangover
+        let speech: Vec<f32> = (0..FRAME_SAMPLES * 20)
+            .map(|i| if (i / 8) % 2 == 0 { 0.25 } else { -0.25 })
+            .collect();
+        producer.lock().unwrap().push_slice(&speech);
+        for _ in 0..20 {
+            push_silence(&producer);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let utterance = rx.recv_timeout(Duration::from_secs(2)).expect("utterance");
+        assert_eq!(utterance.samples.len() % FRAME_SAMPLES, 0);
+        let frames = utterance.samples.len() / FRAME_SAMPLES;
+        assert!((20..=32).contains(&frames), "{} frames", frames);
+        assert!(!utterance.forced_split);
+        core.stop();
+        assert!(rx.try_recv().is_err(), "nothing left open");
+    }
+
+    #[test]
+    fn test_restart_drops_frames_left_unread() {
+        use crate::delivery::PushOutcome;
+
+        let source = FakeSource::new();
+        let producer = source.producer.clone();
+        let mut core = CaptureCore::new("Test", source, passthrough_settings());
+
+        // Pull run (startPull): silence queued, never read
+        let (tx, rx) = mpsc::channel();
+        let queue = core.delivery().clone();
+        core.start(move |frame| {
+            queue.push(frame);
+            let _ = tx.send(());
+        })
+        .expect("start should succeed");
+        for _ in 0..3 {
+            push_silence(&producer);
+            rx.recv_timeout(Duration::from_secs(2)).expect("frame");
+        }
+        core.stop();
+        assert_eq!(core.delivery().stats().queued, 3);
+
+        // Callback run (start): one token per queued frame
+        let (tx, rx) = mpsc::channel();
+        let queue = core.delivery().clone();
+        core.start(move |frame| {
+            let run = queue.run();
+            if queue.push(frame).0 == PushOutcome::Queued {
+                let _ = tx.send(run);
+            }
+        })
+        .expect("start should succeed");
+        push_frame(&producer);
+        let run = rx.recv_timeout(Duration::from_secs(2)).expect("token");
+        let frame = core.delivery().pop_token(run).0.expect("frame for the token");
+        assert!(frame.samples().iter().any(|&s| s != 0), "got the previous run's silence");
+        assert_eq!(core.delivery().stats().queued, 0);
+        core.stop();
+    }
+}
@@ -0,0 +1,479 @@
+// Content Classifier - speech / music / noise on the system audio stream
+//
+// WHY:
+// - The suppressor is RMS based: background music or a noisy stream looks
+//   exactly like someone talking, and STT bills (and hallucinates) on it
+// - This classifier labels the last second of audio so the pipeline can gate
+//   non-speech and, optionally, attenuate music under speech
+//
+// HOW (CPU only, one FFT per frame, see features.rs):
+// - Per frame: energy, spectral flatness, log-mel flux
+// - Over a 1s window:
+//   * low-energy ratio: speech pauses between syllables, music doesn't
+//   * mean flux: formants move all the time, sustained notes don't
+//   * mean flatness: noise is spectrally flat, voiced speech and music are not
+// - Three logits from those statistics -> softmax -> smoothed per-class
+//   confidence; the class changes only once its confidence is clear
+//
+// LATENCY:
+// - Speech onset after silence is never gated: a mostly silent window is
+//   classified as Silence, which lets the suppressor decide on its own
+//
+// IN THE PIPELINE (system audio by default):
+// - Class changes are raised as ContentClass events
+// - While music / noise is playing the suppressor treats frames as
+//   non-speech (keepalives only)
+// - Separation (opt-in, see separation.rs) attenuates music under speech
+
+use std::collections::VecDeque;
+
+use crate::features::{FrameFeatures, SpectralAnalyzer};
+
+/// Frames in the statistics window (1s)
+const WINDOW_FRAMES: usize = 50;
+
+/// Frames needed before a non-silent class is reported (500ms)
+const MIN_ACTIVE_FRAMES: usize = 25;
+
+/// Frames quieter than this don't count as content (i16 RMS ~30)
+const ACTIVE_ENERGY_DB: f32 = 30.0;
+
+/// Smoothing of the per-class confidences (per frame)
+const SCORE_SMOOTHING: f32 = 0.1;
+
+/// Frames between periodic classification reports (500ms)
+const REPORT_INTERVAL_FRAMES: u32 = 25;
+
+/// How long music counts as "still playing" once speech starts over it (10s)
+const MUSIC_MEMORY_FRAMES: u32 = 500;
+
+/// What the last second of audio sounds like
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentClass {
+    Silence,
+    Speech,
+    Music,
+    Noise,
+}
+
+impl ContentClass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContentClass::Silence => "silence",
+            ContentClass::Speech => "speech",
+            ContentClass::Music => "music",
+            ContentClass::Noise => "noise",
+        }
+    }
+}
+
+/// Per-class confidence (sums to 1 while there is content)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClassScores {
+    pub speech: f32,
+    pub music: f32,
+    pub noise: f32,
+}
+
+/// Classifier options (system audio enables it by default)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassifierConfig {
+    /// Treat frames as non-speech while music / noise is detected
+    pub gate: bool,
+    /// Attenuate music under speech (adds one frame of latency)
+    pub separation: bool,
+    /// Confidence a class needs before the reported class switches to it
+    pub min_confidence: f32,
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        Self {
+            gate: true,
+            separation: false,
+            min_confidence: 0.6,
+        }
+    }
+}
+
+/// Statistics of the current window
+#[derive(Debug, Clone, Copy, Default)]
+struct WindowStats {
+    active_frames: usize,
+    low_energy_ratio: f32,
+    mean_flux: f32,
+    mean_flatness: f32,
+}
+
+/// A classification worth telling JS about
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassReport {
+    pub class: ContentClass,
+    pub scores: ClassScores,
+}
+
+pub struct ContentClassifier {
+    config: ClassifierConfig,
+    analyzer: SpectralAnalyzer,
+    window: VecDeque<FrameFeatures>,
+    scores: ClassScores,
+    class: ContentClass,
+    frames_since_report: u32,
+    frames_since_music: u32,
+    pending_report: Option<ClassReport>,
+}
+
+impl ContentClassifier {
+    pub fn new(config: ClassifierConfig) -> Self {
+        println!(
+            "[Classifier] Created (gate={}, separation={})",
+            config.gate, config.separation
+        );
+        Self {
+            config,
+            analyzer: SpectralAnalyzer::new(),
+            window: VecDeque::with_capacity(WINDOW_FRAMES),
+            scores: ClassScores::default(),
+            class: ContentClass::Silence,
+            frames_since_report: 0,
+            frames_since_music: u32::MAX,
+            pending_report: None,
+        }
+    }
+
+    /// Classify the next mono 16kHz frame
+    pub fn process(&mut self, frame: &[i16]) -> ContentClass {
+        let features = self.analyzer.analyze(frame);
+        if self.window.len() == WINDOW_FRAMES {
+            self.window.pop_front();
+        }
+        self.window.push_back(features);
+
+        let stats = self.window_stats();
+        let previous = self.class;
+        if stats.active_frames < MIN_ACTIVE_FRAMES {
+            // Not enough content to judge: decay towards "nothing"
+            self.scores = blend(self.scores, ClassScores::default(), SCORE_SMOOTHING);
+            self.class = ContentClass::Silence;
+        } else {
+            self.scores = blend(self.scores, score(&stats), SCORE_SMOOTHING);
+            let (best, confidence) = self.best();
+            if confidence >= self.config.min_confidence {
+                self.class = best;
+            }
+        }
+
+        self.frames_since_music = match self.class {
+            ContentClass::Music => 0,
+            _ => self.frames_since_music.saturating_add(1),
+        };
+
+        self.frames_since_report += 1;
+        if self.class != previous || self.frames_since_report >= REPORT_INTERVAL_FRAMES {
+            if self.class != previous {
+                println!(
+                    "[Classifier] {} -> {} (speech={:.2}, music={:.2}, noise={:.2})",
+                    previous.name(),
+                    self.class.name(),
+                    self.scores.speech,
+                    self.scores.music,
+                    self.scores.noise
+                );
+            }
+            self.frames_since_report = 0;
+            self.pending_report = Some(ClassReport {
+                class: self.class,
+                scores: self.scores,
+            });
+        }
+        self.class
+    }
+
+    /// Music or noise is playing and nobody seems to be talking over it
+    pub fn blocks_speech(&self) -> bool {
+        self.config.gate && matches!(self.class, ContentClass::Music | ContentClass::Noise)
+    }
+
+    /// Music was heard recently (speech over it is classified as speech,
+    /// so this is what tells the separation stage to stay on)
+    pub fn music_recent(&self) -> bool {
+        self.frames_since_music < MUSIC_MEMORY_FRAMES
+    }
+
+    pub fn class(&self) -> ContentClass {
+        self.class
+    }
+
+    pub fn scores(&self) -> ClassScores {
+        self.scores
+    }
+
+    pub fn config(&self) -> &ClassifierConfig {
+        &self.config
+    }
+
+    /// Replace options without losing the window
+    pub fn set_config(&mut self, config: ClassifierConfig) {
+        println!(
+            "[Classifier] Reconfigured (gate={}, separation={})",
+            config.gate, config.separation
+        );
+        self.config = config;
+    }
+
+    /// Classification changed, or the periodic report is due
+    pub fn take_report(&mut self) -> Option<ClassReport> {
+        self.pending_report.take()
+    }
+
+    fn best(&self) -> (ContentClass, f32) {
+        let ClassScores { speech, music, noise } = self.scores;
+        if speech >= music && speech >= noise {
+            (ContentClass::Speech, speech)
+        } else if music >= noise {
+            (ContentClass::Music, music)
+        } else {
+            (ContentClass::Noise, noise)
+        }
+    }
+
+    fn window_stats(&self) -> WindowStats {
+        let mean_power = self
+            .window
+            .iter()
+            .map(|f| db_to_power(f.energy_db))
+            .sum::<f32>()
+            / self.window.len() as f32;
+        let low_energy = self
+            .window
+            .iter()
+            .filter(|f| db_to_power(f.energy_db) < 0.5 * mean_power)
+            .count();
+
+        let mut stats = WindowStats {
+            low_energy_ratio: low_energy as f32 / self.window.len() as f32,
+            ..WindowStats::default()
+        };
+        for frame in self.window.iter().filter(|f| f.energy_db >= ACTIVE_ENERGY_DB) {
+            stats.active_frames += 1;
+            stats.mean_flux += frame.flux;
+            stats.mean_flatness += frame.flatness;
+        }
+        let count = stats.active_frames.max(1) as f32;
+        stats.mean_flux /= count;
+        stats.mean_flatness /= count;
+        stats
+    }
+}
+
+/// Confidences of one window (softmax over hand-tuned logits)
+fn score(stats: &WindowStats) -> ClassScores {
+    let speech = 10.0 * (stats.low_energy_ratio - 0.15) + 1.0 * (stats.mean_flux - 3.0);
+    let music = 10.0 * (0.15 - stats.low_energy_ratio) + 1.0 * (3.0 - stats.mean_flux)
+        - 20.0 * (stats.mean_flatness - 0.2);
+    let noise = 20.0 * (stats.mean_flatness - 0.3);
+
+    let max = speech.max(music).max(noise);
+    let (s, m, n) = ((speech - max).exp(), (music - max).exp(), (noise - max).exp());
+    let total = s + m + n;
+    ClassScores {
+        speech: s / total,
+        music: m / total,
+        noise: n / total,
+    }
+}
+
+fn blend(current: ClassScores, target: ClassScores, amount: f32) -> ClassScores {
+    ClassScores {
+        speech: current.speech + (target.speech - current.speech) * amount,
+        music: current.music + (target.music - current.music) * amount,
+        noise: current.noise + (target.noise - current.noise) * amount,
+    }
+}
+
+fn db_to_power(db: f32) -> f32 {
+    10f32.powf(db / 10.0)
+}
+
+/// Synthetic test material shared with the separation tests
+#[cfg(test)]
+pub(crate) mod test_signals {
+    use crate::audio_config::{FRAME_SAMPLES, SAMPLE_RATE};
+    use std::f32::consts::PI;
+
+    /// Deterministic noise source
+    pub struct Lcg(pub u32);
+
+    impl Lcg {
+        /// Uniform in -1..1
+        pub fn next(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (self.0 >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+        }
+    }
+
+    /// Speech-like: voiced syllables (gliding pitch, moving formants) separated
+    /// by short pauses, with the odd fricative
+    pub fn speech(seconds: f32, seed: u32) -> Vec<f32> {
+        voice(seconds, seed, 1.0, 1.0)
+    }
+
+    /// `speech()` from a different speaker: pitch and formants (vocal tract
+    /// length) scaled
+    pub fn voice(seconds: f32, seed: u32, pitch_scale: f32, formant_scale: f32) -> Vec<f32> {
+        let total = (seconds * SAMPLE_RATE as f32) as usize;
+        let mut rng = Lcg(seed);
+        let mut out = Vec::with_capacity(total);
+        let mut phase = 0.0f32;
+        while out.len() < total {
+            let len = ((0.12 + 0.07 * (rng.next() + 1.0)) * SAMPLE_RATE as f32) as usize;
+            let f0_start = pitch_scale * (110.0 + 60.0 * (rng.next() + 1.0));
+            let f0_end = f0_start * (0.8 + 0.15 * (rng.next() + 1.0));
+            let f1 = formant_scale * (500.0 + 250.0 * rng.next());
+            let f2_start = formant_scale * (1500.0 + 600.0 * rng.next());
+            let f2_end = formant_scale * (1500.0 + 600.0 * rng.next());
+            for i in 0..len {
+                let t = i as f32 / len as f32;
+                let f0 = f0_start + (f0_end - f0_start) * t;
+                let f2 = f2_start + (f2_end - f2_start) * t;
+                phase += 2.0 * PI * f0 / SAMPLE_RATE as f32;
+                let mut sample = 0.0;
+                let mut k = 1;
+                while k as f32 * f0 < 4000.0 {
+                    let f = k as f32 * f0;
+                    let gain = resonance(f, f1, 120.0) + 0.6 * resonance(f, f2, 200.0) + 0.02;
+                    sample += gain * (k as f32 * phase).sin();
+                    k += 1;
+                }
+                out.push(0.25 * (PI * t).sin() * sample);
+            }
+            // Pause, half of them starting with a fricative
+            let pause = ((0.05 + 0.05 * (rng.next() + 1.0)) * SAMPLE_RATE as f32) as usize;
+            let fricative = rng.next() > 0.0;
+            let mut prev = 0.0;
+            for i in 0..pause {
+                let white = rng.next();
+                if fricative && i < pause / 2 {
+                    // First difference: crude high-pass
+                    out.push(0.15 * (white - prev));
+                } else {
+                    out.push(0.002 * white);
+                }
+                prev = white;
+            }
+        }
+        out.truncate(total);
+        out
+    }
+
+    fn resonance(f: f32, center: f32, bandwidth: f32) -> f32 {
+        let x = (f - center) / bandwidth;
+        1.0 / (1.0 + x * x)
+    }
+
+    /// Music-like: sustained three-note chords with harmonics, changing every 500ms
+    pub fn music(seconds: f32, seed: u32) -> Vec<f32> {
+        const CHORDS: [[f32; 3]; 4] = [
+            [261.6, 329.6, 392.0],
+            [220.0, 261.6, 329.6],
+            [174.6, 220.0, 261.6],
+            [196.0, 246.9, 293.7],
+        ];
+        let total = (seconds * SAMPLE_RATE as f32) as usize;
+        let chord_len = SAMPLE_RATE as usize / 2;
+        let offset = seed as usize % CHORDS.len();
+        (0..total)
+            .map(|n| {
+                let chord = &CHORDS[(n / chord_len + offset) % CHORDS.len()];
+                let t = n as f32 / SAMPLE_RATE as f32;
+                let since_onset = (n % chord_len) as f32 / SAMPLE_RATE as f32;
+                let envelope = 0.6 + 0.4 * (-since_onset * 6.0).exp();
+                let mut sample = 0.0;
+                for &f in chord {
+                    for k in 1..=5 {
+                        sample += (2.0 * PI * f * k as f32 * t).sin() / k as f32;
+                    }
+                }
+                0.12 * envelope * sample
+            })
+            .collect()
+    }
+
+    /// Stationary white noise
+    pub fn noise(seconds: f32, seed: u32) -> Vec<f32> {
+        let mut rng = Lcg(seed);
+        (0..(seconds * SAMPLE_RATE as f32) as usize).map(|_| 0.2 * rng.next()).collect()
+    }
+
+    /// `a + gain * b`
+    pub fn mix(a: &[f32], b: &[f32], gain: f32) -> Vec<f32> {
+        a.iter().zip(b).map(|(x, y)| x + gain * y).collect()
+    }
+
+    /// Split into i16 frames
+    pub fn frames(signal: &[f32]) -> Vec<Vec<i16>> {
+        signal
+            .chunks_exact(FRAME_SAMPLES)
+            .map(|chunk| chunk.iter().map(|&s| (s * 32767.0).clamp(-32768.0, 32767.0) as i16).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_signals::*;
+    use super::*;
+
+    fn run(signal: &[f32]) -> ContentClassifier {
+        let mut classifier = ContentClassifier::new(ClassifierConfig::default());
+        for frame in frames(signal) {
+            classifier.process(&frame);
+        }
+        classifier
+    }
+
+    #[test]
+    fn test_separates_speech_music_and_noise() {
+        for (signal, expected) in [
+            (speech(3.0, 1), ContentClass::Speech),
+            (speech(3.0, 7), ContentClass::Speech),
+            (music(3.0, 1), ContentClass::Music),
+            (noise(3.0, 1), ContentClass::Noise),
+        ] {
+            let classifier = run(&signal);
+            assert_eq!(classifier.class(), expected, "{:?}", classifier.scores());
+        }
+        assert!(run(&music(3.0, 2)).blocks_speech());
+    }
+
+    #[test]
+    fn test_speech_over_music_is_not_gated() {
+        let classifier = run(&mix(&speech(3.0, 3), &music(3.0, 2), 1.0));
+        assert_eq!(classifier.class(), ContentClass::Speech, "{:?}", classifier.scores());
+        assert!(!classifier.blocks_speech());
+    }
+
+    #[test]
+    fn test_speech_onset_after_silence_is_never_gated() {
+        let mut classifier = ContentClassifier::new(ClassifierConfig::default());
+        let signal: Vec<f32> = std::iter::repeat_n(0.0, 16_000).chain(speech(2.0, 4)).collect();
+        for frame in frames(&signal) {
+            classifier.process(&frame);
+            assert!(!classifier.blocks_speech(), "{:?}", classifier.class());
+        }
+        assert_eq!(classifier.class(), ContentClass::Speech);
+    }
+
+    #[test]
+    fn test_reports_on_change_and_periodically() {
+        let mut classifier = ContentClassifier::new(ClassifierConfig::default());
+        let mut reports = Vec::new();
+        for frame in frames(&music(3.0, 1)) {
+            classifier.process(&frame);
+            reports.extend(classifier.take_report());
+        }
+        // 150 frames: one report every 25 frames, the switch to music resets the interval
+        assert!((6..=7).contains(&reports.len()), "{} reports", reports.len());
+        assert!(reports.iter().any(|r| r.class == ContentClass::Music));
+    }
+}
@@ -0,0 +1,87 @@
+// Clock - injectable time source for the DSP pipeline
+//
+// Production code uses SystemClock (Instant::now()). The virtual-time driver
+// uses a VirtualClock that only moves when the script advances it. (The DSP
+// state machines don't read a clock at all: hangover and keepalive cadence
+// count processed samples.)
+//
+// Capture timestamps are wall-clock (ms since the Unix epoch) so JS can line
+// them up with Date.now(); backends convert their device clock with
+// epoch_ms_ago().
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// Shared handle passed down to every component that reads time
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Wall-clock time
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Wall-clock time in ms since the Unix epoch
+pub fn epoch_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |since| since.as_secs_f64() * 1000.0)
+}
+
+/// Wall-clock time `age` ago (ms since the Unix epoch)
+pub fn epoch_ms_ago(age: Duration) -> f64 {
+    epoch_ms() - age.as_secs_f64() * 1000.0
+}
+
+/// Manually advanced time (tests / virtual-time driver)
+pub struct VirtualClock {
+    origin: Instant,
+    elapsed_ns: AtomicU64,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            elapsed_ns: AtomicU64::new(0),
+        }
+    }
+
+    /// Virtual time since the clock was created
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_ns.load(Ordering::Acquire))
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.elapsed_ns.fetch_add(by.as_nanos() as u64, Ordering::AcqRel);
+    }
+
+    /// Jump to `elapsed` (never moves backwards)
+    pub fn set_elapsed(&self, elapsed: Duration) {
+        self.elapsed_ns.fetch_max(elapsed.as_nanos() as u64, Ordering::AcqRel);
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+}
@@ -0,0 +1,229 @@
+// Comfort Noise - keepalive frames that sound like the room, not like a fault
+//
+// Some STT engines treat digital-zero frames as a broken stream or finalize
+// the utterance early. Instead of zeros, suppressed slots can carry low-level
+// noise shaped like the measured background:
+// - Background frames (non-speech) update a smoothed autocorrelation
+//   (order 10 LPC model) and a smoothed level
+// - A fill runs Levinson-Durbin on the average and drives the all-pole
+//   filter with white noise scaled to the background level
+// - The level is capped below the speech threshold, so comfort noise can
+//   never be mistaken for speech downstream
+//
+// REAL-TIME NOTES:
+// - Fixed-size state, no allocation after new()
+
+/// LPC order (enough for the spectral envelope of room noise at 16kHz)
+const ORDER: usize = 10;
+
+/// Smoothing of the background statistics per frame (~0.5s memory)
+const SMOOTHING: f32 = 0.95;
+
+/// Bandwidth expansion of the synthesis filter (keeps it from ringing)
+const BANDWIDTH_EXPANSION: f32 = 0.98;
+
+pub struct ComfortNoise {
+    /// Smoothed normalized autocorrelation, lags 0..=ORDER
+    autocorrelation: [f32; ORDER + 1],
+    /// Smoothed mean-square level (i16 scale)
+    level: f32,
+    measured: bool,
+    /// Synthesis filter memory (most recent first)
+    history: [f32; ORDER],
+    rng: u32,
+}
+
+impl ComfortNoise {
+    pub fn new() -> Self {
+        Self {
+            autocorrelation: [0.0; ORDER + 1],
+            level: 0.0,
+            measured: false,
+            history: [0.0; ORDER],
+            rng: 0x2545_f491,
+        }
+    }
+
+    /// Learn from a background (non-speech) frame
+    pub fn analyze(&mut self, frame: &[i16]) {
+        let n = frame.len();
+        if n <= ORDER {
+            return;
+        }
+        let mut r = [0.0f32; ORDER + 1];
+        // Hann-windowed autocorrelation
+        for (lag, value) in r.iter_mut().enumerate() {
+            let mut sum = 0.0f64;
+            for i in lag..n {
+                sum += (hann(i, n) * frame[i] as f32) as f64 * (hann(i - lag, n) * frame[i - lag] as f32) as f64;
+            }
+            *value = sum as f32;
+        }
+        let energy = frame.iter().map(|&s| (s as f32) * (s as f32)).sum::<f32>() / n as f32;
+        if r[0] <= 0.0 {
+            // Digital silence: the model keeps its shape, the level decays
+            self.level *= SMOOTHING;
+            return;
+        }
+        let r0 = r[0];
+        for value in r.iter_mut() {
+            *value /= r0;
+        }
+
+        if self.measured {
+            for (average, value) in self.autocorrelation.iter_mut().zip(r) {
+                *average = SMOOTHING * *average + (1.0 - SMOOTHING) * value;
+            }
+            self.level = SMOOTHING * self.level + (1.0 - SMOOTHING) * energy;
+        } else {
+            self.autocorrelation = r;
+            self.level = energy;
+            self.measured = true;
+        }
+    }
+
+    /// Fill `frame` with noise matching the background, at most `max_rms`
+    /// (zeros until a background frame has been analyzed)
+    pub fn fill(&mut self, frame: &mut [i16], max_rms: f32) {
+        if !self.measured {
+            frame.fill(0);
+            return;
+        }
+        let (coefficients, prediction_error) = levinson_durbin(&self.autocorrelation);
+        let rms = self.level.sqrt().min(max_rms);
+        // Innovation variance of an AR model = normalized error * signal power
+        let gain = (prediction_error.max(0.0) * rms * rms).sqrt();
+
+        for out in frame.iter_mut() {
+            // Uniform [-1, 1) has variance 1/3
+            let excitation = self.next_uniform() * 3f32.sqrt() * gain;
+            let prediction: f32 = coefficients.iter().zip(&self.history).map(|(a, y)| a * y).sum();
+            let value = excitation - prediction;
+            self.history.copy_within(0..ORDER - 1, 1);
+            self.history[0] = value;
+            *out = value.round().clamp(-32768.0, 32767.0) as i16;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// xorshift32, uniform in [-1, 1)
+    fn next_uniform(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+impl Default for ComfortNoise {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hann(i: usize, n: usize) -> f32 {
+    0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / n as f32).cos()
+}
+
+/// LPC coefficients a[1..=ORDER] (y[n] = e[n] - sum a[k] y[n-k]) and the
+/// normalized prediction error; falls back to white noise if unstable
+fn levinson_durbin(r: &[f32; ORDER + 1]) -> ([f32; ORDER], f32) {
+    let mut a = [0.0f32; ORDER + 1];
+    let mut previous = [0.0f32; ORDER + 1];
+    // Slight white-noise correction keeps the recursion well conditioned
+    let mut error = r[0] * 1.0001;
+    for i in 1..=ORDER {
+        let mut acc = r[i];
+        for j in 1..i {
+            acc += a[j] * r[i - j];
+        }
+        let k = -acc / error;
+        if !k.is_finite() || k.abs() >= 1.0 {
+            return ([0.0; ORDER], 1.0);
+        }
+        previous.copy_from_slice(&a);
+        a[i] = k;
+        for j in 1..i {
+            a[j] = previous[j] + k * previous[i - j];
+        }
+        error *= 1.0 - k * k;
+    }
+
+    let mut coefficients = [0.0f32; ORDER];
+    let mut expansion = 1.0;
+    for (coefficient, &value) in coefficients.iter_mut().zip(&a[1..]) {
+        expansion *= BANDWIDTH_EXPANSION;
+        *coefficient = value * expansion;
+    }
+    (coefficients, error / r[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_config::FRAME_SAMPLES;
+
+    fn rms(frame: &[i16]) -> f32 {
+        (frame.iter().map(|&s| (s as f32) * (s as f32)).sum::<f32>() / frame.len() as f32).sqrt()
+    }
+
+    /// Mean-square of the first difference over mean-square (white ~2, low-pass << 1)
+    fn tilt(frame: &[i16]) -> f32 {
+        let diff: f32 = frame.windows(2).map(|w| (w[1] as f32 - w[0] as f32).powi(2)).sum();
+        diff / frame.iter().map(|&s| (s as f32).powi(2)).sum::<f32>()
+    }
+
+    /// Low-passed noise (a fan / air conditioning hum)
+    fn rumble(frames: usize) -> Vec<Vec<i16>> {
+        let mut state = 7u32;
+        let mut smooth = 0.0f32;
+        (0..frames)
+            .map(|_| {
+                (0..FRAME_SAMPLES)
+                    .map(|_| {
+                        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                        let white = (state >> 16) as f32 / 65536.0 - 0.5;
+                        smooth = 0.9 * smooth + 0.1 * white;
+                        (smooth * 2000.0) as i16
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_background_level_and_shape() {
+        let background = rumble(50);
+        let mut noise = ComfortNoise::new();
+        for frame in &background {
+            noise.analyze(frame);
+        }
+
+        let mut out = vec![0i16; FRAME_SAMPLES * 10];
+        noise.fill(&mut out, 1000.0);
+        let target = rms(&background[49]);
+        let level = rms(&out);
+        assert!(level > target * 0.5 && level < target * 2.0, "level {} vs background {}", level, target);
+        assert!(tilt(&out) < 0.5, "comfort noise tilt {} (background {})", tilt(&out), tilt(&background[49]));
+    }
+
+    #[test]
+    fn test_silent_until_measured_and_capped() {
+        let mut noise = ComfortNoise::new();
+        let mut out = vec![1i16; FRAME_SAMPLES];
+        noise.fill(&mut out, 50.0);
+        assert!(out.iter().all(|&s| s == 0));
+
+        // Loud background (music under a gate): kept below the cap
+        for frame in rumble(20) {
+            let loud: Vec<i16> = frame.iter().map(|&s| s.saturating_mul(8)).collect();
+            noise.analyze(&loud);
+        }
+        let mut out = vec![0i16; FRAME_SAMPLES * 10];
+        noise.fill(&mut out, 50.0);
+        assert!(rms(&out) < 75.0, "capped level {}", rms(&out));
+    }
+}
@@ -0,0 +1,475 @@
+// Conversation Tracking - overlap, interruptions and turns across two captures
+//
+// WHY:
+// - The copilot wants to know when the user talks over the other side (and
+//   should hold off generating an answer while two people speak at once)
+//
+// HOW:
+// - Both captures (microphone = user, system audio = remote) report every
+//   processed frame: capture time + whether the suppressor found it voiced
+// - Per party: speaking from the first voiced frame until `release` without
+//   one; it only counts once `min_speech` of it has gone by (clicks, coughs)
+// - Overlap: both parties voiced for at least `min_overlap` since the
+//   later one started (backchannels shorter than that don't count)
+// - Floor: the party who spoke alone last. It changes hands when the other
+//   party is the only one speaking
+// - Session metrics (metrics.rs) are accumulated from the same speech and
+//   events, and reported every `metrics_interval`
+//
+// TIMESTAMPS:
+// - Capture times (ms since the Unix epoch) of the frames involved, so an
+//   event is stamped with when it happened, not when it was decided (speech
+//   ends are known `release` late, overlaps `min_overlap` late)
+// - napi-free: lib.rs feeds it from both DSP threads under a mutex
+
+use std::time::Duration;
+
+use crate::audio_config::FRAME_MS;
+use crate::metrics::{SessionMetrics, SessionSummary};
+
+/// Who is talking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Party {
+    /// Microphone
+    User,
+    /// System audio (the other side of the call)
+    Remote,
+}
+
+impl Party {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Party::User => "user",
+            Party::Remote => "remote",
+        }
+    }
+
+    pub(crate) fn other(&self) -> Party {
+        match self {
+            Party::User => Party::Remote,
+            Party::Remote => Party::User,
+        }
+    }
+
+    pub(crate) fn index(&self) -> usize {
+        match self {
+            Party::User => 0,
+            Party::Remote => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversationConfig {
+    /// Speech shorter than this is ignored
+    pub min_speech: Duration,
+    /// Silence that ends a party's speech
+    pub release: Duration,
+    /// Shortest simultaneous speech reported as an overlap
+    pub min_overlap: Duration,
+    /// Shortest silence within a party's speech counted as a pause
+    pub min_pause: Duration,
+    /// Period of the session metrics event (None: on request only)
+    pub metrics_interval: Option<Duration>,
+}
+
+impl Default for ConversationConfig {
+    fn default() -> Self {
+        Self {
+            min_speech: Duration::from_millis(200),
+            release: Duration::from_millis(300),
+            min_overlap: Duration::from_millis(300),
+            min_pause: Duration::from_millis(500),
+            metrics_interval: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversationEvent {
+    /// Both parties have been talking for `min_overlap`; `by` joined last
+    OverlapStart { time_ms: f64, by: Party },
+    /// One party stopped; `remaining` is still talking (None if both stopped)
+    OverlapEnd {
+        time_ms: f64,
+        duration_ms: f64,
+        remaining: Option<Party>,
+    },
+    /// The user started talking over the remote party
+    UserInterrupted { time_ms: f64 },
+    /// `party` has the floor, speaking since `time_ms`
+    /// `gap_ms`: from the previous turn's end (negative if they overlapped)
+    TurnTaken {
+        party: Party,
+        time_ms: f64,
+        gap_ms: Option<f64>,
+    },
+    /// Session metrics so far (every `metrics_interval`)
+    Metrics {
+        time_ms: f64,
+        summary: Box<SessionSummary>,
+    },
+}
+
+impl ConversationEvent {
+    /// Value of the `type` field on the JS object
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConversationEvent::OverlapStart { .. } => "overlapStart",
+            ConversationEvent::OverlapEnd { .. } => "overlapEnd",
+            ConversationEvent::UserInterrupted { .. } => "userInterrupted",
+            ConversationEvent::TurnTaken { .. } => "turnTaken",
+            ConversationEvent::Metrics { .. } => "sessionMetrics",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Speech {
+    start_ms: f64,
+    /// End of the last voiced frame
+    last_voiced_ms: f64,
+}
+
+impl Speech {
+    fn duration_ms(&self) -> f64 {
+        self.last_voiced_ms - self.start_ms
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PartyState {
+    speech: Option<Speech>,
+    /// min_speech reached
+    confirmed: bool,
+    /// End of the party's last confirmed speech
+    last_end_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Overlap {
+    start_ms: f64,
+    by: Party,
+    reported: bool,
+}
+
+pub struct ConversationTracker {
+    config: ConversationConfig,
+    parties: [PartyState; 2],
+    overlap: Option<Overlap>,
+    floor: Option<Party>,
+    metrics: SessionMetrics,
+    /// Time of the last metrics event (or of the first frame)
+    metrics_reported_ms: Option<f64>,
+}
+
+impl ConversationTracker {
+    pub fn new(config: ConversationConfig) -> Self {
+        Self {
+            metrics: SessionMetrics::new(ms(config.min_pause)),
+            config,
+            parties: [PartyState::default(); 2],
+            overlap: None,
+            floor: None,
+            metrics_reported_ms: None,
+        }
+    }
+
+    /// One processed 20ms frame of `party`'s stream (`level_db`: see
+    /// metrics::level_db)
+    pub fn push(&mut self, party: Party, time_ms: f64, voiced: bool, level_db: f32) -> Vec<ConversationEvent> {
+        let mut events = Vec::new();
+        self.metrics.frame(party, time_ms, voiced, level_db);
+        let frame_end = time_ms + FRAME_MS as f64;
+        let state = &mut self.parties[party.index()];
+
+        if voiced {
+            let speech = state.speech.get_or_insert(Speech {
+                start_ms: time_ms,
+                last_voiced_ms: frame_end,
+            });
+            speech.last_voiced_ms = frame_end;
+            if !state.confirmed && speech.duration_ms() >= ms(self.config.min_speech) {
+                state.confirmed = true;
+                self.started(party, &mut events);
+            }
+        } else if let Some(speech) = state.speech {
+            if frame_end - speech.last_voiced_ms >= ms(self.config.release) {
+                self.stopped(party, &mut events);
+            }
+        }
+
+        // Overlap long enough to report (both still voicing, not just
+        // within their release time)
+        let both_voiced_until = match (self.speaking(Party::User), self.speaking(Party::Remote)) {
+            (Some(user), Some(remote)) => user.last_voiced_ms.min(remote.last_voiced_ms),
+            _ => f64::MIN,
+        };
+        if let Some(overlap) = self.overlap.as_mut() {
+            if !overlap.reported && both_voiced_until - overlap.start_ms >= ms(self.config.min_overlap) {
+                overlap.reported = true;
+                events.push(ConversationEvent::OverlapStart {
+                    time_ms: overlap.start_ms,
+                    by: overlap.by,
+                });
+                if overlap.by == Party::User {
+                    events.push(ConversationEvent::UserInterrupted { time_ms: overlap.start_ms });
+                }
+            }
+        }
+        self.record(&events);
+
+        if let Some(interval) = self.config.metrics_interval {
+            let since = *self.metrics_reported_ms.get_or_insert(time_ms);
+            if time_ms - since >= ms(interval) {
+                self.metrics_reported_ms = Some(time_ms);
+                events.push(ConversationEvent::Metrics {
+                    time_ms,
+                    summary: Box::new(self.metrics.summary()),
+                });
+            }
+        }
+        events
+    }
+
+    /// `party`'s stream ended (capture stopped / detached): its speech ends now
+    pub fn end(&mut self, party: Party) -> Vec<ConversationEvent> {
+        let mut events = Vec::new();
+        if self.parties[party.index()].speech.is_some() {
+            self.stopped(party, &mut events);
+        }
+        self.record(&events);
+        events
+    }
+
+    pub fn floor(&self) -> Option<Party> {
+        self.floor
+    }
+
+    /// Both parties are talking (reported overlap)
+    pub fn is_overlapping(&self) -> bool {
+        self.overlap.is_some_and(|overlap| overlap.reported)
+    }
+
+    /// Session metrics so far
+    pub fn metrics(&self) -> SessionSummary {
+        self.metrics.summary()
+    }
+
+    /// Start a new session (turn / overlap state is kept)
+    pub fn reset_metrics(&mut self) {
+        self.metrics.reset();
+        self.metrics_reported_ms = None;
+    }
+
+    fn record(&mut self, events: &[ConversationEvent]) {
+        for event in events {
+            self.metrics.event(event);
+        }
+    }
+
+    fn speaking(&self, party: Party) -> Option<Speech> {
+        let state = &self.parties[party.index()];
+        state.speech.filter(|_| state.confirmed)
+    }
+
+    /// `party`'s speech just reached min_speech
+    fn started(&mut self, party: Party, events: &mut Vec<ConversationEvent>) {
+        let Some(speech) = self.speaking(party) else {
+            return;
+        };
+        if let Some(other) = self.speaking(party.other()) {
+            self.overlap = Some(Overlap {
+                start_ms: speech.start_ms.max(other.start_ms),
+                by: party,
+                reported: false,
+            });
+        } else {
+            self.take_floor(party, events);
+        }
+    }
+
+    /// `party`'s speech ended (release elapsed, or its stream ended)
+    fn stopped(&mut self, party: Party, events: &mut Vec<ConversationEvent>) {
+        let state = &mut self.parties[party.index()];
+        let speech = state.speech.take();
+        let confirmed = std::mem::take(&mut state.confirmed);
+        let Some(speech) = speech.filter(|_| confirmed) else {
+            return;
+        };
+        state.last_end_ms = Some(speech.last_voiced_ms);
+        let other_since = self.speaking(party.other()).map(|other| other.start_ms);
+        self.metrics.speech(party, speech.start_ms, speech.last_voiced_ms, other_since);
+
+        let remaining = self.speaking(party.other()).map(|_| party.other());
+        if let Some(overlap) = self.overlap.take() {
+            if overlap.reported {
+                events.push(ConversationEvent::OverlapEnd {
+                    time_ms: speech.last_voiced_ms,
+                    duration_ms: speech.last_voiced_ms - overlap.start_ms,
+                    remaining,
+                });
+            }
+        }
+        if let Some(remaining) = remaining {
+            self.take_floor(remaining, events);
+        }
+    }
+
+    fn take_floor(&mut self, party: Party, events: &mut Vec<ConversationEvent>) {
+        if self.floor == Some(party) {
+            return;
+        }
+        let Some(speech) = self.speaking(party) else {
+            return;
+        };
+        let previous_end = self.parties[party.other().index()].last_end_ms;
+        self.floor = Some(party);
+        events.push(ConversationEvent::TurnTaken {
+            party,
+            time_ms: speech.start_ms,
+            gap_ms: previous_end.map(|end| speech.start_ms - end),
+        });
+    }
+}
+
+impl Default for ConversationTracker {
+    fn default() -> Self {
+        Self::new(ConversationConfig::default())
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames of both parties from 0 to `until_ms`; `user` / `remote` give
+    /// their voiced ranges
+    fn run(tracker: &mut ConversationTracker, until_ms: u32, user: &[(u32, u32)], remote: &[(u32, u32)]) -> Vec<ConversationEvent> {
+        let voiced = |ranges: &[(u32, u32)], t: u32| ranges.iter().any(|&(start, end)| t >= start && t < end);
+        let mut events = Vec::new();
+        for t in (0..until_ms).step_by(FRAME_MS as usize) {
+            events.extend(tracker.push(Party::Remote, t as f64, voiced(remote, t), 60.0));
+            events.extend(tracker.push(Party::User, t as f64, voiced(user, t), 60.0));
+        }
+        events
+    }
+
+    fn names(events: &[ConversationEvent]) -> Vec<&'static str> {
+        events.iter().map(|event| event.name()).collect()
+    }
+
+    #[test]
+    fn test_alternating_turns() {
+        let mut tracker = ConversationTracker::default();
+        let events = run(&mut tracker, 5000, &[(2500, 4000)], &[(0, 2000)]);
+        assert_eq!(
+            events,
+            vec![
+                ConversationEvent::TurnTaken {
+                    party: Party::Remote,
+                    time_ms: 0.0,
+                    gap_ms: None
+                },
+                ConversationEvent::TurnTaken {
+                    party: Party::User,
+                    time_ms: 2500.0,
+                    gap_ms: Some(500.0)
+                },
+            ]
+        );
+        assert_eq!(tracker.floor(), Some(Party::User));
+    }
+
+    #[test]
+    fn test_user_interrupts_and_takes_over() {
+        let mut tracker = ConversationTracker::default();
+        let events = run(&mut tracker, 5000, &[(1500, 4000)], &[(0, 2500)]);
+        assert_eq!(
+            names(&events),
+            vec!["turnTaken", "overlapStart", "userInterrupted", "overlapEnd", "turnTaken"]
+        );
+        assert_eq!(
+            events[1],
+            ConversationEvent::OverlapStart {
+                time_ms: 1500.0,
+                by: Party::User
+            }
+        );
+        assert_eq!(
+            events[3],
+            ConversationEvent::OverlapEnd {
+                time_ms: 2500.0,
+                duration_ms: 1000.0,
+                remaining: Some(Party::User)
+            }
+        );
+        assert_eq!(
+            events[4],
+            ConversationEvent::TurnTaken {
+                party: Party::User,
+                time_ms: 1500.0,
+                gap_ms: Some(-1000.0)
+            }
+        );
+    }
+
+    #[test]
+    fn test_backchannel_is_not_an_overlap() {
+        let mut tracker = ConversationTracker::default();
+        // 240ms "mm-hm" from the user while the remote keeps talking
+        let events = run(&mut tracker, 4000, &[(1000, 1240)], &[(0, 3000)]);
+        assert_eq!(names(&events), vec!["turnTaken"]);
+        assert!(!tracker.is_overlapping());
+        assert_eq!(tracker.floor(), Some(Party::Remote));
+
+        // A click is not speech at all
+        let mut tracker = ConversationTracker::default();
+        let events = run(&mut tracker, 1000, &[(100, 140)], &[]);
+        assert!(events.is_empty(), "{:?}", events);
+    }
+
+    #[test]
+    fn test_remote_talks_over_user_and_stream_end() {
+        let mut tracker = ConversationTracker::default();
+        let events = run(&mut tracker, 3000, &[(0, 3000)], &[(1000, 3000)]);
+        assert_eq!(names(&events), vec!["turnTaken", "overlapStart"]);
+        assert!(tracker.is_overlapping());
+        // The microphone stops while both are still talking
+        let events = tracker.end(Party::User);
+        assert_eq!(names(&events), vec!["overlapEnd", "turnTaken"]);
+        assert_eq!(tracker.floor(), Some(Party::Remote));
+    }
+
+    #[test]
+    fn test_session_metrics() {
+        let mut tracker = ConversationTracker::new(ConversationConfig {
+            metrics_interval: Some(Duration::from_secs(2)),
+            ..ConversationConfig::default()
+        });
+        let events = run(&mut tracker, 5000, &[(2500, 4000)], &[(0, 1000), (1600, 2000)]);
+        let reports: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                ConversationEvent::Metrics { summary, .. } => Some(summary),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reports.len(), 2);
+
+        let summary = tracker.metrics();
+        assert_eq!(summary.remote.talk_time_ms, 1400.0);
+        assert_eq!(summary.user.talk_time_ms, 1500.0);
+        assert_eq!(summary.remote.longest_monologue_ms, 2000.0);
+        assert_eq!((summary.pauses, summary.longest_pause_ms), (1, 600.0));
+        assert_eq!(summary.median_response_latency_ms, Some(500.0));
+        assert_eq!(summary.duration_ms, 5000.0);
+
+        tracker.reset_metrics();
+        assert_eq!(tracker.metrics(), SessionSummary::default());
+    }
+}
@@ -0,0 +1,507 @@
+// Frame Delivery Queue - bounded backpressure between DSP thread and JS
+//
+// PROBLEM:
+// The JS callback runs on the Electron main thread. If it blocks for a few
+// seconds, an unbounded threadsafe-function queue grows without limit and
+// then bursts seconds of stale audio into STT.
+//
+// DESIGN:
+// - Frames wait here (bounded), the threadsafe function only carries tokens
+// - Invariant: outstanding tokens == frames in the queue
+//   (a token is only issued when the queue grows, each JS call pops one frame)
+// - Tokens belong to a run: open() starts a new one, so tokens still in
+//   flight from a stopped capture find nothing instead of the new run's frames
+//
+// LIFECYCLE (driven by CaptureCore, whatever the delivery mode):
+// - start: open() drops what the previous run left unread (push or pull)
+// - stop: close() ends the stream; leftovers stay readable until the next start
+// - When full, the overflow policy decides what to drop
+// - Crossing into / out of the full state is reported as a `lagging` event
+//
+// PULL MODE (read(maxFrames)):
+// - No tokens; JS asks for frames and at most one read is pending at a time
+// - set_pull() picks the mode before a start; it stays until the next one, so
+//   read() after a callback run stops can't take frames its tokens are owed
+// - A pending read is completed by the next push (on the DSP thread) or with
+//   an empty batch when the queue is closed (stop = end of stream)
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use anyhow::Result;
+
+use crate::frame_pool::PooledFrame;
+
+/// What to drop when JS falls behind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+    /// Keep the newest audio (lowest latency)
+    DropOldest,
+    /// Keep the oldest audio (no holes in what was already queued)
+    DropNewest,
+    /// Drop queued keepalive/silence frames first, then the oldest frame
+    CoalesceSilence,
+}
+
+impl OverflowPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "drop-oldest" => Some(OverflowPolicy::DropOldest),
+            "drop-newest" => Some(OverflowPolicy::DropNewest),
+            "coalesce-silence" => Some(OverflowPolicy::CoalesceSilence),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeliveryConfig {
+    /// Maximum frames (or batches) waiting for JS
+    pub max_queued: usize,
+    pub policy: OverflowPolicy,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            max_queued: 100, // 2s of 20ms frames
+            policy: OverflowPolicy::CoalesceSilence,
+        }
+    }
+}
+
+/// Result of pushing a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PushOutcome {
+    /// Queue grew: caller must issue one JS call
+    Queued,
+    /// A queued frame was dropped to make room: no new JS call needed
+    Replaced,
+    /// The new frame was dropped
+    Dropped,
+}
+
+/// Transition of the lagging state caused by a push/pop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LagChange {
+    None,
+    Started,
+    Recovered,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DeliveryStats {
+    pub delivered: u64,
+    pub dropped: u64,
+    pub coalesced: u64,
+    pub queued: usize,
+    pub lagging: bool,
+}
+
+/// Completes a pull-mode read (empty batch = end of stream)
+pub type PullResolve = Box<dyn FnOnce(Vec<PooledFrame>) + Send>;
+
+struct PullWaiter {
+    max_frames: usize,
+    resolve: PullResolve,
+}
+
+struct QueueState {
+    config: DeliveryConfig,
+    frames: VecDeque<PooledFrame>,
+    stats: DeliveryStats,
+    waiter: Option<PullWaiter>,
+    /// No more frames until the next open() (capture stopped)
+    closed: bool,
+    /// The last run was started for read() (no tokens)
+    pull: bool,
+    /// Bumped by open()
+    run: u64,
+}
+
+impl QueueState {
+    fn pop(&mut self) -> (Option<PooledFrame>, LagChange) {
+        let frame = self.frames.pop_front();
+        if frame.is_some() {
+            self.stats.delivered += 1;
+        }
+        self.stats.queued = self.frames.len();
+
+        // Caught up once the backlog is down to a quarter of the capacity
+        let lag = if self.stats.lagging && self.frames.len() <= self.config.max_queued / 4 {
+            self.stats.lagging = false;
+            LagChange::Recovered
+        } else {
+            LagChange::None
+        };
+        (frame, lag)
+    }
+
+    /// Pop up to `max_frames` (a read batch)
+    fn drain(&mut self, max_frames: usize) -> (Vec<PooledFrame>, LagChange) {
+        let mut frames = Vec::with_capacity(max_frames.min(self.frames.len()));
+        let mut lag = LagChange::None;
+        while frames.len() < max_frames {
+            match self.pop() {
+                (Some(frame), change) => {
+                    frames.push(frame);
+                    if change != LagChange::None {
+                        lag = change;
+                    }
+                }
+                (None, _) => break,
+            }
+        }
+        (frames, lag)
+    }
+}
+
+pub struct DeliveryQueue {
+    state: Mutex<QueueState>,
+}
+
+impl DeliveryQueue {
+    pub fn new(config: DeliveryConfig) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                frames: VecDeque::with_capacity(config.max_queued),
+                config,
+                stats: DeliveryStats::default(),
+                waiter: None,
+                closed: true,
+                pull: false,
+                run: 0,
+            }),
+        }
+    }
+
+    pub fn config(&self) -> DeliveryConfig {
+        self.state.lock().map(|state| state.config.clone()).unwrap_or_default()
+    }
+
+    /// Update capacity / policy (applies to the next push)
+    pub fn set_config(&self, config: DeliveryConfig) {
+        if let Ok(mut state) = self.state.lock() {
+            state.config = config;
+        }
+    }
+
+    /// Queue a frame from the DSP thread
+    pub fn push(&self, frame: PooledFrame) -> (PushOutcome, LagChange) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let max_queued = state.config.max_queued.max(1);
+
+        if state.frames.len() < max_queued {
+            state.frames.push_back(frame);
+            state.stats.queued = state.frames.len();
+            if let Some(waiter) = state.waiter.take() {
+                let (frames, _) = state.drain(waiter.max_frames);
+                drop(state);
+                (waiter.resolve)(frames);
+            }
+            return (PushOutcome::Queued, LagChange::None);
+        }
+
+        // Full: JS is behind
+        let lag = if state.stats.lagging {
+            LagChange::None
+        } else {
+            state.stats.lagging = true;
+            LagChange::Started
+        };
+
+        let outcome = match state.config.policy {
+            OverflowPolicy::DropNewest => {
+                state.stats.dropped += 1;
+                PushOutcome::Dropped
+            }
+            OverflowPolicy::DropOldest => {
+                state.frames.pop_front();
+                state.frames.push_back(frame);
+                state.stats.dropped += 1;
+                PushOutcome::Replaced
+            }
+            OverflowPolicy::CoalesceSilence => {
+                if frame.is_silence() {
+                    // A keepalive adds nothing while silence is already queued
+                    state.stats.coalesced += 1;
+                    PushOutcome::Dropped
+                } else if let Some(index) = state.frames.iter().position(|f| f.is_silence()) {
+                    state.frames.remove(index);
+                    state.frames.push_back(frame);
+                    state.stats.coalesced += 1;
+                    PushOutcome::Replaced
+                } else {
+                    state.frames.pop_front();
+                    state.frames.push_back(frame);
+                    state.stats.dropped += 1;
+                    PushOutcome::Replaced
+                }
+            }
+        };
+        (outcome, lag)
+    }
+
+    /// Take the next frame (JS thread, one per issued call)
+    pub fn pop(&self) -> (Option<PooledFrame>, LagChange) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        state.pop()
+    }
+
+    /// Take the frame for a token issued in `run` (None once the run is over)
+    pub fn pop_token(&self, run: u64) -> (Option<PooledFrame>, LagChange) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if state.run != run {
+            return (None, LagChange::None);
+        }
+        state.pop()
+    }
+
+    /// Current run (tag for the tokens issued for pushed frames)
+    pub fn run(&self) -> u64 {
+        self.state.lock().map(|state| state.run).unwrap_or_default()
+    }
+
+    /// Pull mode: complete `resolve` with up to `max_frames` frames
+    ///
+    /// Resolves immediately if frames are queued (or the queue is closed),
+    /// otherwise when the next frame is pushed. Only one read may be pending.
+    pub fn read(&self, max_frames: usize, resolve: PullResolve) -> Result<LagChange> {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if !state.pull {
+            return Err(anyhow::anyhow!("read() requires startPull()"));
+        }
+        if state.waiter.is_some() {
+            return Err(anyhow::anyhow!("read() already pending"));
+        }
+        if state.frames.is_empty() && !state.closed {
+            state.waiter = Some(PullWaiter {
+                max_frames: max_frames.max(1),
+                resolve,
+            });
+            return Ok(LagChange::None);
+        }
+        let (frames, lag) = state.drain(max_frames.max(1));
+        drop(state);
+        resolve(frames);
+        Ok(lag)
+    }
+
+    /// Delivery mode of the next run: read() (true) or tokens (false)
+    pub fn set_pull(&self, pull: bool) {
+        if let Ok(mut state) = self.state.lock() {
+            state.pull = pull;
+        }
+    }
+
+    /// Start a run (capture started): frames the previous run left unread
+    /// are dropped, tokens issued before are stale
+    pub fn open(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.frames.clear();
+            state.stats.queued = 0;
+            state.stats.lagging = false;
+            state.closed = false;
+            state.run += 1;
+        }
+    }
+
+    /// End of stream: a pending read completes with an empty batch
+    /// Frames still queued can be read until the queue is empty.
+    pub fn close(&self) {
+        let waiter = match self.state.lock() {
+            Ok(mut state) => {
+                state.closed = true;
+                state.waiter.take()
+            }
+            Err(_) => None,
+        };
+        if let Some(waiter) = waiter {
+            (waiter.resolve)(Vec::new());
+        }
+    }
+
+    pub fn stats(&self) -> DeliveryStats {
+        self.state.lock().map(|state| state.stats.clone()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_pool::FramePool;
+
+    fn frame(pool: &FramePool, value: i16) -> PooledFrame {
+        let mut frame = pool.take(320);
+        frame.samples_mut().resize(320, value);
+        frame.set_silence(value == 0);
+        frame
+    }
+
+    fn queue(max_queued: usize, policy: OverflowPolicy) -> DeliveryQueue {
+        DeliveryQueue::new(DeliveryConfig { max_queued, policy })
+    }
+
+    #[test]
+    fn test_drop_oldest_keeps_newest_and_reports_lag() {
+        let pool = FramePool::new(8);
+        let q = queue(2, OverflowPolicy::DropOldest);
+        assert_eq!(q.push(frame(&pool, 1)), (PushOutcome::Queued, LagChange::None));
+        assert_eq!(q.push(frame(&pool, 2)), (PushOutcome::Queued, LagChange::None));
+        assert_eq!(q.push(frame(&pool, 3)), (PushOutcome::Replaced, LagChange::Started));
+        assert_eq!(q.push(frame(&pool, 4)), (PushOutcome::Replaced, LagChange::None));
+
+        let (first, _) = q.pop();
+        assert_eq!(first.unwrap().samples()[0], 3);
+        let (second, lag) = q.pop();
+        assert_eq!(second.unwrap().samples()[0], 4);
+        assert_eq!(lag, LagChange::Recovered);
+
+        let stats = q.stats();
+        assert_eq!(stats.dropped, 2);
+        assert_eq!(stats.delivered, 2);
+        assert!(!stats.lagging);
+    }
+
+    #[test]
+    fn test_drop_newest_rejects_incoming() {
+        let pool = FramePool::new(8);
+        let q = queue(1, OverflowPolicy::DropNewest);
+        q.push(frame(&pool, 1));
+        assert_eq!(q.push(frame(&pool, 2)).0, PushOutcome::Dropped);
+        assert_eq!(q.pop().0.unwrap().samples()[0], 1);
+    }
+
+    #[test]
+    fn test_coalesce_silence_drops_keepalives_first() {
+        let pool = FramePool::new(8);
+        let q = queue(2, OverflowPolicy::CoalesceSilence);
+        q.push(frame(&pool, 0));
+        q.push(frame(&pool, 1));
+
+        // Incoming silence is coalesced away
+        assert_eq!(q.push(frame(&pool, 0)).0, PushOutcome::Dropped);
+        // Incoming speech replaces the queued keepalive, not the speech
+        assert_eq!(q.push(frame(&pool, 2)).0, PushOutcome::Replaced);
+
+        assert_eq!(q.pop().0.unwrap().samples()[0], 1);
+        assert_eq!(q.pop().0.unwrap().samples()[0], 2);
+        let stats = q.stats();
+        assert_eq!(stats.coalesced, 2);
+        assert_eq!(stats.dropped, 0);
+    }
+
+    #[test]
+    fn test_pending_read_completes_on_push_and_on_close() {
+        use std::sync::mpsc;
+
+        let pool = FramePool::new(8);
+        let q = queue(10, OverflowPolicy::DropOldest);
+        q.set_pull(true);
+        q.open();
+
+        // Nothing queued: read waits for the next push
+        let (tx, rx) = mpsc::channel();
+        let sender = tx.clone();
+        q.read(4, Box::new(move |frames| sender.send(frames.len()).unwrap())).unwrap();
+        assert!(rx.try_recv().is_err());
+        assert!(q.read(4, Box::new(|_| {})).is_err(), "only one pending read");
+        q.push(frame(&pool, 1));
+        assert_eq!(rx.try_recv(), Ok(1));
+
+        // Queued frames are returned immediately, up to max_frames
+        for value in 2..5 {
+            q.push(frame(&pool, value));
+        }
+        let sender = tx.clone();
+        q.read(2, Box::new(move |frames| sender.send(frames.len()).unwrap())).unwrap();
+        assert_eq!(rx.try_recv(), Ok(2));
+
+        // Stop cancels a pending read with an empty batch (after draining)
+        let sender = tx.clone();
+        q.read(4, Box::new(move |frames| sender.send(frames.len()).unwrap())).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        q.read(4, Box::new(move |frames| tx.send(frames.len()).unwrap())).unwrap();
+        q.close();
+        assert_eq!(rx.try_recv(), Ok(0));
+    }
+
+    #[test]
+    fn test_restart_drops_unread_frames_and_stale_tokens() {
+        let pool = FramePool::new(8);
+        let q = queue(10, OverflowPolicy::DropOldest);
+
+        // Pull run stopped with frames unread
+        q.open();
+        for value in 1..4 {
+            q.push(frame(&pool, value));
+        }
+        q.close();
+
+        // Callback run: a token in flight from before the restart
+        let stale = q.run();
+        q.open();
+        let run = q.run();
+        assert_eq!(q.push(frame(&pool, 7)).0, PushOutcome::Queued);
+        assert!(q.pop_token(stale).0.is_none());
+        // The first callback frame is the new run's
+        assert_eq!(q.pop_token(run).0.unwrap().samples()[0], 7);
+        assert_eq!(q.stats().queued, 0);
+    }
+
+    #[test]
+    fn test_open_ends_the_previous_pull_stream() {
+        use std::sync::mpsc;
+
+        let pool = FramePool::new(8);
+        let q = queue(10, OverflowPolicy::DropOldest);
+        q.set_pull(true);
+        q.open();
+        q.push(frame(&pool, 1));
+        q.push(frame(&pool, 2));
+        q.close();
+
+        // Until the next start the leftovers can still be read
+        let (tx, rx) = mpsc::channel();
+        let sender = tx.clone();
+        q.read(1, Box::new(move |frames| sender.send(frames[0].samples()[0]).unwrap())).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+
+        // Restarted (any mode): nothing of the old run is read
+        q.open();
+        assert_eq!(q.stats().queued, 0);
+        q.read(4, Box::new(move |frames| tx.send(frames[0].samples()[0]).unwrap())).unwrap();
+        assert!(rx.try_recv().is_err(), "read must wait for the new run");
+        q.push(frame(&pool, 3));
+        assert_eq!(rx.try_recv(), Ok(3));
+    }
+
+    #[test]
+    fn test_read_after_callback_run_leaves_frames_to_tokens() {
+        let pool = FramePool::new(8);
+        let q = queue(10, OverflowPolicy::DropOldest);
+        q.set_pull(false);
+        q.open();
+        let run = q.run();
+        q.push(frame(&pool, 1));
+        q.push(frame(&pool, 2));
+        q.close();
+
+        // Stopped with two tokens in flight: read() is refused
+        assert!(q.read(4, Box::new(|_| panic!("read must not resolve"))).is_err());
+        assert_eq!(q.pop_token(run).0.unwrap().samples()[0], 1);
+        assert_eq!(q.pop_token(run).0.unwrap().samples()[0], 2);
+    }
+}
@@ -0,0 +1,378 @@
+// Speaker Diarization - who is talking on the system audio stream
+//
+// WHY:
+// - Everything SystemAudioCapture delivers is "the other side"; in a panel
+//   interview that is several people. Stable speaker IDs on time ranges let
+//   JS attribute any provider's transcript per speaker
+//
+// HOW (CPU only, no model files):
+// - Per voiced frame: 12 MFCCs (DCT of the log-mel bands, c0 / level dropped)
+// - Speech is cut into windows of up to 1.5s of voiced audio; a window ends
+//   early when the suppressor leaves speech
+// - Window embedding: mean MFCC vector. Distances are RMS over the
+//   coefficients, each scaled by its spread within windows (phonetic
+//   variation, pooled over all windows so far), which makes one threshold
+//   work for every stream regardless of how many people talk
+// - Online clustering: a window joins the nearest speaker if it is within
+//   `max_distance` (the centroid follows it), otherwise it starts a new
+//   speaker. IDs are assigned in order of appearance and never reused
+// - Short windows can only join known speakers: too little audio to trust
+//   as a new voice. The short tail of a turn keeps the turn's speaker
+//
+// OUTPUT:
+// - One SpeakerSegment per labelled window (frame sequence numbers and
+//   capture times), raised as a Speaker event; consecutive windows of the same speaker are adjacent,
+//   merging them is up to the consumer
+//
+// REAL-TIME NOTES:
+// - Buffers and the DCT table are allocated in new(); push() does not
+//   allocate except when a new speaker appears
+
+use crate::audio_config::FRAME_MS;
+use crate::features::{Cepstrum, SpectralAnalyzer, MFCC_COEFFS};
+use crate::segmenter::{FrameAnalyzer, FrameInfo};
+
+/// Voiced frames per window (1.5s)
+const WINDOW_VOICED_FRAMES: usize = 75;
+
+/// Windows with fewer voiced frames are not labelled (300ms)
+const MIN_LABEL_FRAMES: usize = 15;
+
+/// Windows with fewer voiced frames can't start a new speaker (800ms)
+const MIN_NEW_SPEAKER_FRAMES: usize = 40;
+
+/// Voiced frames a centroid is averaged over at most (60s): speakers
+/// keep adapting to the room / codec
+const MAX_CENTROID_FRAMES: f32 = 3000.0;
+
+/// Spread floor per coefficient (keeps early distances sane)
+const MIN_SPREAD: f32 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiarizationConfig {
+    /// Largest distance (in spreads) at which a window still joins a speaker
+    pub max_distance: f32,
+    /// Speakers tracked at most; further voices join the nearest one
+    pub max_speakers: usize,
+}
+
+impl Default for DiarizationConfig {
+    fn default() -> Self {
+        Self {
+            max_distance: 0.5,
+            max_speakers: 8,
+        }
+    }
+}
+
+/// A labelled range of frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerSegment {
+    /// Index in order of appearance (see label())
+    pub speaker: usize,
+    /// First and last frame (inclusive)
+    pub seq_start: u64,
+    pub seq_end: u64,
+    /// Capture time of the first sample (ms since the Unix epoch)
+    pub start_time_ms: Option<f64>,
+    /// Capture time just after the last sample
+    pub end_time_ms: Option<f64>,
+    /// First segment of this speaker
+    pub new_speaker: bool,
+}
+
+impl SpeakerSegment {
+    /// Stable ID handed to JS ("spk_0", "spk_1", ...)
+    pub fn label(&self) -> String {
+        speaker_label(self.speaker)
+    }
+}
+
+pub fn speaker_label(speaker: usize) -> String {
+    format!("spk_{}", speaker)
+}
+
+/// Speech collected since the window opened
+struct Window {
+    seq_start: u64,
+    seq_end: u64,
+    start_time_ms: Option<f64>,
+    end_time_ms: Option<f64>,
+    voiced: usize,
+    sum: [f32; MFCC_COEFFS],
+    sum_sq: [f32; MFCC_COEFFS],
+}
+
+struct Speaker {
+    centroid: [f32; MFCC_COEFFS],
+    /// Voiced frames behind the centroid (capped)
+    weight: f32,
+}
+
+pub struct SpeakerDiarizer {
+    config: DiarizationConfig,
+    analyzer: SpectralAnalyzer,
+    cepstrum: Cepstrum,
+    mfcc: [f32; MFCC_COEFFS],
+    window: Option<Window>,
+    /// Squared deviations from their window's mean, summed over all
+    /// labelled windows (spread per coefficient)
+    pooled_frames: f64,
+    pooled_sq: [f64; MFCC_COEFFS],
+    speakers: Vec<Speaker>,
+    /// Speaker and last frame of the previous segment
+    last: Option<(usize, u64)>,
+}
+
+impl SpeakerDiarizer {
+    pub fn new(config: DiarizationConfig) -> Self {
+        println!(
+            "[Diarization] Created (max_distance={}, max_speakers={})",
+            config.max_distance, config.max_speakers
+        );
+        Self {
+            config,
+            analyzer: SpectralAnalyzer::new(),
+            cepstrum: Cepstrum::new(),
+            mfcc: [0.0; MFCC_COEFFS],
+            window: None,
+            pooled_frames: 0.0,
+            pooled_sq: [0.0; MFCC_COEFFS],
+            speakers: Vec::new(),
+            last: None,
+        }
+    }
+
+    /// Replace options (known speakers are kept)
+    pub fn set_config(&mut self, config: DiarizationConfig) {
+        self.config = config;
+    }
+
+    fn segment(&mut self, speaker: usize, window: &Window, new_speaker: bool) -> SpeakerSegment {
+        self.last = Some((speaker, window.seq_end));
+        SpeakerSegment {
+            speaker,
+            seq_start: window.seq_start,
+            seq_end: window.seq_end,
+            start_time_ms: window.start_time_ms,
+            end_time_ms: window.end_time_ms,
+            new_speaker,
+        }
+    }
+
+    /// RMS over coefficients of the difference in units of their spread
+    fn distance(&self, a: &[f32; MFCC_COEFFS], b: &[f32; MFCC_COEFFS]) -> f32 {
+        let n = self.pooled_frames.max(1.0);
+        let sum = a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .map(|(i, (x, y))| {
+                let spread = ((self.pooled_sq[i] / n).sqrt() as f32).max(MIN_SPREAD);
+                let d = (x - y) / spread;
+                d * d
+            })
+            .sum::<f32>();
+        (sum / MFCC_COEFFS as f32).sqrt()
+    }
+
+    /// Speakers seen so far
+    pub fn speakers(&self) -> usize {
+        self.speakers.len()
+    }
+
+    /// Forget every speaker (IDs start again at spk_0)
+    pub fn reset(&mut self) {
+        self.window = None;
+        self.speakers.clear();
+        self.last = None;
+        self.pooled_frames = 0.0;
+        self.pooled_sq = [0.0; MFCC_COEFFS];
+        self.analyzer.reset();
+    }
+}
+
+impl FrameAnalyzer for SpeakerDiarizer {
+    type Output = SpeakerSegment;
+
+    /// Next mono 16kHz frame; returns a segment when a window is labelled
+    fn push(&mut self, frame: &[i16], info: &FrameInfo) -> Option<SpeakerSegment> {
+        if !info.speech {
+            return self.flush();
+        }
+        // Non-speech frames are skipped entirely (the analyzer's overlap
+        // with stale audio only affects the first frame of a turn)
+        self.analyzer.analyze(frame);
+        let window = self.window.get_or_insert(Window {
+            seq_start: info.seq,
+            seq_end: info.seq,
+            start_time_ms: info.capture_time_ms,
+            end_time_ms: None,
+            voiced: 0,
+            sum: [0.0; MFCC_COEFFS],
+            sum_sq: [0.0; MFCC_COEFFS],
+        });
+        window.seq_end = info.seq;
+        window.end_time_ms = info.capture_time_ms.map(|time| time + FRAME_MS as f64);
+        if !info.voiced {
+            return None;
+        }
+
+        self.cepstrum.apply(self.analyzer.log_mel(), &mut self.mfcc);
+        for (i, &coeff) in self.mfcc.iter().enumerate() {
+            window.sum[i] += coeff;
+            window.sum_sq[i] += coeff * coeff;
+        }
+        window.voiced += 1;
+        if window.voiced >= WINDOW_VOICED_FRAMES {
+            return self.flush();
+        }
+        None
+    }
+
+    /// Label whatever speech is collected (end of a turn / of the run)
+    fn flush(&mut self) -> Option<SpeakerSegment> {
+        let window = self.window.take()?;
+        if window.voiced < MIN_LABEL_FRAMES {
+            // Too short to judge: only the rest of a labelled turn gets a label
+            let (speaker, seq_end) = self.last?;
+            if window.seq_start != seq_end + 1 {
+                return None;
+            }
+            return Some(self.segment(speaker, &window, false));
+        }
+        let n = window.voiced as f32;
+        let mut embedding = window.sum;
+        for (i, value) in embedding.iter_mut().enumerate() {
+            *value /= n;
+            self.pooled_sq[i] += (window.sum_sq[i] - n * *value * *value).max(0.0) as f64;
+        }
+        self.pooled_frames += n as f64;
+
+        let nearest = self
+            .speakers
+            .iter()
+            .enumerate()
+            .map(|(i, speaker)| (i, self.distance(&speaker.centroid, &embedding)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let long_enough = window.voiced >= MIN_NEW_SPEAKER_FRAMES;
+        let (speaker, new_speaker) = match nearest {
+            Some((i, distance)) if distance <= self.config.max_distance => (i, false),
+            _ if long_enough && self.speakers.len() < self.config.max_speakers.max(1) => {
+                self.speakers.push(Speaker {
+                    centroid: embedding,
+                    weight: 0.0,
+                });
+                println!("[Diarization] New speaker {}", speaker_label(self.speakers.len() - 1));
+                (self.speakers.len() - 1, true)
+            }
+            // No room for another voice: closest match
+            Some((i, _)) if long_enough => (i, false),
+            // Too short to tell, and nobody it sounds like
+            _ => return None,
+        };
+
+        let frames = window.voiced as f32;
+        let entry = &mut self.speakers[speaker];
+        let amount = frames / (entry.weight + frames);
+        for (c, e) in entry.centroid.iter_mut().zip(&embedding) {
+            *c += (e - *c) * amount;
+        }
+        entry.weight = (entry.weight + frames).min(MAX_CENTROID_FRAMES);
+        Some(self.segment(speaker, &window, new_speaker))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classifier::test_signals::{frames, voice};
+
+    /// Feed `signal` as one turn followed by a pause, starting at `seq`
+    fn turn(diarizer: &mut SpeakerDiarizer, signal: &[f32], seq: &mut u64) -> Vec<SpeakerSegment> {
+        let mut segments = Vec::new();
+        for frame in frames(signal) {
+            let rms = (frame.iter().map(|&s| (s as f32).powi(2)).sum::<f32>() / frame.len() as f32).sqrt();
+            let info = FrameInfo {
+                seq: *seq,
+                capture_time_ms: Some(*seq as f64 * FRAME_MS as f64),
+                voiced: rms > 300.0,
+                speech: true,
+            };
+            segments.extend(diarizer.push(&frame, &info));
+            *seq += 1;
+        }
+        let pause = FrameInfo {
+            seq: *seq,
+            capture_time_ms: None,
+            voiced: false,
+            speech: false,
+        };
+        segments.extend(diarizer.push(&vec![0; frames(signal)[0].len()], &pause));
+        *seq += 1;
+        segments
+    }
+
+    fn speakers_of(segments: &[SpeakerSegment]) -> Vec<usize> {
+        segments.iter().map(|segment| segment.speaker).collect()
+    }
+
+    #[test]
+    fn test_one_voice_stays_one_speaker() {
+        let mut diarizer = SpeakerDiarizer::new(DiarizationConfig::default());
+        let mut seq = 0;
+        let mut labels = Vec::new();
+        for seed in 1..6 {
+            labels.extend(speakers_of(&turn(&mut diarizer, &voice(4.0, seed, 1.0, 1.0), &mut seq)));
+        }
+        assert!(labels.len() >= 10, "{} segments", labels.len());
+        assert!(labels.iter().all(|&speaker| speaker == 0), "{:?}", labels);
+        assert_eq!(diarizer.speakers(), 1);
+    }
+
+    #[test]
+    fn test_alternating_voices_get_stable_ids() {
+        let mut diarizer = SpeakerDiarizer::new(DiarizationConfig::default());
+        let mut seq = 0;
+        let mut turns = Vec::new();
+        for seed in 1..7 {
+            let (pitch, formants) = if seed % 2 == 1 { (1.0, 1.0) } else { (1.8, 1.3) };
+            let segments = turn(&mut diarizer, &voice(4.0, seed, pitch, formants), &mut seq);
+            turns.push(speakers_of(&segments));
+        }
+        for (i, labels) in turns.iter().enumerate() {
+            assert!(!labels.is_empty());
+            let expected = i % 2;
+            assert!(labels.iter().all(|&speaker| speaker == expected), "turn {}: {:?}", i, turns);
+        }
+        assert_eq!(diarizer.speakers(), 2);
+    }
+
+    #[test]
+    fn test_segments_cover_the_turn() {
+        let mut diarizer = SpeakerDiarizer::new(DiarizationConfig::default());
+        let mut seq = 10;
+        let segments = turn(&mut diarizer, &voice(4.0, 3, 1.0, 1.0), &mut seq);
+        assert!(segments[0].new_speaker);
+        assert_eq!(segments[0].seq_start, 10);
+        assert_eq!(segments[0].label(), "spk_0");
+        for pair in segments.windows(2) {
+            assert_eq!(pair[1].seq_start, pair[0].seq_end + 1);
+            assert!(!pair[1].new_speaker);
+        }
+        // 4s = 200 frames, the pause frame is not part of it
+        assert_eq!(segments.last().unwrap().seq_end, 209);
+        assert_eq!(segments.last().unwrap().end_time_ms, Some(210.0 * FRAME_MS as f64));
+    }
+
+    #[test]
+    fn test_short_unknown_voice_is_not_labelled() {
+        let mut diarizer = SpeakerDiarizer::new(DiarizationConfig::default());
+        let mut seq = 0;
+        turn(&mut diarizer, &voice(4.0, 1, 1.0, 1.0), &mut seq);
+        // 0.5s: too short to start a new speaker
+        let segments = turn(&mut diarizer, &voice(0.5, 2, 1.8, 1.3), &mut seq);
+        assert!(segments.is_empty(), "{:?}", segments);
+        assert_eq!(diarizer.speakers(), 1);
+    }
+}
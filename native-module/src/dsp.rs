@@ -0,0 +1,1138 @@
+// DSP Pipeline - shared by MicrophoneCapture and SystemAudioCapture
+//
+// Per-frame chain (16kHz i16, 20ms; [optional] stages, see their modules):
+//   ring buffer -> resample (downmix or keep channels) -> pause / mute
+//   -> HighPassFilter -> AutomaticGainControl
+//   -> [ContentClassifier -> MusicAttenuator] -> SilenceSuppressor
+//   -> [AudioHistory] -> [SpeakerDiarizer, QuestionDetector, KeywordSpotter]
+//   -> [keyword holdback] -> FrameBatcher or [UtteranceSegmenter] -> emit
+//
+// RUNTIME RECONFIGURATION:
+// - JS pushes DspCommands through a lock-free SPSC queue (ringbuf)
+// - The DSP thread drains the queue at every frame boundary
+// - No locks are taken on the audio path, no restart needed
+//
+// TIMESTAMPS:
+// - Every frame gets a sequence number (suppressed frames included) and the
+//   capture time of its first sample, corrected for the pipeline's latency
+// - Format changes arrive in-band (see sample_ring.rs): the resampler is
+//   retuned at the exact sample, frames stay 16kHz
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use ringbuf::{traits::{Consumer, Split}, HeapCons, HeapProd, HeapRb};
+
+use crate::classifier::{ClassReport, ClassifierConfig, ContentClassifier};
+use crate::comfort_noise::ComfortNoise;
+use crate::diarization::{DiarizationConfig, SpeakerDiarizer, SpeakerSegment};
+use crate::keywords::{KeywordConfig, KeywordMatch, KeywordSpotter};
+use crate::pitch::{QuestionConfig, QuestionCue, QuestionDetector};
+use crate::audio_config::{
+    DSP_COMMAND_QUEUE_SIZE, DSP_WAKE_TIMEOUT_MS, FRAME_MS, FRAME_POOL_SIZE, FRAME_SAMPLES, SAMPLE_RATE,
+};
+use crate::frame_pool::{FrameBatcher, FramePool, PooledFrame};
+use crate::history::AudioHistory;
+use crate::gain_control::{AgcConfig, AutomaticGainControl, HighPassFilter};
+use crate::silence_suppression::{FrameAction, SilenceFill, SilenceSuppressionConfig, SilenceSuppressor};
+use crate::events::{CaptureEvent, EventHandler};
+use crate::sample_ring::{AudioFormat, SampleConsumer};
+use crate::segmenter::{FrameAnalyzer, FrameInfo, Utterance, UtteranceSegmenter};
+use crate::separation::MusicAttenuator;
+use crate::streaming_resampler::StreamingResampler;
+
+/// How the suppressor output is used
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VadMode {
+    /// Gate silence, send keepalives (default)
+    Suppress,
+    /// Send every frame regardless of speech state
+    Passthrough,
+}
+
+impl VadMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "suppress" => Some(VadMode::Suppress),
+            "passthrough" => Some(VadMode::Passthrough),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to multichannel input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelMode {
+    /// Average all channels into mono frames (default)
+    Downmix,
+    /// Deliver interleaved frames with every input channel; AGC and speech
+    /// detection act on all channels together, so a participant panned to
+    /// one side still counts as speech
+    Keep,
+}
+
+impl ChannelMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "downmix" => Some(ChannelMode::Downmix),
+            "keep" => Some(ChannelMode::Keep),
+            _ => None,
+        }
+    }
+}
+
+/// Command sent from JS to a running DSP thread
+#[derive(Debug, Clone)]
+pub enum DspCommand {
+    /// Replace suppressor thresholds/timings (state is kept)
+    Suppression(SilenceSuppressionConfig),
+    /// Replace AGC config, or disable AGC with None
+    Agc(Option<AgcConfig>),
+    /// Replace content classifier options, or disable it with None
+    Classifier(Option<ClassifierConfig>),
+    /// Replace diarization options (speakers are kept), or disable it with None
+    Diarization(Option<DiarizationConfig>),
+    /// Replace question detection options, or disable it with None
+    QuestionDetection(Option<QuestionConfig>),
+    /// Replace enrolled keywords and options, or disable spotting with None
+    Keywords(Option<KeywordConfig>),
+    VadMode(VadMode),
+    /// Zero-fill frames (keepalive cadence is preserved)
+    Muted(bool),
+    /// Drop frames; the device stream keeps running so resume is instant
+    Paused(bool),
+    /// Number of 20ms frames delivered per JS callback
+    FramesPerCallback(usize),
+}
+
+/// Receives complete utterances on the DSP thread (utterance mode)
+pub type UtteranceHandler = Box<dyn FnMut(Utterance) + Send>;
+
+/// Sees the speech state and samples of every processed frame on the DSP
+/// thread (must not block; see conversation.rs)
+pub type SpeechObserver = Arc<dyn Fn(FrameInfo, &[i16]) + Send + Sync>;
+
+pub type CommandProducer = HeapProd<DspCommand>;
+pub type CommandConsumer = HeapCons<DspCommand>;
+
+/// Create the lock-free command queue for one DSP thread
+pub fn command_queue() -> (CommandProducer, CommandConsumer) {
+    HeapRb::<DspCommand>::new(DSP_COMMAND_QUEUE_SIZE).split()
+}
+
+/// Current DSP parameters of a capture
+///
+/// Kept on the capture object so that settings made while stopped are
+/// applied when the next DSP thread starts.
+#[derive(Debug, Clone)]
+pub struct DspSettings {
+    pub suppression: SilenceSuppressionConfig,
+    pub agc: Option<AgcConfig>,
+    pub classifier: Option<ClassifierConfig>,
+    pub diarization: Option<DiarizationConfig>,
+    pub question_detection: Option<QuestionConfig>,
+    pub keywords: Option<KeywordConfig>,
+    pub vad_mode: VadMode,
+    pub muted: bool,
+    pub paused: bool,
+    pub frames_per_callback: usize,
+    /// Fixed when the DSP thread starts (the frame layout can't change mid-stream)
+    pub channel_mode: ChannelMode,
+}
+
+impl DspSettings {
+    pub fn for_microphone() -> Self {
+        Self {
+            suppression: SilenceSuppressionConfig::for_microphone(),
+            agc: Some(AgcConfig::default()),
+            classifier: None,
+            diarization: None,
+            question_detection: None,
+            keywords: None,
+            vad_mode: VadMode::Suppress,
+            muted: false,
+            paused: false,
+            frames_per_callback: 1,
+            channel_mode: ChannelMode::Downmix,
+        }
+    }
+
+    pub fn for_system_audio() -> Self {
+        Self {
+            suppression: SilenceSuppressionConfig::for_system_audio(),
+            agc: Some(AgcConfig::default()),
+            classifier: Some(ClassifierConfig::default()),
+            diarization: None,
+            question_detection: Some(QuestionConfig::default()),
+            keywords: None,
+            vad_mode: VadMode::Suppress,
+            muted: false,
+            paused: false,
+            frames_per_callback: 1,
+            channel_mode: ChannelMode::Downmix,
+        }
+    }
+
+    /// Record a command so the settings stay in sync with the DSP thread
+    pub fn apply(&mut self, command: &DspCommand) {
+        match command {
+            DspCommand::Suppression(config) => self.suppression = config.clone(),
+            DspCommand::Agc(config) => self.agc = config.clone(),
+            DspCommand::Classifier(config) => self.classifier = config.clone(),
+            DspCommand::Diarization(config) => self.diarization = config.clone(),
+            DspCommand::QuestionDetection(config) => self.question_detection = config.clone(),
+            DspCommand::Keywords(config) => self.keywords = config.clone(),
+            DspCommand::VadMode(mode) => self.vad_mode = *mode,
+            DspCommand::Muted(muted) => self.muted = *muted,
+            DspCommand::Paused(paused) => self.paused = *paused,
+            DspCommand::FramesPerCallback(n) => self.frames_per_callback = *n,
+        }
+    }
+}
+
+/// What the analysis stages found in one frame (or at the end of a run)
+#[derive(Debug, Default)]
+pub struct FrameFindings {
+    pub speaker: Option<SpeakerSegment>,
+    pub question: Option<QuestionCue>,
+    pub keyword: Option<KeywordMatch>,
+}
+
+/// Per-frame processing chain
+pub struct DspPipeline {
+    /// One filter per channel
+    high_pass: Vec<HighPassFilter>,
+    agc: Option<AutomaticGainControl>,
+    classifier: Option<ContentClassifier>,
+    /// One per channel while separation is enabled
+    attenuators: Vec<MusicAttenuator>,
+    diarizer: Option<SpeakerDiarizer>,
+    question_detector: Option<QuestionDetector>,
+    spotter: Option<KeywordSpotter>,
+    suppressor: SilenceSuppressor,
+    /// One background model per channel (comfort-noise fill)
+    comfort_noise: Vec<ComfortNoise>,
+    vad_mode: VadMode,
+    muted: bool,
+    paused: bool,
+    frames_per_callback: usize,
+    channel_mode: ChannelMode,
+    channels: usize,
+    /// Mono mix of an interleaved frame (speech detection)
+    mix: Vec<i16>,
+    /// One channel of an interleaved frame (separation)
+    channel_frame: Vec<i16>,
+}
+
+impl DspPipeline {
+    pub fn new(settings: &DspSettings) -> Self {
+        let mut pipeline = Self {
+            high_pass: vec![HighPassFilter::default()],
+            agc: settings.agc.clone().map(AutomaticGainControl::new),
+            classifier: None,
+            attenuators: Vec::new(),
+            diarizer: settings.diarization.clone().map(SpeakerDiarizer::new),
+            question_detector: settings.question_detection.clone().map(QuestionDetector::new),
+            spotter: settings.keywords.clone().map(KeywordSpotter::new),
+            suppressor: SilenceSuppressor::new(settings.suppression.clone()),
+            comfort_noise: vec![ComfortNoise::new()],
+            vad_mode: settings.vad_mode,
+            muted: settings.muted,
+            paused: settings.paused,
+            frames_per_callback: settings.frames_per_callback,
+            channel_mode: settings.channel_mode,
+            channels: 1,
+            mix: Vec::with_capacity(FRAME_SAMPLES),
+            channel_frame: Vec::with_capacity(FRAME_SAMPLES),
+        };
+        pipeline.set_classifier(settings.classifier.clone());
+        pipeline
+    }
+
+    /// Number of interleaved channels in the frames passed to process()
+    pub fn set_channels(&mut self, channels: usize) {
+        self.channels = channels.max(1);
+        self.high_pass.resize_with(self.channels, HighPassFilter::default);
+        self.comfort_noise.resize_with(self.channels, ComfortNoise::new);
+        if !self.attenuators.is_empty() {
+            self.attenuators.resize_with(self.channels, MusicAttenuator::new);
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn channel_mode(&self) -> ChannelMode {
+        self.channel_mode
+    }
+
+    /// Apply a command (called at frame boundaries only)
+    pub fn apply(&mut self, command: DspCommand) {
+        match command {
+            DspCommand::Suppression(config) => self.suppressor.set_config(config),
+            DspCommand::Agc(config) => match (self.agc.as_mut(), config) {
+                (Some(agc), Some(config)) => agc.set_config(config),
+                (None, Some(config)) => self.agc = Some(AutomaticGainControl::new(config)),
+                (_, None) => self.agc = None,
+            },
+            DspCommand::Classifier(config) => self.set_classifier(config),
+            DspCommand::Diarization(config) => match (self.diarizer.as_mut(), config) {
+                (Some(diarizer), Some(config)) => diarizer.set_config(config),
+                (None, Some(config)) => self.diarizer = Some(SpeakerDiarizer::new(config)),
+                (_, None) => self.diarizer = None,
+            },
+            DspCommand::QuestionDetection(config) => match (self.question_detector.as_mut(), config) {
+                (Some(detector), Some(config)) => detector.set_config(config),
+                (None, Some(config)) => self.question_detector = Some(QuestionDetector::new(config)),
+                (_, None) => self.question_detector = None,
+            },
+            DspCommand::Keywords(config) => match (self.spotter.as_mut(), config) {
+                (Some(spotter), Some(config)) => spotter.set_config(config),
+                (None, Some(config)) => self.spotter = Some(KeywordSpotter::new(config)),
+                (_, None) => self.spotter = None,
+            },
+            DspCommand::VadMode(mode) => self.vad_mode = mode,
+            DspCommand::Muted(muted) => self.muted = muted,
+            DspCommand::Paused(paused) => {
+                if self.paused && !paused {
+                    // Start active again so the first words after resume are not gated
+                    self.suppressor.reset();
+                }
+                self.paused = paused;
+            }
+            DspCommand::FramesPerCallback(n) => self.frames_per_callback = n,
+        }
+    }
+
+    /// Enable / reconfigure / disable classification and separation
+    /// (the running classifier keeps its window when reconfigured)
+    fn set_classifier(&mut self, config: Option<ClassifierConfig>) {
+        let separation = config.as_ref().is_some_and(|config| config.separation);
+        match (self.classifier.as_mut(), config) {
+            (Some(classifier), Some(config)) => classifier.set_config(config),
+            (None, Some(config)) => self.classifier = Some(ContentClassifier::new(config)),
+            (_, None) => self.classifier = None,
+        }
+        if !separation {
+            self.attenuators.clear();
+        } else if self.attenuators.is_empty() {
+            self.attenuators = (0..self.channels).map(|_| MusicAttenuator::new()).collect();
+        }
+    }
+
+    /// Run one 20ms frame through the chain (interleaved if channels > 1)
+    pub fn process(&mut self, frame: &mut [i16]) -> FrameAction {
+        if self.paused {
+            return FrameAction::Suppress;
+        }
+        if self.muted {
+            frame.fill(0);
+        }
+        if self.channels == 1 {
+            self.high_pass[0].process(frame);
+        } else {
+            for (i, sample) in frame.iter_mut().enumerate() {
+                *sample = self.high_pass[i % self.channels].process_sample(*sample);
+            }
+        }
+        // Interleaved: one gain for all channels (level measured across them),
+        // so the stereo image is preserved
+        if let Some(agc) = self.agc.as_mut() {
+            agc.process(frame);
+        }
+
+        let mut gated = false;
+        if let Some(classifier) = self.classifier.as_mut() {
+            if self.channels == 1 {
+                classifier.process(frame);
+            } else {
+                downmix(frame, self.channels, &mut self.mix);
+                classifier.process(&self.mix);
+            }
+            gated = classifier.blocks_speech();
+
+            let music = classifier.music_recent();
+            if self.channels == 1 {
+                if let Some(attenuator) = self.attenuators.first_mut() {
+                    attenuator.process(frame, music);
+                }
+            } else {
+                for (channel, attenuator) in self.attenuators.iter_mut().enumerate() {
+                    self.channel_frame.clear();
+                    self.channel_frame.extend(frame.iter().skip(channel).step_by(self.channels));
+                    attenuator.process(&mut self.channel_frame, music);
+                    for (sample, &value) in frame.iter_mut().skip(channel).step_by(self.channels).zip(&self.channel_frame) {
+                        *sample = value;
+                    }
+                }
+            }
+        }
+
+        let action = if self.channels == 1 {
+            self.suppressor.process_gated(frame, gated)
+        } else {
+            downmix(frame, self.channels, &mut self.mix);
+            self.suppressor.process_gated(&self.mix, gated)
+        };
+        if !matches!(action, FrameAction::Send) && self.suppressor.config().silence_fill == SilenceFill::ComfortNoise {
+            self.learn_background(frame);
+        }
+        match self.vad_mode {
+            VadMode::Suppress => action,
+            VadMode::Passthrough => FrameAction::Send,
+        }
+    }
+
+    /// Update the per-channel background models from a non-speech frame
+    fn learn_background(&mut self, frame: &[i16]) {
+        if self.channels == 1 {
+            self.comfort_noise[0].analyze(frame);
+            return;
+        }
+        for (channel, noise) in self.comfort_noise.iter_mut().enumerate() {
+            self.channel_frame.clear();
+            self.channel_frame.extend(frame.iter().skip(channel).step_by(self.channels));
+            noise.analyze(&self.channel_frame);
+        }
+    }
+
+    /// Content of a keepalive / fill frame: zeros, or comfort noise kept
+    /// well below the speech threshold
+    pub fn fill_silence(&mut self, frame: &mut [i16]) {
+        if self.muted || self.suppressor.config().silence_fill == SilenceFill::Zeros {
+            frame.fill(0);
+            return;
+        }
+        let max_rms = self.suppressor.config().speech_threshold_rms * 0.5;
+        if self.channels == 1 {
+            self.comfort_noise[0].fill(frame, max_rms);
+            return;
+        }
+        for (channel, noise) in self.comfort_noise.iter_mut().enumerate() {
+            self.channel_frame.clear();
+            self.channel_frame.resize(frame.len() / self.channels, 0);
+            noise.fill(&mut self.channel_frame, max_rms);
+            for (sample, &value) in frame.iter_mut().skip(channel).step_by(self.channels).zip(&self.channel_frame) {
+                *sample = value;
+            }
+        }
+    }
+
+    pub fn is_speech(&self) -> bool {
+        !self.paused && self.suppressor.is_speech()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// The last processed frame was speech (hangover excluded)
+    pub fn is_voiced(&self) -> bool {
+        !self.paused && self.suppressor.is_voiced()
+    }
+
+    /// The device delivered nothing for `gap` (stall): timers run on
+    pub fn advance(&mut self, gap: Duration) {
+        self.suppressor.advance(gap);
+    }
+
+    /// Feed the last processed frame to the enabled analysis stages
+    /// (diarizer, question detector, keyword spotter), downmixed once
+    pub fn analyze(&mut self, frame: &[i16], info: &FrameInfo) -> FrameFindings {
+        let frame = if self.channels == 1 {
+            frame
+        } else {
+            downmix(frame, self.channels, &mut self.mix);
+            &self.mix
+        };
+        FrameFindings {
+            speaker: feed(&mut self.diarizer, frame, info),
+            question: feed(&mut self.question_detector, frame, info),
+            keyword: feed(&mut self.spotter, frame, info),
+        }
+    }
+
+    /// Whatever the analysis stages still hold (end of run)
+    pub fn flush_analysis(&mut self) -> FrameFindings {
+        FrameFindings {
+            speaker: self.diarizer.as_mut().and_then(FrameAnalyzer::flush),
+            question: self.question_detector.as_mut().and_then(FrameAnalyzer::flush),
+            keyword: self.spotter.as_mut().and_then(FrameAnalyzer::flush),
+        }
+    }
+
+    /// Frames to hold back before delivery (keyword suppression)
+    pub fn keyword_holdback(&self) -> usize {
+        self.spotter.as_ref().map_or(0, |spotter| spotter.holdback_frames())
+    }
+
+    /// Classification to report to JS (on change, and periodically)
+    pub fn take_class_report(&mut self) -> Option<ClassReport> {
+        self.classifier.as_mut().and_then(|classifier| classifier.take_report())
+    }
+
+    pub fn frames_per_callback(&self) -> usize {
+        self.frames_per_callback
+    }
+
+    /// Delay between a frame's input and output (ms): music separation
+    /// outputs each frame one frame late
+    pub fn latency_ms(&self) -> f64 {
+        if self.attenuators.is_empty() {
+            0.0
+        } else {
+            FRAME_MS as f64
+        }
+    }
+}
+
+/// Capture time jump (beyond one frame) treated as a device stall
+const MIN_STALL_MS: f64 = 2.0 * FRAME_MS as f64;
+
+/// Frame processing state of one DSP thread run
+///
+/// Split from the thread body so the same code can be driven step by step
+/// (see scripted::VirtualDriver).
+pub struct CaptureLoop {
+    pipeline: DspPipeline,
+    commands: CommandConsumer,
+    /// One resampler per output channel
+    resamplers: Vec<StreamingResampler>,
+    input_sample_rate: u32,
+    input_channels: usize,
+    /// Interleaved 16kHz samples waiting to fill a frame
+    frame_buffer: Vec<i16>,
+    raw_batch: Vec<f32>,
+    /// Per-channel scratch (downmix / deinterleave)
+    channel_scratch: Vec<f32>,
+    /// Interleaved samples per emitted frame
+    frame_len: usize,
+    pool: FramePool,
+    batcher: FrameBatcher,
+    events: Option<EventHandler>,
+    speech_observer: Option<SpeechObserver>,
+    /// Sequence number of the next frame
+    frame_seq: u64,
+    /// Capture time of frame_buffer[0] (ms since the Unix epoch)
+    buffer_time_ms: Option<f64>,
+    /// Capture time of the last processed frame
+    last_frame_time_ms: Option<f64>,
+    /// Utterance mode: frames are segmented instead of emitted
+    segmenter: Option<(UtteranceSegmenter, UtteranceHandler)>,
+    history: Option<Arc<Mutex<AudioHistory>>>,
+    /// Processed frames not delivered yet (keyword suppression)
+    held: VecDeque<HeldFrame>,
+}
+
+/// A processed frame waiting in the keyword holdback
+struct HeldFrame {
+    frame: PooledFrame,
+    action: FrameAction,
+    info: FrameInfo,
+}
+
+impl CaptureLoop {
+    /// `input_channels`: interleaved channels in the ring buffer; the
+    /// pipeline's channel mode decides whether they are kept
+    pub fn new(
+        input_sample_rate: f64,
+        input_channels: usize,
+        mut pipeline: DspPipeline,
+        commands: CommandConsumer,
+    ) -> Self {
+        let input_channels = input_channels.max(1);
+        let output_channels = match pipeline.channel_mode() {
+            ChannelMode::Downmix => 1,
+            ChannelMode::Keep => input_channels,
+        };
+        pipeline.set_channels(output_channels);
+        let frame_len = FRAME_SAMPLES * output_channels;
+        let batcher = FrameBatcher::new(pipeline.frames_per_callback(), frame_len);
+        Self {
+            pipeline,
+            commands,
+            resamplers: (0..output_channels)
+                .map(|_| StreamingResampler::new(input_sample_rate, SAMPLE_RATE as f64))
+                .collect(),
+            input_sample_rate: input_sample_rate as u32,
+            input_channels,
+            frame_buffer: Vec::with_capacity(frame_len * 4),
+            raw_batch: Vec::with_capacity(4096),
+            channel_scratch: Vec::with_capacity(4096),
+            frame_len,
+            pool: FramePool::new(FRAME_POOL_SIZE),
+            batcher,
+            events: None,
+            speech_observer: None,
+            frame_seq: 0,
+            buffer_time_ms: None,
+            last_frame_time_ms: None,
+            segmenter: None,
+            history: None,
+            held: VecDeque::new(),
+        }
+    }
+
+    /// Receive events raised by the loop (format changes, classification)
+    pub fn set_event_handler(&mut self, handler: EventHandler) {
+        self.events = Some(handler);
+    }
+
+    /// Report every frame's speech state to `observer`
+    pub fn set_speech_observer(&mut self, observer: SpeechObserver) {
+        self.speech_observer = Some(observer);
+    }
+
+    /// Switch to utterance mode: `handler` receives whole utterances and
+    /// the frame callback gets nothing
+    pub fn set_segmenter(&mut self, segmenter: UtteranceSegmenter, handler: UtteranceHandler) {
+        self.segmenter = Some((segmenter, handler));
+    }
+
+    /// Keep every processed frame in `history` too
+    pub fn set_history(&mut self, history: Arc<Mutex<AudioHistory>>) {
+        self.history = Some(history);
+    }
+
+    /// Interleaved channels of the emitted frames
+    pub fn output_channels(&self) -> usize {
+        self.resamplers.len()
+    }
+
+    pub fn input_format(&self) -> AudioFormat {
+        AudioFormat {
+            sample_rate: self.input_sample_rate,
+            channels: self.input_channels as u16,
+        }
+    }
+
+    /// One pass: drain up to 480 samples, emit every completed frame
+    pub fn step<F>(&mut self, consumer: &mut SampleConsumer, emit: &mut F)
+    where
+        F: FnMut(PooledFrame),
+    {
+        // 0. Format change reached in the ring: retune before reading past it
+        if let Some(format) = consumer.take_format_change() {
+            self.apply_format(format, emit);
+        }
+
+        // 1. Drain ring buffer (lock-free, stops at the next format change)
+        // A leftover partial sample frame was captured just before the next sample
+        let leftover = self.input_format().duration_of(self.raw_batch.len()).as_secs_f64() * 1000.0;
+        let batch_time = consumer.capture_time_ms().map(|time| time - leftover);
+        let limit = (480 / self.input_channels).max(1) * self.input_channels;
+        while let Some(sample) = consumer.try_pop() {
+            self.raw_batch.push(sample);
+            if self.raw_batch.len() >= limit {
+                break;
+            }
+        }
+
+        // 2. Downmix / deinterleave + resample (whole sample frames only)
+        let whole = self.raw_batch.len() / self.input_channels * self.input_channels;
+        if whole > 0 {
+            let buffered = self.frame_buffer.len() / self.output_channels();
+            self.resample(whole);
+            self.raw_batch.drain(..whole);
+            if let Some(time) = batch_time {
+                self.buffer_time_ms = Some(time - buffered as f64 * 1000.0 / SAMPLE_RATE as f64);
+            }
+        }
+
+        // 3. Normalize + Silence Suppression
+        let frame_len = self.frame_len;
+        while self.frame_buffer.len() >= frame_len {
+            // Frame boundary: apply pending reconfiguration
+            while let Some(command) = self.commands.try_pop() {
+                self.pipeline.apply(command);
+                self.batcher.set_frames_per_batch(self.pipeline.frames_per_callback());
+            }
+
+            let mut frame = self.pool.take(frame_len);
+            frame.samples_mut().extend(self.frame_buffer.drain(0..frame_len));
+            let capture_time = self.buffer_time_ms.map(|time| time - self.pipeline.latency_ms());
+            self.detect_stall(capture_time);
+            frame.set_timing(self.frame_seq, capture_time);
+            self.frame_seq += 1;
+            self.buffer_time_ms = self.buffer_time_ms.map(|time| time + FRAME_MS as f64);
+            let action = self.pipeline.process(frame.samples_mut());
+            if let Some(history) = self.history.as_ref().filter(|_| !self.pipeline.is_paused()) {
+                if let Ok(mut history) = history.lock() {
+                    history.push(frame.samples(), frame.seq(), frame.capture_time_ms());
+                }
+            }
+            let info = FrameInfo {
+                seq: frame.seq(),
+                capture_time_ms: frame.capture_time_ms(),
+                voiced: self.pipeline.is_voiced(),
+                speech: self.pipeline.is_speech(),
+            };
+            if let Some(observer) = &self.speech_observer {
+                observer(info, frame.samples());
+            }
+            let findings = self.pipeline.analyze(frame.samples(), &info);
+            if let Some(segment) = findings.speaker {
+                self.emit_speaker(segment);
+            }
+            if let Some(cue) = findings.question {
+                self.emit_question(cue);
+            }
+            if let Some(found) = findings.keyword {
+                self.silence_held(&found);
+                self.emit_keyword(found);
+            }
+            self.held.push_back(HeldFrame { frame, action, info });
+            self.release_held(self.pipeline.keyword_holdback(), emit);
+            if let Some(report) = self.pipeline.take_class_report() {
+                if let Some(events) = &self.events {
+                    events(CaptureEvent::ContentClass {
+                        class: report.class,
+                        speech: report.scores.speech,
+                        music: report.scores.music,
+                        noise: report.scores.noise,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Emit whatever is still held or batched (end of run)
+    pub fn finish<F>(&mut self, emit: &mut F)
+    where
+        F: FnMut(PooledFrame),
+    {
+        self.release_held(0, emit);
+        if let Some(batch) = self.batcher.flush() {
+            emit(batch);
+        }
+        if let Some((segmenter, handler)) = self.segmenter.as_mut() {
+            if let Some(utterance) = segmenter.flush() {
+                handler(utterance);
+            }
+        }
+        let findings = self.pipeline.flush_analysis();
+        if let Some(segment) = findings.speaker {
+            self.emit_speaker(segment);
+        }
+        if let Some(cue) = findings.question {
+            self.emit_question(cue);
+        }
+        if let Some(found) = findings.keyword {
+            self.emit_keyword(found);
+        }
+    }
+
+    /// Deliver held frames until at most `keep` are left
+    fn release_held<F>(&mut self, keep: usize, emit: &mut F)
+    where
+        F: FnMut(PooledFrame),
+    {
+        while self.held.len() > keep {
+            if let Some(held) = self.held.pop_front() {
+                self.deliver(held, emit);
+            }
+        }
+    }
+
+    /// Hand a processed frame to the segmenter or the batcher
+    fn deliver<F>(&mut self, held: HeldFrame, emit: &mut F)
+    where
+        F: FnMut(PooledFrame),
+    {
+        let HeldFrame { mut frame, action, info } = held;
+        let ready = if let Some((segmenter, handler)) = self.segmenter.as_mut() {
+            if let Some(utterance) = segmenter.push(frame.samples(), info) {
+                handler(utterance);
+            }
+            None
+        } else {
+            match action {
+                FrameAction::Send => self.batcher.push(frame, &self.pool),
+                FrameAction::SendSilence => {
+                    self.pipeline.fill_silence(frame.samples_mut());
+                    frame.set_silence(true);
+                    self.batcher.push(frame, &self.pool)
+                }
+                FrameAction::Suppress => {
+                    // Nothing to send (bandwidth saving); don't hold a partial batch back
+                    self.batcher.flush()
+                }
+            }
+        };
+        if let Some(batch) = ready {
+            emit(batch);
+        }
+    }
+
+    /// Turn the held frames of a spotted keyword into silence (suppression)
+    fn silence_held(&mut self, found: &KeywordMatch) {
+        for held in self.held.iter_mut() {
+            if (found.seq_start..=found.seq_end).contains(&held.frame.seq()) {
+                // Filled here so utterance mode loses the phrase too
+                self.pipeline.fill_silence(held.frame.samples_mut());
+                held.frame.set_silence(true);
+                held.info.voiced = false;
+            }
+        }
+    }
+
+    fn emit_speaker(&self, segment: SpeakerSegment) {
+        if let Some(events) = &self.events {
+            events(CaptureEvent::Speaker {
+                speaker: segment.speaker,
+                seq_start: segment.seq_start,
+                seq_end: segment.seq_end,
+                start_time_ms: segment.start_time_ms,
+                end_time_ms: segment.end_time_ms,
+                new_speaker: segment.new_speaker,
+            });
+        }
+    }
+
+    fn emit_question(&self, cue: QuestionCue) {
+        if let Some(events) = &self.events {
+            events(CaptureEvent::LikelyQuestion {
+                seq: cue.seq,
+                time_ms: cue.time_ms,
+                confidence: cue.confidence,
+                rise_semitones: cue.rise_semitones,
+            });
+        }
+    }
+
+    fn emit_keyword(&self, found: KeywordMatch) {
+        if let Some(events) = &self.events {
+            events(CaptureEvent::Keyword {
+                keyword: found.keyword,
+                seq_start: found.seq_start,
+                seq_end: found.seq_end,
+                start_time_ms: found.start_time_ms,
+                end_time_ms: found.end_time_ms,
+                distance: found.distance,
+            });
+        }
+    }
+
+    pub fn pipeline(&self) -> &DspPipeline {
+        &self.pipeline
+    }
+
+    /// Switch to the device's new format between two samples
+    ///
+    /// A rate change retunes the resamplers in place (no gap). With kept
+    /// channels a channel count change also changes the frame layout: the
+    /// pending batch is emitted and an incomplete old-layout frame is dropped.
+    fn apply_format<F>(&mut self, format: AudioFormat, emit: &mut F)
+    where
+        F: FnMut(PooledFrame),
+    {
+        let previous = self.input_format();
+        if format == previous {
+            return;
+        }
+        // A trailing partial sample frame can't be decoded in either layout
+        self.raw_batch.clear();
+
+        if format.sample_rate != previous.sample_rate {
+            for resampler in self.resamplers.iter_mut() {
+                resampler.set_input_rate(format.sample_rate as f64);
+            }
+        }
+        self.input_sample_rate = format.sample_rate;
+        self.input_channels = format.channels.max(1) as usize;
+
+        let output_channels = match self.pipeline.channel_mode() {
+            ChannelMode::Downmix => 1,
+            ChannelMode::Keep => self.input_channels,
+        };
+        if output_channels != self.resamplers.len() {
+            self.release_held(0, emit);
+            if let Some(batch) = self.batcher.flush() {
+                emit(batch);
+            }
+            self.frame_buffer.clear();
+            self.resamplers = (0..output_channels)
+                .map(|_| StreamingResampler::new(format.sample_rate as f64, SAMPLE_RATE as f64))
+                .collect();
+            self.pipeline.set_channels(output_channels);
+            if let Some((segmenter, handler)) = self.segmenter.as_mut() {
+                if let Some(utterance) = segmenter.set_channels(output_channels) {
+                    handler(utterance);
+                }
+            }
+            self.frame_len = FRAME_SAMPLES * output_channels;
+            self.batcher = FrameBatcher::new(self.pipeline.frames_per_callback(), self.frame_len);
+        }
+
+        println!(
+            "[DSP] Input format changed: {}Hz {}ch -> {}Hz {}ch ({}ch out)",
+            previous.sample_rate, previous.channels, format.sample_rate, format.channels, output_channels
+        );
+        if let Some(events) = &self.events {
+            events(CaptureEvent::FormatChanged {
+                sample_rate: format.sample_rate,
+                channels: format.channels,
+                output_channels: output_channels as u16,
+            });
+        }
+    }
+
+    /// Let the pipeline's timers cover audio the device never delivered
+    /// (shorter jumps are stamp jitter)
+    fn detect_stall(&mut self, capture_time: Option<f64>) {
+        if let (Some(last), Some(time)) = (self.last_frame_time_ms, capture_time) {
+            let gap = time - last - FRAME_MS as f64;
+            if gap >= MIN_STALL_MS {
+                self.pipeline.advance(Duration::from_secs_f64(gap / 1000.0));
+            }
+        }
+        if capture_time.is_some() {
+            self.last_frame_time_ms = capture_time;
+        }
+    }
+
+    /// Resample the first `len` raw samples into the frame buffer
+    fn resample(&mut self, len: usize) {
+        let channels = self.input_channels;
+        let raw = &self.raw_batch[..len];
+
+        if channels == 1 {
+            let resampled = self.resamplers[0].resample(raw);
+            self.frame_buffer.extend(resampled);
+        } else if self.resamplers.len() == 1 {
+            // Downmix: average (not sum) so correlated channels don't clip
+            self.channel_scratch.clear();
+            self.channel_scratch
+                .extend(raw.chunks_exact(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32));
+            let resampled = self.resamplers[0].resample(&self.channel_scratch);
+            self.frame_buffer.extend(resampled);
+        } else {
+            // Keep: resample each channel, then interleave again
+            let mut outputs = Vec::with_capacity(channels);
+            for (channel, resampler) in self.resamplers.iter_mut().enumerate() {
+                self.channel_scratch.clear();
+                self.channel_scratch.extend(raw.iter().skip(channel).step_by(channels));
+                outputs.push(resampler.resample(&self.channel_scratch));
+            }
+            // Same input length and ratio: every channel yields the same count
+            let count = outputs.iter().map(Vec::len).min().unwrap_or(0);
+            for i in 0..count {
+                self.frame_buffer.extend(outputs.iter().map(|output| output[i]));
+            }
+        }
+    }
+}
+
+/// Push a frame to an analysis stage if it is enabled
+fn feed<A: FrameAnalyzer>(stage: &mut Option<A>, frame: &[i16], info: &FrameInfo) -> Option<A::Output> {
+    stage.as_mut()?.push(frame, info)
+}
+
+/// Average an interleaved frame into mono
+fn downmix(frame: &[i16], channels: usize, mono: &mut Vec<i16>) {
+    mono.clear();
+    mono.extend(
+        frame
+            .chunks_exact(channels)
+            .map(|samples| (samples.iter().map(|&s| s as i32).sum::<i32>() / channels as i32) as i16),
+    );
+}
+
+/// DSP thread body: drain ring buffer -> resample -> frame pipeline -> emit
+///
+/// Sleeps until the producer signals a frame's worth of samples (no polling).
+/// Returns the ring buffer consumer when `stop_signal` is set,
+/// so the capture can be restarted.
+pub fn run_capture_loop<F>(
+    tag: &str,
+    mut consumer: SampleConsumer,
+    mut capture_loop: CaptureLoop,
+    stop_signal: Arc<AtomicBool>,
+    mut emit: F,
+) -> SampleConsumer
+where
+    F: FnMut(PooledFrame),
+{
+    println!(
+        "[{}] DSP thread started (suppression active, {}ch in, {}ch out)",
+        tag,
+        capture_loop.input_channels,
+        capture_loop.output_channels()
+    );
+
+    loop {
+        if stop_signal.load(Ordering::Relaxed) {
+            break;
+        }
+
+        capture_loop.step(&mut consumer, &mut emit);
+
+        // 4. Ring drained: sleep until the producer signals more data
+        if consumer.is_empty() {
+            consumer.wait_for_data(Duration::from_millis(DSP_WAKE_TIMEOUT_MS));
+        }
+    }
+
+    capture_loop.finish(&mut emit);
+
+    println!("[{}] DSP thread stopped.", tag);
+    consumer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1kHz square wave, survives the high-pass filter
+    fn tone(amplitude: i16) -> Vec<i16> {
+        (0..FRAME_SAMPLES)
+            .map(|i| if (i / 8) % 2 == 0 { amplitude } else { -amplitude })
+            .collect()
+    }
+
+    #[test]
+    fn test_mute_zero_fills() {
+        let mut settings = DspSettings::for_microphone();
+        settings.vad_mode = VadMode::Passthrough;
+        let mut pipeline = DspPipeline::new(&settings);
+        pipeline.apply(DspCommand::Muted(true));
+
+        let mut frame = tone(8000);
+        match pipeline.process(&mut frame) {
+            FrameAction::Send => assert!(frame.iter().all(|&s| s == 0)),
+            other => panic!("Passthrough should send every frame, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reconfigure_threshold_keeps_running() {
+        let mut pipeline = DspPipeline::new(&DspSettings::for_microphone());
+        pipeline.apply(DspCommand::Agc(None));
+
+        let mut frame = tone(2000);
+        assert!(matches!(pipeline.process(&mut frame), FrameAction::Send));
+
+        // Raise the threshold above the frame level: no longer speech
+        let mut config = SilenceSuppressionConfig::for_microphone();
+        config.speech_threshold_rms = 10_000.0;
+        config.speech_hangover = Duration::from_millis(0);
+        pipeline.apply(DspCommand::Suppression(config));
+
+        let mut frame = tone(2000);
+        pipeline.process(&mut frame);
+        assert!(!pipeline.is_speech());
+    }
+
+    #[test]
+    fn test_agc_does_not_lift_background_noise_into_speech() {
+        use crate::classifier::test_signals::{frames, Lcg};
+        // White noise at -55dBFS (RMS ~58): below the speech threshold
+        let mut rng = Lcg(7);
+        let noise: Vec<f32> = (0..SAMPLE_RATE * 5).map(|_| 0.0031 * rng.next()).collect();
+        for mut settings in [DspSettings::for_microphone(), DspSettings::for_system_audio()] {
+            settings.classifier = None;
+            let mut pipeline = DspPipeline::new(&settings);
+            // The suppressor starts active: skip its initial hangover
+            for (i, mut frame) in frames(&noise).into_iter().enumerate() {
+                let action = pipeline.process(&mut frame);
+                if i >= 25 {
+                    assert!(!matches!(action, FrameAction::Send), "noise frame {} sent", i);
+                }
+            }
+            assert!(!pipeline.is_speech());
+        }
+    }
+
+    #[test]
+    fn test_agc_lifts_quiet_speech_over_a_quieter_floor() {
+        use crate::classifier::test_signals::{frames, speech, Lcg};
+        // A quiet remote talker (-55dBFS) over a -70dBFS line floor
+        let talker = speech(4.0, 3);
+        let rms = (talker.iter().map(|s| s * s).sum::<f32>() / talker.len() as f32).sqrt();
+        let mut rng = Lcg(11);
+        let mut signal: Vec<f32> = (0..SAMPLE_RATE).map(|_| 0.00055 * rng.next()).collect();
+        signal.extend(talker.iter().map(|s| s * 0.00178 / rms + 0.00055 * rng.next()));
+
+        let mut pipeline = DspPipeline::new(&DspSettings::for_system_audio());
+        let mut sent = 0;
+        for (i, mut frame) in frames(&signal).into_iter().enumerate() {
+            let action = pipeline.process(&mut frame);
+            if i < 50 {
+                // Floor only (the suppressor starts active: skip its hangover)
+                assert!(i < 25 || !matches!(action, FrameAction::Send), "floor frame {} sent", i);
+            } else if matches!(action, FrameAction::Send) {
+                sent += 1;
+            }
+        }
+        assert!(sent > talker.len() / FRAME_SAMPLES / 2, "only {} speech frames sent", sent);
+    }
+
+    #[test]
+    fn test_system_audio_gates_music_but_not_speech() {
+        use crate::classifier::test_signals::{frames, music, speech};
+        let mut pipeline = DspPipeline::new(&DspSettings::for_system_audio());
+        let run = |pipeline: &mut DspPipeline, signal: &[f32]| {
+            let mut sent = 0;
+            for (i, mut frame) in frames(signal).into_iter().enumerate() {
+                let action = pipeline.process(&mut frame);
+                // The classifier (and the suppressor's hangover) need ~1s to settle
+                if i >= 60 && matches!(action, FrameAction::Send) {
+                    sent += 1;
+                }
+            }
+            sent
+        };
+
+        // Music plays: gated once classified
+        let sent = run(&mut pipeline, &music(4.0, 1));
+        assert_eq!(sent, 0, "music frames sent");
+        assert!(!pipeline.is_speech());
+
+        let sent = run(&mut pipeline, &speech(4.0, 1));
+        assert!(sent > 100, "only {} of 140 speech frames sent", sent);
+
+        // Classification off: loud music is speech to the RMS suppressor again
+        pipeline.apply(DspCommand::Classifier(None));
+        let sent = run(&mut pipeline, &music(4.0, 2));
+        assert_eq!(sent, 140);
+    }
+
+    #[test]
+    fn test_pause_drops_frames_and_resume_is_instant() {
+        let mut pipeline = DspPipeline::new(&DspSettings::for_microphone());
+        pipeline.apply(DspCommand::Paused(true));
+
+        let mut frame = tone(8000);
+        assert!(matches!(pipeline.process(&mut frame), FrameAction::Suppress));
+        assert!(!pipeline.is_speech());
+
+        pipeline.apply(DspCommand::Paused(false));
+        let mut frame = tone(8000);
+        assert!(matches!(pipeline.process(&mut frame), FrameAction::Send));
+    }
+
+    #[test]
+    fn test_comfort_noise_fill_stays_below_threshold() {
+        let mut settings = DspSettings::for_microphone();
+        settings.agc = None;
+        settings.suppression.speech_hangover = Duration::from_millis(0);
+        settings.suppression.silence_fill = SilenceFill::ComfortNoise;
+        settings.suppression.contiguous = true;
+        let threshold = settings.suppression.speech_threshold_rms;
+        let mut pipeline = DspPipeline::new(&settings);
+
+        // Background just under the threshold: every slot is filled
+        for _ in 0..25 {
+            let mut frame = tone((threshold * 0.8) as i16);
+            let action = pipeline.process(&mut frame);
+            assert!(matches!(action, FrameAction::SendSilence), "got {:?}", action);
+        }
+        let mut fill = vec![0i16; FRAME_SAMPLES];
+        pipeline.fill_silence(&mut fill);
+        let rms = (fill.iter().map(|&s| (s as f32).powi(2)).sum::<f32>() / fill.len() as f32).sqrt();
+        assert!(rms > 0.0 && rms <= threshold * 0.6, "fill rms {} (threshold {})", rms, threshold);
+    }
+}
@@ -0,0 +1,84 @@
+// Capture Events - out-of-band notifications for the JS side
+//
+// Frames go through the frame callback; everything else a capture wants to
+// tell JS about (backpressure, ...) is a CaptureEvent delivered through the
+// optional `onEvent` callback. napi-free: lib.rs converts events into
+// `{ type: "...", ... }` objects.
+
+use std::sync::Arc;
+
+use crate::classifier::ContentClass;
+
+/// Receives events raised on the DSP thread (must not block)
+pub type EventHandler = Arc<dyn Fn(CaptureEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureEvent {
+    /// JS stopped keeping up with frame delivery (or caught up again)
+    Lagging {
+        lagging: bool,
+        /// Frames waiting for JS at the time of the transition
+        queued: usize,
+        /// Total frames dropped so far
+        dropped: u64,
+    },
+    /// The device changed its sample rate / channel count mid-stream
+    /// Frames stay 16kHz; `output_channels` changes only if channels are kept.
+    FormatChanged {
+        sample_rate: u32,
+        channels: u16,
+        output_channels: u16,
+    },
+    /// Content classification changed (and every 500ms while it runs)
+    ContentClass {
+        class: ContentClass,
+        speech: f32,
+        music: f32,
+        noise: f32,
+    },
+    /// A range of frames was attributed to a speaker (see diarization.rs)
+    Speaker {
+        /// Index in order of appearance ("spk_<n>" on the JS side)
+        speaker: usize,
+        /// First and last frame (inclusive)
+        seq_start: u64,
+        seq_end: u64,
+        start_time_ms: Option<f64>,
+        end_time_ms: Option<f64>,
+        new_speaker: bool,
+    },
+    /// An utterance ended with rising intonation (see pitch.rs)
+    LikelyQuestion {
+        /// Last pitched frame of the utterance
+        seq: u64,
+        /// Capture time of the utterance end
+        time_ms: Option<f64>,
+        /// 0..1
+        confidence: f32,
+        rise_semitones: f32,
+    },
+    /// An enrolled phrase was spoken (see keywords.rs)
+    Keyword {
+        keyword: String,
+        /// First and last frame (inclusive)
+        seq_start: u64,
+        seq_end: u64,
+        start_time_ms: Option<f64>,
+        end_time_ms: Option<f64>,
+        distance: f32,
+    },
+}
+
+impl CaptureEvent {
+    /// Value of the `type` field on the JS object
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaptureEvent::Lagging { .. } => "lagging",
+            CaptureEvent::FormatChanged { .. } => "formatChanged",
+            CaptureEvent::ContentClass { .. } => "contentClass",
+            CaptureEvent::Speaker { .. } => "speaker",
+            CaptureEvent::LikelyQuestion { .. } => "likelyQuestion",
+            CaptureEvent::Keyword { .. } => "keyword",
+        }
+    }
+}
@@ -0,0 +1,331 @@
+// Spectral Features - per-frame analysis on the 16kHz output frames
+//
+// One FFT per 20ms frame over the last 32ms of audio (512 samples, Hann
+// window, the previous frame supplies the overlap). Everything a content
+// classifier needs is derived from that single power spectrum:
+// - frame energy (dB, i16 scale)
+// - spectral flatness (tonal ~0, white noise ~0.5)
+// - log-mel band energies and their frame-to-frame flux
+// - cepstral coefficients of the log-mel bands (Cepstrum, for voice
+//   comparisons: diarization, keyword templates)
+//
+// REAL-TIME NOTES:
+// - FFT plan, window and buffers are allocated once in new()
+// - analyze() does no allocation
+
+use std::sync::Arc;
+
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use crate::audio_config::SAMPLE_RATE;
+
+/// Analysis window / FFT length (32ms at 16kHz)
+pub const FFT_SIZE: usize = 512;
+
+/// Log-mel bands between MEL_MIN_HZ and MEL_MAX_HZ
+pub const MEL_BANDS: usize = 24;
+/// Cepstral coefficients per frame (c1..c12; c0 / level dropped)
+pub const MFCC_COEFFS: usize = 12;
+
+const MEL_MIN_HZ: f32 = 100.0;
+const MEL_MAX_HZ: f32 = 7000.0;
+
+/// Power added before taking logs, roughly the quantization noise of an
+/// i16 frame, so digital silence doesn't produce -inf or flux from noise
+const POWER_FLOOR: f32 = 1.0e3;
+
+/// Features of one frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameFeatures {
+    /// Mean-square level in dB (i16 scale: RMS 100 = 40dB)
+    pub energy_db: f32,
+    /// Geometric / arithmetic mean of the power spectrum (100Hz-7kHz)
+    pub flatness: f32,
+    /// Mean absolute change of the log-mel bands since the last frame (dB)
+    pub flux: f32,
+}
+
+/// Triangular mel filterbank over a power spectrum
+pub struct MelFilterbank {
+    /// (first FFT bin, weights) per band
+    filters: Vec<(usize, Vec<f32>)>,
+}
+
+impl MelFilterbank {
+    pub fn new(bands: usize, fft_size: usize, sample_rate: u32, min_hz: f32, max_hz: f32) -> Self {
+        let bin_hz = sample_rate as f32 / fft_size as f32;
+        let min_mel = hz_to_mel(min_hz);
+        let max_mel = hz_to_mel(max_hz);
+        // bands + 2 edges: band i spans edges[i]..edges[i + 2], peaking at edges[i + 1]
+        let edges: Vec<f32> = (0..bands + 2)
+            .map(|i| mel_to_hz(min_mel + (max_mel - min_mel) * i as f32 / (bands + 1) as f32) / bin_hz)
+            .collect();
+
+        let filters = (0..bands)
+            .map(|band| {
+                let (lo, mid, hi) = (edges[band], edges[band + 1], edges[band + 2]);
+                let first = lo.ceil() as usize;
+                let last = (hi.floor() as usize).min(fft_size / 2);
+                let weights: Vec<f32> = (first..=last)
+                    .map(|bin| {
+                        let bin = bin as f32;
+                        if bin <= mid {
+                            (bin - lo) / (mid - lo)
+                        } else {
+                            (hi - bin) / (hi - mid)
+                        }
+                    })
+                    .collect();
+                // Narrow low bands can fall between bins: use the nearest one
+                if weights.iter().all(|&w| w <= 0.0) {
+                    (mid.round() as usize, vec![1.0])
+                } else {
+                    (first, weights)
+                }
+            })
+            .collect();
+        Self { filters }
+    }
+
+    pub fn bands(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Band energies of `power` (one value per band written to `out`)
+    pub fn apply(&self, power: &[f32], out: &mut [f32]) {
+        for ((first, weights), value) in self.filters.iter().zip(out.iter_mut()) {
+            *value = weights
+                .iter()
+                .zip(&power[*first..])
+                .map(|(w, p)| w * p)
+                .sum();
+        }
+    }
+}
+
+/// DCT-II of the log-mel bands: MFCCs c1..=MFCC_COEFFS
+pub struct Cepstrum {
+    rows: Vec<[f32; MEL_BANDS]>,
+}
+
+impl Cepstrum {
+    pub fn new() -> Self {
+        let rows = (1..=MFCC_COEFFS)
+            .map(|k| {
+                let mut row = [0.0; MEL_BANDS];
+                for (n, value) in row.iter_mut().enumerate() {
+                    *value = (std::f32::consts::PI * k as f32 * (n as f32 + 0.5) / MEL_BANDS as f32).cos();
+                }
+                row
+            })
+            .collect();
+        Self { rows }
+    }
+
+    /// Coefficients of `log_mel` (MEL_BANDS values, dB)
+    pub fn apply(&self, log_mel: &[f32], out: &mut [f32; MFCC_COEFFS]) {
+        for (coeff, row) in out.iter_mut().zip(&self.rows) {
+            *coeff = row.iter().zip(log_mel).map(|(w, x)| w * x).sum::<f32>() / MEL_BANDS as f32;
+        }
+    }
+}
+
+impl Default for Cepstrum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Sliding FFT analysis of consecutive frames
+pub struct SpectralAnalyzer {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    /// Last FFT_SIZE samples (oldest first)
+    history: Vec<f32>,
+    input: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    power: Vec<f32>,
+    mel: MelFilterbank,
+    log_mel: Vec<f32>,
+    prev_log_mel: Vec<f32>,
+    has_prev: bool,
+    /// Power spectrum bins used for flatness
+    flatness_bins: (usize, usize),
+}
+
+impl SpectralAnalyzer {
+    pub fn new() -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(FFT_SIZE);
+        let input = fft.make_input_vec();
+        let spectrum = fft.make_output_vec();
+        let scratch = fft.make_scratch_vec();
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        let bin_hz = SAMPLE_RATE as f32 / FFT_SIZE as f32;
+        Self {
+            fft,
+            window,
+            history: vec![0.0; FFT_SIZE],
+            input,
+            power: vec![0.0; spectrum.len()],
+            spectrum,
+            scratch,
+            mel: MelFilterbank::new(MEL_BANDS, FFT_SIZE, SAMPLE_RATE, MEL_MIN_HZ, MEL_MAX_HZ),
+            log_mel: vec![0.0; MEL_BANDS],
+            prev_log_mel: vec![0.0; MEL_BANDS],
+            has_prev: false,
+            flatness_bins: (
+                (MEL_MIN_HZ / bin_hz).ceil() as usize,
+                (MEL_MAX_HZ / bin_hz).floor() as usize,
+            ),
+        }
+    }
+
+    /// Analyze the next frame (mono, any length up to FFT_SIZE)
+    pub fn analyze(&mut self, frame: &[i16]) -> FrameFeatures {
+        let n = frame.len().min(FFT_SIZE);
+        self.history.rotate_left(n);
+        for (slot, &sample) in self.history[FFT_SIZE - n..].iter_mut().zip(&frame[frame.len() - n..]) {
+            *slot = sample as f32;
+        }
+
+        let energy = frame.iter().map(|&s| (s as f32) * (s as f32)).sum::<f32>() / frame.len().max(1) as f32;
+
+        for ((x, h), w) in self.input.iter_mut().zip(&self.history).zip(&self.window) {
+            *x = h * w;
+        }
+        // Lengths come from the plan, so this can't fail
+        let _ = self.fft.process_with_scratch(&mut self.input, &mut self.spectrum, &mut self.scratch);
+        for (p, c) in self.power.iter_mut().zip(&self.spectrum) {
+            *p = c.norm_sqr();
+        }
+
+        // Flatness over the speech / music band
+        let (lo, hi) = self.flatness_bins;
+        let band = &self.power[lo..=hi];
+        let log_mean = band.iter().map(|&p| (p + POWER_FLOOR).ln()).sum::<f32>() / band.len() as f32;
+        let mean = band.iter().map(|&p| p + POWER_FLOOR).sum::<f32>() / band.len() as f32;
+        let flatness = log_mean.exp() / mean;
+
+        // Log-mel flux
+        self.mel.apply(&self.power, &mut self.log_mel);
+        for value in self.log_mel.iter_mut() {
+            *value = 10.0 * (*value + POWER_FLOOR).log10();
+        }
+        let flux = if self.has_prev {
+            self.log_mel
+                .iter()
+                .zip(&self.prev_log_mel)
+                .map(|(a, b)| (a - b).abs())
+                .sum::<f32>()
+                / MEL_BANDS as f32
+        } else {
+            0.0
+        };
+        self.prev_log_mel.copy_from_slice(&self.log_mel);
+        self.has_prev = true;
+
+        FrameFeatures {
+            energy_db: 10.0 * (energy + 1.0).log10(),
+            flatness,
+            flux,
+        }
+    }
+
+    /// Power spectrum of the last analyzed window (FFT_SIZE / 2 + 1 bins)
+    pub fn power_spectrum(&self) -> &[f32] {
+        &self.power
+    }
+
+    /// Log-mel energies (dB) of the last analyzed window
+    pub fn log_mel(&self) -> &[f32] {
+        &self.log_mel
+    }
+
+    pub fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.has_prev = false;
+    }
+}
+
+impl Default for SpectralAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_config::FRAME_SAMPLES;
+
+    fn sine(freq: f32, amplitude: f32, offset: usize) -> Vec<i16> {
+        (0..FRAME_SAMPLES)
+            .map(|i| {
+                let t = (offset + i) as f32 / SAMPLE_RATE as f32;
+                (amplitude * (2.0 * std::f32::consts::PI * freq * t).sin()) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tone_is_tonal_and_noise_is_flat() {
+        let mut analyzer = SpectralAnalyzer::new();
+        let mut tone = FrameFeatures::default();
+        for n in 0..4 {
+            tone = analyzer.analyze(&sine(1000.0, 8000.0, n * FRAME_SAMPLES));
+        }
+        assert!(tone.flatness < 0.05, "tone flatness {}", tone.flatness);
+        assert!(tone.flux < 1.0, "steady tone flux {}", tone.flux);
+        assert!((tone.energy_db - 75.0).abs() < 1.0, "tone energy {}", tone.energy_db);
+
+        let mut analyzer = SpectralAnalyzer::new();
+        let mut state = 1u32;
+        let mut noise = FrameFeatures::default();
+        for _ in 0..4 {
+            let frame: Vec<i16> = (0..FRAME_SAMPLES)
+                .map(|_| {
+                    state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    ((state >> 16) as i16) / 8
+                })
+                .collect();
+            noise = analyzer.analyze(&frame);
+        }
+        assert!(noise.flatness > 0.4, "noise flatness {}", noise.flatness);
+    }
+
+    #[test]
+    fn test_mel_bands_cover_their_frequency() {
+        let mut analyzer = SpectralAnalyzer::new();
+        for n in 0..2 {
+            analyzer.analyze(&sine(300.0, 8000.0, n * FRAME_SAMPLES));
+        }
+        let low = analyzer.log_mel().to_vec();
+        for n in 0..2 {
+            analyzer.analyze(&sine(3000.0, 8000.0, n * FRAME_SAMPLES));
+        }
+        let high = analyzer.log_mel();
+        let peak = |bands: &[f32]| {
+            bands
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(i, _)| i)
+                .unwrap()
+        };
+        assert!(peak(&low) < 5, "300Hz peaks in band {}", peak(&low));
+        assert!(peak(high) > 12, "3kHz peaks in band {}", peak(high));
+    }
+}
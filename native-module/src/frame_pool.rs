@@ -0,0 +1,230 @@
+// Frame Pool - recycled sample buffers for frame delivery to JS
+//
+// OLD PATH (per frame, 50x/s per stream):
+//   Vec<i16> -> to_vec() clone -> byte-by-byte Vec<u8> -> new Buffer
+//
+// NEW PATH:
+//   pooled Vec<i16> filled by the DSP thread -> handed to JS as a Buffer that
+//   borrows the same memory (napi external buffer) -> returned to the pool by
+//   the Buffer's finalizer
+//
+// NOTE: Electron builds with the V8 memory cage do not allow external buffers;
+// napi then copies once into a JS-owned Buffer and the pooled Vec is recycled
+// immediately. Either way there are no per-frame Rust allocations.
+
+use std::sync::{Arc, Mutex};
+
+/// Shared free list of sample buffers
+#[derive(Clone)]
+pub struct FramePool {
+    free: Arc<Mutex<Vec<Vec<i16>>>>,
+    max_pooled: usize,
+}
+
+impl FramePool {
+    /// Create a pool that keeps at most `max_pooled` idle buffers
+    pub fn new(max_pooled: usize) -> Self {
+        Self {
+            free: Arc::new(Mutex::new(Vec::with_capacity(max_pooled))),
+            max_pooled,
+        }
+    }
+
+    /// Get an empty buffer with room for at least `capacity` samples
+    pub fn take(&self, capacity: usize) -> PooledFrame {
+        let recycled = self.free.lock().ok().and_then(|mut free| free.pop());
+        let mut samples = recycled.unwrap_or_default();
+        samples.clear();
+        samples.reserve(capacity);
+        PooledFrame {
+            samples,
+            silence: false,
+            seq: 0,
+            capture_time_ms: None,
+            pool: self.clone(),
+        }
+    }
+
+    /// Number of idle buffers (diagnostics / tests)
+    pub fn idle(&self) -> usize {
+        self.free.lock().map(|free| free.len()).unwrap_or(0)
+    }
+
+    fn recycle(&self, samples: Vec<i16>) {
+        if let Ok(mut free) = self.free.lock() {
+            if free.len() < self.max_pooled {
+                free.push(samples);
+            }
+        }
+    }
+}
+
+/// A frame (or batch of frames) on its way to JS
+/// Returns its buffer to the pool when dropped.
+pub struct PooledFrame {
+    samples: Vec<i16>,
+    /// Keepalive / digital silence (may be coalesced under backpressure)
+    silence: bool,
+    /// Index of the (first) 20ms frame since the capture started; suppressed
+    /// frames use up numbers too, so gaps show where audio was gated
+    seq: u64,
+    /// Capture time of the first sample (ms since the Unix epoch)
+    capture_time_ms: Option<f64>,
+    pool: FramePool,
+}
+
+impl PooledFrame {
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    pub fn samples_mut(&mut self) -> &mut Vec<i16> {
+        &mut self.samples
+    }
+
+    pub fn is_silence(&self) -> bool {
+        self.silence
+    }
+
+    pub fn set_silence(&mut self, silence: bool) {
+        self.silence = silence;
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn capture_time_ms(&self) -> Option<f64> {
+        self.capture_time_ms
+    }
+
+    pub fn set_timing(&mut self, seq: u64, capture_time_ms: Option<f64>) {
+        self.seq = seq;
+        self.capture_time_ms = capture_time_ms;
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Raw little-endian PCM bytes (for borrowing into a JS Buffer)
+    /// All supported targets (x86_64, aarch64) are little-endian.
+    pub fn as_mut_byte_ptr(&mut self) -> *mut u8 {
+        self.samples.as_mut_ptr() as *mut u8
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.samples.len() * std::mem::size_of::<i16>()
+    }
+}
+
+impl Drop for PooledFrame {
+    fn drop(&mut self) {
+        self.pool.recycle(std::mem::take(&mut self.samples));
+    }
+}
+
+/// Groups N frames into one JS callback
+///
+/// With N = 1 frames are passed through untouched (zero-copy).
+/// A partial batch is flushed as soon as the stream goes quiet, so batching
+/// never holds speech back during silence.
+pub struct FrameBatcher {
+    frames_per_batch: usize,
+    frame_samples: usize,
+    pending: Option<PooledFrame>,
+    pending_frames: usize,
+}
+
+impl FrameBatcher {
+    pub fn new(frames_per_batch: usize, frame_samples: usize) -> Self {
+        Self {
+            frames_per_batch: frames_per_batch.max(1),
+            frame_samples,
+            pending: None,
+            pending_frames: 0,
+        }
+    }
+
+    /// Change batch size (takes effect after the current batch)
+    pub fn set_frames_per_batch(&mut self, frames_per_batch: usize) {
+        self.frames_per_batch = frames_per_batch.max(1);
+    }
+
+    /// Add a frame; returns a batch when it is complete
+    pub fn push(&mut self, frame: PooledFrame, pool: &FramePool) -> Option<PooledFrame> {
+        if self.frames_per_batch == 1 && self.pending.is_none() {
+            return Some(frame);
+        }
+
+        let capacity = self.frames_per_batch * self.frame_samples;
+        let batch = self.pending.get_or_insert_with(|| {
+            // A batch is timed by its first frame
+            let mut batch = pool.take(capacity);
+            batch.set_silence(true);
+            batch.set_timing(frame.seq, frame.capture_time_ms);
+            batch
+        });
+        batch.samples_mut().extend_from_slice(frame.samples());
+        // A batch only counts as silence if every frame in it is
+        batch.set_silence(batch.is_silence() && frame.is_silence());
+        self.pending_frames += 1;
+
+        if self.pending_frames >= self.frames_per_batch {
+            self.flush()
+        } else {
+            None
+        }
+    }
+
+    /// Emit whatever is pending (partial batch)
+    pub fn flush(&mut self) -> Option<PooledFrame> {
+        self.pending_frames = 0;
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_recycled() {
+        let pool = FramePool::new(4);
+        let mut frame = pool.take(320);
+        frame.samples_mut().resize(320, 7);
+        let ptr = frame.samples().as_ptr();
+        drop(frame);
+        assert_eq!(pool.idle(), 1);
+
+        let frame = pool.take(320);
+        assert_eq!(frame.samples().as_ptr(), ptr);
+        assert!(frame.is_empty());
+    }
+
+    #[test]
+    fn test_batcher_groups_frames() {
+        let pool = FramePool::new(8);
+        let mut batcher = FrameBatcher::new(3, 320);
+        let mut batches = Vec::new();
+        for i in 0..7 {
+            let mut frame = pool.take(320);
+            frame.samples_mut().resize(320, i);
+            frame.set_timing(i as u64, Some(i as f64 * 20.0));
+            if let Some(batch) = batcher.push(frame, &pool) {
+                batches.push(batch);
+            }
+        }
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1].seq(), 3);
+        assert_eq!(batches[0].len(), 960);
+        assert_eq!(batches[1].samples()[0], 3);
+
+        let rest = batcher.flush().expect("partial batch");
+        assert_eq!(rest.len(), 320);
+    }
+}
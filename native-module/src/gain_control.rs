@@ -0,0 +1,370 @@
+// Input Normalization - High-Pass Filter + Automatic Gain Control
+//
+// PIPELINE POSITION:
+// resampler (16kHz i16) -> HighPassFilter -> AutomaticGainControl -> SilenceSuppressor
+//
+// WHY:
+// - Mic and system audio arrive at wildly different levels (quiet Zoom call vs loud YouTube)
+// - Normalizing before suppression lets ONE speech threshold work for both sources
+// - STT gets consistent levels regardless of source
+//
+// REAL-TIME NOTES:
+// - Operates in place on 20ms frames, no allocations
+// - Gain is ramped linearly across each frame (no zipper noise)
+// - The noise floor is tracked as a running minimum of frame levels (falls
+//   at once, rises slowly). Only frames `noise_gate_margin_db` above it may
+//   RAISE the gain, and the gain never lifts the floor above `max_noise_dbfs`,
+//   so room tone / line noise stays below the speech threshold while quiet
+//   speech over a quieter floor is still boosted
+
+use std::time::Duration;
+
+use crate::audio_config::{FRAME_MS, SAMPLE_RATE};
+
+/// Full-scale reference for dBFS on the i16 scale
+const FULL_SCALE: f32 = 32768.0;
+
+/// Floor used when converting silent frames to dB (avoids -inf)
+const MIN_DBFS: f32 = -120.0;
+
+/// How fast the tracked noise floor follows a louder background (dB/s)
+const NOISE_FLOOR_RISE_DB_PER_SEC: f32 = 3.0;
+
+/// First-order high-pass filter (DC blocker)
+///
+/// y[n] = x[n] - x[n-1] + R * y[n-1]
+/// Removes DC offset and low-frequency rumble that would otherwise
+/// inflate RMS and fool the suppressor / AGC.
+pub struct HighPassFilter {
+    r: f32,
+    prev_input: f32,
+    prev_output: f32,
+}
+
+impl HighPassFilter {
+    /// Create a high-pass filter
+    ///
+    /// # Arguments
+    /// * `cutoff_hz` - -3dB corner frequency (e.g., 60Hz)
+    /// * `sample_rate` - Sample rate of the frames being filtered
+    pub fn new(cutoff_hz: f32, sample_rate: u32) -> Self {
+        let r = (-2.0 * std::f32::consts::PI * cutoff_hz / sample_rate as f32).exp();
+        Self {
+            r,
+            prev_input: 0.0,
+            prev_output: 0.0,
+        }
+    }
+
+    /// Filter a frame in place
+    pub fn process(&mut self, frame: &mut [i16]) {
+        for sample in frame.iter_mut() {
+            *sample = self.process_sample(*sample);
+        }
+    }
+
+    /// Filter one sample (for interleaved audio, one filter per channel)
+    pub fn process_sample(&mut self, sample: i16) -> i16 {
+        let x = sample as f32;
+        let y = x - self.prev_input + self.r * self.prev_output;
+        self.prev_input = x;
+        self.prev_output = y;
+        y.round().clamp(-32768.0, 32767.0) as i16
+    }
+
+    /// Reset filter state
+    pub fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.prev_output = 0.0;
+    }
+}
+
+impl Default for HighPassFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_HIGH_PASS_HZ, SAMPLE_RATE)
+    }
+}
+
+/// Default high-pass corner (removes DC + mains hum fundamentals, keeps voice)
+pub const DEFAULT_HIGH_PASS_HZ: f32 = 60.0;
+
+/// Configuration for automatic gain control
+#[derive(Debug, Clone)]
+pub struct AgcConfig {
+    /// Level the AGC steers speech towards (dBFS)
+    pub target_dbfs: f32,
+
+    /// Maximum boost applied to quiet sources (dB)
+    pub max_gain_db: f32,
+
+    /// Maximum cut applied to loud sources (dB, positive number)
+    pub max_attenuation_db: f32,
+
+    /// How quickly gain is reduced when the level rises
+    pub attack: Duration,
+
+    /// How quickly gain recovers when the level falls
+    pub release: Duration,
+
+    /// Frames less than this above the tracked noise floor never raise
+    /// the gain (dB)
+    pub noise_gate_margin_db: f32,
+
+    /// The noise floor is never boosted above this (dBFS)
+    /// Keep it below the suppressor's speech threshold (RMS 100 ~ -50dBFS):
+    /// background noise must not be boosted over it
+    pub max_noise_dbfs: f32,
+}
+
+impl Default for AgcConfig {
+    fn default() -> Self {
+        Self {
+            target_dbfs: -20.0,
+            max_gain_db: 24.0,
+            max_attenuation_db: 12.0,
+            attack: Duration::from_millis(20),
+            release: Duration::from_millis(800),
+            noise_gate_margin_db: 10.0,
+            max_noise_dbfs: -56.0,
+        }
+    }
+}
+
+/// Automatic gain control operating on 20ms frames
+pub struct AutomaticGainControl {
+    config: AgcConfig,
+    /// Current gain in dB (smoothed)
+    gain_db: f32,
+    /// Running minimum of frame levels (dBFS); the first frame sets it
+    noise_floor_dbfs: Option<f32>,
+    /// Per-frame smoothing coefficients derived from attack/release
+    attack_coeff: f32,
+    release_coeff: f32,
+}
+
+impl AutomaticGainControl {
+    pub fn new(config: AgcConfig) -> Self {
+        let frame = Duration::from_millis(FRAME_MS as u64);
+        let attack_coeff = smoothing_coeff(frame, config.attack);
+        let release_coeff = smoothing_coeff(frame, config.release);
+        println!(
+            "[AGC] Created with target={}dBFS, max_gain={}dB, attack={}ms, release={}ms",
+            config.target_dbfs,
+            config.max_gain_db,
+            config.attack.as_millis(),
+            config.release.as_millis()
+        );
+        Self {
+            config,
+            gain_db: 0.0,
+            noise_floor_dbfs: None,
+            attack_coeff,
+            release_coeff,
+        }
+    }
+
+    /// Replace options (gain and noise floor are kept, so levels don't jump
+    /// mid-sentence)
+    pub fn set_config(&mut self, config: AgcConfig) {
+        let frame = Duration::from_millis(FRAME_MS as u64);
+        self.attack_coeff = smoothing_coeff(frame, config.attack);
+        self.release_coeff = smoothing_coeff(frame, config.release);
+        self.config = config;
+        self.set_gain_db(self.gain_db);
+    }
+
+    /// Apply gain to a frame in place
+    pub fn process(&mut self, frame: &mut [i16]) {
+        if frame.is_empty() {
+            return;
+        }
+
+        let level_dbfs = frame_dbfs(frame);
+        let rise_db = NOISE_FLOOR_RISE_DB_PER_SEC * FRAME_MS as f32 / 1000.0;
+        let floor_dbfs = self
+            .noise_floor_dbfs
+            .map_or(level_dbfs, |floor| (floor + rise_db).min(level_dbfs));
+        self.noise_floor_dbfs = Some(floor_dbfs);
+        let max_boost_db = (self.config.max_noise_dbfs - floor_dbfs).clamp(0.0, self.config.max_gain_db);
+        let desired_db = (self.config.target_dbfs - level_dbfs)
+            .clamp(-self.config.max_attenuation_db, max_boost_db);
+
+        let start_db = self.gain_db;
+        if desired_db < self.gain_db {
+            // Level went up: cut quickly (attack)
+            self.gain_db = desired_db + (self.gain_db - desired_db) * self.attack_coeff;
+        } else if level_dbfs >= floor_dbfs + self.config.noise_gate_margin_db {
+            // Level went down on real signal: recover slowly (release)
+            self.gain_db = desired_db + (self.gain_db - desired_db) * self.release_coeff;
+        }
+        // else: below noise gate - hold gain
+
+        // Ramp linearly from previous to new gain across the frame
+        let start = db_to_linear(start_db);
+        let end = db_to_linear(self.gain_db);
+        let step = (end - start) / frame.len() as f32;
+        let mut gain = start;
+        for sample in frame.iter_mut() {
+            gain += step;
+            *sample = (*sample as f32 * gain).round().clamp(-32768.0, 32767.0) as i16;
+        }
+    }
+
+    /// Current gain in dB (for diagnostics)
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    /// Tracked noise floor in dBFS (None before the first frame)
+    pub fn noise_floor_dbfs(&self) -> Option<f32> {
+        self.noise_floor_dbfs
+    }
+
+    /// Start from a known gain (e.g., carried over on reconfiguration)
+    pub fn set_gain_db(&mut self, gain_db: f32) {
+        self.gain_db = gain_db.clamp(-self.config.max_attenuation_db, self.config.max_gain_db);
+    }
+
+    /// Reset to unity gain and forget the noise floor
+    pub fn reset(&mut self) {
+        self.gain_db = 0.0;
+        self.noise_floor_dbfs = None;
+    }
+}
+
+impl Default for AutomaticGainControl {
+    fn default() -> Self {
+        Self::new(AgcConfig::default())
+    }
+}
+
+/// One-pole smoothing coefficient for a time constant, evaluated per frame
+fn smoothing_coeff(frame: Duration, time_constant: Duration) -> f32 {
+    if time_constant.is_zero() {
+        return 0.0;
+    }
+    (-frame.as_secs_f32() / time_constant.as_secs_f32()).exp()
+}
+
+/// RMS level of a frame in dBFS
+pub fn frame_dbfs(frame: &[i16]) -> f32 {
+    if frame.is_empty() {
+        return MIN_DBFS;
+    }
+    let sum_of_squares: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    let rms = (sum_of_squares / frame.len() as f64).sqrt() as f32;
+    if rms <= 0.0 {
+        return MIN_DBFS;
+    }
+    (20.0 * (rms / FULL_SCALE).log10()).max(MIN_DBFS)
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_frame(amplitude: f32, offset: usize) -> Vec<i16> {
+        (0..320)
+            .map(|i| {
+                let t = (i + offset) as f32 / 16000.0;
+                (amplitude * (2.0 * std::f32::consts::PI * 440.0 * t).sin()) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_high_pass_removes_dc() {
+        let mut hpf = HighPassFilter::default();
+        let mut last = Vec::new();
+        for _ in 0..50 {
+            let mut frame = vec![5000i16; 320];
+            hpf.process(&mut frame);
+            last = frame;
+        }
+        // After 1s of constant input the DC should be gone
+        assert!(last.iter().all(|&s| s.abs() < 50));
+    }
+
+    #[test]
+    fn test_agc_boosts_quiet_and_cuts_loud() {
+        let mut quiet_agc = AutomaticGainControl::default();
+        let mut loud_agc = AutomaticGainControl::default();
+
+        let mut quiet_out = Vec::new();
+        let mut loud_out = Vec::new();
+        for n in 0..200 {
+            // Quiet talker: bursts over a near-silent floor (a steady level
+            // would be taken for the noise floor)
+            let burst = n % 10 < 6;
+            let mut quiet = sine_frame(if burst { 300.0 } else { 3.0 }, n * 320);
+            let mut loud = sine_frame(30000.0, n * 320);
+            quiet_agc.process(&mut quiet);
+            loud_agc.process(&mut loud);
+            if burst {
+                quiet_out = quiet;
+            }
+            loud_out = loud;
+        }
+
+        let quiet_db = frame_dbfs(&quiet_out);
+        let loud_db = frame_dbfs(&loud_out);
+        assert!(quiet_agc.gain_db() > 0.0);
+        assert!(loud_agc.gain_db() < 0.0);
+        // Both converge much closer together than the 40dB they started apart
+        assert!((quiet_db - loud_db).abs() < 12.0);
+    }
+
+    #[test]
+    fn test_agc_does_not_boost_noise_floor() {
+        let mut agc = AutomaticGainControl::default();
+        for n in 0..100 {
+            // ~-70dBFS hiss: a steady level is the noise floor
+            let mut frame = sine_frame(10.0, n * 320);
+            agc.process(&mut frame);
+        }
+        assert_eq!(agc.gain_db(), 0.0);
+    }
+
+    #[test]
+    fn test_agc_keeps_boosted_floor_below_max_noise() {
+        let mut agc = AutomaticGainControl::default();
+        for n in 0..200 {
+            // Bursts at ~-44dBFS over a ~-74dBFS floor
+            let mut frame = sine_frame(if n % 10 < 6 { 300.0 } else { 10.0 }, n * 320);
+            agc.process(&mut frame);
+        }
+        let floor = agc.noise_floor_dbfs().unwrap();
+        assert!((floor - frame_dbfs(&sine_frame(10.0, 0))).abs() < 1.0, "floor {}", floor);
+        assert!(agc.gain_db() > 6.0);
+        assert!(floor + agc.gain_db() <= AgcConfig::default().max_noise_dbfs + 0.1);
+
+        // The background gets louder: the floor follows and the gain backs off
+        for n in 0..500 {
+            let mut frame = sine_frame(100.0, n * 320);
+            agc.process(&mut frame);
+        }
+        let floor = agc.noise_floor_dbfs().unwrap();
+        assert!((floor - frame_dbfs(&sine_frame(100.0, 0))).abs() < 1.0, "floor {}", floor);
+        assert!(agc.gain_db() < 0.1);
+    }
+
+    #[test]
+    fn test_set_config_keeps_gain_and_floor() {
+        let mut agc = AutomaticGainControl::default();
+        for n in 0..100 {
+            let mut frame = sine_frame(if n % 10 < 6 { 300.0 } else { 10.0 }, n * 320);
+            agc.process(&mut frame);
+        }
+        let (gain, floor) = (agc.gain_db(), agc.noise_floor_dbfs());
+        agc.set_config(AgcConfig {
+            release: Duration::from_millis(400),
+            ..AgcConfig::default()
+        });
+        assert_eq!(agc.gain_db(), gain);
+        assert_eq!(agc.noise_floor_dbfs(), floor);
+    }
+}
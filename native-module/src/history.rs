@@ -0,0 +1,366 @@
+// Audio History - the last few minutes of processed audio, on demand
+//
+// "What did they just say?": every processed 16kHz frame (suppressed ones
+// included, before keepalive fill replaces their samples; paused ones not)
+// is kept in a bounded ring so JS can fetch the
+// last N seconds, or a range of frame sequence numbers, after the fact:
+// - Replay / re-transcribe a mis-heard question
+// - Re-send audio lost while an STT websocket was reconnecting
+//
+// STORAGE:
+// - Fixed slots of one frame each, allocated when configured
+// - Pcm16: raw samples (32KB/s mono)
+// - MuLaw: G.711 mu-law, 8 bits per sample (16KB/s mono), telephone quality
+//   but plenty for re-transcription
+// - A change of frame layout (channel count) starts a fresh history
+//
+// THREADING:
+// - Shared as Arc<Mutex<AudioHistory>> between the DSP thread (one short
+//   push per frame) and JS (copies out under the lock)
+
+use std::time::Duration;
+
+use crate::audio_config::{FRAME_MS, FRAME_SAMPLES, SAMPLE_RATE};
+use crate::wav::ClipFormat;
+
+/// How history samples are stored
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryEncoding {
+    Pcm16,
+    MuLaw,
+}
+
+impl HistoryEncoding {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pcm16" => Some(HistoryEncoding::Pcm16),
+            "mulaw" => Some(HistoryEncoding::MuLaw),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryConfig {
+    /// How much audio is kept (zero disables the history)
+    pub duration: Duration,
+    pub encoding: HistoryEncoding,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(30),
+            encoding: HistoryEncoding::Pcm16,
+        }
+    }
+}
+
+/// A stretch of history copied out for JS
+#[derive(Debug, Clone)]
+pub struct AudioClip {
+    pub samples: Vec<i16>,
+    pub channels: u16,
+    /// Sequence number of the first frame
+    pub seq: u64,
+    /// Number of 20ms frames
+    pub frames: usize,
+    /// Capture time of the first sample (ms since the Unix epoch)
+    pub start_time_ms: Option<f64>,
+    /// Capture time just after the last sample
+    pub end_time_ms: Option<f64>,
+}
+
+impl AudioClip {
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.frames as u64 * FRAME_MS as u64)
+    }
+
+    pub fn encode(&self, format: ClipFormat) -> Vec<u8> {
+        format.encode(&self.samples, SAMPLE_RATE, self.channels)
+    }
+}
+
+enum Storage {
+    Pcm16(Vec<i16>),
+    MuLaw(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SlotInfo {
+    seq: u64,
+    capture_time_ms: Option<f64>,
+}
+
+pub struct AudioHistory {
+    config: HistoryConfig,
+    /// Interleaved samples per frame (slot size)
+    frame_len: usize,
+    storage: Storage,
+    slots: Vec<SlotInfo>,
+    /// Slot written next
+    head: usize,
+    /// Frames held
+    len: usize,
+}
+
+impl AudioHistory {
+    pub fn new(config: HistoryConfig) -> Self {
+        let mut history = Self {
+            config,
+            frame_len: FRAME_SAMPLES,
+            storage: Storage::Pcm16(Vec::new()),
+            slots: Vec::new(),
+            head: 0,
+            len: 0,
+        };
+        history.allocate();
+        history
+    }
+
+    pub fn config(&self) -> &HistoryConfig {
+        &self.config
+    }
+
+    /// Resize / re-encode (drops what was kept if anything changed)
+    pub fn set_config(&mut self, config: HistoryConfig) {
+        if config != self.config {
+            self.config = config;
+            self.allocate();
+        }
+    }
+
+    /// Forget everything (new capture run: sequence numbers start over)
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Frames currently held
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Keep one processed frame (evicts the oldest when full)
+    pub fn push(&mut self, frame: &[i16], seq: u64, capture_time_ms: Option<f64>) {
+        if self.slots.is_empty() || frame.is_empty() {
+            return;
+        }
+        if frame.len() != self.frame_len {
+            // Channel count changed: old frames can't be mixed with new ones
+            self.frame_len = frame.len();
+            self.allocate();
+        }
+        let offset = self.head * self.frame_len;
+        match &mut self.storage {
+            Storage::Pcm16(samples) => samples[offset..offset + self.frame_len].copy_from_slice(frame),
+            Storage::MuLaw(bytes) => {
+                for (byte, &sample) in bytes[offset..offset + self.frame_len].iter_mut().zip(frame) {
+                    *byte = mulaw_encode(sample);
+                }
+            }
+        }
+        self.slots[self.head] = SlotInfo { seq, capture_time_ms };
+        self.head = (self.head + 1) % self.slots.len();
+        self.len = (self.len + 1).min(self.slots.len());
+    }
+
+    /// The last `duration` of audio (less if not that much is held)
+    pub fn recent(&self, duration: Duration) -> Option<AudioClip> {
+        let frames = (duration.as_millis() / FRAME_MS as u128) as usize;
+        let count = frames.min(self.len);
+        self.clip(self.len - count, count)
+    }
+
+    /// Frames with sequence numbers `seq_start..=seq_end` that are still held
+    pub fn range(&self, seq_start: u64, seq_end: u64) -> Option<AudioClip> {
+        if seq_end < seq_start {
+            return None;
+        }
+        // Sequence numbers increase from oldest to newest (gaps while paused)
+        let first = self.partition_point(|seq| seq < seq_start);
+        let end = self.partition_point(|seq| seq <= seq_end);
+        self.clip(first, end.saturating_sub(first))
+    }
+
+    /// Copy `count` frames starting at logical index `first` (0 = oldest)
+    fn clip(&self, first: usize, count: usize) -> Option<AudioClip> {
+        if count == 0 {
+            return None;
+        }
+        let mut samples = Vec::with_capacity(count * self.frame_len);
+        for index in first..first + count {
+            let offset = self.slot(index) * self.frame_len;
+            match &self.storage {
+                Storage::Pcm16(stored) => samples.extend_from_slice(&stored[offset..offset + self.frame_len]),
+                Storage::MuLaw(stored) => {
+                    samples.extend(stored[offset..offset + self.frame_len].iter().map(|&byte| mulaw_decode(byte)))
+                }
+            }
+        }
+        let start = self.slots[self.slot(first)];
+        let last = self.slots[self.slot(first + count - 1)];
+        Some(AudioClip {
+            samples,
+            channels: (self.frame_len / FRAME_SAMPLES).max(1) as u16,
+            seq: start.seq,
+            frames: count,
+            start_time_ms: start.capture_time_ms,
+            end_time_ms: last.capture_time_ms.map(|time| time + FRAME_MS as f64),
+        })
+    }
+
+    /// Slot of logical index `index` (0 = oldest)
+    fn slot(&self, index: usize) -> usize {
+        let oldest = (self.head + self.slots.len() - self.len) % self.slots.len();
+        (oldest + index) % self.slots.len()
+    }
+
+    /// Number of held frames (oldest first) whose seq satisfies `before`
+    fn partition_point<P: Fn(u64) -> bool>(&self, before: P) -> usize {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = (low + high) / 2;
+            if before(self.slots[self.slot(mid)].seq) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    fn allocate(&mut self) {
+        let frames = (self.config.duration.as_millis() / FRAME_MS as u128) as usize;
+        let samples = frames * self.frame_len;
+        self.storage = match self.config.encoding {
+            HistoryEncoding::Pcm16 => Storage::Pcm16(vec![0; samples]),
+            HistoryEncoding::MuLaw => Storage::MuLaw(vec![0; samples]),
+        };
+        self.slots = vec![SlotInfo::default(); frames];
+        self.clear();
+    }
+}
+
+/// G.711 bias and clip level
+const MULAW_BIAS: i32 = 0x84;
+const MULAW_CLIP: i32 = 32635;
+
+fn mulaw_encode(sample: i16) -> u8 {
+    let mut value = sample as i32;
+    let sign = if value < 0 {
+        value = -value;
+        0x80
+    } else {
+        0
+    };
+    value = value.min(MULAW_CLIP) + MULAW_BIAS;
+    // Segment = position of the highest set bit above bit 7
+    let exponent = (31 - value.leading_zeros() as i32 - 7).clamp(0, 7);
+    let mantissa = (value >> (exponent + 3)) & 0x0F;
+    !(sign | (exponent << 4) | mantissa) as u8
+}
+
+fn mulaw_decode(byte: u8) -> i16 {
+    let value = !byte as i32;
+    let exponent = (value >> 4) & 0x07;
+    let mantissa = value & 0x0F;
+    let magnitude = (((mantissa << 3) + MULAW_BIAS) << exponent) - MULAW_BIAS;
+    if value & 0x80 != 0 {
+        -magnitude as i16
+    } else {
+        magnitude as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(seconds: u64, encoding: HistoryEncoding) -> AudioHistory {
+        AudioHistory::new(HistoryConfig {
+            duration: Duration::from_secs(seconds),
+            encoding,
+        })
+    }
+
+    /// Frame whose samples all carry its sequence number
+    fn frame(seq: u64) -> Vec<i16> {
+        vec![seq as i16; FRAME_SAMPLES]
+    }
+
+    #[test]
+    fn test_recent_after_wraparound() {
+        let mut history = history(1, HistoryEncoding::Pcm16);
+        for seq in 0..80 {
+            history.push(&frame(seq), seq, Some(seq as f64 * 20.0));
+        }
+        assert_eq!(history.len(), 50);
+
+        let clip = history.recent(Duration::from_millis(100)).unwrap();
+        assert_eq!(clip.frames, 5);
+        assert_eq!(clip.seq, 75);
+        assert_eq!(clip.start_time_ms, Some(1_500.0));
+        assert_eq!(clip.end_time_ms, Some(1_600.0));
+        assert_eq!(clip.samples[0], 75);
+        assert_eq!(*clip.samples.last().unwrap(), 79);
+
+        // More than is held: everything
+        let clip = history.recent(Duration::from_secs(60)).unwrap();
+        assert_eq!((clip.seq, clip.frames), (30, 50));
+    }
+
+    #[test]
+    fn test_range_by_sequence_number() {
+        let mut history = history(1, HistoryEncoding::Pcm16);
+        // Paused between 10 and 20: no frames for those numbers
+        for seq in (0..10).chain(20..40) {
+            history.push(&frame(seq), seq, None);
+        }
+
+        let clip = history.range(5, 24).unwrap();
+        assert_eq!(clip.seq, 5);
+        assert_eq!(clip.frames, 5 + 5);
+        assert_eq!(clip.samples[5 * FRAME_SAMPLES], 20);
+        assert_eq!(clip.end_time_ms, None);
+
+        assert!(history.range(12, 18).is_none());
+        assert!(history.range(40, 50).is_none());
+        assert_eq!(history.range(35, 99).unwrap().frames, 5);
+    }
+
+    #[test]
+    fn test_mulaw_round_trip() {
+        for sample in (-32768i32..=32767).step_by(7) {
+            let decoded = mulaw_decode(mulaw_encode(sample as i16)) as i32;
+            let tolerance = sample.abs().min(MULAW_CLIP) / 16 + 9;
+            let expected = sample.clamp(-MULAW_CLIP, MULAW_CLIP);
+            assert!((decoded - expected).abs() <= tolerance, "{} -> {}", sample, decoded);
+        }
+
+        let mut history = history(1, HistoryEncoding::MuLaw);
+        history.push(&vec![1000; FRAME_SAMPLES], 0, None);
+        let clip = history.recent(Duration::from_secs(1)).unwrap();
+        assert!(clip.samples.iter().all(|&s| (s - 1000).abs() <= 64));
+    }
+
+    #[test]
+    fn test_layout_change_starts_over() {
+        let mut history = history(1, HistoryEncoding::Pcm16);
+        history.push(&frame(0), 0, None);
+        history.push(&vec![0; FRAME_SAMPLES * 2], 1, None);
+        let clip = history.recent(Duration::from_secs(1)).unwrap();
+        assert_eq!((clip.seq, clip.frames, clip.channels), (1, 1, 2));
+
+        history.set_config(HistoryConfig {
+            duration: Duration::ZERO,
+            encoding: HistoryEncoding::Pcm16,
+        });
+        history.push(&frame(2), 2, None);
+        assert!(history.is_empty());
+    }
+}
@@ -0,0 +1,450 @@
+// Keyword Spotting - wake phrases on the microphone stream
+//
+// WHY:
+// - "Answer this" hands-free, without a hotkey that shows up on a screen share
+//
+// HOW (CPU only, no model files, speaker dependent):
+// - A phrase is enrolled from one or more recordings of the user saying it
+//   (Template::from_audio): 12 MFCCs per 20ms frame, leading / trailing
+//   silence trimmed. Log-mel bands are floored at BAND_FLOOR_DB first, so
+//   pauses look alike whatever the background noise
+// - Live speech frames get the same features and are aligned against every
+//   template with subsequence DTW: a match may start at any frame, the
+//   template may be spoken between half and twice as fast, and the cost is
+//   the mean frame distance along the path
+// - A match fires at its best end point (SETTLE_FRAMES later, so a slightly
+//   longer alignment can still win) and is raised as a Keyword event; all
+//   alignments then start over
+//
+// SUPPRESSION (opt-in):
+// - The capture loop holds frames back for holdback_frames() (the longest
+//   possible match) and turns the matched ones into silence before they
+//   reach JS, so the phrase never reaches the STT stream. Adds that much
+//   delivery latency; capture times are unchanged
+//
+// REAL-TIME NOTES:
+// - Templates are computed on the JS thread; push() does not allocate
+
+use crate::audio_config::{FRAME_MS, FRAME_SAMPLES};
+use crate::features::{Cepstrum, SpectralAnalyzer, MEL_BANDS, MFCC_COEFFS};
+use crate::segmenter::{FrameAnalyzer, FrameInfo};
+
+/// Longest template (3s)
+pub const MAX_TEMPLATE_FRAMES: usize = 150;
+
+/// Shortest template (200ms)
+const MIN_TEMPLATE_FRAMES: usize = 10;
+
+/// Template frames quieter than the loudest one by more than this are
+/// trimmed from its ends
+const TRIM_DB: f32 = 30.0;
+
+/// Absolute floor for template sound (RMS 100)
+const MIN_LEVEL_DB: f32 = 40.0;
+
+/// Log-mel level (dB) below which bands count as silent (white noise of
+/// RMS ~100 reaches it)
+const BAND_FLOOR_DB: f32 = 70.0;
+
+/// Frames after a candidate's end before it fires
+const SETTLE_FRAMES: u64 = 4;
+
+/// Enrolled recording of a phrase
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    frames: Vec<[f32; MFCC_COEFFS]>,
+}
+
+impl Template {
+    /// Features of a recording of the phrase (16kHz mono i16); None if it
+    /// holds less than 200ms of sound
+    pub fn from_audio(samples: &[i16]) -> Option<Template> {
+        let mut analyzer = SpectralAnalyzer::new();
+        let cepstrum = Cepstrum::new();
+        let mut frames = Vec::new();
+        let mut levels = Vec::new();
+        for chunk in samples.chunks_exact(FRAME_SAMPLES) {
+            let features = analyzer.analyze(chunk);
+            let mut mfcc = [0.0; MFCC_COEFFS];
+            frame_features(&analyzer, &cepstrum, &mut mfcc);
+            frames.push(mfcc);
+            levels.push(features.energy_db);
+        }
+        let loudest = levels.iter().copied().fold(f32::MIN, f32::max);
+        let floor = (loudest - TRIM_DB).max(MIN_LEVEL_DB);
+        let first = levels.iter().position(|&level| level >= floor)?;
+        let last = levels.iter().rposition(|&level| level >= floor)?;
+        let frames: Vec<_> = frames[first..=last].iter().copied().take(MAX_TEMPLATE_FRAMES).collect();
+        (frames.len() >= MIN_TEMPLATE_FRAMES).then_some(Template { frames })
+    }
+
+    /// Length in 20ms frames
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyword {
+    /// Reported with every match
+    pub name: String,
+    pub templates: Vec<Template>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeywordConfig {
+    pub keywords: Vec<Keyword>,
+    /// Largest mean frame distance (MFCC RMS) still counted as a match;
+    /// unrelated speech rarely scores below 0.6
+    pub max_distance: f32,
+    /// Silence matched frames before delivery (see SUPPRESSION)
+    pub suppress: bool,
+}
+
+impl Default for KeywordConfig {
+    fn default() -> Self {
+        Self {
+            keywords: Vec::new(),
+            max_distance: 0.5,
+            suppress: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeywordMatch {
+    pub keyword: String,
+    /// First and last frame (inclusive)
+    pub seq_start: u64,
+    pub seq_end: u64,
+    pub start_time_ms: Option<f64>,
+    pub end_time_ms: Option<f64>,
+    /// Mean frame distance along the alignment
+    pub distance: f32,
+}
+
+/// Path through the DTW grid ending at one template frame
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    cost: f32,
+    len: u32,
+    seq_start: u64,
+    start_time_ms: Option<f64>,
+}
+
+impl Cell {
+    const EMPTY: Cell = Cell {
+        cost: f32::INFINITY,
+        len: 1,
+        seq_start: 0,
+        start_time_ms: None,
+    };
+
+    fn mean(&self) -> f32 {
+        self.cost / self.len as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    distance: f32,
+    seq_start: u64,
+    seq_end: u64,
+    start_time_ms: Option<f64>,
+    end_time_ms: Option<f64>,
+}
+
+/// Online alignment of the stream against one template
+struct Alignment {
+    keyword: usize,
+    template: usize,
+    /// Column of the previous stream frame, and the one being filled
+    previous: Vec<Cell>,
+    current: Vec<Cell>,
+    best: Option<Candidate>,
+}
+
+impl Alignment {
+    fn reset(&mut self) {
+        self.previous.fill(Cell::EMPTY);
+        self.best = None;
+    }
+}
+
+pub struct KeywordSpotter {
+    config: KeywordConfig,
+    analyzer: SpectralAnalyzer,
+    cepstrum: Cepstrum,
+    mfcc: [f32; MFCC_COEFFS],
+    alignments: Vec<Alignment>,
+    in_speech: bool,
+}
+
+impl KeywordSpotter {
+    pub fn new(config: KeywordConfig) -> Self {
+        let mut spotter = Self {
+            config: KeywordConfig::default(),
+            analyzer: SpectralAnalyzer::new(),
+            cepstrum: Cepstrum::new(),
+            mfcc: [0.0; MFCC_COEFFS],
+            alignments: Vec::new(),
+            in_speech: false,
+        };
+        spotter.set_config(config);
+        spotter
+    }
+
+    /// Replace the phrases / options (alignments in progress are dropped)
+    pub fn set_config(&mut self, config: KeywordConfig) {
+        println!(
+            "[KeywordSpotter] {} keyword(s), max_distance={}, suppress={}",
+            config.keywords.len(),
+            config.max_distance,
+            config.suppress
+        );
+        self.alignments = config
+            .keywords
+            .iter()
+            .enumerate()
+            .flat_map(|(k, keyword)| keyword.templates.iter().enumerate().map(move |(t, template)| (k, t, template.frames())))
+            .map(|(keyword, template, frames)| Alignment {
+                keyword,
+                template,
+                previous: vec![Cell::EMPTY; frames],
+                current: vec![Cell::EMPTY; frames],
+                best: None,
+            })
+            .collect();
+        self.config = config;
+    }
+
+    /// Frames the stream has to be held back to silence any match (0 when
+    /// suppression is off)
+    pub fn holdback_frames(&self) -> usize {
+        if !self.config.suppress {
+            return 0;
+        }
+        let longest = self.alignments.iter().map(|alignment| alignment.previous.len()).max().unwrap_or(0);
+        2 * longest + SETTLE_FRAMES as usize + 1
+    }
+
+    /// Drop alignments in progress
+    pub fn reset(&mut self) {
+        for alignment in self.alignments.iter_mut() {
+            alignment.reset();
+        }
+    }
+
+    /// Best candidate if it ended at least SETTLE_FRAMES before `seq`
+    fn take_best(&mut self, seq: u64) -> Option<KeywordMatch> {
+        let settled = self
+            .alignments
+            .iter()
+            .filter_map(|alignment| alignment.best.map(|best| (alignment.keyword, best)))
+            .min_by(|a, b| a.1.distance.total_cmp(&b.1.distance))
+            .filter(|(_, best)| best.seq_end.saturating_add(SETTLE_FRAMES) <= seq)?;
+        self.reset();
+        let (keyword, best) = settled;
+        Some(KeywordMatch {
+            keyword: self.config.keywords[keyword].name.clone(),
+            seq_start: best.seq_start,
+            seq_end: best.seq_end,
+            start_time_ms: best.start_time_ms,
+            end_time_ms: best.end_time_ms,
+            distance: best.distance,
+        })
+    }
+}
+
+impl FrameAnalyzer for KeywordSpotter {
+    type Output = KeywordMatch;
+
+    /// One processed frame (mono); returns a match once it has settled
+    fn push(&mut self, frame: &[i16], info: &FrameInfo) -> Option<KeywordMatch> {
+        if !info.speech {
+            // A phrase can't span a speech end: settle what's there
+            let found = if self.in_speech { self.take_best(u64::MAX) } else { None };
+            self.in_speech = false;
+            self.reset();
+            return found;
+        }
+        if !self.in_speech {
+            self.in_speech = true;
+            self.analyzer.reset();
+        }
+        self.analyzer.analyze(frame);
+        frame_features(&self.analyzer, &self.cepstrum, &mut self.mfcc);
+
+        let end_time_ms = info.capture_time_ms.map(|time| time + FRAME_MS as f64);
+        for alignment in self.alignments.iter_mut() {
+            let template = &self.config.keywords[alignment.keyword].templates[alignment.template];
+            step(alignment, template, &self.mfcc, info.seq, info.capture_time_ms);
+            let end = alignment.current[alignment.current.len() - 1];
+            alignment.previous.copy_from_slice(&alignment.current);
+            if end.mean() <= self.config.max_distance && alignment.best.is_none_or(|best| end.mean() < best.distance) {
+                alignment.best = Some(Candidate {
+                    distance: end.mean(),
+                    seq_start: end.seq_start,
+                    seq_end: info.seq,
+                    start_time_ms: end.start_time_ms,
+                    end_time_ms,
+                });
+            }
+        }
+        self.take_best(info.seq)
+    }
+}
+
+/// Fill `alignment.current` for stream frame `seq`
+fn step(alignment: &mut Alignment, template: &Template, features: &[f32; MFCC_COEFFS], seq: u64, time_ms: Option<f64>) {
+    let frames = template.frames.len();
+    let max_span = 2 * frames as u64;
+    let start = Cell {
+        cost: 0.0,
+        len: 0,
+        seq_start: seq,
+        start_time_ms: time_ms,
+    };
+    for j in 0..frames {
+        let distance = frame_distance(features, &template.frames[j]);
+        // Predecessors: the stream lingers on frame j, both advance, or the
+        // template skips ahead within this stream frame
+        let options = if j == 0 {
+            [alignment.previous[0], start, Cell::EMPTY]
+        } else {
+            [alignment.previous[j], alignment.previous[j - 1], alignment.current[j - 1]]
+        };
+        let best = options
+            .into_iter()
+            .filter(|cell| cell.cost.is_finite() && seq - cell.seq_start < max_span)
+            .min_by(|a, b| ((a.cost + distance) / (a.len + 1) as f32).total_cmp(&((b.cost + distance) / (b.len + 1) as f32)));
+        alignment.current[j] = match best {
+            Some(cell) => Cell {
+                cost: cell.cost + distance,
+                len: cell.len + 1,
+                ..cell
+            },
+            None => Cell::EMPTY,
+        };
+    }
+    // Faster than twice the template's pace: not a match
+    let end = &mut alignment.current[frames - 1];
+    if (seq - end.seq_start + 1) * 2 < frames as u64 {
+        *end = Cell::EMPTY;
+    }
+}
+
+/// MFCCs of the analyzer's last frame, bands floored at BAND_FLOOR_DB
+fn frame_features(analyzer: &SpectralAnalyzer, cepstrum: &Cepstrum, out: &mut [f32; MFCC_COEFFS]) {
+    let mut bands = [0.0; MEL_BANDS];
+    for (band, &level) in bands.iter_mut().zip(analyzer.log_mel()) {
+        *band = level.max(BAND_FLOOR_DB);
+    }
+    cepstrum.apply(&bands, out);
+}
+
+/// RMS difference of two MFCC vectors
+fn frame_distance(a: &[f32; MFCC_COEFFS], b: &[f32; MFCC_COEFFS]) -> f32 {
+    (a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f32>() / MFCC_COEFFS as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classifier::test_signals::{frames, speech, voice};
+
+    fn pcm(signal: &[f32]) -> Vec<i16> {
+        frames(signal).concat()
+    }
+
+    /// `samples` said again: quieter, over background noise, every 10th
+    /// frame held longer (10% slower)
+    fn repeat(samples: &[i16]) -> Vec<i16> {
+        let mut out = Vec::new();
+        for (i, frame) in samples.chunks_exact(FRAME_SAMPLES).enumerate() {
+            let said = frame.iter().enumerate().map(|(j, &s)| (s as f32 * 0.7) as i16 + ((i * 31 + j * 17) % 61) as i16 - 30);
+            out.extend(said.clone());
+            if i % 10 == 5 {
+                out.extend(said);
+            }
+        }
+        out
+    }
+
+    /// Frames of `stream` through the spotter, all speech; matches found
+    fn spot(spotter: &mut KeywordSpotter, stream: &[i16]) -> Vec<KeywordMatch> {
+        let silence = vec![0i16; FRAME_SAMPLES];
+        let frames = stream.chunks_exact(FRAME_SAMPLES).map(|frame| (frame, true)).chain([(&silence[..], false)]);
+        let mut found = Vec::new();
+        for (seq, (frame, speech)) in frames.enumerate() {
+            let info = FrameInfo {
+                seq: seq as u64,
+                capture_time_ms: Some(seq as f64 * FRAME_MS as f64),
+                voiced: speech,
+                speech,
+            };
+            found.extend(spotter.push(frame, &info));
+        }
+        found
+    }
+
+    fn config(phrase: &[i16]) -> KeywordConfig {
+        KeywordConfig {
+            keywords: vec![Keyword {
+                name: "answer this".into(),
+                templates: vec![Template::from_audio(phrase).unwrap()],
+            }],
+            ..KeywordConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_template_trims_silence() {
+        let phrase = pcm(&voice(0.8, 11, 1.2, 1.0));
+        let mut padded = vec![0i16; 10 * FRAME_SAMPLES];
+        padded.extend(&phrase);
+        padded.extend(vec![0i16; 10 * FRAME_SAMPLES]);
+        let template = Template::from_audio(&padded).unwrap();
+        assert!((36..=41).contains(&template.frames()), "{}", template.frames());
+
+        assert_eq!(Template::from_audio(&[0; 20 * FRAME_SAMPLES]), None);
+        assert_eq!(Template::from_audio(&phrase[..5 * FRAME_SAMPLES]), None);
+    }
+
+    #[test]
+    fn test_spots_phrase_in_speech() {
+        let phrase = pcm(&voice(0.8, 11, 1.2, 1.0));
+        let mut spotter = KeywordSpotter::new(config(&phrase));
+
+        // Other speech, the phrase said again, more speech
+        let mut stream = pcm(&speech(2.0, 3));
+        let phrase_start = stream.len() / FRAME_SAMPLES;
+        stream.extend(repeat(&phrase));
+        let phrase_end = stream.len() / FRAME_SAMPLES;
+        stream.extend(pcm(&speech(2.0, 4)));
+
+        let found = spot(&mut spotter, &stream);
+        assert_eq!(found.len(), 1, "{:?}", found);
+        let spotted = &found[0];
+        assert_eq!(spotted.keyword, "answer this");
+        assert!((spotted.seq_start as i64 - phrase_start as i64).abs() <= 3, "{:?} vs {}", spotted, phrase_start);
+        assert!((spotted.seq_end as i64 - phrase_end as i64).abs() <= 3, "{:?} vs {}", spotted, phrase_end);
+        assert_eq!(spotted.start_time_ms, Some(spotted.seq_start as f64 * FRAME_MS as f64));
+    }
+
+    #[test]
+    fn test_other_speech_does_not_match() {
+        let phrase = pcm(&voice(0.8, 11, 1.2, 1.0));
+        let mut spotter = KeywordSpotter::new(config(&phrase));
+        let stream = pcm(&speech(20.0, 5));
+        assert!(spot(&mut spotter, &stream).is_empty());
+
+        let holdback = spotter.holdback_frames();
+        assert_eq!(holdback, 0);
+        spotter.set_config(KeywordConfig {
+            suppress: true,
+            ..config(&phrase)
+        });
+        assert!(spotter.holdback_frames() > 2 * phrase.len() / FRAME_SAMPLES);
+    }
+}
@@ -3,14 +3,12 @@
 #[macro_use]
 extern crate napi_derive;
 
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::thread;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use napi::bindgen_prelude::*;
+use napi::{JsBuffer, JsObject, NapiRaw, NapiValue};
 use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode, ErrorStrategy};
-use ringbuf::traits::Consumer;
 
 pub mod vad; 
 pub mod microphone;
@@ -18,15 +16,909 @@ pub mod speaker;
 pub mod streaming_resampler;
 pub mod audio_config;
 pub mod silence_suppression;
+pub mod gain_control;
+pub mod dsp;
+pub mod capture;
+pub mod sample_ring;
+pub mod frame_pool;
+pub mod delivery;
+pub mod events;
+pub mod clock;
+pub mod scripted;
+pub mod features;
+pub mod classifier;
+pub mod separation;
+pub mod comfort_noise;
+pub mod segmenter;
+pub mod wav;
+pub mod history;
+pub mod diarization;
+pub mod pitch;
+pub mod keywords;
+pub mod conversation;
+pub mod metrics;
+pub mod transcriber;
+#[cfg(feature = "offline-stt")]
+pub mod whisper;
 
 // Keep old resampler module for compatibility
 pub mod resampler;
 
-use crate::streaming_resampler::StreamingResampler;
-use crate::audio_config::{FRAME_SAMPLES, DSP_POLL_MS};
-use crate::silence_suppression::{
-    SilenceSuppressor, SilenceSuppressionConfig, FrameAction, generate_silence_frame
-};
+use crate::dsp::{ChannelMode, DspCommand, DspSettings, VadMode};
+use crate::capture::{CaptureCore, CaptureSource, CaptureState, SystemAudioSource};
+use crate::speaker::{AppSelection, SpeakerOptions, MAX_CHANNELS};
+use crate::delivery::{DeliveryConfig, DeliveryQueue, LagChange, OverflowPolicy, PushOutcome};
+use crate::clock::epoch_ms;
+use crate::conversation::{ConversationConfig, ConversationEvent, Party};
+use crate::diarization::speaker_label;
+use crate::events::CaptureEvent;
+use crate::frame_pool::PooledFrame;
+use crate::sample_ring::{RingConfig, RingOverflow};
+use crate::audio_config::FRAME_MS;
+use crate::silence_suppression::SilenceFill;
+use crate::segmenter::{SegmenterConfig, Utterance};
+use crate::wav::ClipFormat;
+use crate::history::{AudioClip, HistoryConfig, HistoryEncoding};
+use crate::keywords::{Keyword, KeywordConfig, Template};
+use crate::transcriber::{spawn_transcriber, RecognizerConfig, SpeechRecognizer, Transcript, TranscriberConfig};
+use crate::audio_config::SAMPLE_RATE;
+
+// ============================================================================
+// RUNTIME CONFIGURATION
+// ============================================================================
+
+/// Options accepted by `configure()` on both capture classes
+/// Every field is optional; omitted fields keep their current value.
+#[napi(object)]
+pub struct CaptureOptions {
+    /// Suppressor speech threshold (RMS, i16 scale)
+    pub speech_threshold_rms: Option<f64>,
+    pub speech_hangover_ms: Option<u32>,
+    pub silence_keepalive_interval_ms: Option<u32>,
+    /// Keepalive content: "zeros" (default) or "comfort-noise"
+    pub silence_fill: Option<String>,
+    /// Send a fill frame for every suppressed 20ms slot (default false)
+    pub contiguous_silence: Option<bool>,
+    pub agc_enabled: Option<bool>,
+    pub agc_target_dbfs: Option<f64>,
+    pub agc_max_gain_db: Option<f64>,
+    pub agc_attack_ms: Option<u32>,
+    pub agc_release_ms: Option<u32>,
+    /// Speech / music / noise classification (default on for system audio)
+    pub content_classification: Option<bool>,
+    /// Treat frames classified as music / noise as non-speech (default true)
+    pub content_gate: Option<bool>,
+    /// Attenuate background music under speech, adds 20ms latency (default false)
+    pub music_attenuation: Option<bool>,
+    /// Label speech with stable speaker IDs, raised as `speaker` events (default false)
+    pub speaker_diarization: Option<bool>,
+    /// Voices tracked at most; further voices join the closest one (default 8)
+    pub max_speakers: Option<u32>,
+    /// Largest voice distance still counted as the same speaker (default 0.5;
+    /// lower splits more readily)
+    pub speaker_distance_threshold: Option<f64>,
+    /// Flag utterances ending with rising intonation, raised as `likelyQuestion`
+    /// events (default on for system audio)
+    pub question_detection: Option<bool>,
+    /// Lowest confidence reported as a likely question (0..1, default 0.5)
+    pub question_min_confidence: Option<f64>,
+    /// "suppress" (default) or "passthrough"
+    pub vad_mode: Option<String>,
+    pub muted: Option<bool>,
+    /// Number of 20ms frames per callback (default 1)
+    pub frames_per_callback: Option<u32>,
+    /// Frames (or batches) allowed to wait for a stalled JS thread (default 100)
+    pub max_queued_frames: Option<u32>,
+    /// "drop-oldest", "drop-newest" or "coalesce-silence" (default)
+    pub overflow_policy: Option<String>,
+    /// Seconds of processed audio kept for getRecent() / getRange() (default 30, 0 disables)
+    pub history_seconds: Option<u32>,
+    /// "pcm16" (default) or "mulaw" (half the memory)
+    pub history_encoding: Option<String>,
+}
+
+/// Frame delivery counters returned by `getStats()`
+#[napi(object)]
+pub struct CaptureStats {
+    /// Frames handed to the JS callback
+    pub delivered: i64,
+    /// Frames dropped because JS was not keeping up
+    pub dropped: i64,
+    /// Silence / keepalive frames merged away under backpressure
+    pub coalesced: i64,
+    /// Frames currently waiting for JS
+    pub queued: u32,
+    pub lagging: bool,
+    /// Device pushes that did not fit into the ring buffer
+    pub ring_overflows: i64,
+    /// Device audio lost to ring buffer overflows (ms)
+    pub ring_dropped_ms: f64,
+}
+
+/// Ring buffer between the device callback and the DSP thread (fixed at construction)
+#[napi(object)]
+pub struct RingBufferOptions {
+    /// Capacity in ms of device audio (default 680)
+    pub ring_buffer_ms: Option<u32>,
+    /// "drop-newest" (default) or "overwrite-oldest"
+    pub ring_overflow: Option<String>,
+}
+
+/// Longest ring buffer accepted from JS (ms)
+const MAX_RING_BUFFER_MS: u32 = 10_000;
+
+fn ring_options(opts: Option<RingBufferOptions>) -> napi::Result<RingConfig> {
+    let mut config = RingConfig::default();
+    let Some(opts) = opts else {
+        return Ok(config);
+    };
+    if let Some(ms) = opts.ring_buffer_ms {
+        if !(2 * FRAME_MS..=MAX_RING_BUFFER_MS).contains(&ms) {
+            return Err(napi::Error::from_reason(format!(
+                "ringBufferMs must be between {} and {}",
+                2 * FRAME_MS,
+                MAX_RING_BUFFER_MS
+            )));
+        }
+        config.capacity_ms = ms;
+    }
+    if let Some(overflow) = opts.ring_overflow.as_deref() {
+        config.overflow = RingOverflow::parse(overflow)
+            .ok_or_else(|| napi::Error::from_reason(format!("Unknown ringOverflow: {}", overflow)))?;
+    }
+    Ok(config)
+}
+
+/// Delivery queue settings from JS options (None if untouched)
+fn options_to_delivery(current: DeliveryConfig, opts: &CaptureOptions) -> napi::Result<Option<DeliveryConfig>> {
+    if opts.max_queued_frames.is_none() && opts.overflow_policy.is_none() {
+        return Ok(None);
+    }
+    let mut config = current;
+    if let Some(n) = opts.max_queued_frames {
+        if n == 0 {
+            return Err(napi::Error::from_reason("maxQueuedFrames must be at least 1"));
+        }
+        config.max_queued = n as usize;
+    }
+    if let Some(policy) = opts.overflow_policy.as_deref() {
+        config.policy = OverflowPolicy::parse(policy)
+            .ok_or_else(|| napi::Error::from_reason(format!("Unknown overflowPolicy: {}", policy)))?;
+    }
+    Ok(Some(config))
+}
+
+/// Longest history accepted from JS (5 minutes)
+const MAX_HISTORY_SECONDS: u32 = 300;
+
+fn options_to_history(current: &HistoryConfig, opts: &CaptureOptions) -> napi::Result<Option<HistoryConfig>> {
+    if opts.history_seconds.is_none() && opts.history_encoding.is_none() {
+        return Ok(None);
+    }
+    let mut config = current.clone();
+    if let Some(seconds) = opts.history_seconds {
+        if seconds > MAX_HISTORY_SECONDS {
+            return Err(napi::Error::from_reason(format!(
+                "historySeconds must be at most {}",
+                MAX_HISTORY_SECONDS
+            )));
+        }
+        config.duration = Duration::from_secs(seconds as u64);
+    }
+    if let Some(encoding) = opts.history_encoding.as_deref() {
+        config.encoding = HistoryEncoding::parse(encoding)
+            .ok_or_else(|| napi::Error::from_reason(format!("Unknown historyEncoding: {}", encoding)))?;
+    }
+    Ok(Some(config))
+}
+
+fn clip_format(format: Option<String>) -> napi::Result<ClipFormat> {
+    match format.as_deref() {
+        None => Ok(ClipFormat::Wav),
+        Some(value) => ClipFormat::parse(value)
+            .ok_or_else(|| napi::Error::from_reason(format!("Unknown format: {}", value))),
+    }
+}
+
+/// `{ data, format, sampleRate, channels, seq, frames, startTimeMs, endTimeMs, durationMs }`
+fn clip_to_object(env: &Env, clip: AudioClip, format: ClipFormat) -> napi::Result<JsObject> {
+    let data = env.create_buffer_with_data(clip.encode(format))?.into_raw();
+    let mut obj = env.create_object()?;
+    obj.set_named_property("data", data)?;
+    obj.set("format", format.name())?;
+    obj.set("sampleRate", SAMPLE_RATE)?;
+    obj.set("channels", clip.channels as u32)?;
+    obj.set("seq", clip.seq as i64)?;
+    obj.set("frames", clip.frames as u32)?;
+    obj.set("startTimeMs", clip.start_time_ms)?;
+    obj.set("endTimeMs", clip.end_time_ms)?;
+    obj.set("durationMs", clip.duration().as_secs_f64() * 1000.0)?;
+    Ok(obj)
+}
+
+/// Translate JS options into DSP commands, based on the current settings
+fn options_to_commands(settings: &DspSettings, opts: CaptureOptions) -> napi::Result<Vec<DspCommand>> {
+    let mut commands = Vec::new();
+
+    if opts.speech_threshold_rms.is_some()
+        || opts.speech_hangover_ms.is_some()
+        || opts.silence_keepalive_interval_ms.is_some()
+        || opts.silence_fill.is_some()
+        || opts.contiguous_silence.is_some()
+    {
+        let mut config = settings.suppression.clone();
+        if let Some(threshold) = opts.speech_threshold_rms {
+            config.speech_threshold_rms = threshold as f32;
+        }
+        if let Some(ms) = opts.speech_hangover_ms {
+            config.speech_hangover = Duration::from_millis(ms as u64);
+        }
+        if let Some(ms) = opts.silence_keepalive_interval_ms {
+            config.silence_keepalive_interval = Duration::from_millis(ms as u64);
+        }
+        if let Some(fill) = opts.silence_fill.as_deref() {
+            config.silence_fill = SilenceFill::parse(fill)
+                .ok_or_else(|| napi::Error::from_reason(format!("Unknown silenceFill: {}", fill)))?;
+        }
+        if let Some(contiguous) = opts.contiguous_silence {
+            config.contiguous = contiguous;
+        }
+        commands.push(DspCommand::Suppression(config));
+    }
+
+    let agc_touched = opts.agc_target_dbfs.is_some()
+        || opts.agc_max_gain_db.is_some()
+        || opts.agc_attack_ms.is_some()
+        || opts.agc_release_ms.is_some();
+    match opts.agc_enabled {
+        Some(false) => commands.push(DspCommand::Agc(None)),
+        enabled if agc_touched || (enabled == Some(true) && settings.agc.is_none()) => {
+            let mut config = settings.agc.clone().unwrap_or_default();
+            if let Some(dbfs) = opts.agc_target_dbfs {
+                config.target_dbfs = dbfs as f32;
+            }
+            if let Some(db) = opts.agc_max_gain_db {
+                config.max_gain_db = db as f32;
+            }
+            if let Some(ms) = opts.agc_attack_ms {
+                config.attack = Duration::from_millis(ms as u64);
+            }
+            if let Some(ms) = opts.agc_release_ms {
+                config.release = Duration::from_millis(ms as u64);
+            }
+            commands.push(DspCommand::Agc(Some(config)));
+        }
+        _ => {}
+    }
+
+    let classifier_touched = opts.content_gate.is_some() || opts.music_attenuation.is_some();
+    match opts.content_classification {
+        Some(false) => commands.push(DspCommand::Classifier(None)),
+        enabled if classifier_touched || (enabled == Some(true) && settings.classifier.is_none()) => {
+            let mut config = settings.classifier.clone().unwrap_or_default();
+            if let Some(gate) = opts.content_gate {
+                config.gate = gate;
+            }
+            if let Some(separation) = opts.music_attenuation {
+                config.separation = separation;
+            }
+            commands.push(DspCommand::Classifier(Some(config)));
+        }
+        _ => {}
+    }
+
+    let diarization_touched = opts.max_speakers.is_some() || opts.speaker_distance_threshold.is_some();
+    match opts.speaker_diarization {
+        Some(false) => commands.push(DspCommand::Diarization(None)),
+        enabled if diarization_touched || (enabled == Some(true) && settings.diarization.is_none()) => {
+            let mut config = settings.diarization.clone().unwrap_or_default();
+            if let Some(n) = opts.max_speakers {
+                if n == 0 {
+                    return Err(napi::Error::from_reason("maxSpeakers must be at least 1"));
+                }
+                config.max_speakers = n as usize;
+            }
+            if let Some(threshold) = opts.speaker_distance_threshold {
+                if threshold <= 0.0 {
+                    return Err(napi::Error::from_reason("speakerDistanceThreshold must be positive"));
+                }
+                config.max_distance = threshold as f32;
+            }
+            commands.push(DspCommand::Diarization(Some(config)));
+        }
+        _ => {}
+    }
+
+    match opts.question_detection {
+        Some(false) => commands.push(DspCommand::QuestionDetection(None)),
+        enabled if opts.question_min_confidence.is_some() || (enabled == Some(true) && settings.question_detection.is_none()) => {
+            let mut config = settings.question_detection.clone().unwrap_or_default();
+            if let Some(confidence) = opts.question_min_confidence {
+                if !(0.0..=1.0).contains(&confidence) {
+                    return Err(napi::Error::from_reason("questionMinConfidence must be between 0 and 1"));
+                }
+                config.min_confidence = confidence as f32;
+            }
+            commands.push(DspCommand::QuestionDetection(Some(config)));
+        }
+        _ => {}
+    }
+
+    if let Some(mode) = opts.vad_mode {
+        let mode = VadMode::parse(&mode)
+            .ok_or_else(|| napi::Error::from_reason(format!("Unknown vadMode: {}", mode)))?;
+        commands.push(DspCommand::VadMode(mode));
+    }
+
+    if let Some(muted) = opts.muted {
+        commands.push(DspCommand::Muted(muted));
+    }
+
+    if let Some(n) = opts.frames_per_callback {
+        if n == 0 {
+            return Err(napi::Error::from_reason("framesPerCallback must be at least 1"));
+        }
+        commands.push(DspCommand::FramesPerCallback(n as usize));
+    }
+
+    Ok(commands)
+}
+
+/// Utterance mode (`startUtterances()`): whole speech segments instead of frames
+#[napi(object)]
+pub struct UtteranceOptions {
+    /// Audio kept before speech starts (default 300)
+    pub pre_roll_ms: Option<u32>,
+    /// Silence kept after speech ends (default 300, at most speechHangoverMs)
+    pub trailing_padding_ms: Option<u32>,
+    /// Utterances with less speech are dropped (default 250)
+    pub min_duration_ms: Option<u32>,
+    /// Longer utterances are split at their quietest point (default 30000)
+    pub max_duration_ms: Option<u32>,
+    /// "wav" (default) or "pcm"
+    pub format: Option<String>,
+}
+
+/// Shortest maxDurationMs accepted from JS
+const MIN_UTTERANCE_MAX_MS: u32 = 1_000;
+
+fn utterance_options(opts: Option<UtteranceOptions>) -> napi::Result<(SegmenterConfig, ClipFormat)> {
+    let mut config = SegmenterConfig::default();
+    let Some(opts) = opts else {
+        return Ok((config, ClipFormat::Wav));
+    };
+    if let Some(ms) = opts.pre_roll_ms {
+        config.pre_roll = Duration::from_millis(ms as u64);
+    }
+    if let Some(ms) = opts.trailing_padding_ms {
+        config.trailing_padding = Duration::from_millis(ms as u64);
+    }
+    if let Some(ms) = opts.min_duration_ms {
+        config.min_duration = Duration::from_millis(ms as u64);
+    }
+    if let Some(ms) = opts.max_duration_ms {
+        if ms < MIN_UTTERANCE_MAX_MS {
+            return Err(napi::Error::from_reason(format!(
+                "maxDurationMs must be at least {}",
+                MIN_UTTERANCE_MAX_MS
+            )));
+        }
+        config.max_duration = Duration::from_millis(ms as u64);
+    }
+    if config.min_duration > config.max_duration {
+        return Err(napi::Error::from_reason("minDurationMs must not exceed maxDurationMs"));
+    }
+    let format = match opts.format.as_deref() {
+        None => ClipFormat::Wav,
+        Some(format) => ClipFormat::parse(format)
+            .ok_or_else(|| napi::Error::from_reason(format!("Unknown utterance format: {}", format)))?,
+    };
+    Ok((config, format))
+}
+
+/// On-device transcription (`startTranscription()`, `offline-stt` builds only)
+#[napi(object)]
+pub struct TranscriptionOptions {
+    /// Path to a ggml Whisper model file (e.g. ggml-base.en.bin)
+    pub model_path: String,
+    /// Language code ("en", "de", ...) or "auto" (default "en")
+    pub language: Option<String>,
+    /// CPU threads for inference (default: up to 4)
+    pub threads: Option<u32>,
+    /// New audio between partial transcripts (default 1000)
+    pub partial_interval_ms: Option<u32>,
+    /// Longest utterance decoded at once (default 30000)
+    pub max_utterance_ms: Option<u32>,
+}
+
+/// Default inference threads: leave cores for capture and the UI
+const MAX_DEFAULT_STT_THREADS: usize = 4;
+
+fn transcription_options(opts: TranscriptionOptions) -> napi::Result<(TranscriberConfig, RecognizerConfig)> {
+    if opts.model_path.is_empty() {
+        return Err(napi::Error::from_reason("modelPath is required"));
+    }
+    let mut config = TranscriberConfig::default();
+    if let Some(ms) = opts.partial_interval_ms {
+        if ms < FRAME_MS {
+            return Err(napi::Error::from_reason(format!("partialIntervalMs must be at least {}", FRAME_MS)));
+        }
+        config.partial_interval = Duration::from_millis(ms as u64);
+    }
+    if let Some(ms) = opts.max_utterance_ms {
+        if !(MIN_UTTERANCE_MAX_MS..=30_000).contains(&ms) {
+            return Err(napi::Error::from_reason(format!(
+                "maxUtteranceMs must be between {} and 30000",
+                MIN_UTTERANCE_MAX_MS
+            )));
+        }
+        config.max_utterance = Duration::from_millis(ms as u64);
+    }
+    let threads = match opts.threads {
+        Some(0) => return Err(napi::Error::from_reason("threads must be at least 1")),
+        Some(n) => n as usize,
+        None => std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(MAX_DEFAULT_STT_THREADS),
+    };
+    let language = match opts.language.as_deref() {
+        None => Some("en".to_string()),
+        Some("auto") => None,
+        Some(language) => Some(language.to_string()),
+    };
+    let recognizer = RecognizerConfig {
+        model_path: opts.model_path,
+        language,
+        threads,
+    };
+    Ok((config, recognizer))
+}
+
+#[cfg(feature = "offline-stt")]
+fn load_recognizer(config: &RecognizerConfig) -> napi::Result<Box<dyn SpeechRecognizer>> {
+    let recognizer = whisper::WhisperRecognizer::new(config).map_err(to_napi_error)?;
+    Ok(Box::new(recognizer))
+}
+
+#[cfg(not(feature = "offline-stt"))]
+fn load_recognizer(_config: &RecognizerConfig) -> napi::Result<Box<dyn SpeechRecognizer>> {
+    Err(napi::Error::from_reason(
+        "Offline STT is not available in this build (enable the offline-stt feature)",
+    ))
+}
+
+/// True if this build can transcribe on-device (`startTranscription()`)
+#[napi]
+pub fn is_offline_stt_available() -> bool {
+    cfg!(feature = "offline-stt")
+}
+
+fn to_napi_error(e: anyhow::Error) -> napi::Error {
+    napi::Error::from_reason(format!("{}", e))
+}
+
+// ============================================================================
+// FRAME DELIVERY + EVENTS
+// ============================================================================
+
+/// Optional `onEvent` callback shared with the DSP thread
+type EventSink = Arc<Mutex<Option<ThreadsafeFunction<CaptureEvent, ErrorStrategy::Fatal>>>>;
+
+fn emit_event(sink: &EventSink, event: CaptureEvent) {
+    if let Ok(sink) = sink.lock() {
+        if let Some(tsfn) = sink.as_ref() {
+            tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    }
+}
+
+fn create_event_tsfn(env: &Env, callback: JsFunction) -> napi::Result<ThreadsafeFunction<CaptureEvent, ErrorStrategy::Fatal>> {
+    let mut tsfn = callback.create_threadsafe_function(0, |ctx| {
+        let event: CaptureEvent = ctx.value;
+        let mut obj = ctx.env.create_object()?;
+        obj.set("type", event.name())?;
+        match event {
+            CaptureEvent::Lagging { lagging, queued, dropped } => {
+                obj.set("lagging", lagging)?;
+                obj.set("queued", queued as u32)?;
+                obj.set("dropped", dropped as f64)?;
+            }
+            CaptureEvent::FormatChanged { sample_rate, channels, output_channels } => {
+                obj.set("sampleRate", sample_rate)?;
+                obj.set("channels", channels as u32)?;
+                obj.set("outputChannels", output_channels as u32)?;
+            }
+            CaptureEvent::ContentClass { class, speech, music, noise } => {
+                obj.set("class", class.name())?;
+                obj.set("speech", speech as f64)?;
+                obj.set("music", music as f64)?;
+                obj.set("noise", noise as f64)?;
+            }
+            CaptureEvent::Speaker { speaker, seq_start, seq_end, start_time_ms, end_time_ms, new_speaker } => {
+                obj.set("speaker", speaker_label(speaker))?;
+                obj.set("seqStart", seq_start as i64)?;
+                obj.set("seqEnd", seq_end as i64)?;
+                obj.set("startTimeMs", start_time_ms)?;
+                obj.set("endTimeMs", end_time_ms)?;
+                obj.set("newSpeaker", new_speaker)?;
+            }
+            CaptureEvent::LikelyQuestion { seq, time_ms, confidence, rise_semitones } => {
+                obj.set("seq", seq as i64)?;
+                obj.set("timeMs", time_ms)?;
+                obj.set("confidence", confidence as f64)?;
+                obj.set("riseSemitones", rise_semitones as f64)?;
+            }
+            CaptureEvent::Keyword { keyword, seq_start, seq_end, start_time_ms, end_time_ms, distance } => {
+                obj.set("keyword", keyword)?;
+                obj.set("seqStart", seq_start as i64)?;
+                obj.set("seqEnd", seq_end as i64)?;
+                obj.set("startTimeMs", start_time_ms)?;
+                obj.set("endTimeMs", end_time_ms)?;
+                obj.set("distance", distance as f64)?;
+            }
+        }
+        Ok(vec![obj])
+    })?;
+    // Listening for events must not keep the process alive
+    tsfn.unref(env)?;
+    Ok(tsfn)
+}
+
+fn lag_event(tag: &str, queue: &DeliveryQueue, change: LagChange) -> Option<CaptureEvent> {
+    let lagging = match change {
+        LagChange::None => return None,
+        LagChange::Started => true,
+        LagChange::Recovered => false,
+    };
+    let stats = queue.stats();
+    if lagging {
+        println!("[{}] JS is lagging: {} frames queued, dropping per policy", tag, stats.queued);
+    } else {
+        println!("[{}] JS caught up ({} frames dropped so far)", tag, stats.dropped);
+    }
+    Some(CaptureEvent::Lagging {
+        lagging,
+        queued: stats.queued,
+        dropped: stats.dropped,
+    })
+}
+
+/// Build the JS callback wrapper that delivers 16kHz i16 frames as PCM bytes
+///
+/// Frames wait in the bounded DeliveryQueue; the threadsafe function only
+/// carries tokens (one per queued frame, tagged with the queue's run) and
+/// pops the oldest frame when JS gets to it. The Buffer borrows the pooled frame memory; its finalizer
+/// returns the frame to the pool. `callback` must come from skip_empty_calls().
+fn create_frame_tsfn(
+    callback: JsFunction,
+    tag: &'static str,
+    queue: Arc<DeliveryQueue>,
+    events: EventSink,
+) -> napi::Result<ThreadsafeFunction<u64, ErrorStrategy::Fatal>> {
+    callback.create_threadsafe_function(0, move |ctx| {
+        let (frame, lag) = queue.pop_token(ctx.value);
+        if let Some(event) = lag_event(tag, &queue, lag) {
+            emit_event(&events, event);
+        }
+        match frame {
+            Some(frame) => Ok(vec![frame_to_buffer(&ctx.env, frame)?]),
+            // Token of an earlier run (its frame was dropped on restart):
+            // no arguments, the wrapper skips the call
+            None => Ok(Vec::new()),
+        }
+    })
+}
+
+/// Wrap a JS callback so that calls without arguments never reach it
+/// (a threadsafe function always calls JS, even with nothing to deliver)
+fn skip_empty_calls(env: &Env, callback: JsFunction) -> napi::Result<JsFunction> {
+    let wrap: JsFunction =
+        env.run_script("(callback) => function (...args) { if (args.length > 0) return callback(...args); }")?;
+    let wrapped = wrap.call(None, &[callback])?;
+    Ok(unsafe { wrapped.cast::<JsFunction>() })
+}
+
+/// Wrap a pooled frame in a Buffer that borrows its memory
+/// The Buffer's finalizer returns the frame to the pool. Timing travels as
+/// properties of the Buffer: `seq` and `captureTimeMs` (null if unknown).
+fn frame_to_buffer(env: &Env, mut frame: PooledFrame) -> napi::Result<JsBuffer> {
+    let seq = frame.seq();
+    let capture_time_ms = frame.capture_time_ms();
+    let data = frame.as_mut_byte_ptr();
+    let length = frame.byte_len();
+    let buffer = unsafe { env.create_buffer_with_borrowed_data(data, length, frame, |frame, _env| drop(frame))? }.into_raw();
+
+    let mut object = unsafe { JsObject::from_raw_unchecked(env.raw(), buffer.raw()) };
+    object.set_named_property("seq", env.create_int64(seq as i64)?)?;
+    match capture_time_ms {
+        Some(time) => object.set_named_property("captureTimeMs", env.create_double(time)?)?,
+        None => object.set_named_property("captureTimeMs", env.get_null()?)?,
+    }
+    Ok(buffer)
+}
+
+/// Build the JS callback wrapper that delivers utterances as
+/// `{ data, format, sampleRate, channels, seq, startTimeMs, endTimeMs, durationMs, forcedSplit }`
+///
+/// Utterances are rare and each is handed over once, so they go straight
+/// through the threadsafe function rather than the frame DeliveryQueue.
+fn create_utterance_tsfn(
+    callback: JsFunction,
+    format: ClipFormat,
+) -> napi::Result<ThreadsafeFunction<Utterance, ErrorStrategy::Fatal>> {
+    callback.create_threadsafe_function(0, move |ctx| {
+        let utterance: Utterance = ctx.value;
+        let data = ctx.env.create_buffer_with_data(utterance.encode(format))?.into_raw();
+        let mut obj = ctx.env.create_object()?;
+        obj.set_named_property("data", data)?;
+        obj.set("format", format.name())?;
+        obj.set("sampleRate", SAMPLE_RATE)?;
+        obj.set("channels", utterance.channels as u32)?;
+        obj.set("seq", utterance.seq as i64)?;
+        obj.set("startTimeMs", utterance.start_time_ms)?;
+        obj.set("endTimeMs", utterance.end_time_ms)?;
+        obj.set("durationMs", utterance.duration().as_secs_f64() * 1000.0)?;
+        obj.set("forcedSplit", utterance.forced_split)?;
+        Ok(vec![obj])
+    })
+}
+
+/// Build the JS callback wrapper that delivers transcripts as
+/// `{ text, isFinal, confidence, seq, startTimeMs, durationMs, segments: [{ text, startMs, endMs }] }`
+fn create_transcript_tsfn(callback: JsFunction) -> napi::Result<ThreadsafeFunction<Transcript, ErrorStrategy::Fatal>> {
+    callback.create_threadsafe_function(0, |ctx| {
+        let transcript: Transcript = ctx.value;
+        let mut segments = ctx.env.create_array_with_length(transcript.segments.len())?;
+        for (i, segment) in transcript.segments.iter().enumerate() {
+            let mut obj = ctx.env.create_object()?;
+            obj.set("text", segment.text.trim())?;
+            obj.set("startMs", segment.start.as_secs_f64() * 1000.0)?;
+            obj.set("endMs", segment.end.as_secs_f64() * 1000.0)?;
+            segments.set_element(i as u32, obj)?;
+        }
+        let mut obj = ctx.env.create_object()?;
+        obj.set("text", transcript.text)?;
+        obj.set("isFinal", transcript.is_final)?;
+        // Same shape as the cloud providers; Whisper gives no usable confidence
+        obj.set("confidence", 1.0)?;
+        obj.set("seq", transcript.seq as i64)?;
+        obj.set("startTimeMs", transcript.start_time_ms)?;
+        obj.set("durationMs", transcript.duration.as_secs_f64() * 1000.0)?;
+        obj.set_named_property("segments", segments)?;
+        Ok(vec![obj])
+    })
+}
+
+type ReadResolver = Box<dyn FnOnce(Env) -> napi::Result<Vec<JsBuffer>>>;
+
+/// JS-facing plumbing shared by SystemAudioCapture and MicrophoneCapture
+struct JsCapture<S: CaptureSource> {
+    core: CaptureCore<S>,
+    events: EventSink,
+    /// Tracker this capture reports its speech state to
+    conversation: ConversationSlot,
+    /// Who this capture hears, for the conversation tracker
+    party: Party,
+}
+
+impl<S: CaptureSource> JsCapture<S> {
+    fn new(mut core: CaptureCore<S>, party: Party) -> Self {
+        let events: EventSink = Arc::new(Mutex::new(None));
+        let sink = events.clone();
+        core.set_event_handler(Arc::new(move |event| emit_event(&sink, event)));
+        let conversation: ConversationSlot = Arc::new(Mutex::new(None));
+        let slot = conversation.clone();
+        core.set_speech_observer(Arc::new(move |info, samples| {
+            let shared = slot.lock().ok().and_then(|slot| slot.clone());
+            if let Some(shared) = shared {
+                let level = metrics::level_db(samples);
+                shared.push(party, info.capture_time_ms.unwrap_or_else(epoch_ms), info.voiced, level);
+            }
+        }));
+        Self {
+            core,
+            events,
+            conversation,
+            party,
+        }
+    }
+
+    /// Report speech to `shared` (replacing any previous tracker)
+    fn track_conversation(&mut self, shared: Option<Arc<SharedConversation>>) {
+        let previous = match self.conversation.lock() {
+            Ok(mut slot) => std::mem::replace(&mut *slot, shared),
+            Err(_) => return,
+        };
+        if let Some(previous) = previous {
+            previous.end(self.party);
+        }
+    }
+
+    fn configure(&mut self, opts: CaptureOptions) -> napi::Result<()> {
+        // Validate everything before applying anything
+        let delivery = options_to_delivery(self.core.delivery().config(), &opts)?;
+        let history = {
+            let current = self.core.history().lock().map(|history| history.config().clone()).unwrap_or_default();
+            options_to_history(&current, &opts)?
+        };
+        let commands = options_to_commands(self.core.settings(), opts)?;
+        self.send(commands)?;
+        if let Some(config) = delivery {
+            self.core.delivery().set_config(config);
+        }
+        if let Some(config) = history {
+            if let Ok(mut history) = self.core.history().lock() {
+                history.set_config(config);
+            }
+        }
+        Ok(())
+    }
+
+    fn get_recent(&self, env: &Env, seconds: f64, format: Option<String>) -> napi::Result<Option<JsObject>> {
+        if !seconds.is_finite() || seconds <= 0.0 {
+            return Err(napi::Error::from_reason("seconds must be positive"));
+        }
+        let format = clip_format(format)?;
+        let clip = self
+            .core
+            .history()
+            .lock()
+            .ok()
+            .and_then(|history| history.recent(Duration::from_secs_f64(seconds.min(MAX_HISTORY_SECONDS as f64))));
+        clip.map(|clip| clip_to_object(env, clip, format)).transpose()
+    }
+
+    fn get_range(&self, env: &Env, seq_start: i64, seq_end: i64, format: Option<String>) -> napi::Result<Option<JsObject>> {
+        let format = clip_format(format)?;
+        let clip = self
+            .core
+            .history()
+            .lock()
+            .ok()
+            .and_then(|history| history.range(seq_start.max(0) as u64, seq_end.max(0) as u64));
+        clip.map(|clip| clip_to_object(env, clip, format)).transpose()
+    }
+
+    fn send(&mut self, commands: Vec<DspCommand>) -> napi::Result<()> {
+        self.core.send(commands).map_err(to_napi_error)
+    }
+
+    fn on_event(&mut self, env: &Env, callback: JsFunction) -> napi::Result<()> {
+        let tsfn = create_event_tsfn(env, callback)?;
+        if let Ok(mut sink) = self.events.lock() {
+            *sink = Some(tsfn);
+        }
+        Ok(())
+    }
+
+    fn stats(&self) -> CaptureStats {
+        let stats = self.core.delivery().stats();
+        let ring = self.core.ring_totals();
+        CaptureStats {
+            delivered: stats.delivered as i64,
+            dropped: stats.dropped as i64,
+            coalesced: stats.coalesced as i64,
+            queued: stats.queued as u32,
+            lagging: stats.lagging,
+            ring_overflows: ring.overflows as i64,
+            ring_dropped_ms: ring.dropped_ms,
+        }
+    }
+
+    fn start(&mut self, env: &Env, callback: JsFunction) -> napi::Result<()> {
+        if self.core.is_running() {
+            return Ok(());
+        }
+        let tag = self.core.tag();
+        let queue = self.core.delivery().clone();
+        let callback = skip_empty_calls(env, callback)?;
+        let tsfn = create_frame_tsfn(callback, tag, queue.clone(), self.events.clone())?;
+        let events = self.events.clone();
+        // The queue's run starts with core.start(), before the first frame
+        let mut run = None;
+        queue.set_pull(false);
+        self.core
+            .start(move |frame| {
+                let run = *run.get_or_insert_with(|| queue.run());
+                let (outcome, lag) = queue.push(frame);
+                if outcome == PushOutcome::Queued {
+                    tsfn.call(run, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+                if let Some(event) = lag_event(tag, &queue, lag) {
+                    emit_event(&events, event);
+                }
+            })
+            .map(|_| ())
+            .map_err(|e| napi::Error::from_reason(format!("Failed: {}", e)))
+    }
+
+    fn start_pull(&mut self) -> napi::Result<()> {
+        if self.core.is_running() {
+            return Ok(());
+        }
+        let tag = self.core.tag();
+        let queue = self.core.delivery().clone();
+        let events = self.events.clone();
+        queue.set_pull(true);
+        self.core
+            .start(move |frame| {
+                let (_, lag) = queue.push(frame);
+                if let Some(event) = lag_event(tag, &queue, lag) {
+                    emit_event(&events, event);
+                }
+            })
+            .map(|_| ())
+            .map_err(|e| napi::Error::from_reason(format!("Failed: {}", e)))
+    }
+
+    fn start_utterances(&mut self, callback: JsFunction, opts: Option<UtteranceOptions>) -> napi::Result<()> {
+        if self.core.is_running() {
+            return Ok(());
+        }
+        let (config, format) = utterance_options(opts)?;
+        let tsfn = create_utterance_tsfn(callback, format)?;
+        self.core.delivery().set_pull(false);
+        self.core
+            .start_utterances(config, move |utterance| {
+                tsfn.call(utterance, ThreadsafeFunctionCallMode::NonBlocking);
+            })
+            .map(|_| ())
+            .map_err(|e| napi::Error::from_reason(format!("Failed: {}", e)))
+    }
+
+    fn start_transcription(&mut self, callback: JsFunction, opts: TranscriptionOptions) -> napi::Result<()> {
+        if self.core.is_running() {
+            return Ok(());
+        }
+        let (config, recognizer) = transcription_options(opts)?;
+        let recognizer = load_recognizer(&recognizer)?;
+        let tsfn = create_transcript_tsfn(callback)?;
+        let mut sink = spawn_transcriber(recognizer, config, self.core.output_channels() as usize, move |transcript| {
+            tsfn.call(transcript, ThreadsafeFunctionCallMode::NonBlocking);
+        });
+        self.core.delivery().set_pull(false);
+        self.core
+            .start(move |frame| sink.send(&frame))
+            .map(|_| ())
+            .map_err(|e| napi::Error::from_reason(format!("Failed: {}", e)))
+    }
+
+    fn read(&mut self, env: &Env, max_frames: usize) -> napi::Result<JsObject> {
+        // The queue refuses unless the last start was startPull(), running or not
+        let (deferred, promise) = env.create_deferred::<Vec<JsBuffer>, ReadResolver>()?;
+        let lag = self
+            .core
+            .delivery()
+            .read(
+                max_frames,
+                Box::new(move |frames| {
+                    deferred.resolve(Box::new(move |env| {
+                        frames.into_iter().map(|frame| frame_to_buffer(&env, frame)).collect()
+                    }))
+                }),
+            )
+            .map_err(to_napi_error)?;
+        if let Some(event) = lag_event(self.core.tag(), self.core.delivery(), lag) {
+            emit_event(&self.events, event);
+        }
+        Ok(promise)
+    }
+
+    fn stop(&mut self) {
+        self.core.stop();
+        let shared = self.conversation.lock().ok().and_then(|slot| slot.clone());
+        if let Some(shared) = shared {
+            shared.end(self.party);
+        }
+    }
+}
+
+impl<S: CaptureSource> Drop for JsCapture<S> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
 
 // ============================================================================
 // SYSTEM AUDIO CAPTURE (ScreenCaptureKit on macOS)
@@ -34,27 +926,89 @@ use crate::silence_suppression::{
 
 #[napi]
 pub struct SystemAudioCapture {
-    stop_signal: Arc<AtomicBool>,
-    capture_thread: Option<thread::JoinHandle<()>>,
+    inner: JsCapture<SystemAudioSource>,
     sample_rate: u32,
-    device_id: Option<String>,
-    input: Option<speaker::SpeakerInput>,
-    stream: Option<speaker::SpeakerStream>,
+}
+
+/// Restrict system audio capture to some applications (by process id)
+/// Set at most one of the two lists.
+#[napi(object)]
+pub struct AppFilter {
+    /// Capture only these processes
+    pub include_pids: Option<Vec<u32>>,
+    /// Capture everything except these processes
+    pub exclude_pids: Option<Vec<u32>>,
+}
+
+fn filter_to_selection(filter: Option<AppFilter>) -> napi::Result<AppSelection> {
+    let Some(filter) = filter else {
+        return Ok(AppSelection::All);
+    };
+    match (filter.include_pids, filter.exclude_pids) {
+        (Some(_), Some(_)) => Err(napi::Error::from_reason(
+            "Set either includePids or excludePids, not both",
+        )),
+        (Some(pids), None) => Ok(AppSelection::Only(pids)),
+        (None, Some(pids)) => Ok(AppSelection::Except(pids)),
+        (None, None) => Ok(AppSelection::All),
+    }
+}
+
+/// Multichannel system audio capture (fixed at construction)
+#[napi(object)]
+pub struct ChannelOptions {
+    /// Channels to record (default 1; macOS supports 1 or 2)
+    pub channels: Option<u32>,
+    /// "downmix" (default): mono frames averaged from all channels
+    /// "keep": interleaved frames with every channel (see getChannels())
+    pub channel_mode: Option<String>,
+}
+
+fn channel_options(opts: Option<ChannelOptions>) -> napi::Result<(u16, ChannelMode)> {
+    let Some(opts) = opts else {
+        return Ok((1, ChannelMode::Downmix));
+    };
+    let channels = opts.channels.unwrap_or(1);
+    if channels == 0 || channels > MAX_CHANNELS as u32 {
+        return Err(napi::Error::from_reason(format!(
+            "channels must be between 1 and {}",
+            MAX_CHANNELS
+        )));
+    }
+    let mode = match opts.channel_mode.as_deref() {
+        None => ChannelMode::Downmix,
+        Some(mode) => ChannelMode::parse(mode)
+            .ok_or_else(|| napi::Error::from_reason(format!("Unknown channelMode: {}", mode)))?,
+    };
+    Ok((channels as u16, mode))
 }
 
 #[napi]
 impl SystemAudioCapture {
     #[napi(constructor)]
-    pub fn new(device_id: Option<String>) -> napi::Result<Self> {
-        println!("[SystemAudioCapture] Created with lazy init (device: {:?})", device_id);
-        
+    pub fn new(
+        device_id: Option<String>,
+        filter: Option<AppFilter>,
+        channels: Option<ChannelOptions>,
+        ring: Option<RingBufferOptions>,
+    ) -> napi::Result<Self> {
+        let selection = filter_to_selection(filter)?;
+        let (channels, channel_mode) = channel_options(channels)?;
+        let ring = ring_options(ring)?;
+        println!(
+            "[SystemAudioCapture] Created with lazy init (device: {:?}, apps: {:?}, {}ch {:?}, ring {}ms {:?})",
+            device_id, selection, channels, channel_mode, ring.capacity_ms, ring.overflow
+        );
+
+        let mut settings = DspSettings::for_system_audio();
+        settings.channel_mode = channel_mode;
         Ok(SystemAudioCapture {
-            stop_signal: Arc::new(AtomicBool::new(false)),
-            capture_thread: None,
+            inner: JsCapture::new(CaptureCore::new(
+                "SystemAudioCapture",
+                SystemAudioSource::new(device_id, SpeakerOptions { selection, channels, ring }),
+                settings,
+            ), Party::Remote),
             sample_rate: 16000,
-            device_id,
-            input: None,
-            stream: None,
         })
     }
 
@@ -63,115 +1017,121 @@ impl SystemAudioCapture {
         self.sample_rate
     }
 
+    /// Interleaved channels per frame (1 unless channelMode is "keep";
+    /// exact once started, as the backend may not support every count)
     #[napi]
-    pub fn start(&mut self, callback: JsFunction) -> napi::Result<()> {
-        let tsfn: ThreadsafeFunction<Vec<i16>, ErrorStrategy::Fatal> = callback
-            .create_threadsafe_function(0, |ctx| {
-                let vec: Vec<i16> = ctx.value;
-                let mut pcm_bytes = Vec::with_capacity(vec.len() * 2);
-                for sample in vec {
-                    pcm_bytes.extend_from_slice(&sample.to_le_bytes());
-                }
-                Ok(vec![pcm_bytes])
-            })?;
+    pub fn get_channels(&self) -> u32 {
+        self.inner.core.output_channels() as u32
+    }
 
-        self.stop_signal.store(false, Ordering::SeqCst);
-        let stop_signal = self.stop_signal.clone();
-        
-        // Lazy init: Create SpeakerInput now
-        let input = if let Some(existing) = self.input.take() {
-            existing
-        } else {
-            println!("[SystemAudioCapture] Creating ScreenCaptureKit stream...");
-            match speaker::SpeakerInput::new(self.device_id.take()) {
-                Ok(i) => i,
-                Err(e) => {
-                    println!("[SystemAudioCapture] Failed: {}. Trying default...", e);
-                    match speaker::SpeakerInput::new(None) {
-                        Ok(i) => i,
-                        Err(e2) => return Err(napi::Error::from_reason(format!("Failed: {}", e2))),
-                    }
-                }
-            }
-        };
-        
-        let mut stream = input.stream();
-        let input_sample_rate = stream.sample_rate() as f64;
-        let mut consumer = stream.take_consumer()
-            .ok_or_else(|| napi::Error::from_reason("Failed to get consumer"))?;
-        
-        self.stream = Some(stream);
-
-        // DSP thread with silence suppression
-        self.capture_thread = Some(thread::spawn(move || {
-            let mut resampler = StreamingResampler::new(input_sample_rate, 16000.0);
-            let mut frame_buffer: Vec<i16> = Vec::with_capacity(FRAME_SAMPLES * 4);
-            let mut raw_batch: Vec<f32> = Vec::with_capacity(4096);
-            
-            // Use system audio config (lower threshold for quieter system audio)
-            let mut suppressor = SilenceSuppressor::new(
-                SilenceSuppressionConfig::for_system_audio()
-            );
-
-            println!("[SystemAudioCapture] DSP thread started (suppression active)");
-
-            loop {
-                if stop_signal.load(Ordering::Relaxed) {
-                    break;
-                }
-                
-                // 1. Drain ring buffer (lock-free)
-                let mut batch_count = 0;
-                while let Some(sample) = consumer.try_pop() {
-                    raw_batch.push(sample);
-                    batch_count += 1;
-                    if raw_batch.len() >= 480 {
-                        break;
-                    }
-                }
-                
-                // 2. Resample
-                if !raw_batch.is_empty() {
-                    let resampled = resampler.resample(&raw_batch);
-                    frame_buffer.extend(resampled);
-                    raw_batch.clear();
-                }
+    /// "idle", "running" or "stopped"
+    #[napi]
+    pub fn get_state(&self) -> String {
+        state_name(self.inner.core.state()).to_string()
+    }
 
-                // 3. Process frames with Silence Suppression
-                while frame_buffer.len() >= FRAME_SAMPLES {
-                    let frame: Vec<i16> = frame_buffer.drain(0..FRAME_SAMPLES).collect();
-                    match suppressor.process(&frame) {
-                        FrameAction::Send(audio) => {
-                             tsfn.call(audio, ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                        FrameAction::SendSilence => {
-                             tsfn.call(generate_silence_frame(FRAME_SAMPLES), ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                        FrameAction::Suppress => {
-                            // Do nothing (bandwidth saving)
-                        }
-                    }
-                }
-                
-                // 4. Short sleep
-                if frame_buffer.len() < FRAME_SAMPLES {
-                    thread::sleep(Duration::from_millis(DSP_POLL_MS));
-                }
-            }
-            
-            println!("[SystemAudioCapture] DSP thread stopped.");
-        }));
+    /// Update DSP / delivery parameters without restarting capture
+    #[napi]
+    pub fn configure(&mut self, opts: CaptureOptions) -> napi::Result<()> {
+        self.inner.configure(opts)
+    }
 
-        Ok(())
+    /// Stop emitting frames but keep the OS stream alive (instant resume)
+    #[napi]
+    pub fn pause(&mut self) -> napi::Result<()> {
+        self.inner.send(vec![DspCommand::Paused(true)])
+    }
+
+    #[napi]
+    pub fn resume(&mut self) -> napi::Result<()> {
+        self.inner.send(vec![DspCommand::Paused(false)])
+    }
+
+    #[napi]
+    pub fn is_paused(&self) -> bool {
+        self.inner.core.settings().paused
+    }
+
+    /// Zero-fill frames while keeping the stream (and keepalives) running
+    #[napi]
+    pub fn set_muted(&mut self, muted: bool) -> napi::Result<()> {
+        self.inner.send(vec![DspCommand::Muted(muted)])
+    }
+
+    /// Receive capture events (`{ type: "lagging" | "formatChanged" | "contentClass" | "speaker" | "likelyQuestion" | "keyword", ... }`)
+    #[napi]
+    pub fn on_event(&mut self, env: Env, callback: JsFunction) -> napi::Result<()> {
+        self.inner.on_event(&env, callback)
+    }
+
+    /// Frame delivery counters (dropped / coalesced frames, queue depth)
+    #[napi]
+    pub fn get_stats(&self) -> CaptureStats {
+        self.inner.stats()
+    }
+
+    /// The last `seconds` of processed audio (null if none is kept)
+    /// `format`: "wav" (default) or "pcm"
+    #[napi(ts_return_type = "AudioClip | null")]
+    pub fn get_recent(&self, env: Env, seconds: f64, format: Option<String>) -> napi::Result<Option<JsObject>> {
+        self.inner.get_recent(&env, seconds, format)
+    }
+
+    /// Processed audio of frames `seqStart..=seqEnd` still in the history
+    #[napi(ts_return_type = "AudioClip | null")]
+    pub fn get_range(&self, env: Env, seq_start: i64, seq_end: i64, format: Option<String>) -> napi::Result<Option<JsObject>> {
+        self.inner.get_range(&env, seq_start, seq_end, format)
+    }
+
+    /// Start capturing (no-op if already running, restart allowed after stop)
+    #[napi]
+    pub fn start(&mut self, env: Env, callback: JsFunction) -> napi::Result<()> {
+        self.inner.start(&env, callback)
+    }
+
+    /// Start capturing without a callback; fetch frames with read()
+    #[napi]
+    pub fn start_pull(&mut self) -> napi::Result<()> {
+        self.inner.start_pull()
+    }
+
+    /// Start capturing in utterance mode: `callback` receives whole
+    /// utterances (WAV or PCM) instead of frames
+    #[napi(ts_args_type = "callback: (utterance: Utterance) => void, options?: UtteranceOptions | undefined | null")]
+    pub fn start_utterances(&mut self, callback: JsFunction, options: Option<UtteranceOptions>) -> napi::Result<()> {
+        self.inner.start_utterances(callback, options)
+    }
+
+    /// Start capturing and transcribe on-device: `callback` receives partial
+    /// and final transcripts instead of frames (`offline-stt` builds only)
+    #[napi(ts_args_type = "callback: (transcript: Transcript) => void, options: TranscriptionOptions")]
+    pub fn start_transcription(&mut self, callback: JsFunction, options: TranscriptionOptions) -> napi::Result<()> {
+        self.inner.start_transcription(callback, options)
+    }
+
+    /// Wait for up to `maxFrames` frames (default 10)
+    /// Resolves with [] once capture is stopped and the queue is drained.
+    /// Throws unless the last start was startPull().
+    #[napi(ts_return_type = "Promise<Array<Buffer>>")]
+    pub fn read(&mut self, env: Env, max_frames: Option<u32>) -> napi::Result<JsObject> {
+        self.inner.read(&env, max_frames.unwrap_or(10) as usize)
+    }
+
+    /// Report speech to `tracker` (this capture is the "remote" party)
+    #[napi]
+    pub fn track_conversation(&mut self, tracker: &ConversationTracker) {
+        self.inner.track_conversation(Some(tracker.shared.clone()));
+    }
+
+    #[napi]
+    pub fn untrack_conversation(&mut self) {
+        self.inner.track_conversation(None);
     }
 
+    /// Stop capturing (no-op if not running)
     #[napi]
     pub fn stop(&mut self) {
-        self.stop_signal.store(true, Ordering::SeqCst);
-        if let Some(handle) = self.capture_thread.take() {
-            let _ = handle.join();
-        }
-        self.stream = None;
+        self.inner.stop();
     }
 }
 
@@ -179,19 +1139,78 @@ impl SystemAudioCapture {
 // MICROPHONE CAPTURE (CPAL)
 // ============================================================================
 
+/// A phrase to spot and its enrollment recordings
+#[napi(object)]
+pub struct KeywordDefinition {
+    /// Reported as `keyword` on matches
+    pub keyword: String,
+    /// Recordings of the user saying the phrase (16kHz mono 16-bit PCM,
+    /// 200ms..3s of sound; leading / trailing silence is trimmed)
+    pub templates: Vec<Buffer>,
+}
+
+/// Keyword spotting on the microphone (`setKeywordSpotting()`)
+#[napi(object)]
+pub struct KeywordSpottingOptions {
+    pub keywords: Vec<KeywordDefinition>,
+    /// Largest match distance (default 0.5; lower is stricter)
+    pub max_distance: Option<f64>,
+    /// Replace matched phrases with silence before frames are delivered
+    /// (adds up to 2x the longest template of latency, default false)
+    pub suppress: Option<bool>,
+}
+
+fn keyword_config(opts: KeywordSpottingOptions) -> napi::Result<KeywordConfig> {
+    let mut config = KeywordConfig::default();
+    if let Some(distance) = opts.max_distance {
+        if !distance.is_finite() || distance <= 0.0 {
+            return Err(napi::Error::from_reason("maxDistance must be positive"));
+        }
+        config.max_distance = distance as f32;
+    }
+    config.suppress = opts.suppress.unwrap_or(false);
+    for definition in opts.keywords {
+        if definition.templates.is_empty() {
+            return Err(napi::Error::from_reason(format!("Keyword \"{}\" has no templates", definition.keyword)));
+        }
+        let mut templates = Vec::with_capacity(definition.templates.len());
+        for (i, buffer) in definition.templates.iter().enumerate() {
+            let bytes: &[u8] = buffer;
+            if !bytes.len().is_multiple_of(2) {
+                return Err(napi::Error::from_reason(format!(
+                    "Template {} of \"{}\" is not 16-bit PCM",
+                    i, definition.keyword
+                )));
+            }
+            let samples: Vec<i16> = bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+            let template = Template::from_audio(&samples).ok_or_else(|| {
+                napi::Error::from_reason(format!(
+                    "Template {} of \"{}\" holds less than 200ms of sound",
+                    i, definition.keyword
+                ))
+            })?;
+            templates.push(template);
+        }
+        config.keywords.push(Keyword {
+            name: definition.keyword,
+            templates,
+        });
+    }
+    Ok(config)
+}
+
 #[napi]
 pub struct MicrophoneCapture {
-    stop_signal: Arc<AtomicBool>,
-    capture_thread: Option<thread::JoinHandle<()>>,
+    inner: JsCapture<microphone::MicrophoneStream>,
     sample_rate: u32,
-    input: Option<microphone::MicrophoneStream>,
 }
 
 #[napi]
 impl MicrophoneCapture {
     #[napi(constructor)]
-    pub fn new(device_id: Option<String>) -> napi::Result<Self> {
-        let input = match microphone::MicrophoneStream::new(device_id) {
+    pub fn new(device_id: Option<String>, ring: Option<RingBufferOptions>) -> napi::Result<Self> {
+        let ring = ring_options(ring)?;
+        let input = match microphone::MicrophoneStream::with_ring(device_id, ring) {
             Ok(i) => i,
             Err(e) => return Err(napi::Error::from_reason(format!("Failed: {}", e))),
         };
@@ -199,10 +1218,11 @@ impl MicrophoneCapture {
         let sample_rate = 16000;
 
         Ok(MicrophoneCapture {
-            stop_signal: Arc::new(AtomicBool::new(false)),
-            capture_thread: None,
+            inner: JsCapture::new(
+                CaptureCore::new("MicrophoneCapture", input, DspSettings::for_microphone()),
+                Party::User,
+            ),
             sample_rate,
-            input: Some(input),
         })
     }
 
@@ -211,105 +1231,372 @@ impl MicrophoneCapture {
         self.sample_rate
     }
 
+    /// "idle", "running" or "stopped"
+    #[napi]
+    pub fn get_state(&self) -> String {
+        state_name(self.inner.core.state()).to_string()
+    }
+
+    /// Update DSP / delivery parameters without restarting capture
+    #[napi]
+    pub fn configure(&mut self, opts: CaptureOptions) -> napi::Result<()> {
+        self.inner.configure(opts)
+    }
+
+    /// Stop emitting frames but keep the OS stream alive (instant resume)
+    #[napi]
+    pub fn pause(&mut self) -> napi::Result<()> {
+        self.inner.send(vec![DspCommand::Paused(true)])
+    }
+
+    #[napi]
+    pub fn resume(&mut self) -> napi::Result<()> {
+        self.inner.send(vec![DspCommand::Paused(false)])
+    }
+
+    #[napi]
+    pub fn is_paused(&self) -> bool {
+        self.inner.core.settings().paused
+    }
+
+    /// Zero-fill frames while keeping the stream (and keepalives) running
+    #[napi]
+    pub fn set_muted(&mut self, muted: bool) -> napi::Result<()> {
+        self.inner.send(vec![DspCommand::Muted(muted)])
+    }
+
+    /// Spot enrolled phrases (raised as `keyword` events), or stop with null
+    #[napi]
+    pub fn set_keyword_spotting(&mut self, options: Option<KeywordSpottingOptions>) -> napi::Result<()> {
+        let config = options.map(keyword_config).transpose()?;
+        self.inner.send(vec![DspCommand::Keywords(config)])
+    }
+
+    /// Receive capture events (`{ type: "lagging" | "formatChanged" | "contentClass" | "speaker" | "likelyQuestion" | "keyword", ... }`)
+    #[napi]
+    pub fn on_event(&mut self, env: Env, callback: JsFunction) -> napi::Result<()> {
+        self.inner.on_event(&env, callback)
+    }
+
+    /// Frame delivery counters (dropped / coalesced frames, queue depth)
+    #[napi]
+    pub fn get_stats(&self) -> CaptureStats {
+        self.inner.stats()
+    }
+
+    /// The last `seconds` of processed audio (null if none is kept)
+    /// `format`: "wav" (default) or "pcm"
+    #[napi(ts_return_type = "AudioClip | null")]
+    pub fn get_recent(&self, env: Env, seconds: f64, format: Option<String>) -> napi::Result<Option<JsObject>> {
+        self.inner.get_recent(&env, seconds, format)
+    }
+
+    /// Processed audio of frames `seqStart..=seqEnd` still in the history
+    #[napi(ts_return_type = "AudioClip | null")]
+    pub fn get_range(&self, env: Env, seq_start: i64, seq_end: i64, format: Option<String>) -> napi::Result<Option<JsObject>> {
+        self.inner.get_range(&env, seq_start, seq_end, format)
+    }
+
+    /// Start capturing (no-op if already running, restart allowed after stop)
     #[napi]
-    pub fn start(&mut self, callback: JsFunction) -> napi::Result<()> {
-        let tsfn: ThreadsafeFunction<Vec<i16>, ErrorStrategy::Fatal> = callback
-            .create_threadsafe_function(0, |ctx| {
-                let vec: Vec<i16> = ctx.value;
-                let mut pcm_bytes = Vec::with_capacity(vec.len() * 2);
-                for sample in vec {
-                    pcm_bytes.extend_from_slice(&sample.to_le_bytes());
+    pub fn start(&mut self, env: Env, callback: JsFunction) -> napi::Result<()> {
+        self.inner.start(&env, callback)
+    }
+
+    /// Start capturing without a callback; fetch frames with read()
+    #[napi]
+    pub fn start_pull(&mut self) -> napi::Result<()> {
+        self.inner.start_pull()
+    }
+
+    /// Start capturing in utterance mode: `callback` receives whole
+    /// utterances (WAV or PCM) instead of frames
+    #[napi(ts_args_type = "callback: (utterance: Utterance) => void, options?: UtteranceOptions | undefined | null")]
+    pub fn start_utterances(&mut self, callback: JsFunction, options: Option<UtteranceOptions>) -> napi::Result<()> {
+        self.inner.start_utterances(callback, options)
+    }
+
+    /// Start capturing and transcribe on-device: `callback` receives partial
+    /// and final transcripts instead of frames (`offline-stt` builds only)
+    #[napi(ts_args_type = "callback: (transcript: Transcript) => void, options: TranscriptionOptions")]
+    pub fn start_transcription(&mut self, callback: JsFunction, options: TranscriptionOptions) -> napi::Result<()> {
+        self.inner.start_transcription(callback, options)
+    }
+
+    /// Wait for up to `maxFrames` frames (default 10)
+    /// Resolves with [] once capture is stopped and the queue is drained.
+    /// Throws unless the last start was startPull().
+    #[napi(ts_return_type = "Promise<Array<Buffer>>")]
+    pub fn read(&mut self, env: Env, max_frames: Option<u32>) -> napi::Result<JsObject> {
+        self.inner.read(&env, max_frames.unwrap_or(10) as usize)
+    }
+
+    /// Report speech to `tracker` (this capture is the "user" party)
+    #[napi]
+    pub fn track_conversation(&mut self, tracker: &ConversationTracker) {
+        self.inner.track_conversation(Some(tracker.shared.clone()));
+    }
+
+    #[napi]
+    pub fn untrack_conversation(&mut self) {
+        self.inner.track_conversation(None);
+    }
+
+    /// Stop capturing (no-op if not running)
+    #[napi]
+    pub fn stop(&mut self) {
+        self.inner.stop();
+    }
+}
+
+// ============================================================================
+// CONVERSATION TRACKING (microphone vs system audio)
+// ============================================================================
+
+/// Conversation tracking thresholds (`new ConversationTracker()`)
+#[napi(object)]
+pub struct ConversationOptions {
+    /// Speech shorter than this is ignored (default 200)
+    pub min_speech_ms: Option<u32>,
+    /// Silence that ends a party's speech (default 300)
+    pub release_ms: Option<u32>,
+    /// Shortest simultaneous speech reported as an overlap (default 300)
+    pub min_overlap_ms: Option<u32>,
+    /// Shortest silence within a party's speech counted as a pause (default 500)
+    pub min_pause_ms: Option<u32>,
+    /// Period of the "sessionMetrics" event (default 30000, 0 = never)
+    pub metrics_interval_ms: Option<u32>,
+}
+
+fn conversation_options(opts: Option<ConversationOptions>) -> napi::Result<ConversationConfig> {
+    let mut config = ConversationConfig::default();
+    let Some(opts) = opts else {
+        return Ok(config);
+    };
+    if let Some(ms) = opts.min_speech_ms {
+        config.min_speech = Duration::from_millis(ms as u64);
+    }
+    if let Some(ms) = opts.release_ms {
+        if ms < FRAME_MS {
+            return Err(napi::Error::from_reason(format!("releaseMs must be at least {}", FRAME_MS)));
+        }
+        config.release = Duration::from_millis(ms as u64);
+    }
+    if let Some(ms) = opts.min_overlap_ms {
+        config.min_overlap = Duration::from_millis(ms as u64);
+    }
+    if let Some(ms) = opts.min_pause_ms {
+        config.min_pause = Duration::from_millis(ms as u64);
+    }
+    if let Some(ms) = opts.metrics_interval_ms {
+        config.metrics_interval = (ms > 0).then(|| Duration::from_millis(ms as u64));
+    }
+    Ok(config)
+}
+
+/// One party's share of a session (see metrics.rs)
+#[napi(object)]
+pub struct PartyMetrics {
+    pub talk_time_ms: f64,
+    /// Times the party took the floor
+    pub turns: u32,
+    /// Longest stretch holding the floor
+    pub longest_monologue_ms: f64,
+    pub syllables: u32,
+    /// Syllables per second of talk time
+    pub syllable_rate: f64,
+}
+
+/// Conversation analytics since the tracker was created / reset
+#[napi(object)]
+pub struct SessionMetrics {
+    pub duration_ms: f64,
+    pub user: PartyMetrics,
+    pub remote: PartyMetrics,
+    /// User share of the total talk time (0..1)
+    pub user_talk_ratio: f64,
+    pub pauses: u32,
+    pub pause_total_ms: f64,
+    pub longest_pause_ms: f64,
+    /// User turns following the remote party
+    pub responses: u32,
+    /// From the remote party's last speech to the user's answer
+    pub mean_response_latency_ms: Option<f64>,
+    pub median_response_latency_ms: Option<f64>,
+    pub overlaps: u32,
+    pub overlap_total_ms: f64,
+    pub interruptions: u32,
+}
+
+fn party_metrics(party: &metrics::PartyMetrics) -> PartyMetrics {
+    PartyMetrics {
+        talk_time_ms: party.talk_time_ms,
+        turns: party.turns,
+        longest_monologue_ms: party.longest_monologue_ms,
+        syllables: party.syllables,
+        syllable_rate: party.syllable_rate,
+    }
+}
+
+fn session_metrics(summary: &metrics::SessionSummary) -> SessionMetrics {
+    SessionMetrics {
+        duration_ms: summary.duration_ms,
+        user: party_metrics(&summary.user),
+        remote: party_metrics(&summary.remote),
+        user_talk_ratio: summary.user_talk_ratio,
+        pauses: summary.pauses,
+        pause_total_ms: summary.pause_total_ms,
+        longest_pause_ms: summary.longest_pause_ms,
+        responses: summary.responses,
+        mean_response_latency_ms: summary.mean_response_latency_ms,
+        median_response_latency_ms: summary.median_response_latency_ms,
+        overlaps: summary.overlaps,
+        overlap_total_ms: summary.overlap_total_ms,
+        interruptions: summary.interruptions,
+    }
+}
+
+/// Tracker of one conversation, fed by both captures' DSP threads
+struct SharedConversation {
+    tracker: Mutex<conversation::ConversationTracker>,
+    sink: Mutex<Option<ThreadsafeFunction<ConversationEvent, ErrorStrategy::Fatal>>>,
+}
+
+/// Tracker a capture reports to (set with trackConversation())
+type ConversationSlot = Arc<Mutex<Option<Arc<SharedConversation>>>>;
+
+impl SharedConversation {
+    fn push(&self, party: Party, time_ms: f64, voiced: bool, level_db: f32) {
+        let events = match self.tracker.lock() {
+            Ok(mut tracker) => tracker.push(party, time_ms, voiced, level_db),
+            Err(_) => return,
+        };
+        self.emit(events);
+    }
+
+    fn end(&self, party: Party) {
+        let events = match self.tracker.lock() {
+            Ok(mut tracker) => tracker.end(party),
+            Err(_) => return,
+        };
+        self.emit(events);
+    }
+
+    fn emit(&self, events: Vec<ConversationEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        if let Ok(sink) = self.sink.lock() {
+            if let Some(tsfn) = sink.as_ref() {
+                for event in events {
+                    tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
                 }
-                Ok(vec![pcm_bytes])
-            })?;
+            }
+        }
+    }
+}
 
-        self.stop_signal.store(false, Ordering::SeqCst);
-        let stop_signal = self.stop_signal.clone();
-        
-        let input_ref = self.input.as_mut()
-            .ok_or_else(|| napi::Error::from_reason("Input missing"))?;
-        
-        input_ref.play().map_err(|e| napi::Error::from_reason(format!("{}", e)))?;
-        
-        let input_sample_rate = input_ref.sample_rate() as f64;
-        let mut consumer = input_ref.take_consumer()
-            .ok_or_else(|| napi::Error::from_reason("Failed to get consumer"))?;
-
-        // DSP thread with silence suppression
-        self.capture_thread = Some(thread::spawn(move || {
-            let mut resampler = StreamingResampler::new(input_sample_rate, 16000.0);
-            let mut frame_buffer: Vec<i16> = Vec::with_capacity(FRAME_SAMPLES * 4);
-            let mut raw_batch: Vec<f32> = Vec::with_capacity(4096);
-            
-            // Use microphone config (standard threshold)
-            let mut suppressor = SilenceSuppressor::new(
-                SilenceSuppressionConfig::for_microphone()
-            );
-
-            println!("[MicrophoneCapture] DSP thread started (suppression active)");
-
-            loop {
-                if stop_signal.load(Ordering::Relaxed) {
-                    break;
+/// Overlap / interruption / turn detection and session metrics between a
+/// MicrophoneCapture (the user) and a SystemAudioCapture (the remote side)
+#[napi]
+pub struct ConversationTracker {
+    shared: Arc<SharedConversation>,
+}
+
+#[napi]
+impl ConversationTracker {
+    #[napi(constructor)]
+    pub fn new(options: Option<ConversationOptions>) -> napi::Result<Self> {
+        let config = conversation_options(options)?;
+        println!(
+            "[ConversationTracker] Created (min speech {:?}, release {:?}, min overlap {:?}, metrics every {:?})",
+            config.min_speech, config.release, config.min_overlap, config.metrics_interval
+        );
+        Ok(ConversationTracker {
+            shared: Arc::new(SharedConversation {
+                tracker: Mutex::new(conversation::ConversationTracker::new(config)),
+                sink: Mutex::new(None),
+            }),
+        })
+    }
+
+    /// Receive `{ type: "overlapStart" | "overlapEnd" | "userInterrupted" | "turnTaken", timeMs, ... }`
+    /// and `{ type: "sessionMetrics", timeMs, metrics }`
+    #[napi(ts_args_type = "callback: (event: ConversationEvent) => void")]
+    pub fn on_event(&mut self, env: Env, callback: JsFunction) -> napi::Result<()> {
+        let mut tsfn = callback.create_threadsafe_function(0, |ctx| {
+            let event: ConversationEvent = ctx.value;
+            let mut obj = ctx.env.create_object()?;
+            obj.set("type", event.name())?;
+            match event {
+                ConversationEvent::OverlapStart { time_ms, by } => {
+                    obj.set("timeMs", time_ms)?;
+                    obj.set("by", by.name())?;
                 }
-                
-                // 1. Drain ring buffer (lock-free)
-                let mut batch_count = 0;
-                while let Some(sample) = consumer.try_pop() {
-                    raw_batch.push(sample);
-                    batch_count += 1;
-                    if raw_batch.len() >= 480 {
-                        break;
-                    }
+                ConversationEvent::OverlapEnd { time_ms, duration_ms, remaining } => {
+                    obj.set("timeMs", time_ms)?;
+                    obj.set("durationMs", duration_ms)?;
+                    obj.set("remaining", remaining.map(|party| party.name()))?;
                 }
-                
-                // 2. Resample
-                if !raw_batch.is_empty() {
-                    let resampled = resampler.resample(&raw_batch);
-                    frame_buffer.extend(resampled);
-                    raw_batch.clear();
+                ConversationEvent::UserInterrupted { time_ms } => {
+                    obj.set("timeMs", time_ms)?;
                 }
-
-                // 3. Process frames with Silence Suppression
-                while frame_buffer.len() >= FRAME_SAMPLES {
-                    let frame: Vec<i16> = frame_buffer.drain(0..FRAME_SAMPLES).collect();
-                    match suppressor.process(&frame) {
-                        FrameAction::Send(audio) => {
-                             tsfn.call(audio, ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                        FrameAction::SendSilence => {
-                             tsfn.call(generate_silence_frame(FRAME_SAMPLES), ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                         FrameAction::Suppress => {
-                            // Do nothing
-                        }
-                    }
+                ConversationEvent::TurnTaken { party, time_ms, gap_ms } => {
+                    obj.set("party", party.name())?;
+                    obj.set("timeMs", time_ms)?;
+                    obj.set("gapMs", gap_ms)?;
                 }
-                
-                // 4. Short sleep
-                if frame_buffer.len() < FRAME_SAMPLES {
-                    thread::sleep(Duration::from_millis(DSP_POLL_MS));
+                ConversationEvent::Metrics { time_ms, summary } => {
+                    obj.set("timeMs", time_ms)?;
+                    obj.set("metrics", session_metrics(&summary))?;
                 }
             }
-            
-            println!("[MicrophoneCapture] DSP thread stopped.");
-        }));
-
+            Ok(vec![obj])
+        })?;
+        // Listening for events must not keep the process alive
+        tsfn.unref(&env)?;
+        if let Ok(mut sink) = self.shared.sink.lock() {
+            *sink = Some(tsfn);
+        }
         Ok(())
     }
 
+    /// Party that spoke alone last: "user", "remote" or null
+    #[napi(ts_return_type = "string | null")]
+    pub fn get_floor(&self) -> Option<&'static str> {
+        self.shared.tracker.lock().ok()?.floor().map(|party| party.name())
+    }
+
+    /// Both parties are talking right now
     #[napi]
-    pub fn stop(&mut self) {
-        self.stop_signal.store(true, Ordering::SeqCst);
-        if let Some(handle) = self.capture_thread.take() {
-            let _ = handle.join();
-        }
-        if let Some(input) = self.input.as_ref() {
-            let _ = input.pause();
+    pub fn is_overlapping(&self) -> bool {
+        self.shared.tracker.lock().is_ok_and(|tracker| tracker.is_overlapping())
+    }
+
+    /// Talk time, monologues, pauses, response latency and speaking rate so far
+    #[napi]
+    pub fn get_session_metrics(&self) -> napi::Result<SessionMetrics> {
+        let tracker = self.shared.tracker.lock().map_err(|_| napi::Error::from_reason("Conversation tracker poisoned"))?;
+        Ok(session_metrics(&tracker.metrics()))
+    }
+
+    /// Start a new session (e.g. the next interview)
+    #[napi]
+    pub fn reset_session_metrics(&self) {
+        if let Ok(mut tracker) = self.shared.tracker.lock() {
+            tracker.reset_metrics();
         }
     }
 }
 
+fn state_name(state: CaptureState) -> &'static str {
+    match state {
+        CaptureState::Idle => "idle",
+        CaptureState::Running => "running",
+        CaptureState::Stopped => "stopped",
+    }
+}
+
 // ============================================================================
 // DEVICE ENUMERATION
 // ============================================================================
@@ -345,3 +1632,23 @@ pub fn get_output_devices() -> Vec<AudioDeviceInfo> {
         }
     }
 }
+
+#[napi(object)]
+pub struct AudioApplicationInfo {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// Applications currently producing audio (for SystemAudioCapture's AppFilter)
+#[napi]
+pub fn list_audio_applications() -> Vec<AudioApplicationInfo> {
+    match speaker::list_audio_applications() {
+        Ok(apps) => apps.into_iter()
+            .map(|app| AudioApplicationInfo { pid: app.pid, name: app.name })
+            .collect(),
+        Err(e) => {
+            eprintln!("[list_audio_applications] Error: {}", e);
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,315 @@
+// Session Metrics - talk time, monologues, pauses, latency, speaking rate
+//
+// Accumulated by the ConversationTracker (conversation.rs) from the same
+// per-frame speech state of both captures, so the numbers agree with the
+// turn / overlap events:
+// - Talk time: sum of each party's speech (first voiced frame to last)
+// - Monologue: a party holding the floor, from taking it to its last speech
+//   before the other party takes it (its own pauses included)
+// - Pause: silence of at least `min_pause` between two stretches of speech of
+//   the same party, nobody else talking in between
+// - Response latency: from the remote party's last speech to the user taking
+//   the turn (negative when the user started early / interrupted)
+// - Speaking rate: syllable nuclei per second of talk time. A nucleus is a
+//   peak of the frame's low-band level (vowels; fricatives are mostly above
+//   it) that stands out by SYLLABLE_PROMINENCE_DB from the dips on both
+//   sides (syllables are ~150-300ms, frames 20ms)
+
+use crate::audio_config::FRAME_MS;
+use crate::conversation::{ConversationEvent, Party};
+
+/// Level rise and fall (dB) around a syllable nucleus
+const SYLLABLE_PROMINENCE_DB: f32 = 3.0;
+
+/// Level used for frames without speech (closes the last syllable)
+const SILENCE_LEVEL_DB: f32 = 0.0;
+
+/// Moving average length of the low band (first null at 2kHz)
+const LOW_BAND_TAPS: usize = 8;
+
+/// Counts level peaks (hysteresis peak picking)
+#[derive(Debug, Clone, Copy, Default)]
+struct SyllableCounter {
+    rising: bool,
+    /// Highest level since the last dip (rising) / lowest since the last peak
+    extreme: f32,
+    count: u32,
+}
+
+impl SyllableCounter {
+    fn push(&mut self, level_db: f32) {
+        if self.rising {
+            if level_db > self.extreme {
+                self.extreme = level_db;
+            } else if level_db < self.extreme - SYLLABLE_PROMINENCE_DB {
+                self.count += 1;
+                self.rising = false;
+                self.extreme = level_db;
+            }
+        } else if level_db < self.extreme {
+            self.extreme = level_db;
+        } else if level_db > self.extreme + SYLLABLE_PROMINENCE_DB {
+            self.rising = true;
+            self.extreme = level_db;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PartyTally {
+    talk_ms: f64,
+    turns: u32,
+    longest_monologue_ms: f64,
+    /// End of the last finished speech
+    last_end_ms: Option<f64>,
+    syllables: SyllableCounter,
+}
+
+/// Per-party numbers of a SessionSummary
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PartyMetrics {
+    pub talk_time_ms: f64,
+    pub turns: u32,
+    pub longest_monologue_ms: f64,
+    pub syllables: u32,
+    /// Syllables per second of talk time (0 without speech)
+    pub syllable_rate: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionSummary {
+    /// First to last frame seen
+    pub duration_ms: f64,
+    pub user: PartyMetrics,
+    pub remote: PartyMetrics,
+    /// User share of the total talk time (0..1, 0 without speech)
+    pub user_talk_ratio: f64,
+    pub pauses: u32,
+    pub pause_total_ms: f64,
+    pub longest_pause_ms: f64,
+    pub responses: u32,
+    pub mean_response_latency_ms: Option<f64>,
+    pub median_response_latency_ms: Option<f64>,
+    pub overlaps: u32,
+    pub overlap_total_ms: f64,
+    pub interruptions: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SessionMetrics {
+    min_pause_ms: f64,
+    first_ms: Option<f64>,
+    last_ms: f64,
+    parties: [PartyTally; 2],
+    /// Floor holder, since when, and the end of its last speech
+    turn: Option<(Party, f64, f64)>,
+    pauses: u32,
+    pause_total_ms: f64,
+    longest_pause_ms: f64,
+    latencies: Vec<f64>,
+    overlaps: u32,
+    overlap_total_ms: f64,
+    interruptions: u32,
+}
+
+impl SessionMetrics {
+    pub fn new(min_pause_ms: f64) -> Self {
+        Self {
+            min_pause_ms,
+            ..Self::default()
+        }
+    }
+
+    /// Every processed frame (`level_db`: see `level_db()`)
+    pub fn frame(&mut self, party: Party, time_ms: f64, voiced: bool, level_db: f32) {
+        self.first_ms.get_or_insert(time_ms);
+        self.last_ms = self.last_ms.max(time_ms + FRAME_MS as f64);
+        let level = if voiced { level_db } else { SILENCE_LEVEL_DB };
+        self.parties[party.index()].syllables.push(level);
+    }
+
+    /// `party` finished speaking `start_ms..end_ms`; `other_since` is when
+    /// the other party's ongoing speech started (if it is talking)
+    pub fn speech(&mut self, party: Party, start_ms: f64, end_ms: f64, other_since: Option<f64>) {
+        let other_end = self.parties[party.other().index()].last_end_ms;
+        let tally = &mut self.parties[party.index()];
+        tally.talk_ms += end_ms - start_ms;
+        if let Some(previous) = tally.last_end_ms {
+            let gap = start_ms - previous;
+            let others_quiet = other_end.is_none_or(|end| end <= previous) && other_since.is_none_or(|since| since >= start_ms);
+            if gap >= self.min_pause_ms && others_quiet {
+                self.pauses += 1;
+                self.pause_total_ms += gap;
+                self.longest_pause_ms = self.longest_pause_ms.max(gap);
+            }
+        }
+        tally.last_end_ms = Some(end_ms);
+
+        if let Some((holder, _, last_end)) = self.turn.as_mut() {
+            if *holder == party {
+                *last_end = last_end.max(end_ms);
+            }
+        }
+    }
+
+    /// Events raised by the tracker
+    pub fn event(&mut self, event: &ConversationEvent) {
+        match *event {
+            ConversationEvent::TurnTaken { party, time_ms, gap_ms } => {
+                self.close_turn();
+                self.turn = Some((party, time_ms, time_ms));
+                self.parties[party.index()].turns += 1;
+                if party == Party::User {
+                    if let Some(gap) = gap_ms {
+                        self.latencies.push(gap);
+                    }
+                }
+            }
+            ConversationEvent::OverlapEnd { duration_ms, .. } => {
+                self.overlaps += 1;
+                self.overlap_total_ms += duration_ms;
+            }
+            ConversationEvent::UserInterrupted { .. } => self.interruptions += 1,
+            ConversationEvent::OverlapStart { .. } | ConversationEvent::Metrics { .. } => {}
+        }
+    }
+
+    fn close_turn(&mut self) {
+        if let Some((holder, start, end)) = self.turn.take() {
+            let tally = &mut self.parties[holder.index()];
+            tally.longest_monologue_ms = tally.longest_monologue_ms.max(end - start);
+        }
+    }
+
+    pub fn summary(&self) -> SessionSummary {
+        let party = |p: Party| {
+            let tally = &self.parties[p.index()];
+            let mut longest = tally.longest_monologue_ms;
+            if let Some((holder, start, end)) = self.turn {
+                if holder == p {
+                    longest = longest.max(end - start);
+                }
+            }
+            PartyMetrics {
+                talk_time_ms: tally.talk_ms,
+                turns: tally.turns,
+                longest_monologue_ms: longest,
+                syllables: tally.syllables.count,
+                syllable_rate: if tally.talk_ms > 0.0 {
+                    tally.syllables.count as f64 / (tally.talk_ms / 1000.0)
+                } else {
+                    0.0
+                },
+            }
+        };
+        let user = party(Party::User);
+        let remote = party(Party::Remote);
+        let talk = user.talk_time_ms + remote.talk_time_ms;
+
+        let mut sorted = self.latencies.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = match sorted.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(sorted[n / 2]),
+            n => Some((sorted[n / 2 - 1] + sorted[n / 2]) / 2.0),
+        };
+
+        SessionSummary {
+            duration_ms: self.first_ms.map_or(0.0, |first| self.last_ms - first),
+            user,
+            remote,
+            user_talk_ratio: if talk > 0.0 { user.talk_time_ms / talk } else { 0.0 },
+            pauses: self.pauses,
+            pause_total_ms: self.pause_total_ms,
+            longest_pause_ms: self.longest_pause_ms,
+            responses: sorted.len() as u32,
+            mean_response_latency_ms: (!sorted.is_empty()).then(|| sorted.iter().sum::<f64>() / sorted.len() as f64),
+            median_response_latency_ms: median,
+            overlaps: self.overlaps,
+            overlap_total_ms: self.overlap_total_ms,
+            interruptions: self.interruptions,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.min_pause_ms);
+    }
+}
+
+/// Low-band (below ~1.5kHz) frame level in dB, i16 scale (0 for silence)
+pub fn level_db(frame: &[i16]) -> f32 {
+    if frame.len() < LOW_BAND_TAPS {
+        return 0.0;
+    }
+    let energy = frame
+        .windows(LOW_BAND_TAPS)
+        .map(|taps| (taps.iter().map(|&s| s as f32).sum::<f32>() / LOW_BAND_TAPS as f32).powi(2))
+        .sum::<f32>()
+        / (frame.len() - LOW_BAND_TAPS + 1) as f32;
+    10.0 * (energy + 1.0).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classifier::test_signals::{frames, speech};
+
+    #[test]
+    fn test_syllable_rate_of_speech() {
+        let mut metrics = SessionMetrics::new(500.0);
+        let signal = speech(10.0, 5);
+        let mut t = 0.0;
+        for frame in frames(&signal) {
+            let level = level_db(&frame);
+            metrics.frame(Party::Remote, t, level > 50.0, level);
+            t += FRAME_MS as f64;
+        }
+        metrics.speech(Party::Remote, 0.0, t, None);
+        let remote = metrics.summary().remote;
+        // test_signals::speech: 120-260ms vowels and 50-150ms pauses (half
+        // of them fricatives, which must not count), ~3.4 per second
+        assert!((3.0..=4.0).contains(&remote.syllable_rate), "{:?}", remote);
+    }
+
+    #[test]
+    fn test_pauses_only_within_a_party() {
+        let mut metrics = SessionMetrics::new(500.0);
+        metrics.speech(Party::Remote, 0.0, 1000.0, None);
+        // 800ms hesitation: a pause
+        metrics.speech(Party::Remote, 1800.0, 3000.0, None);
+        // 200ms: articulation, not a pause
+        metrics.speech(Party::Remote, 3200.0, 4000.0, None);
+        // Remote answered by the user in between: not a pause of the remote
+        metrics.speech(Party::User, 5000.0, 6000.0, None);
+        metrics.speech(Party::Remote, 7000.0, 8000.0, None);
+        let summary = metrics.summary();
+        assert_eq!(summary.pauses, 1);
+        assert_eq!(summary.longest_pause_ms, 800.0);
+        assert_eq!(summary.remote.talk_time_ms, 4000.0);
+        assert_eq!(summary.user_talk_ratio, 0.2);
+    }
+
+    #[test]
+    fn test_monologues_and_latency() {
+        let mut metrics = SessionMetrics::new(500.0);
+        let turn = |party, time_ms, gap_ms| ConversationEvent::TurnTaken { party, time_ms, gap_ms };
+        metrics.event(&turn(Party::Remote, 0.0, None));
+        metrics.speech(Party::Remote, 0.0, 4000.0, None);
+        metrics.speech(Party::Remote, 5000.0, 9000.0, None);
+        metrics.event(&turn(Party::User, 9600.0, Some(600.0)));
+        metrics.speech(Party::User, 9600.0, 12_000.0, None);
+        metrics.event(&turn(Party::Remote, 12_500.0, Some(500.0)));
+        metrics.speech(Party::Remote, 12_500.0, 13_000.0, None);
+        metrics.event(&turn(Party::User, 12_800.0, Some(-200.0)));
+        metrics.speech(Party::User, 12_800.0, 15_000.0, None);
+
+        let summary = metrics.summary();
+        assert_eq!(summary.remote.longest_monologue_ms, 9000.0);
+        assert_eq!(summary.user.longest_monologue_ms, 2400.0);
+        assert_eq!(summary.remote.turns, 2);
+        assert_eq!(summary.user.turns, 2);
+        assert_eq!(summary.responses, 2);
+        assert_eq!(summary.mean_response_latency_ms, Some(200.0));
+        assert_eq!(summary.median_response_latency_ms, Some(200.0));
+    }
+}
@@ -4,15 +4,18 @@
 // 1. CPAL callback: ONLY pushes to lock-free ring buffer
 // 2. No mutexes, allocations, or DSP in callback
 // 3. Background thread: drains buffer, resamples, emits to JS
+//    (woken by the callback once a frame's worth of samples is buffered)
+// 4. Each callback stamps its first sample with the capture time CPAL
+//    reports (InputCallbackInfo), converted to ms since the Unix epoch
 
 use anyhow::Result;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleFormat, Stream};
-use ringbuf::{traits::{Producer, Consumer, Split}, HeapRb, HeapProd, HeapCons};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::audio_config::RING_BUFFER_SAMPLES;
+use crate::clock::epoch_ms_ago;
+use crate::sample_ring::{AudioFormat, RingConfig, SampleConsumer, SampleProducer};
 
 /// List available input devices
 pub fn list_input_devices() -> Result<Vec<(String, String)>> {
@@ -33,16 +36,20 @@ pub fn list_input_devices() -> Result<Vec<(String, String)>> {
 /// Lock-free microphone stream
 /// 
 /// Callback pushes raw f32 samples to ring buffer.
-/// Consumer is polled by DSP thread.
+/// Consumer is drained by DSP thread.
 pub struct MicrophoneStream {
     stream: Option<Stream>,
-    consumer: Option<HeapCons<f32>>,
+    consumer: Option<SampleConsumer>,
     sample_rate: u32,
     is_running: Arc<AtomicBool>,
 }
 
 impl MicrophoneStream {
-    pub fn new(_device_id: Option<String>) -> Result<Self> {
+    pub fn new(device_id: Option<String>) -> Result<Self> {
+        Self::with_ring(device_id, RingConfig::default())
+    }
+
+    pub fn with_ring(_device_id: Option<String>, ring: RingConfig) -> Result<Self> {
         let host = cpal::default_host();
         let device = host.default_input_device()
             .ok_or_else(|| anyhow::anyhow!("No input device found"))?;
@@ -61,9 +68,8 @@ impl MicrophoneStream {
             config.sample_format()
         );
         
-        // Create lock-free SPSC ring buffer
-        let rb = HeapRb::<f32>::new(RING_BUFFER_SAMPLES);
-        let (producer, consumer) = rb.split();
+        // Create lock-free SPSC ring buffer (the callback pushes mono)
+        let (producer, consumer) = ring.ring(AudioFormat { sample_rate, channels: 1 });
         
         let is_running = Arc::new(AtomicBool::new(false));
         let is_running_clone = is_running.clone();
@@ -111,9 +117,16 @@ impl MicrophoneStream {
     }
 
     /// Take ownership of the consumer for the DSP thread
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+    pub fn take_consumer(&mut self) -> Option<SampleConsumer> {
         self.consumer.take()
     }
+
+    /// Return the consumer after the DSP thread exits (allows restart)
+    /// Stale samples from before the stop are discarded.
+    pub fn return_consumer(&mut self, mut consumer: SampleConsumer) {
+        consumer.clear();
+        self.consumer = Some(consumer);
+    }
     
     /// Check if stream is running
     pub fn is_running(&self) -> bool {
@@ -128,7 +141,7 @@ impl MicrophoneStream {
 fn build_input_stream(
     device: &cpal::Device,
     config: &cpal::SupportedStreamConfig,
-    mut producer: HeapProd<f32>,
+    mut producer: SampleProducer,
     channels: usize,
     is_running: Arc<AtomicBool>,
 ) -> Result<Stream> {
@@ -138,10 +151,11 @@ fn build_input_stream(
         SampleFormat::F32 => {
             device.build_input_stream(
                 &config.clone().into(),
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                move |data: &[f32], info: &cpal::InputCallbackInfo| {
                     if !is_running.load(Ordering::Relaxed) {
                         return;
                     }
+                    producer.set_capture_time(capture_time_ms(info));
                     // REAL-TIME SAFE: Only lock-free push
                     // Convert stereo to mono if needed, then push
                     if channels > 1 {
@@ -149,6 +163,7 @@ fn build_input_stream(
                         for chunk in data.chunks(channels) {
                             let _ = producer.try_push(chunk[0]);
                         }
+                        producer.signal_if_ready();
                     } else {
                         let _ = producer.push_slice(data);
                     }
@@ -160,10 +175,11 @@ fn build_input_stream(
         SampleFormat::I16 => {
             device.build_input_stream(
                 &config.clone().into(),
-                move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                move |data: &[i16], info: &cpal::InputCallbackInfo| {
                     if !is_running.load(Ordering::Relaxed) {
                         return;
                     }
+                    producer.set_capture_time(capture_time_ms(info));
                     // REAL-TIME SAFE: Convert and push
                     if channels > 1 {
                         for chunk in data.chunks(channels) {
@@ -175,6 +191,7 @@ fn build_input_stream(
                             let _ = producer.try_push(sample as f32 / 32768.0);
                         }
                     }
+                    producer.signal_if_ready();
                 },
                 err_fn,
                 None,
@@ -183,10 +200,11 @@ fn build_input_stream(
         SampleFormat::I32 => {
             device.build_input_stream(
                 &config.clone().into(),
-                move |data: &[i32], _: &cpal::InputCallbackInfo| {
+                move |data: &[i32], info: &cpal::InputCallbackInfo| {
                     if !is_running.load(Ordering::Relaxed) {
                         return;
                     }
+                    producer.set_capture_time(capture_time_ms(info));
                     // REAL-TIME SAFE: Convert and push
                     if channels > 1 {
                         for chunk in data.chunks(channels) {
@@ -198,6 +216,7 @@ fn build_input_stream(
                             let _ = producer.try_push(sample as f32 / 2147483648.0);
                         }
                     }
+                    producer.signal_if_ready();
                 },
                 err_fn,
                 None,
@@ -211,6 +230,16 @@ fn build_input_stream(
     Ok(stream)
 }
 
+/// Capture time of a callback's first sample (ms since the Unix epoch)
+///
+/// CPAL's instants have no fixed origin, but the callback and capture
+/// instants share one: the capture happened (callback - capture) before now.
+fn capture_time_ms(info: &cpal::InputCallbackInfo) -> f64 {
+    let timestamp = info.timestamp();
+    let age = timestamp.callback.duration_since(&timestamp.capture).unwrap_or_default();
+    epoch_ms_ago(age)
+}
+
 impl Drop for MicrophoneStream {
     fn drop(&mut self) {
         self.is_running.store(false, Ordering::SeqCst);
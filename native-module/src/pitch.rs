@@ -0,0 +1,375 @@
+// Pitch Tracking - YIN f0 estimates and utterance-final rising intonation
+//
+// WHY:
+// - STT often drops the question mark, so a transcript alone misses the
+//   moment the interviewer asks something. Yes/no and echo questions end
+//   with a rise in pitch; statements fall (declination)
+//
+// HOW (CPU only, no model files):
+// - Per frame: YIN (de Cheveigne & Kawahara 2002) over the last two frames
+//   (40ms window, 60-400Hz), f0 kept only for voiced frames
+// - Contour in semitones; jumps of more than MAX_JUMP_SEMITONES between
+//   neighbouring frames are octave errors and are folded back
+// - When the suppressor leaves speech (end of the utterance) the last
+//   FINAL_FRAMES of pitched speech are compared with the rest:
+//   rise = min(slope over the final stretch, end level over the body median)
+//   Both have to agree, so a single high note or a slow drift doesn't count
+// - Confidence ramps from 0 at RISE_FLOOR_SEMITONES to 1 at RISE_FULL_SEMITONES
+// - A rising utterance is raised as a LikelyQuestion event
+//
+// LIMITS:
+// - Wh-questions usually fall and are left to the transcript; continuation
+//   rises before a pause inside a sentence can look like a question when
+//   the pause outlasts the suppressor hangover
+//
+// REAL-TIME NOTES:
+// - Buffers are allocated in new(); push() does not allocate. The
+//   utterance is evaluated once at its end
+
+use std::collections::VecDeque;
+
+use crate::audio_config::{FRAME_MS, FRAME_SAMPLES, SAMPLE_RATE};
+use crate::segmenter::{FrameAnalyzer, FrameInfo};
+
+const MIN_F0: f32 = 60.0;
+const MAX_F0: f32 = 400.0;
+
+/// Cumulative mean normalized difference below which a lag is a period
+const YIN_THRESHOLD: f32 = 0.15;
+
+/// Frame energy (i16 scale, per sample) below which no pitch is estimated
+const MIN_ENERGY: f32 = 100.0;
+
+/// Pitched frames an utterance needs to be judged (200ms)
+const MIN_PITCHED_FRAMES: usize = 10;
+
+/// End of the utterance examined for a rise (400ms)
+const FINAL_FRAMES: u64 = 20;
+
+/// Pitched frames needed within it
+const MIN_FINAL_PITCHED: usize = 5;
+
+/// Contour points kept per utterance (30s; older ones are dropped)
+const MAX_CONTOUR: usize = 1500;
+
+/// Larger steps between neighbouring estimates are octave errors
+const MAX_JUMP_SEMITONES: f32 = 7.0;
+
+/// Frames apart at most for two estimates to count as neighbours
+const MAX_NEIGHBOUR_GAP: u64 = 3;
+
+const RISE_FLOOR_SEMITONES: f32 = 1.0;
+const RISE_FULL_SEMITONES: f32 = 5.0;
+
+/// YIN pitch estimator over a sliding two-frame window
+pub struct PitchTracker {
+    /// Previous frame followed by the current one
+    window: Vec<f32>,
+    /// Cumulative mean normalized difference per lag
+    cmnd: Vec<f32>,
+}
+
+impl PitchTracker {
+    pub fn new() -> Self {
+        Self {
+            window: vec![0.0; 2 * FRAME_SAMPLES],
+            cmnd: vec![1.0; max_lag() + 1],
+        }
+    }
+
+    /// f0 (Hz) over the previous and this 20ms frame, None if unpitched
+    pub fn push(&mut self, frame: &[i16]) -> Option<f32> {
+        if frame.len() != FRAME_SAMPLES {
+            return None;
+        }
+        self.window.copy_within(FRAME_SAMPLES.., 0);
+        for (dst, &sample) in self.window[FRAME_SAMPLES..].iter_mut().zip(frame) {
+            *dst = sample as f32;
+        }
+        let energy = self.window[FRAME_SAMPLES..].iter().map(|s| s * s).sum::<f32>() / FRAME_SAMPLES as f32;
+        if energy < MIN_ENERGY {
+            return None;
+        }
+
+        let max_lag = max_lag();
+        let mut running = 0.0;
+        for lag in 1..=max_lag {
+            let diff: f32 = (0..FRAME_SAMPLES)
+                .map(|j| {
+                    let d = self.window[j] - self.window[j + lag];
+                    d * d
+                })
+                .sum();
+            running += diff;
+            self.cmnd[lag] = if running > 0.0 { diff * lag as f32 / running } else { 1.0 };
+        }
+
+        let min_lag = (SAMPLE_RATE as f32 / MAX_F0) as usize;
+        let mut lag = (min_lag..=max_lag).find(|&lag| self.cmnd[lag] < YIN_THRESHOLD)?;
+        while lag < max_lag && self.cmnd[lag + 1] < self.cmnd[lag] {
+            lag += 1;
+        }
+
+        // Parabolic interpolation around the minimum
+        let mut period = lag as f32;
+        if lag > 1 && lag < max_lag {
+            let (before, at, after) = (self.cmnd[lag - 1], self.cmnd[lag], self.cmnd[lag + 1]);
+            let curvature = before + after - 2.0 * at;
+            if curvature.abs() > f32::EPSILON {
+                period += 0.5 * (before - after) / curvature;
+            }
+        }
+        Some(SAMPLE_RATE as f32 / period)
+    }
+}
+
+impl Default for PitchTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn max_lag() -> usize {
+    ((SAMPLE_RATE as f32 / MIN_F0) as usize).min(FRAME_SAMPLES)
+}
+
+/// Semitones relative to 100Hz
+fn semitones(f0: f32) -> f32 {
+    12.0 * (f0 / 100.0).log2()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuestionConfig {
+    /// Utterances with a lower confidence are not reported (0..1)
+    pub min_confidence: f32,
+}
+
+impl Default for QuestionConfig {
+    fn default() -> Self {
+        Self { min_confidence: 0.5 }
+    }
+}
+
+/// An utterance that ended with rising intonation
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuestionCue {
+    /// Last pitched frame of the utterance
+    pub seq: u64,
+    /// Capture time of the end of that frame
+    pub time_ms: Option<f64>,
+    /// 0..1
+    pub confidence: f32,
+    /// Final rise over the rest of the utterance
+    pub rise_semitones: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PitchPoint {
+    seq: u64,
+    capture_time_ms: Option<f64>,
+    semitones: f32,
+}
+
+/// Flags utterances ending with rising intonation
+pub struct QuestionDetector {
+    config: QuestionConfig,
+    pitch: PitchTracker,
+    contour: VecDeque<PitchPoint>,
+    in_speech: bool,
+}
+
+impl QuestionDetector {
+    pub fn new(config: QuestionConfig) -> Self {
+        Self {
+            config,
+            pitch: PitchTracker::new(),
+            contour: VecDeque::with_capacity(MAX_CONTOUR),
+            in_speech: false,
+        }
+    }
+
+    pub fn set_config(&mut self, config: QuestionConfig) {
+        self.config = config;
+    }
+
+    fn add(&mut self, mut point: PitchPoint) {
+        if let Some(previous) = self.contour.back() {
+            if point.seq.saturating_sub(previous.seq) <= MAX_NEIGHBOUR_GAP {
+                while point.semitones - previous.semitones > MAX_JUMP_SEMITONES {
+                    point.semitones -= 12.0;
+                }
+                while previous.semitones - point.semitones > MAX_JUMP_SEMITONES {
+                    point.semitones += 12.0;
+                }
+            }
+        }
+        if self.contour.len() == MAX_CONTOUR {
+            self.contour.pop_front();
+        }
+        self.contour.push_back(point);
+    }
+
+    fn finish(&mut self) -> Option<QuestionCue> {
+        let cue = self.evaluate();
+        self.contour.clear();
+        cue.filter(|cue| cue.confidence >= self.config.min_confidence)
+    }
+
+    fn evaluate(&self) -> Option<QuestionCue> {
+        if self.contour.len() < MIN_PITCHED_FRAMES {
+            return None;
+        }
+        let last = *self.contour.back()?;
+        let final_start = last.seq.saturating_sub(FINAL_FRAMES - 1);
+        let split = self.contour.partition_point(|point| point.seq < final_start);
+        let tail: Vec<PitchPoint> = self.contour.range(split..).copied().collect();
+        if tail.len() < MIN_FINAL_PITCHED {
+            return None;
+        }
+        // Too little before the final stretch: compare with the whole utterance
+        let reference = if split >= MIN_FINAL_PITCHED { self.contour.range(..split) } else { self.contour.range(..) };
+        let mut levels: Vec<f32> = reference.map(|point| point.semitones).collect();
+        levels.sort_by(|a, b| a.total_cmp(b));
+        let median = levels[levels.len() / 2];
+
+        // Least-squares slope over the final stretch, scaled to its length
+        let n = tail.len() as f32;
+        let mean_x = tail.iter().map(|point| (point.seq - final_start) as f32).sum::<f32>() / n;
+        let mean_y = tail.iter().map(|point| point.semitones).sum::<f32>() / n;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for point in &tail {
+            let x = (point.seq - final_start) as f32 - mean_x;
+            covariance += x * (point.semitones - mean_y);
+            variance += x * x;
+        }
+        let slope_rise = if variance > 0.0 { covariance / variance * FINAL_FRAMES as f32 } else { 0.0 };
+        let end_level = tail[tail.len() - 3..].iter().map(|point| point.semitones).sum::<f32>() / 3.0;
+
+        let rise = slope_rise.min(end_level - median);
+        let confidence = ((rise - RISE_FLOOR_SEMITONES) / (RISE_FULL_SEMITONES - RISE_FLOOR_SEMITONES)).clamp(0.0, 1.0);
+        Some(QuestionCue {
+            seq: last.seq,
+            time_ms: last.capture_time_ms.map(|time| time + FRAME_MS as f64),
+            confidence,
+            rise_semitones: rise,
+        })
+    }
+}
+
+impl FrameAnalyzer for QuestionDetector {
+    type Output = QuestionCue;
+
+    /// One processed frame (mono); returns a cue when an utterance ends
+    /// with a rise
+    fn push(&mut self, frame: &[i16], info: &FrameInfo) -> Option<QuestionCue> {
+        // Every frame goes through the tracker to keep its window continuous
+        let f0 = self.pitch.push(frame);
+        if let Some(f0) = f0.filter(|_| info.voiced) {
+            self.add(PitchPoint {
+                seq: info.seq,
+                capture_time_ms: info.capture_time_ms,
+                semitones: semitones(f0),
+            });
+        }
+        let ended = self.in_speech && !info.speech;
+        self.in_speech = info.speech;
+        if ended {
+            self.finish()
+        } else {
+            None
+        }
+    }
+
+    /// Judge the utterance in progress (end of run)
+    fn flush(&mut self) -> Option<QuestionCue> {
+        self.in_speech = false;
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// Harmonic tone following `f0(t)` (t in seconds)
+    fn glide(seconds: f32, f0: impl Fn(f32) -> f32) -> Vec<i16> {
+        let mut phase = 0.0f32;
+        (0..(seconds * SAMPLE_RATE as f32) as usize)
+            .map(|i| {
+                let f = f0(i as f32 / SAMPLE_RATE as f32);
+                phase += 2.0 * PI * f / SAMPLE_RATE as f32;
+                let sample: f32 = (1..=8).map(|k| (k as f32 * phase).sin() / k as f32).sum();
+                (4000.0 * sample) as i16
+            })
+            .collect()
+    }
+
+    /// Utterance followed by 300ms hangover and silence; cues raised
+    fn run(detector: &mut QuestionDetector, signal: &[i16]) -> Vec<QuestionCue> {
+        let silence = vec![0i16; FRAME_SAMPLES];
+        let frames = signal.chunks_exact(FRAME_SAMPLES).map(|frame| (frame, true)).chain((0..30).map(|_| (&silence[..], false)));
+        let mut cues = Vec::new();
+        let mut since_voiced = 0;
+        for (seq, (frame, voiced)) in frames.enumerate() {
+            since_voiced = if voiced { 0 } else { since_voiced + 1 };
+            let info = FrameInfo {
+                seq: seq as u64,
+                capture_time_ms: Some(seq as f64 * FRAME_MS as f64),
+                voiced,
+                speech: since_voiced < 15,
+            };
+            cues.extend(detector.push(frame, &info));
+        }
+        cues
+    }
+
+    #[test]
+    fn test_yin_estimates_f0() {
+        let mut tracker = PitchTracker::new();
+        for f0 in [85.0, 140.0, 220.0, 330.0] {
+            let signal = glide(0.2, |_| f0);
+            let estimate = signal.chunks_exact(FRAME_SAMPLES).filter_map(|frame| tracker.push(frame)).last().unwrap();
+            assert!((estimate - f0).abs() / f0 < 0.01, "{} -> {}", f0, estimate);
+        }
+        // Silence and white noise have no pitch
+        assert_eq!(tracker.push(&[0; FRAME_SAMPLES]), None);
+        let mut seed = 1u32;
+        let noise: Vec<i16> = (0..FRAME_SAMPLES)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 16) as i16
+            })
+            .collect();
+        tracker.push(&noise);
+        assert_eq!(tracker.push(&noise), None);
+    }
+
+    #[test]
+    fn test_final_rise_is_a_question() {
+        let mut detector = QuestionDetector::new(QuestionConfig::default());
+        // 1.2s of gently falling pitch, then a 400ms rise of ~7 semitones
+        let signal = glide(1.6, |t| if t < 1.2 { 160.0 - 10.0 * t } else { 148.0 * 2f32.powf(7.0 * (t - 1.2) / 0.4 / 12.0) });
+        let cues = run(&mut detector, &signal);
+        assert_eq!(cues.len(), 1, "{:?}", cues);
+        assert!(cues[0].confidence > 0.8, "{:?}", cues[0]);
+        assert_eq!(cues[0].seq, 79);
+        assert_eq!(cues[0].time_ms, Some(1600.0));
+    }
+
+    #[test]
+    fn test_falling_statement_and_octave_errors() {
+        let mut detector = QuestionDetector::new(QuestionConfig::default());
+        // Declarative fall at the end
+        let signal = glide(1.6, |t| if t < 1.2 { 150.0 } else { 150.0 - 60.0 * (t - 1.2) });
+        assert!(run(&mut detector, &signal).is_empty());
+
+        // An octave jump (tracker error / creak) at the end is not a rise
+        let signal = glide(1.6, |t| if t < 1.5 { 140.0 } else { 280.0 });
+        assert!(run(&mut detector, &signal).is_empty());
+
+        // Too short to judge
+        let signal = glide(0.15, |t| 120.0 + 400.0 * t);
+        assert!(run(&mut detector, &signal).is_empty());
+    }
+}
@@ -0,0 +1,731 @@
+// Sample Ring - lock-free SPSC ring buffer + DSP thread wakeup
+//
+// Every capture backend pushes through a SampleProducer, the DSP thread
+// drains a SampleConsumer. Instead of polling every 1ms (~1000 wakeups/s per
+// stream), the producer signals the consumer once a frame's worth (20ms) of
+// samples has been pushed (~50 wakeups/s).
+//
+// REAL-TIME NOTES:
+// - Sample path is lock-free (ringbuf)
+// - Signalling uses try_lock only, the audio callback never blocks
+// - If a wakeup races with the consumer going to sleep, the consumer's
+//   wait timeout bounds the extra latency (DSP_WAKE_TIMEOUT_MS)
+//
+// SIZING / OVERFLOW (RingConfig, shared by every backend):
+// - Capacity is given in milliseconds and sized for the ring's format
+// - DropNewest (default): a full ring rejects incoming samples
+// - OverwriteOldest: incoming samples wait in a preallocated spill buffer and
+//   the consumer is asked to discard the oldest ones, so after a stall the
+//   DSP thread resumes with the most recent audio (the producer never touches
+//   the consumer's side of the ring)
+// - Both count overflows and dropped audio in RingStats
+//
+// CAPTURE TIMESTAMPS:
+// - Backends stamp the next pushed sample with its capture time (ms since the
+//   Unix epoch, converted from the device clock) via set_capture_time()
+// - Stamps travel in a side queue like format markers; the consumer
+//   extrapolates between them, so not every push needs one
+//
+// FORMAT CHANGES:
+// - Backends declare rate / channel changes with set_format(); a marker
+//   tagged with the stream position goes through a small side queue
+// - try_pop() stops at a due marker, so the DSP thread retunes exactly
+//   between the last old-format and the first new-format sample
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use ringbuf::{traits::{Consumer, Observer, Producer, Split}, HeapCons, HeapProd, HeapRb};
+
+use crate::audio_config::{FRAME_MS, RING_BUFFER_MS};
+
+/// Wakeup channel between the producer(s) and the DSP thread
+pub struct DataNotify {
+    ready: AtomicBool,
+    lock: Mutex<()>,
+    condvar: Condvar,
+}
+
+impl DataNotify {
+    pub fn new() -> Self {
+        Self {
+            ready: AtomicBool::new(false),
+            lock: Mutex::new(()),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Wake the consumer (real-time safe: never blocks)
+    pub fn notify(&self) {
+        self.ready.store(true, Ordering::Release);
+        // Holding the lock (when free) orders us against the consumer's
+        // check-then-wait; if it is busy the consumer is awake anyway.
+        let _guard = self.lock.try_lock();
+        self.condvar.notify_one();
+    }
+
+    /// Block until notified or `timeout` elapses
+    /// Returns true if woken by a notification.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let mut guard = match self.lock.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        while !self.ready.swap(false, Ordering::AcqRel) {
+            let (next, result) = match self.condvar.wait_timeout(guard, timeout) {
+                Ok(r) => r,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            guard = next;
+            if result.timed_out() {
+                return self.ready.swap(false, Ordering::AcqRel);
+            }
+        }
+        true
+    }
+}
+
+impl Default for DataNotify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sample rate + interleaved channel count of the samples in a ring
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// What the producer does when the ring is full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RingOverflow {
+    /// Reject incoming samples (default)
+    DropNewest,
+    /// Discard the oldest buffered samples to make room
+    OverwriteOldest,
+}
+
+impl RingOverflow {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "drop-newest" => Some(RingOverflow::DropNewest),
+            "overwrite-oldest" => Some(RingOverflow::OverwriteOldest),
+            _ => None,
+        }
+    }
+}
+
+/// Ring sizing and overflow behaviour of one capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingConfig {
+    pub capacity_ms: u32,
+    pub overflow: RingOverflow,
+}
+
+impl Default for RingConfig {
+    fn default() -> Self {
+        Self {
+            capacity_ms: RING_BUFFER_MS,
+            overflow: RingOverflow::DropNewest,
+        }
+    }
+}
+
+impl RingConfig {
+    /// Capacity in samples for `format` (whole sample frames, at least two 20ms frames)
+    pub fn capacity(&self, format: AudioFormat) -> usize {
+        let frames = (format.sample_rate as usize * self.capacity_ms as usize / 1000)
+            .max(2 * frame_len(format.sample_rate));
+        frames * format.channels.max(1) as usize
+    }
+
+    /// Create a ring sized for `format`
+    pub fn ring(&self, format: AudioFormat) -> (SampleProducer, SampleConsumer) {
+        let (mut producer, consumer) = sample_ring_with(self.capacity(format), format.sample_rate, self.overflow);
+        producer.set_channels(format.channels);
+        (producer, consumer)
+    }
+}
+
+/// Overflow counters of a ring, shared by both halves and the capture
+#[derive(Debug, Default)]
+pub struct RingStats {
+    overflows: AtomicU64,
+    dropped_us: AtomicU64,
+    /// Interleaved samples per second of the current format
+    samples_per_second: AtomicU64,
+}
+
+impl RingStats {
+    /// Pushes that did not fit into the ring
+    pub fn overflows(&self) -> u64 {
+        self.overflows.load(Ordering::Relaxed)
+    }
+
+    /// Audio lost to overflows (ms)
+    pub fn dropped_ms(&self) -> f64 {
+        self.dropped_us.load(Ordering::Relaxed) as f64 / 1000.0
+    }
+
+    fn record_overflow(&self) {
+        self.overflows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_dropped(&self, samples: usize) {
+        let rate = self.samples_per_second.load(Ordering::Relaxed).max(1);
+        self.dropped_us.fetch_add(samples as u64 * 1_000_000 / rate, Ordering::Relaxed);
+    }
+}
+
+impl AudioFormat {
+    /// Playback duration of `samples` interleaved samples
+    pub fn duration_of(&self, samples: usize) -> Duration {
+        let per_second = self.sample_rate as u64 * self.channels.max(1) as u64;
+        Duration::from_micros(samples as u64 * 1_000_000 / per_second.max(1))
+    }
+}
+
+/// Capture time of sample index `at` of the stream
+#[derive(Debug, Clone, Copy)]
+struct TimeAnchor {
+    at: u64,
+    epoch_ms: f64,
+    /// Interleaved samples per second when the anchor was set
+    samples_per_second: f64,
+}
+
+/// Stamps that can wait for the DSP thread (~0.5s of 10ms callbacks);
+/// when full, new stamps are skipped and the consumer extrapolates
+const TIME_ANCHOR_QUEUE_SIZE: usize = 64;
+
+/// Format change taking effect at sample index `at` of the stream
+#[derive(Debug, Clone, Copy)]
+struct FormatMarker {
+    at: u64,
+    format: AudioFormat,
+}
+
+/// Format changes that can be in flight at once (a device switching profiles
+/// produces one or two)
+const FORMAT_MARKER_QUEUE_SIZE: usize = 16;
+
+/// Producer half, owned by the device callback / capture thread
+pub struct SampleProducer {
+    producer: HeapProd<f32>,
+    markers: HeapProd<FormatMarker>,
+    anchors: HeapProd<TimeAnchor>,
+    notify: Arc<DataNotify>,
+    pending: usize,
+    wake_threshold: usize,
+    format: AudioFormat,
+    /// Samples pushed since the ring was created
+    written: u64,
+    overflow: RingOverflow,
+    /// OverwriteOldest: samples waiting for the consumer to make room
+    spill: Vec<f32>,
+    spill_capacity: usize,
+    /// OverwriteOldest: oldest samples the consumer should discard
+    discard: Arc<AtomicUsize>,
+    stats: Arc<RingStats>,
+}
+
+impl SampleProducer {
+    /// Push samples, waking the DSP thread once a frame's worth is pending
+    /// Returns the number of samples actually pushed.
+    ///
+    /// With interleaved multichannel audio only whole sample frames are
+    /// pushed, so an overrun never shifts the channel order.
+    /// With OverwriteOldest every sample is accepted (the oldest are dropped).
+    pub fn push_slice(&mut self, samples: &[f32]) -> usize {
+        let accepted = self.push_samples(samples);
+        self.signal_if_ready();
+        accepted
+    }
+
+    /// Push a single sample (call `signal_if_ready()` after a batch)
+    pub fn try_push(&mut self, sample: f32) -> bool {
+        self.push_samples(&[sample]) == 1
+    }
+
+    fn push_samples(&mut self, samples: &[f32]) -> usize {
+        match self.overflow {
+            RingOverflow::DropNewest => {
+                let pushed = self.push_fitting(samples);
+                if pushed < samples.len() {
+                    self.stats.record_overflow();
+                    self.stats.record_dropped(samples.len() - pushed);
+                }
+                pushed
+            }
+            RingOverflow::OverwriteOldest => {
+                // Spilled samples are older: they go first
+                self.flush_spill();
+                let direct = if self.spill.is_empty() { self.push_fitting(samples) } else { 0 };
+                if direct < samples.len() {
+                    self.stats.record_overflow();
+                    self.spill_samples(&samples[direct..]);
+                }
+                samples.len()
+            }
+        }
+    }
+
+    /// Push the whole sample frames that fit
+    fn push_fitting(&mut self, samples: &[f32]) -> usize {
+        let len = samples.len().min(self.whole_frames(self.producer.vacant_len()));
+        let pushed = self.producer.push_slice(&samples[..len]);
+        self.pending += pushed;
+        self.written += pushed as u64;
+        pushed
+    }
+
+    fn flush_spill(&mut self) {
+        if self.spill.is_empty() {
+            return;
+        }
+        let len = self.spill.len().min(self.whole_frames(self.producer.vacant_len()));
+        let pushed = self.producer.push_slice(&self.spill[..len]);
+        self.spill.drain(..pushed);
+        self.pending += pushed;
+        self.written += pushed as u64;
+        if self.spill.is_empty() {
+            // The consumer made room on its own
+            self.discard.store(0, Ordering::Release);
+        }
+    }
+
+    /// Keep the newest samples in the spill buffer and ask the consumer to
+    /// discard as many of the oldest ones from the ring
+    fn spill_samples(&mut self, samples: &[f32]) {
+        let capacity = self.whole_frames(self.spill_capacity);
+        let excess = self.whole_frames_up((self.spill.len() + samples.len()).saturating_sub(capacity));
+        let from_spill = excess.min(self.spill.len());
+        self.spill.drain(..from_spill);
+        let from_samples = (excess - from_spill).min(samples.len());
+        self.spill.extend_from_slice(&samples[from_samples..]);
+        if excess > 0 {
+            self.stats.record_dropped(excess);
+        }
+        self.discard.store(self.spill.len(), Ordering::Release);
+    }
+
+    fn whole_frames(&self, samples: usize) -> usize {
+        let channels = self.format.channels as usize;
+        samples / channels * channels
+    }
+
+    fn whole_frames_up(&self, samples: usize) -> usize {
+        let channels = self.format.channels as usize;
+        samples.div_ceil(channels) * channels
+    }
+
+    /// Wake the DSP thread if at least one frame is pending
+    pub fn signal_if_ready(&mut self) {
+        if self.pending >= self.wake_threshold {
+            self.pending = 0;
+            self.notify.notify();
+        }
+    }
+
+    /// Declare the format of the samples pushed from now on
+    ///
+    /// Real-time safe. The change travels in-band: the DSP thread applies it
+    /// exactly at the first sample pushed after this call, so a backend can
+    /// report a device rate / channel switch mid-stream (or its real format
+    /// once known, for rings created before the device was opened).
+    pub fn set_format(&mut self, format: AudioFormat) {
+        let format = AudioFormat {
+            sample_rate: format.sample_rate,
+            channels: format.channels.max(1),
+        };
+        if format == self.format {
+            return;
+        }
+        self.format = format;
+        self.wake_threshold = frame_len(format.sample_rate) * format.channels as usize;
+        self.stats
+            .samples_per_second
+            .store(format.sample_rate as u64 * format.channels as u64, Ordering::Relaxed);
+        // Spilled samples are still in the old format
+        let at = self.written + self.spill.len() as u64;
+        if self.markers.try_push(FormatMarker { at, format }).is_err() {
+            eprintln!("[SampleRing] Format change queue full, dropped change to {:?}", format);
+        }
+    }
+
+    /// Capture time (ms since the Unix epoch) of the next sample pushed
+    ///
+    /// Real-time safe. Call before the push it describes; pushes without a
+    /// stamp are timed by extrapolating from the last one.
+    pub fn set_capture_time(&mut self, epoch_ms: f64) {
+        let anchor = TimeAnchor {
+            at: self.written + self.spill.len() as u64,
+            epoch_ms,
+            samples_per_second: self.format.sample_rate as f64 * self.format.channels as f64,
+        };
+        let _ = self.anchors.try_push(anchor);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.set_format(AudioFormat { sample_rate, ..self.format });
+    }
+
+    /// Interleaved channel count of the pushed samples (default 1)
+    pub fn set_channels(&mut self, channels: u16) {
+        self.set_format(AudioFormat { channels, ..self.format });
+    }
+
+    pub fn format(&self) -> AudioFormat {
+        self.format
+    }
+
+    pub fn stats(&self) -> Arc<RingStats> {
+        self.stats.clone()
+    }
+}
+
+/// Consumer half, owned by the DSP thread
+pub struct SampleConsumer {
+    consumer: HeapCons<f32>,
+    markers: HeapCons<FormatMarker>,
+    anchors: HeapCons<TimeAnchor>,
+    /// Latest capture time stamp at or before `read`
+    anchor: Option<TimeAnchor>,
+    notify: Arc<DataNotify>,
+    /// Samples popped (or cleared) since the ring was created
+    read: u64,
+    discard: Arc<AtomicUsize>,
+    stats: Arc<RingStats>,
+}
+
+impl SampleConsumer {
+    /// Next sample, or None if the ring is empty or a format change is due
+    /// (see take_format_change())
+    pub fn try_pop(&mut self) -> Option<f32> {
+        if self.discard.load(Ordering::Relaxed) != 0 {
+            self.discard_oldest();
+        }
+        // Check availability first: seeing a sample guarantees that a marker
+        // pushed before it is visible too
+        if self.consumer.is_empty() || self.format_change_due() {
+            return None;
+        }
+        let sample = self.consumer.try_pop()?;
+        self.read += 1;
+        Some(sample)
+    }
+
+    /// Format change that takes effect at the current read position
+    /// Samples popped after this call are in the returned format.
+    pub fn take_format_change(&mut self) -> Option<AudioFormat> {
+        let mut latest = None;
+        while let Some(marker) = self.markers.first() {
+            if marker.at > self.read {
+                break;
+            }
+            latest = Some(marker.format);
+            self.markers.skip(1);
+        }
+        latest
+    }
+
+    /// Make room for the producer's spilled samples (OverwriteOldest)
+    fn discard_oldest(&mut self) {
+        let requested = self.discard.swap(0, Ordering::AcqRel);
+        // Never skip past a pending format change: the layout may differ there
+        let limit = self
+            .markers
+            .first()
+            .map_or(usize::MAX, |marker| marker.at.saturating_sub(self.read) as usize);
+        let skipped = self.consumer.skip(requested.min(limit));
+        self.read += skipped as u64;
+        self.stats.record_dropped(skipped);
+    }
+
+    /// Capture time (ms since the Unix epoch) of the next sample to pop,
+    /// None until the backend has stamped a sample
+    pub fn capture_time_ms(&mut self) -> Option<f64> {
+        while let Some(anchor) = self.anchors.first() {
+            if anchor.at > self.read {
+                break;
+            }
+            self.anchor = Some(*anchor);
+            self.anchors.skip(1);
+        }
+        self.anchor.map(|anchor| {
+            anchor.epoch_ms + (self.read - anchor.at) as f64 * 1000.0 / anchor.samples_per_second.max(1.0)
+        })
+    }
+
+    fn format_change_due(&self) -> bool {
+        self.markers.first().is_some_and(|marker| marker.at <= self.read)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.consumer.is_empty()
+    }
+
+    /// Discard everything currently buffered
+    /// Format changes are kept; they apply to whatever is pushed next.
+    pub fn clear(&mut self) {
+        self.discard.store(0, Ordering::Release);
+        self.read += self.consumer.clear() as u64;
+        // Stamps of discarded samples would be extrapolated across the gap
+        self.anchor = None;
+        while self.anchors.first().is_some_and(|anchor| anchor.at < self.read) {
+            self.anchors.skip(1);
+        }
+    }
+
+    /// Overflow counters (shared with the producer)
+    pub fn stats(&self) -> Arc<RingStats> {
+        self.stats.clone()
+    }
+
+    /// Sleep until the producer signals new data (or `timeout` elapses)
+    pub fn wait_for_data(&self, timeout: Duration) -> bool {
+        if !self.consumer.is_empty() {
+            return true;
+        }
+        self.notify.wait(timeout)
+    }
+
+    /// Handle used to wake the DSP thread from outside (e.g., on stop)
+    pub fn notifier(&self) -> Arc<DataNotify> {
+        self.notify.clone()
+    }
+}
+
+/// Create a ring of `capacity` samples for a source at `sample_rate`
+/// (drops the newest samples on overflow; see RingConfig for sizing in ms)
+pub fn sample_ring(capacity: usize, sample_rate: u32) -> (SampleProducer, SampleConsumer) {
+    sample_ring_with(capacity, sample_rate, RingOverflow::DropNewest)
+}
+
+pub fn sample_ring_with(capacity: usize, sample_rate: u32, overflow: RingOverflow) -> (SampleProducer, SampleConsumer) {
+    let (producer, consumer) = HeapRb::<f32>::new(capacity).split();
+    let (marker_producer, marker_consumer) = HeapRb::<FormatMarker>::new(FORMAT_MARKER_QUEUE_SIZE).split();
+    let (anchor_producer, anchor_consumer) = HeapRb::<TimeAnchor>::new(TIME_ANCHOR_QUEUE_SIZE).split();
+    let notify = Arc::new(DataNotify::new());
+    let discard = Arc::new(AtomicUsize::new(0));
+    let stats = Arc::new(RingStats::default());
+    stats.samples_per_second.store(sample_rate as u64, Ordering::Relaxed);
+    let spill_capacity = match overflow {
+        RingOverflow::DropNewest => 0,
+        RingOverflow::OverwriteOldest => capacity,
+    };
+    (
+        SampleProducer {
+            producer,
+            markers: marker_producer,
+            anchors: anchor_producer,
+            notify: notify.clone(),
+            pending: 0,
+            wake_threshold: frame_len(sample_rate),
+            format: AudioFormat { sample_rate, channels: 1 },
+            written: 0,
+            overflow,
+            spill: Vec::with_capacity(spill_capacity),
+            spill_capacity,
+            discard: discard.clone(),
+            stats: stats.clone(),
+        },
+        SampleConsumer {
+            consumer,
+            markers: marker_consumer,
+            anchors: anchor_consumer,
+            anchor: None,
+            notify,
+            read: 0,
+            discard,
+            stats,
+        },
+    )
+}
+
+/// Samples per 20ms frame at the native rate
+fn frame_len(sample_rate: u32) -> usize {
+    ((sample_rate as usize * FRAME_MS as usize) / 1000).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Instant;
+
+    #[test]
+    fn test_wakes_after_one_frame() {
+        let (mut producer, consumer) = sample_ring(4096, 48_000);
+
+        // Less than a frame: no wakeup
+        producer.push_slice(&[0.0; 100]);
+        assert!(!consumer.notify.wait(Duration::from_millis(10)));
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            producer.push_slice(&[0.0; 960]);
+            producer
+        });
+        let start = Instant::now();
+        assert!(consumer.notify.wait(Duration::from_secs(2)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_overrun_keeps_stereo_frames_whole() {
+        let (mut producer, mut consumer) = sample_ring(5, 48_000);
+        producer.set_channels(2);
+        assert_eq!(consumer.take_format_change().map(|f| f.channels), Some(2));
+
+        // Room for 5 samples: only 2 whole L/R frames go in
+        assert_eq!(producer.push_slice(&[1.0, -1.0, 1.0, -1.0, 1.0, -1.0]), 4);
+        consumer.try_pop();
+        consumer.try_pop();
+        assert_eq!(producer.push_slice(&[1.0, -1.0, 1.0, -1.0]), 2);
+
+        let mut samples = Vec::new();
+        while let Some(sample) = consumer.try_pop() {
+            samples.push(sample);
+        }
+        assert_eq!(samples, vec![1.0, -1.0, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_format_change_is_in_band() {
+        let (mut producer, mut consumer) = sample_ring(64, 48_000);
+        producer.push_slice(&[1.0; 3]);
+        producer.set_sample_rate(16_000);
+        producer.push_slice(&[2.0; 2]);
+
+        // Old-format samples first, then the change, then new-format samples
+        let mut popped = Vec::new();
+        while let Some(sample) = consumer.try_pop() {
+            popped.push(sample);
+        }
+        assert_eq!(popped, vec![1.0; 3]);
+        assert_eq!(
+            consumer.take_format_change(),
+            Some(AudioFormat { sample_rate: 16_000, channels: 1 })
+        );
+        assert_eq!(consumer.take_format_change(), None);
+        assert_eq!(consumer.try_pop(), Some(2.0));
+    }
+
+    #[test]
+    fn test_drop_newest_counts_overflow() {
+        let (mut producer, mut consumer) = sample_ring(4, 1_000);
+        assert_eq!(producer.push_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]), 4);
+
+        let stats = consumer.stats();
+        assert_eq!(stats.overflows(), 1);
+        assert_eq!(stats.dropped_ms(), 2.0);
+        assert_eq!(consumer.try_pop(), Some(1.0));
+    }
+
+    #[test]
+    fn test_overwrite_oldest_keeps_newest_samples() {
+        let (mut producer, mut consumer) = sample_ring_with(4, 1_000, RingOverflow::OverwriteOldest);
+        producer.push_slice(&[1.0, 2.0, 3.0]);
+        // Stalled consumer: 4..=9 don't fit, the oldest go
+        assert_eq!(producer.push_slice(&[4.0, 5.0, 6.0, 7.0, 8.0, 9.0]), 6);
+
+        // The consumer discards the oldest, the next push moves the spill in
+        assert_eq!(consumer.try_pop(), None);
+        producer.push_slice(&[]);
+        let mut popped = Vec::new();
+        while let Some(sample) = consumer.try_pop() {
+            popped.push(sample);
+        }
+        assert_eq!(popped, vec![6.0, 7.0, 8.0, 9.0]);
+        assert_eq!(consumer.stats().overflows(), 1);
+        assert_eq!(consumer.stats().dropped_ms(), 5.0);
+    }
+
+    #[test]
+    fn test_capture_time_is_extrapolated_between_stamps() {
+        let (mut producer, mut consumer) = sample_ring(64, 1_000);
+        assert_eq!(consumer.capture_time_ms(), None);
+
+        producer.set_capture_time(5_000.0);
+        producer.push_slice(&[0.0; 10]);
+        assert_eq!(consumer.capture_time_ms(), Some(5_000.0));
+        for _ in 0..4 {
+            consumer.try_pop();
+        }
+        assert_eq!(consumer.capture_time_ms(), Some(5_004.0));
+
+        // The next stamp corrects drift from sample 10 on
+        producer.set_capture_time(5_012.5);
+        producer.push_slice(&[0.0; 10]);
+        for _ in 0..6 {
+            consumer.try_pop();
+        }
+        assert_eq!(consumer.capture_time_ms(), Some(5_012.5));
+    }
+
+    /// Benchmark: poll (1ms sleep) vs notify, 48kHz source delivering 10ms blocks
+    /// Run with: cargo test --release -- --ignored --nocapture bench_dsp_wakeups
+    #[test]
+    #[ignore]
+    fn bench_dsp_wakeups() {
+        const BLOCK: usize = 480;
+        const BLOCKS: usize = 200; // 2 seconds
+
+        fn run(event_driven: bool) -> (usize, Duration) {
+            let (mut producer, mut consumer) = sample_ring(48_000, 48_000);
+            let done = Arc::new(AtomicBool::new(false));
+            let done_producer = done.clone();
+            let pushed_at = Arc::new(Mutex::new(Vec::with_capacity(BLOCKS)));
+            let pushed_at_producer = pushed_at.clone();
+
+            let producer_thread = thread::spawn(move || {
+                for _ in 0..BLOCKS {
+                    thread::sleep(Duration::from_millis(10));
+                    pushed_at_producer.lock().unwrap().push(Instant::now());
+                    producer.push_slice(&[0.0; BLOCK]);
+                }
+                done_producer.store(true, Ordering::SeqCst);
+                producer.notify.notify();
+            });
+
+            let mut wakeups = 0;
+            let mut popped = 0;
+            let mut total_latency = Duration::ZERO;
+            let mut frames_seen = 0;
+            while !(done.load(Ordering::SeqCst) && consumer.is_empty()) {
+                wakeups += 1;
+                let mut got = 0;
+                while consumer.try_pop().is_some() {
+                    got += 1;
+                }
+                popped += got;
+                // Latency of every completed frame (960 samples = 20ms)
+                while frames_seen < popped / 960 {
+                    let pushed = pushed_at.lock().unwrap()[(frames_seen * 2) + 1];
+                    total_latency += pushed.elapsed().min(Duration::from_millis(1000));
+                    frames_seen += 1;
+                }
+                if got == 0 {
+                    if event_driven {
+                        consumer.wait_for_data(Duration::from_millis(50));
+                    } else {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                }
+            }
+            producer_thread.join().unwrap();
+            (wakeups, total_latency / frames_seen.max(1) as u32)
+        }
+
+        let (poll_wakeups, poll_latency) = run(false);
+        let (event_wakeups, event_latency) = run(true);
+        println!("[bench] poll:   {} wakeups/2s, avg frame latency {:?}", poll_wakeups, poll_latency);
+        println!("[bench] notify: {} wakeups/2s, avg frame latency {:?}", event_wakeups, event_latency);
+        assert!(event_wakeups * 5 < poll_wakeups);
+    }
+}
@@ -0,0 +1,670 @@
+// Scripted Capture - null / scripted source backend + virtual-time driver
+//
+// Lets the capture-to-callback path run deterministically in `cargo test`:
+//   Script (sample blocks at virtual timestamps)
+//     -> ring buffer -> CaptureLoop (resample, DSP, batching) -> emit
+//
+// - ScriptedSource: CaptureSource that plays a Script in real time on a
+//   thread. An empty script is a null backend that never produces audio.
+// - VirtualDriver: single-threaded; moves a VirtualClock to each block's
+//   timestamp, pushes the block and runs the DSP loop until the ring is
+//   drained. No sleeps, no races: the same script always yields the same frames.
+// - Scripts can switch sample rate / channels mid-stream like a device
+//   changing profile; the change is published in-band before the next block.
+// - Blocks are stamped with their capture time like a device callback
+//   (delivery time minus the block's duration); in virtual time the stamps
+//   are ms on the VirtualClock, not since the Unix epoch.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::capture::CaptureSource;
+use crate::clock::{epoch_ms_ago, VirtualClock};
+use crate::dsp::{command_queue, CaptureLoop, CommandProducer, DspCommand, DspPipeline, DspSettings};
+use crate::events::EventHandler;
+use crate::frame_pool::PooledFrame;
+use crate::sample_ring::{sample_ring, AudioFormat, SampleConsumer, SampleProducer};
+
+/// Samples delivered by the "device" at virtual time `at`
+#[derive(Debug, Clone)]
+pub struct ScriptBlock {
+    pub at: Duration,
+    /// Format the device switches to before delivering `samples`
+    pub format: Option<AudioFormat>,
+    /// Interleaved if the current format has more than one channel
+    pub samples: Vec<f32>,
+}
+
+/// Timeline of sample blocks, built like a device would deliver them
+///
+/// tone / silence arrive in real-time 10ms blocks; gap, burst and block
+/// model stalls, catch-up bursts and overruns.
+#[derive(Debug, Clone)]
+pub struct Script {
+    /// Format at the start of the script
+    initial: AudioFormat,
+    sample_rate: u32,
+    channels: u16,
+    cursor: Duration,
+    /// Running sample index (keeps the tone phase continuous)
+    position: u64,
+    blocks: Vec<ScriptBlock>,
+}
+
+impl Script {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            initial: AudioFormat { sample_rate, channels: 1 },
+            sample_rate,
+            channels: 1,
+            cursor: Duration::ZERO,
+            position: 0,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Format at the start of the script
+    pub fn initial_format(&self) -> AudioFormat {
+        self.initial
+    }
+
+    pub fn blocks(&self) -> &[ScriptBlock] {
+        &self.blocks
+    }
+
+    /// Virtual length of the script
+    pub fn duration(&self) -> Duration {
+        self.cursor
+    }
+
+    /// Device switches format (e.g., Bluetooth A2DP 48kHz -> HFP 16kHz)
+    /// Applies from the next block on; a format set before any block is
+    /// the script's initial format.
+    pub fn format(mut self, sample_rate: u32, channels: u16) -> Self {
+        let format = AudioFormat { sample_rate, channels: channels.max(1) };
+        self.sample_rate = format.sample_rate;
+        self.channels = format.channels;
+        if self.blocks.is_empty() && self.cursor.is_zero() {
+            self.initial = format;
+        } else {
+            self.blocks.push(ScriptBlock {
+                at: self.cursor,
+                format: Some(format),
+                samples: Vec::new(),
+            });
+        }
+        self
+    }
+
+    /// 1kHz square wave at `amplitude` (0.0 - 1.0), in 10ms blocks
+    /// (same signal on every channel)
+    pub fn tone(self, duration: Duration, amplitude: f32) -> Self {
+        self.realtime(duration, amplitude)
+    }
+
+    /// Digital silence, in 10ms blocks
+    pub fn silence(self, duration: Duration) -> Self {
+        self.realtime(duration, 0.0)
+    }
+
+    /// Device stall: time passes, no samples arrive
+    pub fn gap(mut self, duration: Duration) -> Self {
+        self.cursor += duration;
+        self
+    }
+
+    /// `duration` worth of tone delivered at once (catch-up after a stall)
+    pub fn burst(mut self, duration: Duration, amplitude: f32) -> Self {
+        let samples = self.tone_samples(self.samples_for(duration), amplitude);
+        self.blocks.push(ScriptBlock { at: self.cursor, format: None, samples });
+        self
+    }
+
+    /// Arbitrary (interleaved) samples at the current time (does not advance time)
+    pub fn block(mut self, samples: Vec<f32>) -> Self {
+        self.position += (samples.len() / self.channels as usize) as u64;
+        self.blocks.push(ScriptBlock { at: self.cursor, format: None, samples });
+        self
+    }
+
+    fn realtime(mut self, duration: Duration, amplitude: f32) -> Self {
+        let block_samples = (self.sample_rate as usize / 100).max(1);
+        let mut remaining = self.samples_for(duration);
+        while remaining > 0 {
+            let count = remaining.min(block_samples);
+            let samples = self.tone_samples(count, amplitude);
+            // Like a device callback: the block is delivered once it has been recorded
+            self.cursor += self.duration_of(count);
+            self.blocks.push(ScriptBlock { at: self.cursor, format: None, samples });
+            remaining -= count;
+        }
+        self
+    }
+
+    /// `count` sample frames of tone (interleaved for multichannel)
+    fn tone_samples(&mut self, count: usize, amplitude: f32) -> Vec<f32> {
+        let half_period = (self.sample_rate as u64 / 2000).max(1);
+        let channels = self.channels as usize;
+        let samples = (0..count as u64)
+            .flat_map(|i| {
+                let value = if ((self.position + i) / half_period).is_multiple_of(2) {
+                    amplitude
+                } else {
+                    -amplitude
+                };
+                std::iter::repeat_n(value, channels)
+            })
+            .collect();
+        self.position += count as u64;
+        samples
+    }
+
+    fn samples_for(&self, duration: Duration) -> usize {
+        (duration.as_nanos() * self.sample_rate as u128 / 1_000_000_000) as usize
+    }
+
+    fn duration_of(&self, samples: usize) -> Duration {
+        Duration::from_nanos((samples as u64 * 1_000_000_000) / self.sample_rate as u64)
+    }
+}
+
+// ============================================================================
+// SCRIPTED SOURCE (real time, for CaptureCore)
+// ============================================================================
+
+/// Capture backend that plays a Script instead of a device
+pub struct ScriptedSource {
+    script: Arc<Script>,
+    producer: Option<SampleProducer>,
+    consumer: Option<SampleConsumer>,
+    player: Option<thread::JoinHandle<SampleProducer>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ScriptedSource {
+    pub fn new(script: Script, ring_capacity: usize) -> Self {
+        let format = script.initial_format();
+        let (mut producer, consumer) = sample_ring(ring_capacity, format.sample_rate);
+        producer.set_channels(format.channels);
+        Self {
+            script: Arc::new(script),
+            producer: Some(producer),
+            consumer: Some(consumer),
+            player: None,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Null backend: runs like a device but never produces samples
+    pub fn null(sample_rate: u32) -> Self {
+        Self::new(Script::new(sample_rate), sample_rate as usize)
+    }
+}
+
+impl CaptureSource for ScriptedSource {
+    /// Replays the script from the beginning on every start
+    fn start(&mut self) -> Result<()> {
+        let mut producer = self
+            .producer
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Scripted source already running"))?;
+        let script = self.script.clone();
+        let stop = self.stop.clone();
+        stop.store(false, Ordering::SeqCst);
+
+        self.player = Some(thread::spawn(move || {
+            let started = Instant::now();
+            'play: for block in script.blocks() {
+                while started.elapsed() < block.at {
+                    if stop.load(Ordering::SeqCst) {
+                        break 'play;
+                    }
+                    thread::sleep((block.at - started.elapsed()).min(Duration::from_millis(5)));
+                }
+                if let Some(format) = block.format {
+                    producer.set_format(format);
+                }
+                producer.set_capture_time(epoch_ms_ago(producer.format().duration_of(block.samples.len())));
+                producer.push_slice(&block.samples);
+            }
+            // A replay starts in the initial format again
+            producer.set_format(script.initial_format());
+            producer
+        }));
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(player) = self.player.take() {
+            if let Ok(producer) = player.join() {
+                self.producer = Some(producer);
+            }
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.script.initial_format().sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.script.initial_format().channels
+    }
+
+    fn take_consumer(&mut self) -> Option<SampleConsumer> {
+        self.consumer.take()
+    }
+
+    fn return_consumer(&mut self, mut consumer: SampleConsumer) {
+        consumer.clear();
+        self.consumer = Some(consumer);
+    }
+}
+
+// ============================================================================
+// VIRTUAL-TIME DRIVER
+// ============================================================================
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DriverStats {
+    pub samples_pushed: usize,
+    /// Samples the ring buffer could not take (device overrun)
+    pub samples_overrun: usize,
+    pub frames_emitted: usize,
+}
+
+/// Runs the DSP loop against a Script in virtual time (single-threaded)
+pub struct VirtualDriver {
+    clock: Arc<VirtualClock>,
+    producer: SampleProducer,
+    consumer: SampleConsumer,
+    capture_loop: CaptureLoop,
+    commands: CommandProducer,
+    stats: DriverStats,
+}
+
+impl VirtualDriver {
+    pub fn new(settings: &DspSettings, sample_rate: u32, ring_capacity: usize) -> Self {
+        Self::with_channels(settings, sample_rate, 1, ring_capacity)
+    }
+
+    /// Driver for scripts whose blocks hold interleaved `channels`-channel audio
+    pub fn with_channels(settings: &DspSettings, sample_rate: u32, channels: u16, ring_capacity: usize) -> Self {
+        let clock = Arc::new(VirtualClock::new());
+        let (mut producer, consumer) = sample_ring(ring_capacity, sample_rate);
+        producer.set_channels(channels);
+        let (commands, command_consumer) = command_queue();
+        let pipeline = DspPipeline::new(settings);
+        Self {
+            clock,
+            producer,
+            consumer,
+            capture_loop: CaptureLoop::new(sample_rate as f64, channels as usize, pipeline, command_consumer),
+            commands,
+            stats: DriverStats::default(),
+        }
+    }
+
+    pub fn clock(&self) -> &Arc<VirtualClock> {
+        &self.clock
+    }
+
+    pub fn pipeline(&self) -> &DspPipeline {
+        self.capture_loop.pipeline()
+    }
+
+    /// Receive events raised by the DSP loop (format changes)
+    pub fn set_event_handler(&mut self, handler: EventHandler) {
+        self.capture_loop.set_event_handler(handler);
+    }
+
+    /// Queue a command (applied at the next frame boundary, like configure())
+    pub fn send(&mut self, command: DspCommand) {
+        use ringbuf::traits::Producer;
+        let _ = self.commands.try_push(command);
+    }
+
+    /// Play `script` starting at the current virtual time
+    /// `emit` receives each frame with the virtual time it was emitted at.
+    pub fn run<F>(&mut self, script: &Script, mut emit: F) -> DriverStats
+    where
+        F: FnMut(Duration, PooledFrame),
+    {
+        let base = self.clock.elapsed();
+        for block in script.blocks() {
+            self.clock.set_elapsed(base + block.at);
+            if let Some(format) = block.format {
+                self.producer.set_format(format);
+            }
+            let captured = (base + block.at).saturating_sub(self.producer.format().duration_of(block.samples.len()));
+            self.producer.set_capture_time(captured.as_secs_f64() * 1000.0);
+            let pushed = self.producer.push_slice(&block.samples);
+            self.stats.samples_pushed += pushed;
+            self.stats.samples_overrun += block.samples.len() - pushed;
+            self.drain(&mut emit);
+        }
+        self.clock.set_elapsed(base + script.duration());
+        self.stats.clone()
+    }
+
+    /// Emit a partially filled batch (end of capture)
+    pub fn finish<F>(&mut self, mut emit: F)
+    where
+        F: FnMut(Duration, PooledFrame),
+    {
+        let now = self.clock.elapsed();
+        let frames_emitted = &mut self.stats.frames_emitted;
+        self.capture_loop.finish(&mut |frame| {
+            *frames_emitted += 1;
+            emit(now, frame);
+        });
+    }
+
+    fn drain<F>(&mut self, emit: &mut F)
+    where
+        F: FnMut(Duration, PooledFrame),
+    {
+        let now = self.clock.elapsed();
+        let frames_emitted = &mut self.stats.frames_emitted;
+        while !self.consumer.is_empty() {
+            self.capture_loop.step(&mut self.consumer, &mut |frame| {
+                *frames_emitted += 1;
+                emit(now, frame);
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::{CaptureCore, CaptureState};
+    use crate::delivery::{DeliveryConfig, DeliveryQueue, LagChange, OverflowPolicy};
+    use crate::dsp::{ChannelMode, VadMode};
+    use crate::events::CaptureEvent;
+    use std::sync::Mutex;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    fn settings(vad_mode: VadMode) -> DspSettings {
+        let mut settings = DspSettings::for_microphone();
+        settings.agc = None;
+        settings.vad_mode = vad_mode;
+        settings
+    }
+
+    /// (virtual ms, is keepalive) for every emitted frame
+    fn transcript(script: &Script) -> Vec<(u128, bool)> {
+        let mut driver = VirtualDriver::new(&settings(VadMode::Suppress), 16_000, 16_000);
+        let mut frames = Vec::new();
+        driver.run(script, |at, frame| frames.push((at.as_millis(), frame.is_silence())));
+        frames
+    }
+
+    #[test]
+    fn test_keepalive_cadence_is_deterministic() {
+        let script = Script::new(16_000).tone(ms(200), 0.5).silence(ms(1000));
+        let frames = transcript(&script);
+        assert_eq!(frames, transcript(&script), "same script, same frames");
+
+        // Speech + hangover frames first, then keepalives exactly 100ms apart
+        let keepalives: Vec<u128> = frames.iter().filter(|f| f.1).map(|f| f.0).collect();
+        assert!(frames.iter().take_while(|f| !f.1).count() >= 20);
+        assert!(keepalives.len() >= 7);
+        for pair in keepalives.windows(2) {
+            assert_eq!(pair[1] - pair[0], 100);
+        }
+    }
+
+    #[test]
+    fn test_frames_carry_sequence_and_capture_time() {
+        let script = Script::new(48_000).tone(ms(300), 0.5).gap(ms(100)).tone(ms(200), 0.5);
+        let mut driver = VirtualDriver::new(&settings(VadMode::Passthrough), 48_000, 48_000);
+        let mut frames = Vec::new();
+        driver.run(&script, |_, frame| frames.push((frame.seq(), frame.capture_time_ms().unwrap())));
+
+        assert_eq!(frames.len(), 25);
+        for (i, &(seq, _)) in frames.iter().enumerate() {
+            assert_eq!(seq, i as u64);
+        }
+        // Contiguous frames are 20ms apart, starting with the first sample
+        assert!(frames[0].1.abs() < 1.0, "first frame at {}ms", frames[0].1);
+        assert!((frames[10].1 - 200.0).abs() < 1.0);
+        // After the stall the stamps jump with the device clock
+        assert!((frames[15].1 - 400.0).abs() < 1.0, "after gap at {}ms", frames[15].1);
+        assert!((frames[24].1 - 580.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_gap_expires_hangover() {
+        // Silence before the stall is still inside the hangover (sent in
+        // full); after a 1s stall the first silent frame goes out as a keepalive
+        let script = Script::new(16_000)
+            .tone(ms(100), 0.5)
+            .silence(ms(40))
+            .gap(ms(1000))
+            .silence(ms(100));
+        let frames = transcript(&script);
+        assert!(frames.iter().filter(|f| f.0 <= 140).all(|f| !f.1));
+        let after_gap: Vec<&(u128, bool)> = frames.iter().filter(|f| f.0 > 140).collect();
+        assert_eq!(after_gap.first(), Some(&&(1160, true)));
+    }
+
+    #[test]
+    fn test_burst_overrun_drops_samples() {
+        let mut driver = VirtualDriver::new(&settings(VadMode::Passthrough), 16_000, 1600);
+        let script = Script::new(16_000).burst(ms(200), 0.5).tone(ms(100), 0.5);
+        let stats = driver.run(&script, |_, _| {});
+
+        // 3200 samples arrive at once into a 1600 sample ring
+        assert_eq!(stats.samples_overrun, 1600);
+        assert_eq!(stats.samples_pushed, 1600 + 1600);
+        assert_eq!(stats.frames_emitted, 10);
+        assert_eq!(driver.clock().elapsed(), ms(100));
+    }
+
+    #[test]
+    fn test_stalled_js_is_bounded_end_to_end() {
+        let mut driver = VirtualDriver::new(&settings(VadMode::Passthrough), 16_000, 16_000);
+        let queue = DeliveryQueue::new(DeliveryConfig {
+            max_queued: 10,
+            policy: OverflowPolicy::DropOldest,
+        });
+        let mut lag_started = 0;
+
+        // 1s of audio while JS never runs: only the newest 10 frames survive
+        let script = Script::new(16_000).tone(ms(1000), 0.5);
+        driver.run(&script, |_, frame| {
+            if queue.push(frame).1 == LagChange::Started {
+                lag_started += 1;
+            }
+        });
+        let stats = queue.stats();
+        assert_eq!(lag_started, 1);
+        assert_eq!(stats.dropped, 40);
+        assert_eq!(stats.queued, 10);
+
+        let mut recovered = false;
+        while let (Some(_), lag) = queue.pop() {
+            recovered |= lag == LagChange::Recovered;
+        }
+        assert!(recovered);
+    }
+
+    /// Interleaved stereo: tone on one side, digital silence on the other
+    fn panned_tone(duration: Duration, left: bool) -> Script {
+        let tone = Script::new(16_000).tone(duration, 0.5);
+        let samples = tone
+            .blocks()
+            .iter()
+            .flat_map(|block| block.samples.iter())
+            .flat_map(|&s| if left { [s, 0.0] } else { [0.0, s] })
+            .collect();
+        Script::new(16_000).block(samples)
+    }
+
+    fn peak(samples: impl Iterator<Item = i16>) -> i16 {
+        samples.map(|s| s.saturating_abs()).max().unwrap_or(0)
+    }
+
+    #[test]
+    fn test_stereo_keep_and_downmix() {
+        let script = panned_tone(ms(100), true);
+
+        let mut keep = settings(VadMode::Passthrough);
+        keep.channel_mode = ChannelMode::Keep;
+        let mut frames = Vec::new();
+        VirtualDriver::with_channels(&keep, 16_000, 2, 16_000)
+            .run(&script, |_, frame| frames.push(frame.samples().to_vec()));
+        assert_eq!(frames.len(), 5);
+        assert!(frames.iter().all(|frame| frame.len() == 640));
+        assert!(peak(frames[2].iter().step_by(2).copied()) > 12_000);
+        assert_eq!(peak(frames[2].iter().skip(1).step_by(2).copied()), 0);
+
+        // Downmix averages: mono frames at half the level
+        let mut frames = Vec::new();
+        VirtualDriver::with_channels(&settings(VadMode::Passthrough), 16_000, 2, 16_000)
+            .run(&script, |_, frame| frames.push(frame.samples().to_vec()));
+        assert_eq!(frames.len(), 5);
+        assert!(frames.iter().all(|frame| frame.len() == 320));
+        let level = peak(frames[2].iter().copied());
+        assert!((6_000..10_000).contains(&level), "downmix peak {}", level);
+    }
+
+    #[test]
+    fn test_kept_channels_detect_speech_on_either_side() {
+        let mut keep = settings(VadMode::Suppress);
+        keep.channel_mode = ChannelMode::Keep;
+        keep.suppression.speech_hangover = Duration::ZERO;
+
+        let mut frames = Vec::new();
+        VirtualDriver::with_channels(&keep, 16_000, 2, 32_000)
+            .run(&panned_tone(ms(500), false), |_, frame| frames.push(frame.is_silence()));
+        assert_eq!(frames.len(), 25);
+        assert!(frames.iter().all(|&silence| !silence));
+    }
+
+    fn record_events(driver: &mut VirtualDriver) -> Arc<Mutex<Vec<CaptureEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        driver.set_event_handler(Arc::new(move |event| sink.lock().unwrap().push(event)));
+        events
+    }
+
+    #[test]
+    fn test_rate_change_mid_stream_is_seamless() {
+        // Bluetooth headset switching from A2DP (48kHz) to HFP (16kHz)
+        let script = Script::new(48_000)
+            .tone(ms(200), 0.5)
+            .format(16_000, 1)
+            .tone(ms(200), 0.5);
+        let mut driver = VirtualDriver::new(&settings(VadMode::Passthrough), 48_000, 48_000);
+        let events = record_events(&mut driver);
+
+        let mut frames = Vec::new();
+        driver.run(&script, |_, frame| frames.push(frame.samples().to_vec()));
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![CaptureEvent::FormatChanged {
+                sample_rate: 16_000,
+                channels: 1,
+                output_channels: 1
+            }]
+        );
+        // 400ms of audio -> 20 frames of 16kHz, none lost or stretched at the switch
+        assert!((19..=20).contains(&frames.len()), "{} frames", frames.len());
+        assert!(frames.iter().all(|frame| frame.len() == 320));
+        assert!(frames[1..].iter().all(|frame| peak(frame.iter().copied()) > 12_000));
+    }
+
+    #[test]
+    fn test_channel_change_with_kept_channels() {
+        let script = Script::new(16_000)
+            .format(16_000, 2)
+            .tone(ms(100), 0.5)
+            .format(16_000, 1)
+            .tone(ms(100), 0.5);
+        let mut keep = settings(VadMode::Passthrough);
+        keep.channel_mode = ChannelMode::Keep;
+        let mut driver = VirtualDriver::with_channels(&keep, 16_000, 2, 16_000);
+        let events = record_events(&mut driver);
+
+        let mut lengths = Vec::new();
+        driver.run(&script, |_, frame| lengths.push(frame.len()));
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![CaptureEvent::FormatChanged {
+                sample_rate: 16_000,
+                channels: 1,
+                output_channels: 1
+            }]
+        );
+        assert_eq!(lengths.iter().filter(|&&len| len == 640).count(), 5);
+        assert!(lengths.iter().skip_while(|&&len| len == 640).all(|&len| len == 320));
+    }
+
+    #[test]
+    fn test_keyword_suppression_silences_the_phrase() {
+        use crate::classifier::test_signals::{frames, speech, voice};
+        use crate::keywords::{Keyword, KeywordConfig, Template};
+
+        let phrase = voice(0.8, 11, 1.2, 1.0);
+        let mut spotting = settings(VadMode::Passthrough);
+        spotting.keywords = Some(KeywordConfig {
+            keywords: vec![Keyword {
+                name: "answer this".into(),
+                templates: vec![Template::from_audio(&frames(&phrase).concat()).unwrap()],
+            }],
+            suppress: true,
+            ..KeywordConfig::default()
+        });
+        let script = Script::new(16_000)
+            .block(speech(2.0, 3))
+            .block(phrase)
+            .block(speech(2.0, 4));
+        let mut driver = VirtualDriver::new(&spotting, 16_000, 16_000 * 6);
+        let holdback = driver.pipeline().keyword_holdback();
+        let events = record_events(&mut driver);
+
+        let mut frames = Vec::new();
+        driver.run(&script, |_, frame| frames.push((frame.seq(), frame.is_silence())));
+        // The tail is still held back until the end of the run
+        let total = (16_000 * 4 + 12_800) / crate::audio_config::FRAME_SAMPLES;
+        assert_eq!(frames.len(), total - holdback);
+        driver.finish(|_, frame| frames.push((frame.seq(), frame.is_silence())));
+        assert_eq!(frames.len(), total);
+
+        let events = events.lock().unwrap();
+        let [CaptureEvent::Keyword { keyword, seq_start, seq_end, .. }] = events.as_slice() else {
+            panic!("{:?}", events);
+        };
+        assert_eq!(keyword, "answer this");
+        // The phrase is frames 100..140 (its soft onset is trimmed from the template)
+        assert!((100..=106).contains(seq_start) && (137..=142).contains(seq_end), "{}..={}", seq_start, seq_end);
+        for &(seq, silence) in &frames {
+            assert_eq!(silence, (*seq_start..=*seq_end).contains(&seq), "frame {}", seq);
+        }
+    }
+
+    #[test]
+    fn test_null_source_runs_without_frames() {
+        let mut core = CaptureCore::new("Null", ScriptedSource::null(16_000), settings(VadMode::Passthrough));
+        let (tx, rx) = std::sync::mpsc::channel();
+        core.start(move |frame| {
+            let _ = tx.send(frame.len());
+        })
+        .unwrap();
+        assert_eq!(core.state(), CaptureState::Running);
+        assert!(rx.recv_timeout(ms(100)).is_err());
+        core.stop();
+        assert_eq!(core.state(), CaptureState::Stopped);
+    }
+}
@@ -0,0 +1,390 @@
+// Utterance Segmenter - whole speech segments for REST STT
+//
+// Streaming STT wants a steady flow of frames; REST STT wants one file per
+// utterance. In utterance mode the DSP thread groups processed frames into
+// utterances instead of delivering them one by one:
+// - An utterance opens on the first voiced frame (above the suppressor's
+//   threshold) and starts up to `pre_roll` earlier, so quiet word onsets
+//   are not clipped
+// - It closes when the suppressor leaves speech (hangover elapsed); the
+//   trailing silence is trimmed to `trailing_padding` after the last voiced frame
+// - Less than `min_duration` of speech (first to last voiced frame) is
+//   dropped: clicks, coughs, a door
+// - At `max_duration` it is split after the quietest frame of its second
+//   half; the remainder continues as the next utterance
+//
+// Frames may be interleaved (kept channels); levels are taken across channels.
+//
+// REAL-TIME NOTES:
+// - The pre-roll ring is bounded and reused while idle
+// - An utterance's buffer grows with it and is handed off as a whole
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::audio_config::{FRAME_MS, FRAME_SAMPLES, SAMPLE_RATE};
+use crate::wav::ClipFormat;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmenterConfig {
+    /// Audio kept before the first voiced frame
+    pub pre_roll: Duration,
+    /// Silence kept after the last voiced frame (at most the suppressor's hangover)
+    pub trailing_padding: Duration,
+    /// Utterances with less speech than this are dropped
+    pub min_duration: Duration,
+    /// Longer utterances are split at their quietest point
+    pub max_duration: Duration,
+}
+
+impl Default for SegmenterConfig {
+    fn default() -> Self {
+        Self {
+            pre_roll: Duration::from_millis(300),
+            trailing_padding: Duration::from_millis(300),
+            min_duration: Duration::from_millis(250),
+            max_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+/// What the pipeline decided about one frame
+#[derive(Debug, Clone, Copy)]
+pub struct FrameInfo {
+    pub seq: u64,
+    pub capture_time_ms: Option<f64>,
+    /// The frame itself is speech
+    pub voiced: bool,
+    /// The suppressor is in speech (voiced or hangover)
+    pub speech: bool,
+}
+
+/// A stage that follows the processed stream (diarizer, question detector,
+/// keyword spotter): fed every mono frame with what the pipeline decided
+/// about it
+pub trait FrameAnalyzer {
+    type Output;
+
+    /// Next frame; returns a finding once one is complete
+    fn push(&mut self, frame: &[i16], info: &FrameInfo) -> Option<Self::Output>;
+
+    /// Whatever is still pending (end of run)
+    fn flush(&mut self) -> Option<Self::Output> {
+        None
+    }
+}
+
+/// A complete utterance (16kHz i16, interleaved if channels > 1)
+#[derive(Debug, Clone)]
+pub struct Utterance {
+    pub samples: Vec<i16>,
+    pub channels: u16,
+    /// Sequence number of the first frame
+    pub seq: u64,
+    /// Capture time of the first sample (ms since the Unix epoch)
+    pub start_time_ms: Option<f64>,
+    /// Capture time just after the last sample
+    pub end_time_ms: Option<f64>,
+    /// Cut at max_duration rather than ended by a pause
+    pub forced_split: bool,
+}
+
+impl Utterance {
+    pub fn duration(&self) -> Duration {
+        let frames = self.samples.len() / self.channels.max(1) as usize;
+        Duration::from_micros(frames as u64 * 1_000_000 / SAMPLE_RATE as u64)
+    }
+
+    /// Bytes handed to JS
+    pub fn encode(&self, format: ClipFormat) -> Vec<u8> {
+        format.encode(&self.samples, SAMPLE_RATE, self.channels)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FrameMeta {
+    seq: u64,
+    capture_time_ms: Option<f64>,
+    rms: f32,
+    voiced: bool,
+}
+
+pub struct UtteranceSegmenter {
+    config: SegmenterConfig,
+    channels: usize,
+    /// Interleaved samples per frame
+    frame_len: usize,
+    /// Most recent frames while no utterance is open (oldest first)
+    pre_roll_samples: VecDeque<i16>,
+    pre_roll_frames: VecDeque<FrameMeta>,
+    /// The open utterance (empty when idle)
+    samples: Vec<i16>,
+    frames: Vec<FrameMeta>,
+}
+
+impl UtteranceSegmenter {
+    pub fn new(config: SegmenterConfig, channels: usize) -> Self {
+        let channels = channels.max(1);
+        let pre_roll = frames_in(config.pre_roll);
+        println!(
+            "[Segmenter] Created: pre-roll={}ms, padding={}ms, min={}ms, max={}ms",
+            config.pre_roll.as_millis(),
+            config.trailing_padding.as_millis(),
+            config.min_duration.as_millis(),
+            config.max_duration.as_millis()
+        );
+        Self {
+            config,
+            channels,
+            frame_len: FRAME_SAMPLES * channels,
+            pre_roll_samples: VecDeque::with_capacity((pre_roll + 1) * FRAME_SAMPLES * channels),
+            pre_roll_frames: VecDeque::with_capacity(pre_roll + 1),
+            samples: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Add one processed frame; returns an utterance when one is complete
+    pub fn push(&mut self, frame: &[i16], info: FrameInfo) -> Option<Utterance> {
+        let meta = FrameMeta {
+            seq: info.seq,
+            capture_time_ms: info.capture_time_ms,
+            rms: rms(frame),
+            voiced: info.voiced,
+        };
+        if self.frames.is_empty() {
+            if !info.voiced {
+                self.keep_pre_roll(frame, meta);
+                return None;
+            }
+            self.samples.extend(self.pre_roll_samples.drain(..));
+            self.frames.extend(self.pre_roll_frames.drain(..));
+        }
+        self.samples.extend_from_slice(frame);
+        self.frames.push(meta);
+
+        if !info.speech {
+            return self.close();
+        }
+        if self.frames.len() >= frames_in(self.config.max_duration).max(2) {
+            return self.split();
+        }
+        None
+    }
+
+    /// Close the open utterance (end of capture)
+    pub fn flush(&mut self) -> Option<Utterance> {
+        let utterance = if self.frames.is_empty() { None } else { self.close() };
+        self.pre_roll_samples.clear();
+        self.pre_roll_frames.clear();
+        utterance
+    }
+
+    /// The frame layout changed: the open utterance ends here
+    pub fn set_channels(&mut self, channels: usize) -> Option<Utterance> {
+        let utterance = self.flush();
+        self.channels = channels.max(1);
+        self.frame_len = FRAME_SAMPLES * self.channels;
+        utterance
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.frames.is_empty()
+    }
+
+    /// Speech ended: trim the trailing silence, drop it if too short
+    fn close(&mut self) -> Option<Utterance> {
+        let first = self.frames.iter().position(|frame| frame.voiced);
+        let last = self.frames.iter().rposition(|frame| frame.voiced);
+        let (Some(first), Some(last)) = (first, last) else {
+            self.discard();
+            return None;
+        };
+        if (last - first + 1) < frames_in(self.config.min_duration) {
+            self.discard();
+            return None;
+        }
+
+        let end = (last + 1 + frames_in(self.config.trailing_padding)).min(self.frames.len());
+        // The trimmed silence is the start of the next pre-roll
+        let tail_samples = self.samples.split_off(end * self.frame_len);
+        let tail_frames = self.frames.split_off(end);
+        let utterance = self.take(false);
+        for (frame, meta) in tail_samples.chunks_exact(self.frame_len).zip(tail_frames) {
+            self.keep_pre_roll(frame, meta);
+        }
+        Some(utterance)
+    }
+
+    /// Max duration reached: cut after the quietest frame of the second half
+    fn split(&mut self) -> Option<Utterance> {
+        let len = self.frames.len();
+        let quietest = (len / 2..len)
+            .min_by(|&a, &b| self.frames[a].rms.total_cmp(&self.frames[b].rms))
+            .unwrap_or(len - 1);
+        let cut = quietest + 1;
+        let rest_samples = self.samples.split_off(cut * self.frame_len);
+        let rest_frames = self.frames.split_off(cut);
+        let utterance = self.take(true);
+        self.samples = rest_samples;
+        self.frames = rest_frames;
+        Some(utterance)
+    }
+
+    fn take(&mut self, forced_split: bool) -> Utterance {
+        let first = self.frames[0];
+        let last = self.frames[self.frames.len() - 1];
+        self.frames.clear();
+        Utterance {
+            samples: std::mem::take(&mut self.samples),
+            channels: self.channels as u16,
+            seq: first.seq,
+            start_time_ms: first.capture_time_ms,
+            end_time_ms: last.capture_time_ms.map(|time| time + FRAME_MS as f64),
+            forced_split,
+        }
+    }
+
+    /// Too little speech: the audio only serves as pre-roll
+    fn discard(&mut self) {
+        let samples = std::mem::take(&mut self.samples);
+        let frames = std::mem::take(&mut self.frames);
+        for (frame, meta) in samples.chunks_exact(self.frame_len).zip(frames) {
+            self.keep_pre_roll(frame, meta);
+        }
+    }
+
+    fn keep_pre_roll(&mut self, frame: &[i16], meta: FrameMeta) {
+        let limit = frames_in(self.config.pre_roll);
+        if limit == 0 {
+            return;
+        }
+        self.pre_roll_samples.extend(frame);
+        self.pre_roll_frames.push_back(meta);
+        while self.pre_roll_frames.len() > limit {
+            self.pre_roll_frames.pop_front();
+            self.pre_roll_samples.drain(..self.frame_len);
+        }
+    }
+}
+
+/// Whole frames in `duration`
+fn frames_in(duration: Duration) -> usize {
+    (duration.as_millis() / FRAME_MS as u128) as usize
+}
+
+fn rms(frame: &[i16]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / frame.len() as f64).sqrt() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds frames with consecutive sequence numbers, 20ms apart
+    struct Feed {
+        segmenter: UtteranceSegmenter,
+        seq: u64,
+        utterances: Vec<Utterance>,
+    }
+
+    impl Feed {
+        fn new(config: SegmenterConfig) -> Self {
+            Self {
+                segmenter: UtteranceSegmenter::new(config, 1),
+                seq: 0,
+                utterances: Vec::new(),
+            }
+        }
+
+        fn push(&mut self, frames: usize, amplitude: i16, voiced: bool, speech: bool) {
+            for _ in 0..frames {
+                let info = FrameInfo {
+                    seq: self.seq,
+                    capture_time_ms: Some(1_000.0 + self.seq as f64 * FRAME_MS as f64),
+                    voiced,
+                    speech,
+                };
+                let frame = vec![amplitude; FRAME_SAMPLES];
+                self.utterances.extend(self.segmenter.push(&frame, info));
+                self.seq += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_pre_roll_and_trailing_padding() {
+        let mut feed = Feed::new(SegmenterConfig {
+            pre_roll: Duration::from_millis(100),
+            trailing_padding: Duration::from_millis(40),
+            ..SegmenterConfig::default()
+        });
+        feed.push(20, 10, false, false);
+        feed.push(15, 3000, true, true);
+        // Hangover, then the suppressor gives up
+        feed.push(10, 10, false, true);
+        assert!(feed.utterances.is_empty());
+        feed.push(1, 10, false, false);
+
+        assert_eq!(feed.utterances.len(), 1);
+        let utterance = &feed.utterances[0];
+        // 5 frames pre-roll + 15 speech + 2 padding
+        assert_eq!(utterance.samples.len(), 22 * FRAME_SAMPLES);
+        assert_eq!(utterance.seq, 15);
+        assert_eq!(utterance.start_time_ms, Some(1_300.0));
+        assert_eq!(utterance.end_time_ms, Some(1_740.0));
+        assert_eq!(utterance.duration(), Duration::from_millis(440));
+        assert!(!utterance.forced_split);
+        assert!(!feed.segmenter.is_open());
+    }
+
+    #[test]
+    fn test_short_blips_are_dropped() {
+        let mut feed = Feed::new(SegmenterConfig::default());
+        feed.push(5, 3000, true, true);
+        feed.push(15, 10, false, true);
+        feed.push(1, 10, false, false);
+        assert!(feed.utterances.is_empty());
+        assert!(!feed.segmenter.is_open());
+    }
+
+    #[test]
+    fn test_max_duration_splits_at_quietest_frame() {
+        let mut feed = Feed::new(SegmenterConfig {
+            pre_roll: Duration::ZERO,
+            max_duration: Duration::from_secs(1),
+            ..SegmenterConfig::default()
+        });
+        feed.push(35, 3000, true, true);
+        // A breath inside the hangover
+        feed.push(1, 200, false, true);
+        feed.push(13, 3000, true, true);
+        assert!(feed.utterances.is_empty());
+        feed.push(1, 3000, true, true);
+
+        assert_eq!(feed.utterances.len(), 1);
+        assert!(feed.utterances[0].forced_split);
+        assert_eq!(feed.utterances[0].samples.len(), 36 * FRAME_SAMPLES);
+
+        // The rest continues as the next utterance
+        feed.push(10, 3000, true, true);
+        feed.push(1, 10, false, false);
+        assert_eq!(feed.utterances.len(), 2);
+        assert_eq!(feed.utterances[1].seq, 36);
+        assert_eq!(feed.utterances[1].samples.len(), 25 * FRAME_SAMPLES);
+        assert!(!feed.utterances[1].forced_split);
+    }
+
+    #[test]
+    fn test_flush_closes_open_utterance() {
+        let mut feed = Feed::new(SegmenterConfig::default());
+        feed.push(20, 3000, true, true);
+        let utterance = feed.segmenter.flush().expect("open utterance");
+        assert_eq!(utterance.samples.len(), 20 * FRAME_SAMPLES);
+        assert!(feed.segmenter.flush().is_none());
+    }
+}
@@ -0,0 +1,219 @@
+// Music Attenuation - optional separation stage for system audio
+//
+// Background music under a talker is sustained: a chord holds its partials
+// for hundreds of milliseconds, while speech harmonics glide and stop between
+// syllables. Per FFT bin, the minimum of the smoothed power over the last
+// 300ms therefore tracks the music and not the voice, and subtracting it
+// leaves mostly speech.
+//
+// PROCESSING:
+// - 40ms sqrt-Hann windows, 20ms hop (one window per frame), overlap-add
+// - Gain per bin: sqrt(1 - OVER_SUBTRACTION * music / power), floored
+// - The pipeline ramps the stage in only after the classifier heard music,
+//   so plain speech passes untouched
+//
+// LATENCY:
+// - Overlap-add delays audio by one frame (20ms) while the stage is enabled
+//
+// REAL-TIME NOTES:
+// - FFT plans and buffers are allocated once in new(), process() does no allocation
+
+use std::sync::Arc;
+
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+
+use crate::audio_config::FRAME_SAMPLES;
+
+/// Two frames per analysis window
+const WINDOW_SIZE: usize = FRAME_SAMPLES * 2;
+
+/// Frames over which the per-bin minimum is taken (300ms)
+const MIN_WINDOW_FRAMES: usize = 15;
+
+/// Recursive smoothing of the power spectrum before the minimum
+const POWER_SMOOTHING: f32 = 0.6;
+
+/// The minimum underestimates the mean music level
+const OVER_SUBTRACTION: f32 = 2.5;
+
+/// Lowest gain applied to a bin (-16dB), keeps musical-noise artifacts down
+const GAIN_FLOOR: f32 = 0.15;
+
+/// Per-frame step of the enable / disable ramp
+const STRENGTH_STEP: f32 = 0.1;
+
+pub struct MusicAttenuator {
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    /// sqrt-Hann: analysis * synthesis sums to 1 at 50% overlap
+    window: Vec<f32>,
+    /// Previous input frame (first half of the next window)
+    prev_input: Vec<f32>,
+    /// Second half of the last synthesized window
+    overlap: Vec<f32>,
+    time: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    forward_scratch: Vec<Complex<f32>>,
+    inverse_scratch: Vec<Complex<f32>>,
+    smoothed: Vec<f32>,
+    /// Last MIN_WINDOW_FRAMES smoothed spectra (ring)
+    history: Vec<Vec<f32>>,
+    history_pos: usize,
+    history_len: usize,
+    /// 0 = bypass, 1 = full attenuation
+    strength: f32,
+}
+
+impl MusicAttenuator {
+    pub fn new() -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(WINDOW_SIZE);
+        let ifft = planner.plan_fft_inverse(WINDOW_SIZE);
+        let spectrum = fft.make_output_vec();
+        let bins = spectrum.len();
+        Self {
+            window: (0..WINDOW_SIZE)
+                .map(|i| (std::f32::consts::PI * i as f32 / WINDOW_SIZE as f32).sin())
+                .collect(),
+            prev_input: vec![0.0; FRAME_SAMPLES],
+            overlap: vec![0.0; FRAME_SAMPLES],
+            time: fft.make_input_vec(),
+            forward_scratch: fft.make_scratch_vec(),
+            inverse_scratch: ifft.make_scratch_vec(),
+            spectrum,
+            smoothed: vec![0.0; bins],
+            history: vec![vec![0.0; bins]; MIN_WINDOW_FRAMES],
+            history_pos: 0,
+            history_len: 0,
+            strength: 0.0,
+            fft,
+            ifft,
+        }
+    }
+
+    /// Process one mono frame in place (output is one frame late)
+    ///
+    /// `active`: music was heard recently; the stage ramps in / out over 200ms.
+    pub fn process(&mut self, frame: &mut [i16], active: bool) {
+        let target = if active { 1.0 } else { 0.0 };
+        if self.strength < target {
+            self.strength = (self.strength + STRENGTH_STEP).min(1.0);
+        } else if self.strength > target {
+            self.strength = (self.strength - STRENGTH_STEP).max(0.0);
+        }
+
+        // Analysis window: previous frame + this frame
+        let n = frame.len().min(FRAME_SAMPLES);
+        for (i, slot) in self.time.iter_mut().enumerate() {
+            let sample = if i < FRAME_SAMPLES {
+                self.prev_input[i]
+            } else {
+                frame.get(i - FRAME_SAMPLES).map_or(0.0, |&s| s as f32)
+            };
+            *slot = sample * self.window[i];
+        }
+        for (prev, &sample) in self.prev_input.iter_mut().zip(frame.iter()) {
+            *prev = sample as f32;
+        }
+
+        // Lengths come from the plans, so these can't fail
+        let _ = self.fft.process_with_scratch(&mut self.time, &mut self.spectrum, &mut self.forward_scratch);
+
+        // Track the sustained component and subtract it
+        let slot = &mut self.history[self.history_pos];
+        for ((smoothed, bin), stored) in self.smoothed.iter_mut().zip(&self.spectrum).zip(slot.iter_mut()) {
+            *smoothed = POWER_SMOOTHING * *smoothed + (1.0 - POWER_SMOOTHING) * bin.norm_sqr();
+            *stored = *smoothed;
+        }
+        self.history_pos = (self.history_pos + 1) % MIN_WINDOW_FRAMES;
+        self.history_len = (self.history_len + 1).min(MIN_WINDOW_FRAMES);
+
+        if self.strength > 0.0 && self.history_len == MIN_WINDOW_FRAMES {
+            for (k, bin) in self.spectrum.iter_mut().enumerate() {
+                let music = self.history.iter().map(|h| h[k]).fold(f32::MAX, f32::min);
+                let power = bin.norm_sqr().max(f32::MIN_POSITIVE);
+                let gain = (1.0 - OVER_SUBTRACTION * music / power).max(0.0).sqrt().max(GAIN_FLOOR);
+                *bin *= 1.0 - self.strength * (1.0 - gain);
+            }
+        }
+
+        // DC and Nyquist must be real for the inverse transform
+        let last = self.spectrum.len() - 1;
+        self.spectrum[0].im = 0.0;
+        self.spectrum[last].im = 0.0;
+        let _ = self.ifft.process_with_scratch(&mut self.spectrum, &mut self.time, &mut self.inverse_scratch);
+
+        // Overlap-add (realfft's inverse is unnormalized)
+        let scale = 1.0 / WINDOW_SIZE as f32;
+        for (i, out) in frame.iter_mut().take(n).enumerate() {
+            let value = self.overlap[i] + self.time[i] * self.window[i] * scale;
+            *out = value.round().clamp(-32768.0, 32767.0) as i16;
+        }
+        for (i, tail) in self.overlap.iter_mut().enumerate() {
+            let j = FRAME_SAMPLES + i;
+            *tail = self.time[j] * self.window[j] * scale;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.prev_input.fill(0.0);
+        self.overlap.fill(0.0);
+        self.smoothed.fill(0.0);
+        self.history_len = 0;
+        self.strength = 0.0;
+    }
+}
+
+impl Default for MusicAttenuator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classifier::test_signals::{frames, mix, music, speech};
+
+    fn run(signal: &[f32], active: bool) -> Vec<i16> {
+        let mut attenuator = MusicAttenuator::new();
+        let mut out = Vec::with_capacity(signal.len());
+        for mut frame in frames(signal) {
+            attenuator.process(&mut frame, active);
+            out.extend_from_slice(&frame);
+        }
+        out
+    }
+
+    /// Energy of `reference` over the energy of what differs from it (dB),
+    /// after undoing the one-frame delay and skipping the warm-up
+    fn snr_db(output: &[i16], reference: &[f32]) -> f32 {
+        let skip = FRAME_SAMPLES * 25;
+        let (mut signal, mut error) = (0.0f64, 0.0f64);
+        for (out, r) in output[skip + FRAME_SAMPLES..].iter().zip(&reference[skip..]) {
+            let r = (r * 32767.0) as f64;
+            signal += r * r;
+            error += (*out as f64 - r).powi(2);
+        }
+        10.0 * (signal / error).log10() as f32
+    }
+
+    #[test]
+    fn test_bypass_reconstructs_input() {
+        let signal = speech(1.0, 5);
+        let output = run(&signal, false);
+        assert!(snr_db(&output, &signal) > 40.0);
+    }
+
+    #[test]
+    fn test_music_under_speech_is_attenuated() {
+        let voice = speech(4.0, 3);
+        let mixed = mix(&voice, &music(4.0, 2), 1.0);
+
+        let before = snr_db(&run(&mixed, false), &voice);
+        let after = snr_db(&run(&mixed, true), &voice);
+        println!("speech-to-music: {:.1}dB -> {:.1}dB", before, after);
+        assert!(after > before + 3.0, "{:.1}dB -> {:.1}dB", before, after);
+    }
+}
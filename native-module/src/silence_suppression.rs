@@ -2,18 +2,29 @@
 //
 // DESIGN PRINCIPLES:
 // 1. Google STT requires timing continuity - never send gaps
-// 2. During silence, send keepalive frames every 100ms
+// 2. During silence, send keepalive frames every 100ms (zeros or comfort
+//    noise, see comfort_noise.rs), or every frame for providers that need
+//    contiguous audio
 // 3. During speech, send ALL frames immediately with NO delay
 // 4. Hangover is f.  or cost savings only, NOT for first-word accuracy
 //
 // LATENCY BUDGET:
 // - Speech onset: 0ms delay (immediate)
 // - Hangover: Only affects AFTER speech ends (no latency impact)
+//
+// TIMING:
+// - Time is counted in processed samples (16kHz), not read from a clock:
+//   a backlog drained in one go gets the same hangover / keepalive cadence
+//   as real-time audio, and tests are reproducible
+// - Device stalls (no samples at all) are reported with advance()
+
+use std::time::Duration;
 
-use std::time::{Duration, Instant};  // Added for timing
+use crate::audio_config::SAMPLE_RATE;
 
 /// Configuration for silence suppression
 /// Optimized for low latency
+#[derive(Debug, Clone)]
 pub struct SilenceSuppressionConfig {
     /// RMS threshold for speech detection (i16 scale: 0-32767)
     pub speech_threshold_rms: f32,
@@ -24,6 +35,32 @@ pub struct SilenceSuppressionConfig {
     
     /// How often to send a keepalive frame during silence
     pub silence_keepalive_interval: Duration,
+
+    /// What keepalive / fill frames contain
+    pub silence_fill: SilenceFill,
+
+    /// Send a fill frame for every suppressed slot instead of one per
+    /// keepalive interval (contiguous timeline)
+    pub contiguous: bool,
+}
+
+/// Content of the frames sent in place of silence
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SilenceFill {
+    /// Digital zeros (default)
+    Zeros,
+    /// Low-level noise shaped like the measured background
+    ComfortNoise,
+}
+
+impl SilenceFill {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "zeros" => Some(SilenceFill::Zeros),
+            "comfort-noise" => Some(SilenceFill::ComfortNoise),
+            _ => None,
+        }
+    }
 }
 
 impl Default for SilenceSuppressionConfig {
@@ -32,18 +69,24 @@ impl Default for SilenceSuppressionConfig {
             speech_threshold_rms: 100.0,  // Lower = more sensitive
             speech_hangover: Duration::from_millis(200),  // Shorter = faster cost savings
             silence_keepalive_interval: Duration::from_millis(100),
+            silence_fill: SilenceFill::Zeros,
+            contiguous: false,
         }
     }
 }
 
 impl SilenceSuppressionConfig {
-    /// Create config for system audio (very permissive - system audio is quieter)
+    /// Create config for system audio
+    /// Levels are normalized by the AGC stage upstream (quiet speech over a
+    /// quieter floor is boosted), so the same threshold as the microphone
+    /// applies
     pub fn for_system_audio() -> Self {
         Self {
-            // System audio often has much lower levels
-            speech_threshold_rms: 30.0,  // Very low threshold
+            speech_threshold_rms: 100.0,
             speech_hangover: Duration::from_millis(300),
             silence_keepalive_interval: Duration::from_millis(100),
+            silence_fill: SilenceFill::Zeros,
+            contiguous: false,
         }
     }
     
@@ -53,6 +96,8 @@ impl SilenceSuppressionConfig {
             speech_threshold_rms: 100.0,
             speech_hangover: Duration::from_millis(200),
             silence_keepalive_interval: Duration::from_millis(100),
+            silence_fill: SilenceFill::Zeros,
+            contiguous: false,
         }
     }
 }
@@ -61,8 +106,10 @@ impl SilenceSuppressionConfig {
 pub struct SilenceSuppressor {
     config: SilenceSuppressionConfig,
     state: SuppressionState,
-    last_speech_time: Instant,
-    last_keepalive_time: Instant,
+    /// Samples processed (plus stalls) since creation
+    position: u64,
+    last_speech_at: u64,
+    last_keepalive_at: u64,
     frames_sent: u64,
     frames_suppressed: u64,
 }
@@ -77,8 +124,8 @@ enum SuppressionState {
 /// Result of processing a frame
 #[derive(Debug, Clone)]
 pub enum FrameAction {
-    /// Send this frame to STT
-    Send(Vec<i16>),
+    /// Send this frame to STT (caller still owns the frame - no copy)
+    Send,
     /// Replace with silence keepalive frame
     SendSilence,
     /// Suppress this frame (timing maintained by keepalives)
@@ -87,7 +134,6 @@ pub enum FrameAction {
 
 impl SilenceSuppressor {
     pub fn new(config: SilenceSuppressionConfig) -> Self {
-        let now = Instant::now();
         println!("[SilenceSuppressor] Created with threshold={}, hangover={}ms, keepalive={}ms",
             config.speech_threshold_rms,
             config.speech_hangover.as_millis(),
@@ -96,8 +142,9 @@ impl SilenceSuppressor {
         Self {
             config,
             state: SuppressionState::Active, // Start in active to not miss first words
-            last_speech_time: now,
-            last_keepalive_time: now,
+            position: 0,
+            last_speech_at: 0,
+            last_keepalive_at: 0,
             frames_sent: 0,
             frames_suppressed: 0,
         }
@@ -106,30 +153,38 @@ impl SilenceSuppressor {
     /// Process a frame and determine what to do with it
     /// CRITICAL: Speech frames are NEVER delayed
     pub fn process(&mut self, frame: &[i16]) -> FrameAction {
-        let now = Instant::now();
+        self.process_gated(frame, false)
+    }
+
+    /// Like process(), but `gated` frames (the content classifier hears music
+    /// or noise) never count as speech, however loud they are
+    pub fn process_gated(&mut self, frame: &[i16], gated: bool) -> FrameAction {
+        // A frame is timed by its end: hangover runs from the end of the last speech frame
+        self.position += frame.len() as u64;
+        let now = self.position;
         let rms = calculate_rms(frame);
-        let has_speech = rms >= self.config.speech_threshold_rms;
+        let has_speech = !gated && rms >= self.config.speech_threshold_rms;
         
         // ALWAYS check for speech first - immediate response
         if has_speech {
             self.state = SuppressionState::Active;
-            self.last_speech_time = now;
+            self.last_speech_at = now;
             self.frames_sent += 1;
-            return FrameAction::Send(frame.to_vec());
+            return FrameAction::Send;
         }
         
         // No speech detected - check state
         match self.state {
             SuppressionState::Active | SuppressionState::Hangover => {
                 // Check if hangover period has elapsed
-                if now.duration_since(self.last_speech_time) > self.config.speech_hangover {
+                if now - self.last_speech_at > samples_for(self.config.speech_hangover) {
                     self.state = SuppressionState::Suppressed;
                     // Fall through to check keepalive
                 } else {
                     // Still in hangover - send full frame
                     self.state = SuppressionState::Hangover;
                     self.frames_sent += 1;
-                    return FrameAction::Send(frame.to_vec());
+                    return FrameAction::Send;
                 }
             }
             SuppressionState::Suppressed => {
@@ -138,8 +193,8 @@ impl SilenceSuppressor {
         }
         
         // In suppressed state - check if time for keepalive
-        if now.duration_since(self.last_keepalive_time) >= self.config.silence_keepalive_interval {
-            self.last_keepalive_time = now;
+        if self.config.contiguous || now - self.last_keepalive_at >= samples_for(self.config.silence_keepalive_interval) {
+            self.last_keepalive_at = now;
             self.frames_sent += 1;
             FrameAction::SendSilence
         } else {
@@ -148,6 +203,22 @@ impl SilenceSuppressor {
         }
     }
     
+    /// Replace thresholds/timings without resetting state
+    /// (used for runtime reconfiguration of a running capture)
+    pub fn set_config(&mut self, config: SilenceSuppressionConfig) {
+        println!("[SilenceSuppressor] Reconfigured: threshold={}, hangover={}ms, keepalive={}ms",
+            config.speech_threshold_rms,
+            config.speech_hangover.as_millis(),
+            config.silence_keepalive_interval.as_millis()
+        );
+        self.config = config;
+    }
+
+    /// Current configuration
+    pub fn config(&self) -> &SilenceSuppressionConfig {
+        &self.config
+    }
+    
     /// Get statistics
     pub fn stats(&self) -> (u64, u64) {
         (self.frames_sent, self.frames_suppressed)
@@ -157,16 +228,31 @@ impl SilenceSuppressor {
     pub fn is_speech(&self) -> bool {
         matches!(self.state, SuppressionState::Active | SuppressionState::Hangover)
     }
+
+    /// The last processed frame itself was speech (not hangover)
+    pub fn is_voiced(&self) -> bool {
+        self.state == SuppressionState::Active
+    }
     
+    /// Time passed without samples (device stall): hangover and keepalive
+    /// intervals run on as if silence had been processed
+    pub fn advance(&mut self, elapsed: Duration) {
+        self.position += samples_for(elapsed);
+    }
+
     /// Reset state (e.g., when meeting ends)
     pub fn reset(&mut self) {
-        let now = Instant::now();
         self.state = SuppressionState::Active;
-        self.last_speech_time = now;
-        self.last_keepalive_time = now;
+        self.last_speech_at = self.position;
+        self.last_keepalive_at = self.position;
     }
 }
 
+/// Samples at 16kHz spanning `duration`
+fn samples_for(duration: Duration) -> u64 {
+    (duration.as_micros() * SAMPLE_RATE as u128 / 1_000_000) as u64
+}
+
 /// Calculate RMS of i16 samples efficiently
 fn calculate_rms(samples: &[i16]) -> f32 {
     if samples.is_empty() {
@@ -179,7 +265,7 @@ fn calculate_rms(samples: &[i16]) -> f32 {
         .map(|&s| (s as f64) * (s as f64))
         .sum();
     
-    let count = (samples.len() + 3) / 4;
+    let count = samples.len().div_ceil(4);
     (sum_of_squares / count as f64).sqrt() as f32
 }
 
@@ -199,7 +285,7 @@ mod tests {
         // Loud frame should be sent immediately
         let loud_frame: Vec<i16> = vec![500; 320];
         match suppressor.process(&loud_frame) {
-            FrameAction::Send(_) => {}
+            FrameAction::Send => {}
             _ => panic!("Loud frame should be sent immediately"),
         }
         assert!(suppressor.is_speech());
@@ -211,10 +297,52 @@ mod tests {
             speech_threshold_rms: 100.0,
             speech_hangover: Duration::from_millis(0),
             silence_keepalive_interval: Duration::from_millis(50),
+            ..SilenceSuppressionConfig::default()
         });
         
         let silent_frame: Vec<i16> = vec![0; 320];
         let action = suppressor.process(&silent_frame);
         assert!(matches!(action, FrameAction::SendSilence | FrameAction::Suppress));
     }
+
+    #[test]
+    fn test_backlog_keeps_frame_cadence() {
+        // Frames processed back to back (a drained backlog): timing follows
+        // the samples, not how fast they were processed
+        let mut suppressor = SilenceSuppressor::new(SilenceSuppressionConfig {
+            speech_threshold_rms: 100.0,
+            speech_hangover: Duration::from_millis(200),
+            silence_keepalive_interval: Duration::from_millis(100),
+            ..SilenceSuppressionConfig::default()
+        });
+        assert!(matches!(suppressor.process(&[500; 320]), FrameAction::Send));
+
+        let actions: Vec<FrameAction> = (0..31).map(|_| suppressor.process(&[0; 320])).collect();
+        let hangover = actions.iter().take_while(|a| matches!(a, FrameAction::Send)).count();
+        assert_eq!(hangover, 10);
+        let keepalives: Vec<usize> = actions
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| matches!(a, FrameAction::SendSilence))
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(keepalives, vec![10, 15, 20, 25, 30]);
+
+        // A stall counts as elapsed time (the next keepalive would be 4 frames away)
+        suppressor.advance(Duration::from_millis(100));
+        assert!(matches!(suppressor.process(&[0; 320]), FrameAction::SendSilence));
+    }
+
+    #[test]
+    fn test_contiguous_fills_every_slot() {
+        let mut suppressor = SilenceSuppressor::new(SilenceSuppressionConfig {
+            speech_hangover: Duration::from_millis(0),
+            contiguous: true,
+            ..SilenceSuppressionConfig::default()
+        });
+        for _ in 0..20 {
+            assert!(matches!(suppressor.process(&[0; 320]), FrameAction::SendSilence));
+        }
+        assert_eq!(suppressor.stats(), (20, 0));
+    }
 }
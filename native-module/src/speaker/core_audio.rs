@@ -1,20 +1,20 @@
 use anyhow::Result;
 use cidre::{arc, av, cat, cf, core_audio as ca, ns, os};
-use ringbuf::{traits::{Producer, Split}, HeapProd, HeapRb, HeapCons};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::{Arc, Mutex};
-use std::task::{Waker};
+use std::sync::Arc;
+use std::time::Duration;
 use ca::aggregate_device_keys as agg_keys;
 
-struct WakerState {
-    waker: Option<Waker>,
-    has_data: bool,
-}
+use super::{AppSelection, AudioApplication, SpeakerOptions};
+use crate::clock::epoch_ms_ago;
+use crate::sample_ring::{AudioFormat, RingConfig, SampleConsumer, SampleProducer};
 
 struct Ctx {
     format: arc::R<av::AudioFormat>,
-    producer: HeapProd<f32>,
-    waker_state: Arc<Mutex<WakerState>>,
+    producer: SampleProducer,
+    channels: usize,
+    /// Scratch for interleaving non-interleaved (planar) stereo buffers
+    interleaved: Vec<f32>,
     current_sample_rate: Arc<AtomicU32>,
     consecutive_drops: Arc<AtomicU32>,
     should_terminate: Arc<AtomicBool>,
@@ -23,10 +23,17 @@ struct Ctx {
 pub struct SpeakerInput {
     tap: ca::TapGuard, 
     agg_desc: arc::R<cf::DictionaryOf<cf::String, cf::Type>>,
+    ring: RingConfig,
 }
 
 impl SpeakerInput {
-    pub fn new(device_id: Option<String>) -> Result<Self> {
+    /// Process taps are mono or stereo, so `options.channels` must be 1 or 2
+    pub fn new(device_id: Option<String>, options: &SpeakerOptions) -> Result<Self> {
+        let stereo = match options.channels {
+            0 | 1 => false,
+            2 => true,
+            n => return Err(anyhow::anyhow!("CoreAudio taps record 1 or 2 channels, not {}", n)),
+        };
         // 1. Find the target output device
         let output_device = match device_id {
             Some(ref uid) if !uid.is_empty() && uid != "default" => {
@@ -48,9 +55,19 @@ impl SpeakerInput {
             &[output_uid.as_type_ref()],
         );
 
-        // Create global tap (mono for STT processing)
-        // NOTE: Using mono tap. If audio quality issues persist, revisit this.
-        let tap_desc = ca::TapDesc::with_mono_global_tap_excluding_processes(&ns::Array::new());
+        // Create tap (mono by default; stereo keeps panned participants apart)
+        let tap_desc = match (&options.selection, stereo) {
+            (AppSelection::All, false) => ca::TapDesc::with_mono_global_tap_excluding_processes(&ns::Array::new()),
+            (AppSelection::All, true) => ca::TapDesc::with_stereo_global_tap_excluding_processes(&ns::Array::new()),
+            (AppSelection::Except(pids), false) => {
+                ca::TapDesc::with_mono_global_tap_excluding_processes(&process_objects(pids)?)
+            }
+            (AppSelection::Except(pids), true) => {
+                ca::TapDesc::with_stereo_global_tap_excluding_processes(&process_objects(pids)?)
+            }
+            (AppSelection::Only(pids), false) => ca::TapDesc::with_mono_mixdown_of_processes(&process_objects(pids)?),
+            (AppSelection::Only(pids), true) => ca::TapDesc::with_stereo_mixdown_of_processes(&process_objects(pids)?),
+        };
         let tap = tap_desc.create_process_tap()?;
         println!("[CoreAudioTap] Tap created: {:?}", tap.uid());
 
@@ -86,7 +103,7 @@ impl SpeakerInput {
             ],
         );
 
-        Ok(Self { tap, agg_desc })
+        Ok(Self { tap, agg_desc, ring: options.ring.clone() })
     }
 
     fn start_device(
@@ -95,31 +112,48 @@ impl SpeakerInput {
     ) -> Result<ca::hardware::StartedDevice<ca::AggregateDevice>> {
         extern "C" fn proc(
             device: ca::Device,
-            _now: &cat::AudioTimeStamp,
+            now: &cat::AudioTimeStamp,
             input_data: &cat::AudioBufList<1>,
-            _input_time: &cat::AudioTimeStamp,
+            input_time: &cat::AudioTimeStamp,
             _output_data: &mut cat::AudioBufList<1>,
             _output_time: &cat::AudioTimeStamp,
             ctx: Option<&mut Ctx>,
         ) -> os::Status {
             let ctx = ctx.unwrap();
 
-            // Update sample rate if needed
-            ctx.current_sample_rate.store(
-                device
-                    .actual_sample_rate()
-                    .unwrap_or(ctx.format.absd().sample_rate) as u32,
-                Ordering::Release,
-            );
+            // Device rate changed (e.g., Bluetooth profile switch): publish it
+            // in-band so the DSP thread retunes exactly at this callback's samples
+            let rate = device
+                .actual_sample_rate()
+                .unwrap_or(ctx.format.absd().sample_rate) as u32;
+            if ctx.current_sample_rate.swap(rate, Ordering::AcqRel) != rate {
+                ctx.producer.set_sample_rate(rate);
+            }
+
+            // Input was recorded (now - input time) device samples ago
+            let age_samples = (now.sample_time - input_time.sample_time).max(0.0);
+            ctx.producer
+                .set_capture_time(epoch_ms_ago(Duration::from_secs_f64(age_samples / rate.max(1) as f64)));
 
             // Extract audio data
-            if let Some(view) =
-                av::AudioPcmBuf::with_buf_list_no_copy(&ctx.format, input_data, None)
+            let planar = ctx.channels > 1 && !ctx.format.is_interleaved();
+            if let Some(view) = av::AudioPcmBuf::with_buf_list_no_copy(&ctx.format, input_data, None)
+                .filter(|_| ctx.channels == 1 || planar)
             {
-                if let Some(data) = view.data_f32_at(0) {
+                if planar {
+                    // One buffer per channel (taps are at most stereo): interleave L/R
+                    if let (Some(left), Some(right)) = (view.data_f32_at(0), view.data_f32_at(1)) {
+                        let mut interleaved = std::mem::take(&mut ctx.interleaved);
+                        interleaved.clear();
+                        interleaved.extend(left.iter().zip(right).flat_map(|(&l, &r)| [l, r]));
+                        process_audio_data(ctx, &interleaved);
+                        ctx.interleaved = interleaved;
+                    }
+                } else if let Some(data) = view.data_f32_at(0) {
                      process_audio_data(ctx, data);
                 }
             } else if ctx.format.common_format() == av::audio::CommonFormat::PcmF32 {
+                // Mono, or interleaved multichannel (all channels in the first buffer)
                 let first_buffer = &input_data.buffers[0];
                 let byte_count = first_buffer.data_bytes_size as usize;
                 let float_count = byte_count / std::mem::size_of::<f32>();
@@ -149,21 +183,19 @@ impl SpeakerInput {
         let format = av::AudioFormat::with_asbd(&asbd).unwrap();
         println!("[CoreAudioTap] Format: {}Hz, {}ch", asbd.sample_rate, asbd.channels_per_frame);
 
-        let buffer_size = 1024 * 128; // ~340ms at 48k
-        let rb = HeapRb::<f32>::new(buffer_size);
-        let (producer, consumer) = rb.split();
-
-        let waker_state = Arc::new(Mutex::new(WakerState {
-            waker: None,
-            has_data: false,
-        }));
+        let channels = asbd.channels_per_frame.max(1) as usize;
+        let (producer, consumer) = self.ring.ring(AudioFormat {
+            sample_rate: asbd.sample_rate as u32,
+            channels: channels as u16,
+        });
 
         let current_sample_rate = Arc::new(AtomicU32::new(asbd.sample_rate as u32));
 
         let mut ctx = Box::new(Ctx {
             format,
             producer,
-            waker_state: waker_state.clone(),
+            channels,
+            interleaved: Vec::with_capacity(4096),
             current_sample_rate: current_sample_rate.clone(),
             consecutive_drops: Arc::new(AtomicU32::new(0)),
             should_terminate: Arc::new(AtomicBool::new(false)),
@@ -174,6 +206,7 @@ impl SpeakerInput {
 
         SpeakerStream {
             consumer: Some(consumer),
+            channels: channels as u16,
             _device: device,
             _ctx: ctx,
             _tap: self.tap,
@@ -182,6 +215,40 @@ impl SpeakerInput {
     }
 }
 
+/// Processes currently playing audio (CoreAudio process objects)
+pub fn list_audio_applications() -> Result<Vec<AudioApplication>> {
+    let mut apps = Vec::new();
+    for process in ca::System::processes()? {
+        if !process.is_running_output().unwrap_or(false) {
+            continue;
+        }
+        let Ok(pid) = process.pid() else { continue };
+        let name = process
+            .bundle_id()
+            .map(|id| id.to_string())
+            .unwrap_or_else(|_| format!("pid {}", pid));
+        apps.push(AudioApplication { pid: pid as u32, name });
+    }
+    apps.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(apps)
+}
+
+/// Translate pids into the CoreAudio process object IDs a tap description expects
+fn process_objects(pids: &[u32]) -> Result<arc::R<ns::Array<ns::Number>>> {
+    let mut objects = Vec::new();
+    for process in ca::System::processes()? {
+        match process.pid() {
+            Ok(pid) if pids.contains(&(pid as u32)) => objects.push(ns::Number::with_u32(process.0 .0)),
+            _ => {}
+        }
+    }
+    if objects.is_empty() {
+        println!("[CoreAudioTap] None of the selected processes ({:?}) are known to CoreAudio", pids);
+    }
+    let refs: Vec<&ns::Number> = objects.iter().map(|n| n.as_ref()).collect();
+    Ok(ns::Array::from_slice(&refs))
+}
+
 fn process_audio_data(ctx: &mut Ctx, data: &[f32]) {
     // Debug Logging for signal analysis
     static mut LOG_COUNTER: usize = 0;
@@ -201,7 +268,7 @@ fn process_audio_data(ctx: &mut Ctx, data: &[f32]) {
         }
     }
 
-    // Processing Logic
+    // Processing Logic (push_slice wakes the DSP thread once a frame is buffered)
     let buffer_size = data.len();
     let pushed = ctx.producer.push_slice(data);
 
@@ -218,24 +285,11 @@ fn process_audio_data(ctx: &mut Ctx, data: &[f32]) {
     } else {
         ctx.consecutive_drops.store(0, Ordering::Release);
     }
-
-    let should_wake = {
-        let mut waker_state = ctx.waker_state.lock().unwrap();
-        if !waker_state.has_data {
-            waker_state.has_data = true;
-            waker_state.waker.take()
-        } else {
-            None
-        }
-    };
-
-    if let Some(waker) = should_wake {
-        waker.wake();
-    }
 }
 
 pub struct SpeakerStream {
-    consumer: Option<HeapCons<f32>>, // Option so we can take it
+    consumer: Option<SampleConsumer>, // Option so we can take it
+    channels: u16,
     _device: ca::hardware::StartedDevice<ca::AggregateDevice>,
     _ctx: Box<Ctx>,
     _tap: ca::TapGuard,
@@ -247,7 +301,11 @@ impl SpeakerStream {
         self.current_sample_rate.load(Ordering::Acquire)
     }
 
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn take_consumer(&mut self) -> Option<SampleConsumer> {
         self.consumer.take()
     }
 }
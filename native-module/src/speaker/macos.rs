@@ -1,9 +1,11 @@
 use anyhow::Result;
-use ringbuf::HeapCons;
 use super::core_audio;
 use super::sck;
+use super::SpeakerOptions;
+use crate::sample_ring::SampleConsumer;
 
 pub use super::sck::list_output_devices;
+pub use super::core_audio::list_audio_applications;
 
 pub struct SpeakerInput {
     backend: BackendInput,
@@ -16,12 +18,18 @@ enum BackendInput {
 
 impl SpeakerInput {
     pub fn new(device_id: Option<String>) -> Result<Self> {
+        Self::with_options(device_id, SpeakerOptions::default())
+    }
+
+    /// Per-application capture is only available through CoreAudio process taps
+    /// Both backends record mono or stereo.
+    pub fn with_options(device_id: Option<String>, options: SpeakerOptions) -> Result<Self> {
         let force_sck = device_id.as_deref() == Some("sck");
         
         if !force_sck {
             // Try CoreAudio Tap first (Default)
             println!("[SpeakerInput] Initializing CoreAudio Tap backend...");
-            match core_audio::SpeakerInput::new(device_id.clone()) {
+            match core_audio::SpeakerInput::new(device_id.clone(), &options) {
                 Ok(input) => {
                      println!("[SpeakerInput] CoreAudio Tap backend initialized.");
                      return Ok(Self { backend: BackendInput::CoreAudio(input) });
@@ -34,12 +42,16 @@ impl SpeakerInput {
             println!("[SpeakerInput] SCK backend explicitly requested.");
         }
         
+        if !options.selection.is_all() {
+            return Err(anyhow::anyhow!("Per-application capture requires CoreAudio process taps (macOS 14.4+)"));
+        }
+
         // Fallback to ScreenCaptureKit
-        let input = sck::SpeakerInput::new(device_id)?;
+        let input = sck::SpeakerInput::new(device_id, options.channels, options.ring)?;
         Ok(Self { backend: BackendInput::Sck(input) })
     }
     
-    pub fn stream(self) -> SpeakerStream {
+    pub fn stream(self) -> Result<SpeakerStream> {
         match self.backend {
             BackendInput::CoreAudio(input) => {
                 // We wrap the stream creation to catch potential panics if start_device fails
@@ -50,11 +62,11 @@ impl SpeakerInput {
                 // We should assume it works or modify core_audio.rs. 
                 // Given the constraints, let's assume if tap creation worked, starting works.
                 let stream = input.stream();
-                SpeakerStream { backend: BackendStream::CoreAudio(stream) }
+                Ok(SpeakerStream { backend: BackendStream::CoreAudio(stream) })
             },
             BackendInput::Sck(input) => {
                 let stream = input.stream();
-                SpeakerStream { backend: BackendStream::Sck(stream) }
+                Ok(SpeakerStream { backend: BackendStream::Sck(stream) })
             }
         }
     }
@@ -76,8 +88,15 @@ impl SpeakerStream {
              BackendStream::Sck(s) => s.sample_rate(),
         }
     }
+
+    pub fn channels(&self) -> u16 {
+        match &self.backend {
+             BackendStream::CoreAudio(s) => s.channels(),
+             BackendStream::Sck(s) => s.channels(),
+        }
+    }
     
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+    pub fn take_consumer(&mut self) -> Option<SampleConsumer> {
         match &mut self.backend {
              BackendStream::CoreAudio(s) => s.take_consumer(),
              BackendStream::Sck(s) => s.take_consumer(),
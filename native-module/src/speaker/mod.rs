@@ -14,6 +14,8 @@ pub use macos::SpeakerInput;
 pub use macos::SpeakerStream;
 #[cfg(target_os = "macos")]
 pub use macos::list_output_devices;
+#[cfg(target_os = "macos")]
+pub use macos::list_audio_applications;
 
 #[cfg(target_os = "windows")]
 pub mod windows;
@@ -23,21 +25,102 @@ pub use windows::SpeakerInput;
 pub use windows::SpeakerStream;
 #[cfg(target_os = "windows")]
 pub use windows::list_output_devices;
+#[cfg(target_os = "windows")]
+pub use windows::list_audio_applications;
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+#[cfg(target_os = "linux")]
+pub mod pulse;
+#[cfg(target_os = "linux")]
+pub use pulse::SpeakerInput;
+#[cfg(target_os = "linux")]
+pub use pulse::SpeakerStream;
+#[cfg(target_os = "linux")]
+pub use pulse::list_output_devices;
+#[cfg(target_os = "linux")]
+pub use pulse::list_audio_applications;
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
 pub mod fallback {
     use anyhow::Result;
+    use super::{AudioApplication, SpeakerOptions};
     pub struct SpeakerInput;
     impl SpeakerInput {
         pub fn new(_device_id: Option<String>) -> Result<Self> {
             Err(anyhow::anyhow!("Unsupported platform"))
         }
+        pub fn with_options(_device_id: Option<String>, _options: SpeakerOptions) -> Result<Self> {
+            Err(anyhow::anyhow!("Unsupported platform"))
+        }
     }
     pub fn list_output_devices() -> Result<Vec<(String, String)>> {
         Ok(Vec::new())
     }
+    pub fn list_audio_applications() -> Result<Vec<AudioApplication>> {
+        Ok(Vec::new())
+    }
 }
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
 pub use fallback::SpeakerInput;
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
 pub use fallback::list_output_devices;
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub use fallback::list_audio_applications;
+
+/// An application currently producing audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioApplication {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// Which applications a system audio capture records
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AppSelection {
+    /// The whole output mix
+    #[default]
+    All,
+    /// Only these processes
+    Only(Vec<u32>),
+    /// Everything except these processes
+    Except(Vec<u32>),
+}
+
+impl AppSelection {
+    /// Whether audio from `pid` is captured
+    pub fn includes(&self, pid: u32) -> bool {
+        match self {
+            AppSelection::All => true,
+            AppSelection::Only(pids) => pids.contains(&pid),
+            AppSelection::Except(pids) => !pids.contains(&pid),
+        }
+    }
+
+    pub fn is_all(&self) -> bool {
+        *self == AppSelection::All
+    }
+}
+
+use crate::sample_ring::RingConfig;
+
+/// Most channels a system audio capture can record
+pub const MAX_CHANNELS: u16 = 8;
+
+/// What a system audio capture records
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerOptions {
+    pub selection: AppSelection,
+    /// Interleaved channels pushed into the ring buffer (1 = mono)
+    pub channels: u16,
+    /// Ring buffer sizing / overflow policy (same for every backend)
+    pub ring: RingConfig,
+}
+
+impl Default for SpeakerOptions {
+    fn default() -> Self {
+        Self {
+            selection: AppSelection::All,
+            channels: 1,
+            ring: RingConfig::default(),
+        }
+    }
+}
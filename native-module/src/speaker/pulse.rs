@@ -0,0 +1,648 @@
+// PulseAudio / PipeWire system audio capture (Linux)
+//
+// Talks to the sound server through the standard `pactl` / `parec` clients,
+// which work against both PulseAudio and pipewire-pulse:
+// - Whole mix: one parec recording the output sink's monitor source
+// - Per application: one `parec --monitor-stream=<index>` per selected sink
+//   input (an app's playback stream), mixed here. Sink inputs are rescanned
+//   every second so apps that start playing later are picked up.
+//
+// Samples are interleaved with the requested channel count; the mixer and
+// backlog trimming only ever move whole sample frames.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ringbuf::{traits::{Consumer, Observer, Producer, Split}, HeapCons, HeapRb};
+
+use super::{AppSelection, AudioApplication, SpeakerOptions};
+use crate::clock::epoch_ms_ago;
+use crate::sample_ring::{AudioFormat, RingConfig, SampleConsumer, SampleProducer};
+
+/// Rate requested from parec (the server resamples if needed, so the ring
+/// format never changes mid-stream even when the sink's rate does)
+const CAPTURE_SAMPLE_RATE: u32 = 48000;
+
+/// Mixer tick for per-application capture
+const MIX_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often sink inputs are rescanned for new / finished apps
+const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sample frames an app stream must buffer before it is mixed (absorbs pipe jitter)
+const STREAM_PREBUFFER: usize = CAPTURE_SAMPLE_RATE as usize / 25; // 40ms
+
+/// Backlog (sample frames) beyond which an app stream is trimmed (its clock runs ahead of ours)
+const MAX_STREAM_BACKLOG: usize = CAPTURE_SAMPLE_RATE as usize / 5; // 200ms
+
+/// One application playback stream ("Sink Input #N" in pactl)
+#[derive(Debug, Clone, PartialEq)]
+struct SinkInput {
+    index: u32,
+    pid: Option<u32>,
+    name: String,
+}
+
+pub struct SpeakerInput {
+    device_id: Option<String>,
+    selection: AppSelection,
+    channels: u16,
+    ring: RingConfig,
+}
+
+pub struct SpeakerStream {
+    consumer: Option<SampleConsumer>,
+    capture: Capture,
+    channels: u16,
+}
+
+enum Capture {
+    /// parec on the monitor source pushes straight into the ring
+    Mix(Option<Tap>),
+    /// Mixer thread owning one tap per selected sink input
+    Apps {
+        shutdown: Arc<AtomicBool>,
+        mixer_thread: Option<thread::JoinHandle<()>>,
+    },
+}
+
+impl SpeakerStream {
+    pub fn sample_rate(&self) -> u32 {
+        CAPTURE_SAMPLE_RATE
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn take_consumer(&mut self) -> Option<SampleConsumer> {
+        self.consumer.take()
+    }
+}
+
+impl SpeakerInput {
+    pub fn new(device_id: Option<String>) -> Result<Self> {
+        Self::with_options(device_id, SpeakerOptions::default())
+    }
+
+    /// Capture only some applications (device_id is ignored unless capturing All)
+    /// and/or more than one channel (the server up/downmixes as needed)
+    pub fn with_options(device_id: Option<String>, options: SpeakerOptions) -> Result<Self> {
+        let device_id = device_id.filter(|id| !id.is_empty() && id != "default");
+        // Fail early (so the caller can fall back) if no sound server is reachable
+        pactl(&["info"])?;
+        // Every capture records through parec; without it start() would succeed silently
+        Command::new("parec")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| anyhow::anyhow!("parec not available: {}", e))?;
+        Ok(Self {
+            device_id,
+            selection: options.selection,
+            channels: options.channels.max(1),
+            ring: options.ring,
+        })
+    }
+
+    /// Fails if the monitor source's parec can't be started (whole mix)
+    pub fn stream(self) -> Result<SpeakerStream> {
+        let channels = self.channels;
+        let (mut producer, consumer) = self.ring.ring(AudioFormat { sample_rate: CAPTURE_SAMPLE_RATE, channels });
+
+        let capture = if self.selection.is_all() {
+            let monitor = match &self.device_id {
+                Some(sink) => format!("{}.monitor", sink),
+                None => "@DEFAULT_MONITOR@".to_string(),
+            };
+            println!("[PulseCapture] Recording monitor source {}", monitor);
+            let tap = Tap::spawn(&[format!("--device={}", monitor)], channels, move |samples| {
+                // parec gives no timestamps: the chunk was recorded just before it arrived
+                producer.set_capture_time(epoch_ms_ago(producer.format().duration_of(samples.len())));
+                producer.push_slice(samples);
+            })?;
+            Capture::Mix(Some(tap))
+        } else {
+            println!("[PulseCapture] Per-application capture: {:?}", self.selection);
+            let shutdown = Arc::new(AtomicBool::new(false));
+            let shutdown_mixer = shutdown.clone();
+            let selection = self.selection;
+            let mixer_thread =
+                thread::spawn(move || run_app_mixer(selection, channels, producer, shutdown_mixer));
+            Capture::Apps {
+                shutdown,
+                mixer_thread: Some(mixer_thread),
+            }
+        };
+
+        Ok(SpeakerStream {
+            consumer: Some(consumer),
+            capture,
+            channels,
+        })
+    }
+}
+
+impl Drop for SpeakerStream {
+    fn drop(&mut self) {
+        match &mut self.capture {
+            Capture::Mix(tap) => {
+                tap.take();
+            }
+            Capture::Apps { shutdown, mixer_thread } => {
+                shutdown.store(true, Ordering::Release);
+                if let Some(handle) = mixer_thread.take() {
+                    let _ = handle.join();
+                }
+            }
+        }
+    }
+}
+
+pub fn list_output_devices() -> Result<Vec<(String, String)>> {
+    Ok(parse_sinks(&pactl(&["list", "sinks"])?))
+}
+
+/// Applications with an active playback stream (one entry per process)
+pub fn list_audio_applications() -> Result<Vec<AudioApplication>> {
+    let mut apps: Vec<AudioApplication> = Vec::new();
+    for input in parse_sink_inputs(&pactl(&["list", "sink-inputs"])?) {
+        if let Some(pid) = input.pid {
+            if !apps.iter().any(|app| app.pid == pid) {
+                apps.push(AudioApplication { pid, name: input.name });
+            }
+        }
+    }
+    apps.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(apps)
+}
+
+// ============================================================================
+// PER-APPLICATION MIXER
+// ============================================================================
+
+/// A selected sink input's parec and the samples it has delivered so far
+struct AppTap {
+    _tap: Tap,
+    stream: AppStream,
+}
+
+/// Samples of one app stream waiting to be mixed
+struct AppStream {
+    consumer: HeapCons<f32>,
+    channels: usize,
+    primed: bool,
+}
+
+impl AppStream {
+    fn new(consumer: HeapCons<f32>, channels: usize) -> Self {
+        Self {
+            consumer,
+            channels,
+            primed: false,
+        }
+    }
+
+    /// Add this stream's next `mix.len()` samples (whole sample frames) to `mix`
+    fn mix_into(&mut self, mix: &mut [f32]) {
+        let prebuffer = STREAM_PREBUFFER * self.channels;
+        let backlog = self.consumer.occupied_len() / self.channels * self.channels;
+        if !self.primed {
+            if backlog < prebuffer {
+                return;
+            }
+            self.primed = true;
+        }
+        if backlog > mix.len() + MAX_STREAM_BACKLOG * self.channels {
+            self.consumer.skip(backlog - mix.len() - prebuffer);
+        }
+        let available = (self.consumer.occupied_len() / self.channels * self.channels).min(mix.len());
+        for out in mix[..available].iter_mut() {
+            *out += self.consumer.try_pop().unwrap_or(0.0);
+        }
+        if available < mix.len() {
+            // Underrun: wait for a fresh prebuffer instead of crackling
+            self.primed = false;
+        }
+    }
+}
+
+/// Mix selected app streams in real time (10ms ticks) until shutdown
+fn run_app_mixer(selection: AppSelection, channels: u16, mut producer: SampleProducer, shutdown: Arc<AtomicBool>) {
+    let mut taps: HashMap<u32, AppTap> = HashMap::new();
+    let mut last_scan: Option<Instant> = None;
+    let mut last_tick = Instant::now();
+    let mut fractional = 0.0f64;
+    let mut mix: Vec<f32> = Vec::with_capacity(CAPTURE_SAMPLE_RATE as usize / 10 * channels as usize);
+    let prebuffer = Duration::from_secs_f64(STREAM_PREBUFFER as f64 / CAPTURE_SAMPLE_RATE as f64);
+
+    while !shutdown.load(Ordering::Acquire) {
+        if last_scan.is_none_or(|t| t.elapsed() >= RESCAN_INTERVAL) {
+            rescan_sink_inputs(&selection, channels, &mut taps);
+            last_scan = Some(Instant::now());
+        }
+
+        thread::sleep(MIX_INTERVAL);
+
+        // Emit exactly as many samples as wall-clock time has passed
+        let now = Instant::now();
+        let due = now.duration_since(last_tick).as_secs_f64() * CAPTURE_SAMPLE_RATE as f64 + fractional;
+        last_tick = now;
+        let count = due as usize;
+        fractional = due - count as f64;
+
+        mix.clear();
+        mix.resize(count * channels as usize, 0.0);
+        for tap in taps.values_mut() {
+            tap.stream.mix_into(&mut mix);
+        }
+        for sample in mix.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+        // The tick covers the last `count` frames, delayed by the streams' prebuffer
+        let age = producer.format().duration_of(mix.len()) + prebuffer;
+        producer.set_capture_time(epoch_ms_ago(age));
+        producer.push_slice(&mix);
+    }
+    println!("[PulseCapture] Mixer stopped ({} app streams)", taps.len());
+}
+
+fn rescan_sink_inputs(selection: &AppSelection, channels: u16, taps: &mut HashMap<u32, AppTap>) {
+    let inputs = match pactl(&["list", "sink-inputs"]) {
+        Ok(output) => parse_sink_inputs(&output),
+        Err(e) => {
+            eprintln!("[PulseCapture] {}", e);
+            return;
+        }
+    };
+
+    // Streams that finished
+    taps.retain(|index, _| inputs.iter().any(|input| input.index == *index));
+
+    for input in inputs {
+        if !is_selected(selection, &input) || taps.contains_key(&input.index) {
+            continue;
+        }
+
+        let frame = channels as usize;
+        let (mut stream_producer, consumer) = HeapRb::<f32>::new(MAX_STREAM_BACKLOG * 2 * frame).split();
+        let tap = Tap::spawn(&[format!("--monitor-stream={}", input.index)], channels, move |samples| {
+            // Whole sample frames only, so a full buffer never shifts the channel order
+            let whole = stream_producer.vacant_len() / frame * frame;
+            stream_producer.push_slice(&samples[..samples.len().min(whole)]);
+        });
+        match tap {
+            Ok(tap) => {
+                println!(
+                    "[PulseCapture] Capturing sink input #{} ({}, pid {:?})",
+                    input.index, input.name, input.pid
+                );
+                taps.insert(
+                    input.index,
+                    AppTap {
+                        _tap: tap,
+                        stream: AppStream::new(consumer, frame),
+                    },
+                );
+            }
+            Err(e) => eprintln!("[PulseCapture] Sink input #{}: {}", input.index, e),
+        }
+    }
+}
+
+/// Whether `selection` records this sink input
+fn is_selected(selection: &AppSelection, input: &SinkInput) -> bool {
+    match input.pid {
+        Some(pid) => selection.includes(pid),
+        // Unknown owner: only part of "everything except ..."
+        None => matches!(selection, AppSelection::Except(_)),
+    }
+}
+
+// ============================================================================
+// PAREC / PACTL
+// ============================================================================
+
+/// A running parec process and the thread draining its stdout
+struct Tap {
+    child: Child,
+    reader: Option<thread::JoinHandle<()>>,
+}
+
+impl Tap {
+    fn spawn<F>(args: &[String], channels: u16, push: F) -> Result<Self>
+    where
+        F: FnMut(&[f32]) + Send + 'static,
+    {
+        let mut child = Command::new("parec")
+            .args(["--raw", "--format=float32le", "--latency-msec=20"])
+            .arg(format!("--rate={}", CAPTURE_SAMPLE_RATE))
+            .arg(format!("--channels={}", channels))
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start parec: {}", e))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("parec has no stdout"))?;
+        let reader = thread::spawn(move || pump_samples(stdout, channels as usize, push));
+        Ok(Self {
+            child,
+            reader: Some(reader),
+        })
+    }
+}
+
+impl Drop for Tap {
+    fn drop(&mut self) {
+        // Killing parec closes the pipe, which ends the reader thread
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// Decode float32le from parec until the pipe closes
+/// `push` always receives whole sample frames of `channels` samples.
+fn pump_samples<F: FnMut(&[f32])>(mut stdout: ChildStdout, channels: usize, mut push: F) {
+    let frame_bytes = 4 * channels;
+    let mut bytes = vec![0u8; 4096 / frame_bytes * frame_bytes];
+    let mut filled = 0;
+    let mut samples: Vec<f32> = Vec::with_capacity(bytes.len() / 4);
+    loop {
+        match stdout.read(&mut bytes[filled..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => filled += n,
+        }
+        let whole = filled / frame_bytes * frame_bytes;
+        samples.clear();
+        samples.extend(
+            bytes[..whole]
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        );
+        push(&samples);
+        bytes.copy_within(whole..filled, 0);
+        filled -= whole;
+    }
+}
+
+fn pactl(args: &[&str]) -> Result<String> {
+    let output = Command::new("pactl")
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .map_err(|e| anyhow::anyhow!("pactl not available: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "pactl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `pactl list sink-inputs`
+fn parse_sink_inputs(output: &str) -> Vec<SinkInput> {
+    let mut inputs = Vec::new();
+    let mut current: Option<SinkInput> = None;
+    for line in output.lines() {
+        if let Some(index) = line.strip_prefix("Sink Input #") {
+            inputs.extend(current.take());
+            current = index.trim().parse().ok().map(|index| SinkInput {
+                index,
+                pid: None,
+                name: String::new(),
+            });
+            continue;
+        }
+        let Some(input) = current.as_mut() else { continue };
+        match parse_property(line) {
+            Some(("application.process.id", value)) => input.pid = value.parse().ok(),
+            Some(("application.name", value)) => input.name = value.to_string(),
+            Some(("application.process.binary", value)) if input.name.is_empty() => {
+                input.name = value.to_string()
+            }
+            _ => {}
+        }
+    }
+    inputs.extend(current);
+    inputs
+}
+
+/// Parse `pactl list sinks` into (name, description)
+fn parse_sinks(output: &str) -> Vec<(String, String)> {
+    let mut sinks = Vec::new();
+    let mut name: Option<String> = None;
+    for line in output.lines() {
+        if line.starts_with("Sink #") {
+            if let Some(name) = name.take() {
+                sinks.push((name.clone(), name));
+            }
+        } else if let Some(value) = line.trim().strip_prefix("Name: ") {
+            name = Some(value.to_string());
+        } else if let Some(value) = line.trim().strip_prefix("Description: ") {
+            if let Some(name) = name.take() {
+                sinks.push((name, value.to_string()));
+            }
+        }
+    }
+    if let Some(name) = name {
+        sinks.push((name.clone(), name));
+    }
+    sinks
+}
+
+/// `key = "value"` lines from a pactl Properties block
+fn parse_property(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.trim().split_once(" = ")?;
+    Some((key, value.trim_matches('"')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const SINK_INPUTS: &str = "Sink Input #42
+\tDriver: protocol-native.c
+\tOwner Module: 10
+\tClient: 55
+\tSink: 0
+\tProperties:
+\t\tapplication.name = \"Firefox\"
+\t\tapplication.process.id = \"1234\"
+\t\tmedia.name = \"Meeting\"
+
+Sink Input #43
+\tDriver: PipeWire
+\tProperties:
+\t\tapplication.process.binary = \"paplay\"
+\t\tapplication.process.id = \"5678\"
+
+Sink Input #44
+\tProperties:
+\t\tmedia.name = \"system bell\"
+";
+
+    #[test]
+    fn test_parse_sink_inputs() {
+        let inputs = parse_sink_inputs(SINK_INPUTS);
+        assert_eq!(inputs.len(), 3);
+        assert_eq!(
+            inputs[0],
+            SinkInput {
+                index: 42,
+                pid: Some(1234),
+                name: "Firefox".to_string()
+            }
+        );
+        assert_eq!(inputs[1].name, "paplay");
+        assert_eq!(inputs[1].pid, Some(5678));
+        assert_eq!(inputs[2].pid, None);
+    }
+
+    #[test]
+    fn test_selection_picks_sink_inputs() {
+        let inputs = parse_sink_inputs(SINK_INPUTS);
+        let selected = |selection: AppSelection| -> Vec<u32> {
+            inputs
+                .iter()
+                .filter(|input| is_selected(&selection, input))
+                .map(|input| input.index)
+                .collect()
+        };
+        assert_eq!(selected(AppSelection::Only(vec![1234])), vec![42]);
+        assert_eq!(selected(AppSelection::Only(vec![1234, 5678])), vec![42, 43]);
+        // The bell has no owner: excluded unless capturing "everything except"
+        assert_eq!(selected(AppSelection::Except(vec![1234])), vec![43, 44]);
+        assert_eq!(selected(AppSelection::Only(vec![9999])), Vec::<u32>::new());
+    }
+
+    /// Stereo app stream holding sample frames 0..`frames` (both channels = index)
+    fn app_stream(frames: usize) -> AppStream {
+        let (mut producer, consumer) = HeapRb::<f32>::new(MAX_STREAM_BACKLOG * 4).split();
+        for i in 0..frames {
+            producer.push_slice(&[i as f32, i as f32]);
+        }
+        AppStream::new(consumer, 2)
+    }
+
+    #[test]
+    fn test_app_stream_waits_for_prebuffer_then_mixes() {
+        let mut mix = vec![0.0f32; 2 * 480];
+        let mut stream = app_stream(STREAM_PREBUFFER - 1);
+        stream.mix_into(&mut mix);
+        assert!(mix.iter().all(|&s| s == 0.0), "mixed before the prebuffer filled");
+
+        let mut stream = app_stream(STREAM_PREBUFFER + 100);
+        let mut mix = vec![1.0f32; 2 * 100];
+        stream.mix_into(&mut mix);
+        // Added to what is already in the mix, oldest frames first, channels in order
+        assert_eq!(&mix[..4], &[1.0, 1.0, 2.0, 2.0]);
+        assert_eq!(stream.consumer.occupied_len(), 2 * STREAM_PREBUFFER);
+
+        // Underrun: the rest is mixed, then the stream waits for a fresh prebuffer
+        let mut mix = vec![0.0f32; 2 * (STREAM_PREBUFFER + 10)];
+        stream.mix_into(&mut mix);
+        assert_eq!(mix[2 * STREAM_PREBUFFER - 1], (STREAM_PREBUFFER + 99) as f32);
+        assert!(mix[2 * STREAM_PREBUFFER..].iter().all(|&s| s == 0.0));
+        assert!(!stream.primed);
+    }
+
+    #[test]
+    fn test_app_stream_skips_backlog_to_the_prebuffer() {
+        // A stream whose clock ran ahead: 300ms queued, 10ms mixed per tick
+        let backlog = MAX_STREAM_BACKLOG + MAX_STREAM_BACKLOG / 2;
+        let tick = CAPTURE_SAMPLE_RATE as usize / 100;
+        let mut stream = app_stream(backlog);
+        let mut mix = vec![0.0f32; 2 * tick];
+        stream.mix_into(&mut mix);
+
+        // Oldest audio dropped, whole sample frames only: the tick plays the
+        // frames just before the last prebuffer's worth
+        let first = (backlog - tick - STREAM_PREBUFFER) as f32;
+        assert_eq!(&mix[..2], &[first, first]);
+        assert_eq!(mix[2 * tick - 1], first + tick as f32 - 1.0);
+        assert_eq!(stream.consumer.occupied_len(), 2 * STREAM_PREBUFFER);
+
+        // Within the allowed backlog nothing is skipped
+        let mut stream = app_stream(MAX_STREAM_BACKLOG);
+        let mut mix = vec![0.0f32; 2 * tick];
+        stream.mix_into(&mut mix);
+        assert_eq!(mix[0], 0.0);
+        assert_eq!(mix[2], 1.0);
+    }
+
+    /// paplay client playing a 440Hz tone at `amplitude` for 10 seconds
+    fn spawn_paplay(amplitude: f32) -> Child {
+        let mut child = Command::new("paplay")
+            .args(["--raw", "--format=float32le", "--rate=48000", "--channels=1"])
+            .stdin(Stdio::piped())
+            .spawn()
+            .expect("paplay");
+        let mut stdin = child.stdin.take().unwrap();
+        thread::spawn(move || {
+            let bytes: Vec<u8> = (0..48000 * 10)
+                .map(|i| amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48000.0).sin())
+                .flat_map(|s| s.to_le_bytes())
+                .collect();
+            let _ = stdin.write_all(&bytes);
+        });
+        child
+    }
+
+    fn capture_peak(selection: AppSelection) -> f32 {
+        let options = SpeakerOptions {
+            selection,
+            ..SpeakerOptions::default()
+        };
+        let mut stream = SpeakerInput::with_options(None, options).unwrap().stream().unwrap();
+        let mut consumer = stream.take_consumer().unwrap();
+        thread::sleep(Duration::from_millis(2500));
+        let mut peak = 0.0f32;
+        while let Some(sample) = consumer.try_pop() {
+            peak = peak.max(sample.abs());
+        }
+        peak
+    }
+
+    /// Needs a PulseAudio / PipeWire daemon plus pactl, parec and paplay
+    /// Run with: cargo test -- --ignored --nocapture test_per_app_capture
+    #[test]
+    #[ignore]
+    fn test_per_app_capture() {
+        let mut loud = spawn_paplay(0.5);
+        let mut quiet = spawn_paplay(0.05);
+        thread::sleep(Duration::from_millis(500));
+
+        let apps = list_audio_applications().unwrap();
+        println!("apps: {:?}", apps);
+        assert!(apps.iter().any(|app| app.pid == loud.id()));
+        assert!(apps.iter().any(|app| app.pid == quiet.id()));
+
+        let only_loud = capture_peak(AppSelection::Only(vec![loud.id()]));
+        let without_loud = capture_peak(AppSelection::Except(vec![loud.id()]));
+        println!("peak only loud: {}, peak without loud: {}", only_loud, without_loud);
+        assert!(only_loud > 0.3);
+        assert!(without_loud < 0.2);
+
+        for child in [&mut loud, &mut quiet] {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
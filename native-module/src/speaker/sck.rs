@@ -4,7 +4,10 @@
 use anyhow::Result;
 use cidre::{arc, sc, cm, dispatch, ns, objc, define_obj_type};
 use cidre::sc::StreamOutput;
-use ringbuf::{traits::{Producer, Split}, HeapProd, HeapRb, HeapCons};
+use std::time::Duration;
+
+use crate::clock::epoch_ms_ago;
+use crate::sample_ring::{AudioFormat, RingConfig, SampleConsumer, SampleProducer};
 
 // keep for compatibility
 use cidre::core_audio as ca;
@@ -27,7 +30,10 @@ pub fn list_output_devices() -> Result<Vec<(String, String)>> {
 }
 
 pub struct AudioHandlerInner {
-    producer: HeapProd<f32>,
+    producer: SampleProducer,
+    channels: usize,
+    /// Scratch for interleaving SCK's one-buffer-per-channel output
+    interleaved: Vec<f32>,
 }
 
 define_obj_type!(
@@ -54,44 +60,68 @@ impl sc::stream::OutputImpl for AudioHandler {
         // Access inner state safely
         let inner = self.inner_mut();
 
-        match sample_buf.audio_buf_list_in::<1>(cm::sample_buffer::Flags(0), None, None) {
-            Ok(buf_list) => {
-                let buffer_count = buf_list.list().number_buffers as usize;
-                for i in 0..buffer_count {
-                    let buffer = &buf_list.list().buffers[i];
-                    let data_ptr = buffer.data as *const f32;
-                    let byte_count = buffer.data_bytes_size as usize;
-                    
-                    // Validate sample format (must be f32 aligned)
-                    if byte_count == 0 || byte_count % 4 != 0 {
-                        continue;
-                    }
-                    
-                    let float_count = byte_count / 4;
-                    
-                    if float_count > 0 && !data_ptr.is_null() {
-                        unsafe {
-                            let slice = std::slice::from_raw_parts(data_ptr, float_count);
-                            // Push audio to ring buffer
-                            let _pushed = inner.producer.push_slice(slice);
-                        }
-                    }
+        // Presentation time is on the host clock: how long ago it was
+        let age = cm::Clock::host_time_clock().time().as_secs() - sample_buf.pts().as_secs();
+        inner.producer.set_capture_time(epoch_ms_ago(Duration::from_secs_f64(age.max(0.0))));
+
+        if inner.channels == 1 {
+            push_sample_buf::<1>(inner, sample_buf);
+        } else {
+            push_sample_buf::<2>(inner, sample_buf);
+        }
+    }
+}
+
+/// Push one SCK sample buffer (non-interleaved: one buffer per channel)
+fn push_sample_buf<const N: usize>(inner: &mut AudioHandlerInner, sample_buf: &mut cm::SampleBuf) {
+    match sample_buf.audio_buf_list_in::<N>(cm::sample_buffer::Flags(0), None, None) {
+        Ok(buf_list) => {
+            let buffer_count = (buf_list.list().number_buffers as usize).min(N);
+            let mut planes: [&[f32]; N] = [&[]; N];
+            for (i, plane) in planes.iter_mut().enumerate().take(buffer_count) {
+                let buffer = &buf_list.list().buffers[i];
+                let data_ptr = buffer.data as *const f32;
+                let byte_count = buffer.data_bytes_size as usize;
+
+                // Validate sample format (must be f32 aligned)
+                if byte_count == 0 || byte_count % 4 != 0 || data_ptr.is_null() {
+                    continue;
                 }
+                *plane = unsafe { std::slice::from_raw_parts(data_ptr, byte_count / 4) };
             }
-            Err(e) => {
-                println!("[SystemAudio-SCK] Failed to get audio buffer: {:?}", e);
+
+            if N == 1 || buffer_count == 1 {
+                // Mono (or an interleaved single buffer): push as is
+                let _pushed = inner.producer.push_slice(planes[0]);
+            } else {
+                let frames = planes.iter().map(|p| p.len()).min().unwrap_or(0);
+                inner.interleaved.clear();
+                for i in 0..frames {
+                    inner.interleaved.extend(planes.iter().map(|p| p[i]));
+                }
+                let _pushed = inner.producer.push_slice(&inner.interleaved);
             }
         }
+        Err(e) => {
+            println!("[SystemAudio-SCK] Failed to get audio buffer: {:?}", e);
+        }
     }
 }
 
 pub struct SpeakerInput {
     cfg: arc::R<sc::StreamCfg>,
     filter: arc::R<sc::ContentFilter>,
+    channels: u16,
+    ring: RingConfig,
 }
 
 impl SpeakerInput {
-    pub fn new(_device_id: Option<String>) -> Result<Self> {
+    /// SCK records mono or stereo (`channels` 1 or 2)
+    pub fn new(_device_id: Option<String>, channels: u16, ring: RingConfig) -> Result<Self> {
+        let channels = channels.max(1);
+        if channels > 2 {
+            return Err(anyhow::anyhow!("ScreenCaptureKit records 1 or 2 channels, not {}", channels));
+        }
         println!("[SpeakerInput] Initializing ScreenCaptureKit audio capture...");
         
         // NOTE: ScreenCaptureKit captures ALL system audio, not per-device
@@ -152,8 +182,8 @@ impl SpeakerInput {
         // Configure for audio capture
         let mut cfg = sc::StreamCfg::new();
         cfg.set_captures_audio(true);
-        cfg.set_sample_rate(48000);
-        cfg.set_channel_count(1); // Mono - SCK doesn't affect system audio output quality
+        cfg.set_sample_rate(48000); // SCK converts, so the ring format is fixed for the stream's life
+        cfg.set_channel_count(channels as _); // SCK doesn't affect system audio output quality
         cfg.set_excludes_current_process_audio(true);
         cfg.set_queue_depth(8);
         
@@ -162,9 +192,9 @@ impl SpeakerInput {
         cfg.set_height(2);
         cfg.set_minimum_frame_interval(cm::Time::new(1, 1)); // 1 FPS
         
-        println!("[SpeakerInput] Config: 48kHz mono, queue_depth=8");
+        println!("[SpeakerInput] Config: 48kHz {}ch, queue_depth=8", channels);
         
-        Ok(Self { cfg, filter })
+        Ok(Self { cfg, filter, channels, ring })
     }
 
     pub fn sample_rate(&self) -> f64 {
@@ -172,14 +202,17 @@ impl SpeakerInput {
     }
 
     pub fn stream(self) -> SpeakerStream {
-        let buffer_size = 1024 * 128;
-        let rb = HeapRb::<f32>::new(buffer_size);
-        let (producer, consumer) = rb.split();
+        let channels = self.channels;
+        let (producer, consumer) = self.ring.ring(AudioFormat { sample_rate: 48000, channels });
         
         let stream = sc::Stream::new(&self.filter, &self.cfg);
         
         // Initialize handler
-        let inner = AudioHandlerInner { producer };
+        let inner = AudioHandlerInner {
+            producer,
+            channels: channels as usize,
+            interleaved: Vec::with_capacity(4096),
+        };
         let handler = AudioHandler::with(inner);
         
         let queue = dispatch::Queue::serial_with_ar_pool();
@@ -228,6 +261,7 @@ impl SpeakerInput {
         
         SpeakerStream {
             consumer: Some(consumer),
+            channels,
             stream,
             _handler: handler,
             _filter: self.filter,
@@ -237,7 +271,8 @@ impl SpeakerInput {
 }
 
 pub struct SpeakerStream {
-    consumer: Option<HeapCons<f32>>,
+    consumer: Option<SampleConsumer>,
+    channels: u16,
     stream: arc::R<sc::Stream>,
     _handler: arc::R<AudioHandler>,
     _filter: arc::R<sc::ContentFilter>,
@@ -248,8 +283,12 @@ impl SpeakerStream {
     pub fn sample_rate(&self) -> u32 {
         48000
     }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
     
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+    pub fn take_consumer(&mut self) -> Option<SampleConsumer> {
         self.consumer.take()
     }
 }
@@ -5,8 +5,9 @@ use std::thread;
 use std::time::Duration;
 use tracing::error;
 use wasapi::{get_default_device, DeviceCollection, Direction, SampleType, WaveFormat, ShareMode};
-use ringbuf::{traits::{Producer, Consumer, Split}, HeapRb, HeapProd, HeapCons};
-use crate::audio_config::RING_BUFFER_SAMPLES;
+use super::{AudioApplication, SpeakerOptions};
+use crate::clock::epoch_ms_ago;
+use crate::sample_ring::{AudioFormat, RingConfig, SampleConsumer, SampleProducer};
 
 struct WakerState {
     shutdown: bool,
@@ -14,21 +15,28 @@ struct WakerState {
 
 pub struct SpeakerInput {
     device_id: Option<String>,
+    channels: u16,
+    ring: RingConfig,
 }
 
 pub struct SpeakerStream {
-    consumer: Option<HeapCons<f32>>,
+    consumer: Option<SampleConsumer>,
     waker_state: Arc<Mutex<WakerState>>,
     capture_thread: Option<thread::JoinHandle<()>>,
     actual_sample_rate: u32,
+    channels: u16,
 }
 
 impl SpeakerStream {
     pub fn sample_rate(&self) -> u32 {
         self.actual_sample_rate
     }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
     
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+    pub fn take_consumer(&mut self) -> Option<SampleConsumer> {
         self.consumer.take()
     }
 }
@@ -50,6 +58,10 @@ fn find_device_by_id(direction: &Direction, device_id: &str) -> Option<wasapi::D
     None
 }
 
+pub fn list_audio_applications() -> Result<Vec<AudioApplication>> {
+    Err(anyhow::anyhow!("Listing audio applications is not supported on Windows yet"))
+}
+
 pub fn list_output_devices() -> Result<Vec<(String, String)>> {
     let collection = DeviceCollection::new(&Direction::Render).map_err(|e| anyhow::anyhow!("{}", e))?;
     let count = collection.get_nbr_devices().map_err(|e| anyhow::anyhow!("{}", e))?;
@@ -69,13 +81,25 @@ pub fn list_output_devices() -> Result<Vec<(String, String)>> {
 
 impl SpeakerInput {
     pub fn new(device_id: Option<String>) -> Result<Self> {
+        Self::with_options(device_id, SpeakerOptions::default())
+    }
+
+    /// Per-application capture needs WASAPI process loopback (not implemented yet)
+    /// Any channel count is accepted: shared mode converts from the mix format.
+    pub fn with_options(device_id: Option<String>, options: SpeakerOptions) -> Result<Self> {
+        if !options.selection.is_all() {
+            return Err(anyhow::anyhow!("Per-application capture is not supported on Windows yet"));
+        }
         let device_id = device_id.filter(|id| !id.is_empty() && id != "default");
-        Ok(Self { device_id })
+        Ok(Self { device_id, channels: options.channels.max(1), ring: options.ring })
     }
 
-    pub fn stream(self) -> SpeakerStream {
-        let rb = HeapRb::<f32>::new(RING_BUFFER_SAMPLES);
-        let (producer, consumer) = rb.split();
+    pub fn stream(self) -> Result<SpeakerStream> {
+        // Rate is only known once the capture thread has opened the device;
+        // the producer's wakeup threshold is updated there
+        let channels = self.channels;
+        // Sized for 48kHz until the device is open
+        let (producer, consumer) = self.ring.ring(AudioFormat { sample_rate: 48000, channels });
         
         let waker_state = Arc::new(Mutex::new(WakerState {
             shutdown: false,
@@ -86,7 +110,7 @@ impl SpeakerInput {
         let device_id = self.device_id;
 
         let capture_thread = thread::spawn(move || {
-            if let Err(e) = Self::capture_audio_loop(producer, waker_clone, init_tx, device_id) {
+            if let Err(e) = Self::capture_audio_loop(producer, waker_clone, init_tx, device_id, channels) {
                 error!("Audio capture loop failed: {}", e);
             }
         });
@@ -103,47 +127,55 @@ impl SpeakerInput {
             }
         };
 
-        SpeakerStream {
+        Ok(SpeakerStream {
             consumer: Some(consumer),
             waker_state,
             capture_thread: Some(capture_thread),
             actual_sample_rate,
-        }
+            channels,
+        })
+    }
+
+    /// Open and start a loopback client on the render device
+    /// Returns the event handle, capture client, mix rate and audio client
+    fn open_loopback(
+        device_id: &Option<String>,
+        channels: u16,
+    ) -> Result<(wasapi::Handle, wasapi::AudioCaptureClient, u32, wasapi::AudioClient)> {
+        let device = match device_id {
+            Some(ref id) => match find_device_by_id(&Direction::Render, id) {
+                Some(d) => d,
+                None => get_default_device(&Direction::Render).map_err(|e| anyhow::anyhow!("{}", e)).expect("No default render device"),
+            },
+            None => get_default_device(&Direction::Render).map_err(|e| anyhow::anyhow!("{}", e))?,
+        };
+
+        let mut audio_client = device.get_iaudioclient().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let device_format = audio_client.get_mixformat().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let actual_rate = device_format.get_samplespersec();
+        let desired_format = WaveFormat::new(32, 32, &SampleType::Float, actual_rate as usize, channels as usize, None);
+
+        let (_def_time, min_time) = audio_client.get_periods().map_err(|e| anyhow::anyhow!("{}", e))?;
+        // For WASAPI loopback: device=Render, but initialize with Direction::Capture
+        // This triggers AUDCLNT_STREAMFLAGS_LOOPBACK flag in wasapi
+        audio_client.initialize_client(&desired_format, min_time, &Direction::Capture, &ShareMode::Shared, true).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let h_event = audio_client.set_get_eventhandle().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let render_client = audio_client.get_audiocaptureclient().map_err(|e| anyhow::anyhow!("{}", e))?;
+        audio_client.start_stream().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        Ok((h_event, render_client, actual_rate, audio_client))
     }
 
     fn capture_audio_loop(
-        mut producer: HeapProd<f32>,
+        mut producer: SampleProducer,
         waker_state: Arc<Mutex<WakerState>>,
         init_tx: mpsc::Sender<Result<u32>>,
         device_id: Option<String>,
+        channels: u16,
     ) -> Result<()> {
-        let init_result = (|| -> Result<_> {
-            let device = match device_id {
-                Some(ref id) => match find_device_by_id(&Direction::Render, id) {
-                    Some(d) => d,
-                    None => get_default_device(&Direction::Render).map_err(|e| anyhow::anyhow!("{}", e)).expect("No default render device"),
-                },
-                None => get_default_device(&Direction::Render).map_err(|e| anyhow::anyhow!("{}", e))?,
-            };
-
-            let mut audio_client = device.get_iaudioclient().map_err(|e| anyhow::anyhow!("{}", e))?;
-            let device_format = audio_client.get_mixformat().map_err(|e| anyhow::anyhow!("{}", e))?;
-            let actual_rate = device_format.get_samplespersec();
-            let desired_format = WaveFormat::new(32, 32, &SampleType::Float, actual_rate as usize, 1, None);
-
-            let (_def_time, min_time) = audio_client.get_periods().map_err(|e| anyhow::anyhow!("{}", e))?;
-            // For WASAPI loopback: device=Render, but initialize with Direction::Capture
-            // This triggers AUDCLNT_STREAMFLAGS_LOOPBACK flag in wasapi
-            audio_client.initialize_client(&desired_format, min_time, &Direction::Capture, &ShareMode::Shared, true).map_err(|e| anyhow::anyhow!("{}", e))?;
-            let h_event = audio_client.set_get_eventhandle().map_err(|e| anyhow::anyhow!("{}", e))?;
-            let render_client = audio_client.get_audiocaptureclient().map_err(|e| anyhow::anyhow!("{}", e))?;
-            audio_client.start_stream().map_err(|e| anyhow::anyhow!("{}", e))?;
-
-            Ok((h_event, render_client, actual_rate, audio_client))
-        })();
-
-        match init_result {
-            Ok((h_event, render_client, sample_rate, audio_client)) => {
+        match Self::open_loopback(&device_id, channels) {
+            Ok((mut h_event, mut render_client, sample_rate, mut audio_client)) => {
+                producer.set_sample_rate(sample_rate);
                 let _ = init_tx.send(Ok(sample_rate));
                 loop {
                     {
@@ -160,10 +192,26 @@ impl SpeakerInput {
                     }
 
                     let mut temp_queue = VecDeque::new();
-                    // bytes_per_frame for 32-bit float mono = 4 bytes
-                    let bytes_per_frame: usize = 4; // 32-bit float, 1 channel
+                    // bytes_per_frame = 32-bit float * channels (interleaved)
+                    let bytes_per_frame: usize = 4 * channels as usize;
                     if let Err(e) = render_client.read_from_device_to_deque(bytes_per_frame, &mut temp_queue) {
-                        error!("Failed to read audio data: {}", e);
+                        // Usually AUDCLNT_E_DEVICE_INVALIDATED: the endpoint's mix
+                        // format changed (or the device was swapped). Reopen and
+                        // publish the new rate in-band so the DSP thread retunes.
+                        error!("Failed to read audio data: {}, reopening loopback", e);
+                        let _ = audio_client.stop_stream();
+                        match Self::open_loopback(&device_id, channels) {
+                            Ok((event, client, rate, audio)) => {
+                                h_event = event;
+                                render_client = client;
+                                audio_client = audio;
+                                producer.set_sample_rate(rate);
+                            }
+                            Err(e) => {
+                                error!("Failed to reopen loopback: {}", e);
+                                break;
+                            }
+                        }
                         continue;
                     }
 
@@ -184,6 +232,8 @@ impl SpeakerInput {
                     }
 
                     if !samples.is_empty() {
+                         // The packet was recorded just before the event fired
+                         producer.set_capture_time(epoch_ms_ago(producer.format().duration_of(samples.len())));
                          let _ = producer.push_slice(&samples);
                     }
                 }
@@ -10,6 +10,7 @@ pub struct StreamingResampler {
     /// Ratio of input sample rate to output sample rate
     /// e.g., 48000/16000 = 3.0
     ratio: f64,
+    output_sample_rate: f64,
     /// Fractional position in input stream (preserved across calls)
     fractional_pos: f64,
     /// Previous sample for interpolation at chunk boundaries
@@ -33,6 +34,7 @@ impl StreamingResampler {
         
         Self {
             ratio,
+            output_sample_rate,
             fractional_pos: 0.0,
             prev_sample: 0.0,
             initialized: false,
@@ -110,6 +112,20 @@ impl StreamingResampler {
         output
     }
 
+    /// Switch to a new input rate mid-stream (device changed rate)
+    ///
+    /// Interpolation state is kept and the pending fractional position is
+    /// rescaled, so output continues without a gap or a click.
+    pub fn set_input_rate(&mut self, input_sample_rate: f64) {
+        let ratio = input_sample_rate / self.output_sample_rate;
+        self.fractional_pos *= ratio / self.ratio;
+        self.ratio = ratio;
+        println!(
+            "[StreamingResampler] Input rate changed: {}Hz -> {}Hz (ratio: {:.4})",
+            input_sample_rate, self.output_sample_rate, ratio
+        );
+    }
+
     /// Reset the resampler state
     pub fn reset(&mut self) {
         self.fractional_pos = 0.0;
@@ -153,4 +169,19 @@ mod tests {
         // Output should be consistent
         assert!((out1.len() as i32 - out2.len() as i32).abs() <= 1);
     }
+
+    #[test]
+    fn test_rate_change_keeps_timing() {
+        let mut resampler = StreamingResampler::new(48000.0, 16000.0);
+        let before = resampler.resample(&vec![0.5; 4800]);
+
+        // Bluetooth profile switch: 100ms at 16kHz after 100ms at 48kHz
+        resampler.set_input_rate(16000.0);
+        let after = resampler.resample(&vec![0.5; 1600]);
+
+        assert!((before.len() as i32 - 1600).abs() <= 1);
+        assert!((after.len() as i32 - 1600).abs() <= 1);
+        // No click at the switch
+        assert!(after.iter().all(|&s| (s - 16383).abs() <= 1));
+    }
 }
@@ -0,0 +1,400 @@
+// Offline Transcription - on-device speech-to-text fed by the capture's frames
+//
+// For deployments where audio must not leave the machine. A worker thread
+// receives the processed 16kHz frames from the DSP thread and runs a local
+// model on them:
+// - Speech frames accumulate into the current utterance
+// - Every `partial_interval` of new audio, the utterance so far is decoded
+//   and reported as a partial transcript (skipped while the worker is behind)
+// - A keepalive (the suppressor's hangover elapsed), a gap in sequence
+//   numbers (suppressed frames) or `max_utterance` ends the utterance and
+//   produces the final transcript
+//
+// The model sits behind the SpeechRecognizer trait; the Whisper
+// implementation (whisper.rs) is only built with the `offline-stt` feature.
+//
+// REAL-TIME NOTES:
+// - The DSP thread only copies each frame into a bounded channel; if the
+//   worker falls that far behind, frames are dropped and counted
+// - The worker exits (after a final transcript) when the capture stops and
+//   the DSP thread drops its sender
+
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::audio_config::{FRAME_SAMPLES, SAMPLE_RATE};
+use crate::frame_pool::PooledFrame;
+
+/// Text recognized in a stretch of audio, timed relative to its start
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecognizedSegment {
+    pub text: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// A local speech recognition model
+pub trait SpeechRecognizer: Send {
+    /// Transcribe mono 16kHz audio (-1.0..1.0)
+    fn transcribe(&mut self, samples: &[f32]) -> Result<Vec<RecognizedSegment>>;
+}
+
+/// Model options (see whisper.rs)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecognizerConfig {
+    pub model_path: String,
+    /// Language code, None to auto-detect
+    pub language: Option<String>,
+    pub threads: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriberConfig {
+    /// New audio between two partial transcripts
+    pub partial_interval: Duration,
+    /// Longest utterance decoded at once (Whisper's window is 30s)
+    pub max_utterance: Duration,
+    /// Frames waiting for the worker before new ones are dropped
+    pub queue_frames: usize,
+}
+
+impl Default for TranscriberConfig {
+    fn default() -> Self {
+        Self {
+            partial_interval: Duration::from_millis(1000),
+            max_utterance: Duration::from_secs(30),
+            // 60s of audio
+            queue_frames: 3000,
+        }
+    }
+}
+
+/// A partial or final transcript of one utterance
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript {
+    pub text: String,
+    pub is_final: bool,
+    /// Sequence number of the utterance's first frame
+    pub seq: u64,
+    /// Capture time of the utterance's first sample (ms since the Unix epoch)
+    pub start_time_ms: Option<f64>,
+    /// Audio covered so far
+    pub duration: Duration,
+    /// Segments timed relative to the utterance start
+    pub segments: Vec<RecognizedSegment>,
+}
+
+/// One frame on its way to the worker (mono)
+#[derive(Debug, Clone)]
+pub struct WorkerFrame {
+    pub samples: Vec<f32>,
+    pub seq: u64,
+    pub capture_time_ms: Option<f64>,
+    /// Keepalive / fill frame: no speech
+    pub silence: bool,
+}
+
+/// Turns frames into utterances and transcripts (the worker's state)
+pub struct TranscriptAssembler {
+    recognizer: Box<dyn SpeechRecognizer>,
+    config: TranscriberConfig,
+    /// Current utterance (mono, -1.0..1.0)
+    audio: Vec<f32>,
+    seq: u64,
+    start_time_ms: Option<f64>,
+    /// Sequence number expected next (gap detection)
+    next_seq: Option<u64>,
+    /// Utterance length at the last partial
+    decoded_len: usize,
+}
+
+impl TranscriptAssembler {
+    pub fn new(recognizer: Box<dyn SpeechRecognizer>, config: TranscriberConfig) -> Self {
+        Self {
+            recognizer,
+            config,
+            audio: Vec::new(),
+            seq: 0,
+            start_time_ms: None,
+            next_seq: None,
+            decoded_len: 0,
+        }
+    }
+
+    /// Add one frame (or batch); `decode_partial` is false while more
+    /// frames are already waiting
+    pub fn push(&mut self, frame: WorkerFrame, decode_partial: bool) -> Vec<Transcript> {
+        let mut transcripts = Vec::new();
+        let frames = (frame.samples.len() / FRAME_SAMPLES).max(1) as u64;
+        let gap = self.next_seq.is_some_and(|next| frame.seq != next);
+        self.next_seq = Some(frame.seq + frames);
+
+        if frame.silence || gap {
+            transcripts.extend(self.finish());
+        }
+        if frame.silence {
+            return transcripts;
+        }
+
+        if self.audio.is_empty() {
+            self.seq = frame.seq;
+            self.start_time_ms = frame.capture_time_ms;
+        }
+        self.audio.extend_from_slice(&frame.samples);
+
+        if self.audio.len() >= samples_in(self.config.max_utterance) {
+            transcripts.extend(self.finish());
+        } else if decode_partial && self.audio.len() - self.decoded_len >= samples_in(self.config.partial_interval) {
+            self.decoded_len = self.audio.len();
+            transcripts.extend(self.decode(false));
+        }
+        transcripts
+    }
+
+    /// End the current utterance: its final transcript (if it has any text)
+    pub fn finish(&mut self) -> Option<Transcript> {
+        if self.audio.is_empty() {
+            return None;
+        }
+        let transcript = self.decode(true);
+        self.audio.clear();
+        self.decoded_len = 0;
+        transcript
+    }
+
+    fn decode(&mut self, is_final: bool) -> Option<Transcript> {
+        let segments = match self.recognizer.transcribe(&self.audio) {
+            Ok(segments) => segments,
+            Err(e) => {
+                eprintln!("[Transcriber] Recognition failed: {}", e);
+                return None;
+            }
+        };
+        let text = segments
+            .iter()
+            .map(|segment| segment.text.trim())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if text.is_empty() {
+            return None;
+        }
+        Some(Transcript {
+            text,
+            is_final,
+            seq: self.seq,
+            start_time_ms: self.start_time_ms,
+            duration: Duration::from_micros(self.audio.len() as u64 * 1_000_000 / SAMPLE_RATE as u64),
+            segments,
+        })
+    }
+}
+
+/// DSP-thread side of a transcriber: hands frames to the worker
+pub struct FrameSink {
+    sender: SyncSender<WorkerFrame>,
+    /// Interleaved channels of the capture's frames (downmixed here)
+    channels: usize,
+    /// Frames dropped because the worker was behind
+    dropped: u64,
+}
+
+impl FrameSink {
+    /// Queue a frame for the worker (never blocks)
+    pub fn send(&mut self, frame: &PooledFrame) {
+        let samples = frame
+            .samples()
+            .chunks_exact(self.channels)
+            .map(|samples| samples.iter().map(|&s| s as f32).sum::<f32>() / (self.channels as f32 * 32768.0))
+            .collect();
+        let frame = WorkerFrame {
+            samples,
+            seq: frame.seq(),
+            capture_time_ms: frame.capture_time_ms(),
+            silence: frame.is_silence(),
+        };
+        match self.sender.try_send(frame) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                if self.dropped.is_multiple_of(500) {
+                    println!("[Transcriber] Worker behind, dropping frames ({} so far)", self.dropped);
+                }
+                self.dropped += 1;
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+/// Start a worker thread; transcripts go to `handler` (on the worker thread)
+pub fn spawn_transcriber<H>(
+    recognizer: Box<dyn SpeechRecognizer>,
+    config: TranscriberConfig,
+    channels: usize,
+    handler: H,
+) -> FrameSink
+where
+    H: FnMut(Transcript) + Send + 'static,
+{
+    let (sender, receiver) = mpsc::sync_channel(config.queue_frames.max(1));
+    let assembler = TranscriptAssembler::new(recognizer, config);
+    thread::spawn(move || run_worker(receiver, assembler, handler));
+    FrameSink {
+        sender,
+        channels: channels.max(1),
+        dropped: 0,
+    }
+}
+
+fn run_worker<H>(receiver: Receiver<WorkerFrame>, mut assembler: TranscriptAssembler, mut handler: H)
+where
+    H: FnMut(Transcript),
+{
+    println!("[Transcriber] Worker started");
+    while let Ok(frame) = receiver.recv() {
+        // Catch up first: only the newest audio is worth a partial
+        let mut pending = vec![frame];
+        pending.extend(receiver.try_iter());
+        let last = pending.len() - 1;
+        for (i, frame) in pending.into_iter().enumerate() {
+            for transcript in assembler.push(frame, i == last) {
+                handler(transcript);
+            }
+        }
+    }
+    // Capture stopped
+    if let Some(transcript) = assembler.finish() {
+        handler(transcript);
+    }
+    println!("[Transcriber] Worker stopped");
+}
+
+fn samples_in(duration: Duration) -> usize {
+    (duration.as_millis() * SAMPLE_RATE as u128 / 1000) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use crate::audio_config::FRAME_MS;
+
+    /// Reports how much audio it was given, as "<n> frames"
+    struct CountingRecognizer {
+        calls: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl SpeechRecognizer for CountingRecognizer {
+        fn transcribe(&mut self, samples: &[f32]) -> Result<Vec<RecognizedSegment>> {
+            let frames = samples.len() / FRAME_SAMPLES;
+            self.calls.lock().unwrap().push(frames);
+            Ok(vec![RecognizedSegment {
+                text: format!(" {} frames ", frames),
+                start: Duration::ZERO,
+                end: Duration::from_millis(frames as u64 * FRAME_MS as u64),
+            }])
+        }
+    }
+
+    fn assembler(config: TranscriberConfig) -> (TranscriptAssembler, Arc<Mutex<Vec<usize>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recognizer = CountingRecognizer { calls: calls.clone() };
+        (TranscriptAssembler::new(Box::new(recognizer), config), calls)
+    }
+
+    fn frame(seq: u64, silence: bool) -> WorkerFrame {
+        WorkerFrame {
+            samples: vec![0.1; FRAME_SAMPLES],
+            seq,
+            capture_time_ms: Some(seq as f64 * FRAME_MS as f64),
+            silence,
+        }
+    }
+
+    #[test]
+    fn test_partials_then_final_on_keepalive() {
+        let (mut assembler, _) = assembler(TranscriberConfig {
+            partial_interval: Duration::from_millis(200),
+            ..TranscriberConfig::default()
+        });
+        let mut transcripts = Vec::new();
+        for seq in 0..25 {
+            transcripts.extend(assembler.push(frame(seq, false), true));
+        }
+        transcripts.extend(assembler.push(frame(25, true), true));
+
+        let texts: Vec<_> = transcripts.iter().map(|t| (t.text.as_str(), t.is_final)).collect();
+        assert_eq!(
+            texts,
+            vec![("10 frames", false), ("20 frames", false), ("25 frames", true)]
+        );
+        assert_eq!(transcripts[2].seq, 0);
+        assert_eq!(transcripts[2].start_time_ms, Some(0.0));
+        assert_eq!(transcripts[2].duration, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_gap_ends_utterance_and_backlog_skips_partials() {
+        let (mut assembler, calls) = assembler(TranscriberConfig {
+            partial_interval: Duration::from_millis(100),
+            ..TranscriberConfig::default()
+        });
+        // Behind: no partials for queued frames
+        for seq in 0..8 {
+            assert!(assembler.push(frame(seq, false), false).is_empty());
+        }
+        // Frames 8..20 were suppressed
+        let transcripts = assembler.push(frame(20, false), false);
+        assert_eq!(transcripts.len(), 1);
+        assert!(transcripts[0].is_final);
+        assert_eq!(transcripts[0].text, "8 frames");
+        assert_eq!(*calls.lock().unwrap(), vec![8]);
+
+        let last = assembler.finish().unwrap();
+        assert_eq!((last.seq, last.text.as_str()), (20, "1 frames"));
+    }
+
+    #[test]
+    fn test_max_utterance_forces_final() {
+        let (mut assembler, _) = assembler(TranscriberConfig {
+            partial_interval: Duration::from_secs(10),
+            max_utterance: Duration::from_millis(400),
+            ..TranscriberConfig::default()
+        });
+        let mut finals = 0;
+        for seq in 0..40 {
+            finals += assembler.push(frame(seq, false), true).iter().filter(|t| t.is_final).count();
+        }
+        assert_eq!(finals, 2);
+        assert!(assembler.finish().is_none());
+    }
+
+    #[test]
+    fn test_worker_flushes_on_stop() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let (tx, rx) = mpsc::channel();
+        let mut sink = spawn_transcriber(
+            Box::new(CountingRecognizer { calls }),
+            TranscriberConfig::default(),
+            1,
+            move |transcript| {
+                let _ = tx.send(transcript);
+            },
+        );
+        let pool = crate::frame_pool::FramePool::new(4);
+        for seq in 0..5 {
+            let mut frame = pool.take(FRAME_SAMPLES);
+            frame.samples_mut().extend(std::iter::repeat_n(1000, FRAME_SAMPLES));
+            frame.set_timing(seq, None);
+            sink.send(&frame);
+        }
+        drop(sink);
+        let transcript = rx.recv_timeout(Duration::from_secs(2)).expect("final transcript");
+        assert!(transcript.is_final);
+        assert_eq!(transcript.text, "5 frames");
+    }
+}
@@ -8,10 +8,11 @@
 // - Showing "speaking" indicator in UI
 // - Detecting utterance boundaries
 // - Optional stream management (not used currently)
+//
+// Hangover is measured in processed samples (16kHz chunks), so a backlog
+// processed in one go behaves like real-time audio.
 
-use std::time::{SystemTime, UNIX_EPOCH};
-
-use crate::audio_config::{VAD_START_RMS, VAD_END_RMS, VAD_HANGOVER_MS};
+use crate::audio_config::{SAMPLE_RATE, VAD_START_RMS, VAD_END_RMS, VAD_HANGOVER_MS};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum VadState {
@@ -26,8 +27,10 @@ pub struct VadIndicator {
     state: VadState,
     start_threshold: f32,
     end_threshold: f32,
-    hangover_duration_ms: u128,
-    hangover_start_time: u128,
+    hangover_samples: u64,
+    hangover_start: u64,
+    /// Samples processed since creation
+    position: u64,
     pub last_rms: f32,
 }
 
@@ -37,8 +40,9 @@ impl VadIndicator {
             state: VadState::Idle,
             start_threshold: VAD_START_RMS,
             end_threshold: VAD_END_RMS,
-            hangover_duration_ms: VAD_HANGOVER_MS,
-            hangover_start_time: 0,
+            hangover_samples: (VAD_HANGOVER_MS * SAMPLE_RATE as u128 / 1000) as u64,
+            hangover_start: 0,
+            position: 0,
             last_rms: 0.0,
         }
     }
@@ -49,7 +53,8 @@ impl VadIndicator {
     pub fn update(&mut self, chunk: &[i16]) -> VadState {
         let rms = self.calculate_rms(chunk);
         self.last_rms = rms;
-        let now = self.current_time_ms();
+        self.position += chunk.len() as u64;
+        let now = self.position;
 
         match self.state {
             VadState::Idle => {
@@ -61,15 +66,14 @@ impl VadIndicator {
             VadState::Speech => {
                 if rms < self.end_threshold {
                     self.state = VadState::Hangover;
-                    self.hangover_start_time = now;
+                    self.hangover_start = now;
                 }
             }
             VadState::Hangover => {
                 if rms > self.start_threshold {
                     self.state = VadState::Speech;
                 } else {
-                    let time_in_hangover = now - self.hangover_start_time;
-                    if time_in_hangover > self.hangover_duration_ms {
+                    if now - self.hangover_start > self.hangover_samples {
                         self.state = VadState::Idle;
                         println!("[VAD-UI] Speech ended");
                     }
@@ -112,12 +116,11 @@ impl VadIndicator {
 
         (sum / count as f32).sqrt()
     }
+}
 
-    fn current_time_ms(&self) -> u128 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis()
+impl Default for VadIndicator {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -0,0 +1,92 @@
+// WAV Encoding - RIFF container for 16-bit PCM
+//
+// REST STT APIs want a complete WAV file, not raw PCM. The header is the
+// canonical 44-byte PCM layout (same as RestSTT.ts used to write in JS).
+
+/// Size of the canonical PCM header
+pub const WAV_HEADER_BYTES: usize = 44;
+
+/// Container of audio handed to JS (utterances, history clips)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipFormat {
+    /// Complete WAV file (default, what REST STT APIs expect)
+    Wav,
+    /// Raw 16-bit little-endian PCM
+    Pcm,
+}
+
+impl ClipFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "wav" => Some(ClipFormat::Wav),
+            "pcm" => Some(ClipFormat::Pcm),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClipFormat::Wav => "wav",
+            ClipFormat::Pcm => "pcm",
+        }
+    }
+
+    pub fn encode(&self, samples: &[i16], sample_rate: u32, channels: u16) -> Vec<u8> {
+        match self {
+            ClipFormat::Wav => encode(samples, sample_rate, channels),
+            ClipFormat::Pcm => pcm_bytes(samples),
+        }
+    }
+}
+
+/// Wrap interleaved i16 samples in a WAV file
+pub fn encode(samples: &[i16], sample_rate: u32, channels: u16) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = channels as u32 * 2;
+    let mut out = Vec::with_capacity(WAV_HEADER_BYTES + data_len as usize);
+
+    // RIFF chunk descriptor
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    // fmt sub-chunk
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes()); // Subchunk1Size (16 for PCM)
+    out.extend_from_slice(&1u16.to_le_bytes()); // AudioFormat (1 = PCM)
+    out.extend_from_slice(&channels.to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * block_align).to_le_bytes()); // ByteRate
+    out.extend_from_slice(&(block_align as u16).to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes()); // BitsPerSample
+    // data sub-chunk
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    out.extend_from_slice(&pcm_bytes(samples));
+    out
+}
+
+/// Raw little-endian PCM bytes
+pub fn pcm_bytes(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_layout() {
+        let wav = encode(&[1, -2, 3, -4], 16_000, 2);
+        assert_eq!(wav.len(), WAV_HEADER_BYTES + 8);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(wav[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 2);
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 16_000);
+        assert_eq!(u32::from_le_bytes(wav[28..32].try_into().unwrap()), 64_000);
+        assert_eq!(u16::from_le_bytes([wav[32], wav[33]]), 4);
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 8);
+        assert_eq!(&wav[44..], &[1, 0, 0xfe, 0xff, 3, 0, 0xfc, 0xff]);
+    }
+}
@@ -0,0 +1,84 @@
+// Whisper Recognizer - whisper.cpp (via whisper-rs) behind SpeechRecognizer
+//
+// Only built with the `offline-stt` cargo feature. CPU-only: the model is a
+// ggml file on disk (e.g. ggml-base.en.bin), loaded once per transcriber.
+//
+// NOTES:
+// - Each call decodes the whole utterance so far (no streaming state), so
+//   partial and final transcripts of the same audio agree
+// - Input shorter than 1s is padded with silence (whisper.cpp rejects /
+//   hallucinates on very short input)
+
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState};
+
+use crate::audio_config::SAMPLE_RATE;
+use crate::transcriber::{RecognizedSegment, RecognizerConfig, SpeechRecognizer};
+
+/// Shortest input handed to whisper.cpp (samples)
+const MIN_INPUT_SAMPLES: usize = SAMPLE_RATE as usize;
+
+pub struct WhisperRecognizer {
+    config: RecognizerConfig,
+    /// Keeps the model alive for the state
+    _context: WhisperContext,
+    state: WhisperState,
+    input: Vec<f32>,
+}
+
+impl WhisperRecognizer {
+    /// Load the model (takes a moment for the larger ones)
+    pub fn new(config: &RecognizerConfig) -> Result<Self> {
+        println!(
+            "[Whisper] Loading {} ({} threads, language {})",
+            config.model_path,
+            config.threads,
+            config.language.as_deref().unwrap_or("auto")
+        );
+        let context = WhisperContext::new_with_params(&config.model_path, WhisperContextParameters::default())
+            .map_err(|e| anyhow!("Failed to load model {}: {}", config.model_path, e))?;
+        let state = context.create_state().map_err(|e| anyhow!("Failed to create state: {}", e))?;
+        Ok(Self {
+            config: config.clone(),
+            _context: context,
+            state,
+            input: Vec::with_capacity(MIN_INPUT_SAMPLES),
+        })
+    }
+}
+
+impl SpeechRecognizer for WhisperRecognizer {
+    fn transcribe(&mut self, samples: &[f32]) -> Result<Vec<RecognizedSegment>> {
+        self.input.clear();
+        self.input.extend_from_slice(samples);
+        if self.input.len() < MIN_INPUT_SAMPLES {
+            self.input.resize(MIN_INPUT_SAMPLES, 0.0);
+        }
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_n_threads(self.config.threads as i32);
+        params.set_language(Some(self.config.language.as_deref().unwrap_or("auto")));
+        params.set_no_context(true);
+        params.set_suppress_blank(true);
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+
+        self.state.full(params, &self.input)?;
+        let count = self.state.full_n_segments()?;
+        let mut segments = Vec::with_capacity(count.max(0) as usize);
+        for i in 0..count {
+            // Timestamps are in 10ms units
+            let t0 = self.state.full_get_segment_t0(i)?;
+            let t1 = self.state.full_get_segment_t1(i)?;
+            segments.push(RecognizedSegment {
+                text: self.state.full_get_segment_text(i)?,
+                start: Duration::from_millis(t0.max(0) as u64 * 10),
+                end: Duration::from_millis(t1.max(0) as u64 * 10),
+            });
+        }
+        Ok(segments)
+    }
+}